@@ -91,6 +91,7 @@ impl DataChunkBuilder {
         let capacity = self.capacity;
         match size {
             0 => None,
+            _ if self.array_builders.is_empty() => Some(DataChunk::no_column(size)),
             _ => Some(
                 self.array_builders
                     .iter_mut()
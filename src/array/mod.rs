@@ -19,6 +19,7 @@ mod chunked_array;
 mod data_chunk;
 mod data_chunk_builder;
 pub mod ops;
+pub mod parquet;
 mod primitive_array;
 mod var_array;
 
@@ -482,9 +482,10 @@ impl ArrayImpl {
                 })?),
                 Type::Float64 => Self::Float64(a.clone()),
                 Type::String => Self::new_string(StringArray::from_iter_display(a.iter())),
-                Type::Decimal(_, _) => Self::new_decimal(unary_op(a.as_ref(), |&f| {
-                    Decimal::from_f64_retain(f.0).unwrap()
-                })),
+                Type::Decimal(_, _) => Self::new_decimal(try_unary_op(a.as_ref(), |&f| {
+                    Decimal::from_f64_retain(f.0)
+                        .ok_or(ConvertError::ToDecimalError(DataValue::Float64(f)))
+                })?),
                 Type::Null
                 | Type::Date
                 | Type::Timestamp
@@ -545,7 +546,10 @@ impl ArrayImpl {
                     return Err(ConvertError::NoCast("VARCHAR", data_type.clone()));
                 }
             },
-            Self::Blob(_) => todo!("cast array"),
+            Self::Blob(a) => match data_type {
+                Type::Blob => Self::Blob(a.clone()),
+                _ => return Err(ConvertError::NoCast("BLOB", data_type.clone())),
+            },
             Self::Vector(_) => todo!("cast array"),
             Self::Decimal(a) => match data_type {
                 Type::Bool => Self::new_bool(unary_op(a.as_ref(), |&d| !d.is_zero())),
@@ -856,8 +860,12 @@ where
         .zip(b.raw_iter())
         .zip(s.raw_iter())
         .map(|((a, b), s)| if *s { a } else { b });
-    let mut valid = s.get_valid_bitmap().and(a.get_valid_bitmap());
-    valid.or(&s.get_valid_bitmap().not_then_and(b.get_valid_bitmap()));
+    // null condition selects the false branch
+    let cond = s
+        .get_valid_bitmap()
+        .and(&BitVec::from_bool_slice(s.true_array()));
+    let mut valid = cond.and(a.get_valid_bitmap());
+    valid.or(&cond.not_then_and(b.get_valid_bitmap()));
     A::from_data(it, valid)
 }
 
@@ -0,0 +1,334 @@
+// Copyright 2025 RisingLight Project Authors. Licensed under Apache-2.0.
+
+//! Conversion between arrays and Parquet files.
+
+use std::sync::Arc;
+
+use parquet::basic::{ConvertedType, LogicalType, Repetition, TimeUnit, Type as PhysicalType};
+use parquet::data_type::{
+    BoolType, ByteArray, ByteArrayType, DataType as ParquetDataType, DoubleType, FixedLenByteArray,
+    FixedLenByteArrayType, Int32Type, Int64Type,
+};
+use parquet::errors::{ParquetError, Result};
+use parquet::file::writer::SerializedColumnWriter;
+use parquet::record::Field;
+use parquet::schema::types::{ColumnDescriptor, SchemaDescriptor, Type, TypePtr};
+use rust_decimal::Decimal;
+
+use super::{Array, ArrayImpl};
+use crate::types::{DataType, DataValue, Date, Timestamp, TimestampTz};
+
+/// The byte length of decimals written to Parquet, which holds up to 38 digits.
+const DECIMAL_LENGTH: i32 = 16;
+
+/// The maximum precision of decimals written to Parquet.
+const MAX_DECIMAL_PRECISION: u8 = 38;
+
+/// The scale of decimals written to Parquet if not specified.
+const DEFAULT_DECIMAL_SCALE: u8 = 10;
+
+/// The maximum scale of decimals supported by [`Decimal`].
+const MAX_DECIMAL_SCALE: u8 = 28;
+
+/// Returns the data types of columns in a Parquet schema.
+///
+/// Nested columns are not supported.
+pub fn data_types(schema: &SchemaDescriptor) -> Result<Vec<DataType>> {
+    if let Some(field) = (schema.root_schema().get_fields().iter()).find(|f| f.is_group()) {
+        return Err(ParquetError::NYI(format!(
+            "nested column {:?} is not supported",
+            field.name()
+        )));
+    }
+    schema.columns().iter().map(|c| data_type(c)).collect()
+}
+
+/// Returns the data type of a Parquet column.
+pub fn data_type(column: &ColumnDescriptor) -> Result<DataType> {
+    let decimal = || {
+        DataType::Decimal(
+            Some(column.type_precision() as u8),
+            Some(column.type_scale() as u8),
+        )
+    };
+    let timestamp = || match column.logical_type() {
+        Some(LogicalType::Timestamp {
+            is_adjusted_to_u_t_c: false,
+            ..
+        }) => DataType::Timestamp,
+        _ => DataType::TimestampTz,
+    };
+    let ty = match column.physical_type() {
+        PhysicalType::BOOLEAN => DataType::Bool,
+        PhysicalType::INT32 => match column.converted_type() {
+            ConvertedType::INT_8 | ConvertedType::INT_16 | ConvertedType::UINT_8 => DataType::Int16,
+            ConvertedType::UINT_16 => DataType::Int32,
+            ConvertedType::UINT_32 => DataType::Int64,
+            ConvertedType::DATE => DataType::Date,
+            ConvertedType::DECIMAL => decimal(),
+            ConvertedType::INT_32 | ConvertedType::NONE => DataType::Int32,
+            ty => return Err(unsupported(column, ty)),
+        },
+        PhysicalType::INT64 => match column.converted_type() {
+            ConvertedType::UINT_64 => DataType::Decimal(Some(20), Some(0)),
+            ConvertedType::TIMESTAMP_MILLIS | ConvertedType::TIMESTAMP_MICROS => timestamp(),
+            ConvertedType::DECIMAL => decimal(),
+            // timestamps in nanoseconds have no converted type
+            ConvertedType::NONE if is_nanos(column) => timestamp(),
+            ConvertedType::INT_64 | ConvertedType::NONE => DataType::Int64,
+            ty => return Err(unsupported(column, ty)),
+        },
+        PhysicalType::INT96 => DataType::Timestamp,
+        PhysicalType::FLOAT | PhysicalType::DOUBLE => DataType::Float64,
+        PhysicalType::BYTE_ARRAY | PhysicalType::FIXED_LEN_BYTE_ARRAY => {
+            match column.converted_type() {
+                ConvertedType::UTF8 | ConvertedType::ENUM | ConvertedType::JSON => DataType::String,
+                ConvertedType::DECIMAL => decimal(),
+                _ if column.logical_type() == Some(LogicalType::Float16) => DataType::Float64,
+                ConvertedType::BSON | ConvertedType::NONE => DataType::Blob,
+                ty => return Err(unsupported(column, ty)),
+            }
+        }
+    };
+    Ok(ty)
+}
+
+fn is_nanos(column: &ColumnDescriptor) -> bool {
+    matches!(
+        column.logical_type(),
+        Some(LogicalType::Timestamp {
+            unit: TimeUnit::NANOS(_),
+            ..
+        })
+    )
+}
+
+fn unsupported(column: &ColumnDescriptor, ty: ConvertedType) -> ParquetError {
+    ParquetError::NYI(format!(
+        "column {:?} of type {ty} is not supported",
+        column.name()
+    ))
+}
+
+/// Converts a field read from Parquet to a value of `ty`, which is the data type of the column.
+pub fn value(field: Field, ty: &DataType) -> Result<DataValue> {
+    Ok(match field {
+        Field::Null => DataValue::Null,
+        Field::Bool(v) => DataValue::Bool(v),
+        Field::Byte(v) => DataValue::Int16(v.into()),
+        Field::Short(v) => DataValue::Int16(v),
+        Field::Int(v) => DataValue::Int32(v),
+        Field::Long(v) => match ty {
+            DataType::Timestamp => DataValue::Timestamp(Timestamp::from_unix_micros(v / 1000)),
+            DataType::TimestampTz => {
+                DataValue::TimestampTz(TimestampTz::from_unix_micros(v / 1000))
+            }
+            _ => DataValue::Int64(v),
+        },
+        Field::UByte(v) => DataValue::Int16(v.into()),
+        Field::UShort(v) => DataValue::Int32(v.into()),
+        Field::UInt(v) => DataValue::Int64(v.into()),
+        Field::ULong(v) => DataValue::Decimal(v.into()),
+        Field::Float16(v) => DataValue::Float64(f64::from(v).into()),
+        Field::Float(v) => DataValue::Float64(f64::from(v).into()),
+        Field::Double(v) => DataValue::Float64(v.into()),
+        Field::Decimal(v) => {
+            let bytes = v.data();
+            if bytes.len() > 16 {
+                return Err(ParquetError::NYI(format!(
+                    "decimal of {} bytes is not supported",
+                    bytes.len()
+                )));
+            }
+            // sign-extend the big-endian bytes
+            let fill = if bytes.first().is_some_and(|b| *b & 0x80 != 0) {
+                0xff
+            } else {
+                0
+            };
+            let mut buf = [fill; 16];
+            buf[16 - bytes.len()..].copy_from_slice(bytes);
+            let mantissa = i128::from_be_bytes(buf);
+            let decimal = Decimal::try_from_i128_with_scale(mantissa, v.scale() as u32)
+                .map_err(|e| ParquetError::General(e.to_string()))?;
+            DataValue::Decimal(decimal)
+        }
+        Field::Str(v) => DataValue::String(v.into()),
+        Field::Bytes(v) => DataValue::Blob(v.data().into()),
+        Field::Date(v) => DataValue::Date(Date::new(v)),
+        Field::TimestampMillis(v) => timestamp(v * 1000, ty),
+        Field::TimestampMicros(v) => timestamp(v, ty),
+        Field::TimeMillis(_)
+        | Field::TimeMicros(_)
+        | Field::Group(_)
+        | Field::ListInternal(_)
+        | Field::MapInternal(_) => {
+            return Err(ParquetError::NYI(format!("field {field} is not supported")));
+        }
+    })
+}
+
+fn timestamp(micros: i64, ty: &DataType) -> DataValue {
+    match ty {
+        DataType::TimestampTz => DataValue::TimestampTz(TimestampTz::from_unix_micros(micros)),
+        _ => DataValue::Timestamp(Timestamp::from_unix_micros(micros)),
+    }
+}
+
+/// Returns the Parquet schema of columns with the given names and types.
+pub fn schema(columns: &[(String, DataType)]) -> Result<TypePtr> {
+    let fields = (columns.iter())
+        .map(|(name, ty)| column_type(name, ty).map(Arc::new))
+        .collect::<Result<Vec<_>>>()?;
+    let schema = Type::group_type_builder("schema")
+        .with_fields(fields)
+        .build()?;
+    Ok(Arc::new(schema))
+}
+
+/// Returns the Parquet type of a column.
+fn column_type(name: &str, ty: &DataType) -> Result<Type> {
+    let (physical_type, logical_type) = match ty {
+        DataType::Bool => (PhysicalType::BOOLEAN, None),
+        DataType::Int16 => (
+            PhysicalType::INT32,
+            Some(LogicalType::Integer {
+                bit_width: 16,
+                is_signed: true,
+            }),
+        ),
+        DataType::Int32 => (PhysicalType::INT32, None),
+        DataType::Int64 => (PhysicalType::INT64, None),
+        DataType::Float64 => (PhysicalType::DOUBLE, None),
+        DataType::String => (PhysicalType::BYTE_ARRAY, Some(LogicalType::String)),
+        DataType::Blob => (PhysicalType::BYTE_ARRAY, None),
+        DataType::Decimal(precision, scale) => {
+            let (precision, scale) = decimal_precision_scale(*precision, *scale)?;
+            return Type::primitive_type_builder(name, PhysicalType::FIXED_LEN_BYTE_ARRAY)
+                .with_repetition(Repetition::OPTIONAL)
+                .with_logical_type(Some(LogicalType::Decimal {
+                    scale: scale as i32,
+                    precision: precision as i32,
+                }))
+                .with_length(DECIMAL_LENGTH)
+                .with_precision(precision as i32)
+                .with_scale(scale as i32)
+                .build();
+        }
+        DataType::Date => (PhysicalType::INT32, Some(LogicalType::Date)),
+        DataType::Timestamp | DataType::TimestampTz => (
+            PhysicalType::INT64,
+            Some(LogicalType::Timestamp {
+                is_adjusted_to_u_t_c: matches!(ty, DataType::TimestampTz),
+                unit: TimeUnit::MICROS(Default::default()),
+            }),
+        ),
+        DataType::Null | DataType::Interval | DataType::Struct(_) | DataType::Vector(_) => {
+            return Err(ParquetError::NYI(format!(
+                "column {name:?} of type {ty} is not supported"
+            )));
+        }
+    };
+    Type::primitive_type_builder(name, physical_type)
+        .with_repetition(Repetition::OPTIONAL)
+        .with_logical_type(logical_type)
+        .build()
+}
+
+/// Returns the precision and scale of a decimal written to Parquet.
+fn decimal_precision_scale(precision: Option<u8>, scale: Option<u8>) -> Result<(u8, u8)> {
+    let precision = precision.unwrap_or(MAX_DECIMAL_PRECISION);
+    // `DECIMAL(p)` means a scale of 0
+    let scale = scale.unwrap_or(if precision == MAX_DECIMAL_PRECISION {
+        DEFAULT_DECIMAL_SCALE
+    } else {
+        0
+    });
+    if precision > MAX_DECIMAL_PRECISION || scale > precision || scale > MAX_DECIMAL_SCALE {
+        return Err(ParquetError::NYI(format!(
+            "decimal({precision}, {scale}) is not supported"
+        )));
+    }
+    Ok((precision, scale))
+}
+
+/// Writes arrays of a column to Parquet.
+pub fn write_column<'a>(
+    writer: &mut SerializedColumnWriter<'_>,
+    ty: &DataType,
+    arrays: impl IntoIterator<Item = &'a ArrayImpl>,
+) -> Result<()> {
+    for array in arrays {
+        match array {
+            ArrayImpl::Bool(a) => write_batch::<BoolType>(writer, a.iter().map(|v| v.copied()))?,
+            ArrayImpl::Int16(a) => {
+                write_batch::<Int32Type>(writer, a.iter().map(|v| v.map(|v| *v as i32)))?
+            }
+            ArrayImpl::Int32(a) => write_batch::<Int32Type>(writer, a.iter().map(|v| v.copied()))?,
+            ArrayImpl::Int64(a) => write_batch::<Int64Type>(writer, a.iter().map(|v| v.copied()))?,
+            ArrayImpl::Float64(a) => {
+                write_batch::<DoubleType>(writer, a.iter().map(|v| v.map(|v| v.0)))?
+            }
+            ArrayImpl::String(a) => write_batch::<ByteArrayType>(
+                writer,
+                a.iter()
+                    .map(|v| v.map(|v| ByteArray::from(v.as_bytes().to_vec()))),
+            )?,
+            ArrayImpl::Blob(a) => write_batch::<ByteArrayType>(
+                writer,
+                a.iter().map(|v| v.map(|v| ByteArray::from(v.to_vec()))),
+            )?,
+            ArrayImpl::Decimal(a) => {
+                let DataType::Decimal(precision, scale) = ty else {
+                    unreachable!("decimal array of type {ty}");
+                };
+                let (_, scale) = decimal_precision_scale(*precision, *scale)?;
+                let values = a.iter().map(|v| {
+                    v.map(|v| {
+                        let mut v = *v;
+                        v.rescale(scale as u32);
+                        FixedLenByteArray::from(v.mantissa().to_be_bytes().to_vec())
+                    })
+                });
+                write_batch::<FixedLenByteArrayType>(writer, values)?
+            }
+            ArrayImpl::Date(a) => {
+                write_batch::<Int32Type>(writer, a.iter().map(|v| v.map(|v| v.get_inner())))?
+            }
+            ArrayImpl::Timestamp(a) => {
+                write_batch::<Int64Type>(writer, a.iter().map(|v| v.map(|v| v.unix_micros())))?
+            }
+            ArrayImpl::TimestampTz(a) => {
+                write_batch::<Int64Type>(writer, a.iter().map(|v| v.map(|v| v.unix_micros())))?
+            }
+            _ => {
+                return Err(ParquetError::NYI(format!(
+                    "array of type {ty} is not supported"
+                )));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Writes optional values to a column.
+fn write_batch<T: ParquetDataType>(
+    writer: &mut SerializedColumnWriter<'_>,
+    values: impl Iterator<Item = Option<T::T>>,
+) -> Result<()> {
+    let mut def_levels = vec![];
+    let mut non_nulls = vec![];
+    for value in values {
+        match value {
+            Some(v) => {
+                def_levels.push(1);
+                non_nulls.push(v);
+            }
+            None => def_levels.push(0),
+        }
+    }
+    writer
+        .typed::<T>()
+        .write_batch(&non_nulls, Some(&def_levels), None)?;
+    Ok(())
+}
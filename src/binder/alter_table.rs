@@ -0,0 +1,243 @@
+// Copyright 2025 RisingLight Project Authors. Licensed under Apache-2.0.
+
+use std::fmt;
+use std::str::FromStr;
+
+use pretty_xmlish::Pretty;
+use pretty_xmlish::helper::delegate_fmt;
+use serde::{Deserialize, Serialize};
+
+use super::*;
+use crate::catalog::{ColumnCatalog, ColumnId, TableCatalog};
+use crate::types::DataType as Type;
+
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Serialize, Deserialize)]
+pub struct AlterTable {
+    pub table_id: TableRefId,
+    pub operation: AlterTableOperation,
+}
+
+/// A change to the schema of a table.
+///
+/// Rows written before the change are not rewritten. Storages read them with the new schema.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Serialize, Deserialize)]
+pub enum AlterTableOperation {
+    /// Adds a column. Existing rows take the default value of the column.
+    AddColumn(ColumnCatalog),
+    DropColumn(ColumnId),
+    RenameColumn {
+        column_id: ColumnId,
+        name: String,
+    },
+    RenameTable(String),
+    /// Changes the type of a column. Existing values are casted to the new type.
+    AlterColumnType {
+        column_id: ColumnId,
+        data_type: Type,
+    },
+}
+
+impl fmt::Display for AlterTable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let explainer = Pretty::childless_record("AlterTable", self.pretty_alter());
+        delegate_fmt(&explainer, f, String::with_capacity(1000))
+    }
+}
+
+impl AlterTable {
+    pub fn pretty_alter<'a>(&self) -> Vec<(&'a str, Pretty<'a>)> {
+        let mut fields = vec![("table", Pretty::display(&self.table_id))];
+        match &self.operation {
+            AlterTableOperation::AddColumn(column) => {
+                fields.push(("add_column", column.desc().pretty()));
+            }
+            AlterTableOperation::DropColumn(column_id) => {
+                fields.push(("drop_column", Pretty::display(column_id)));
+            }
+            AlterTableOperation::RenameColumn { column_id, name } => {
+                fields.push(("rename_column", Pretty::display(column_id)));
+                fields.push(("to", Pretty::display(name)));
+            }
+            AlterTableOperation::RenameTable(name) => {
+                fields.push(("rename_to", Pretty::display(name)));
+            }
+            AlterTableOperation::AlterColumnType {
+                column_id,
+                data_type,
+            } => {
+                fields.push(("alter_column", Pretty::display(column_id)));
+                fields.push(("type", Pretty::display(data_type)));
+            }
+        }
+        fields
+    }
+}
+
+impl FromStr for Box<AlterTable> {
+    type Err = ();
+
+    fn from_str(_s: &str) -> std::result::Result<Self, Self::Err> {
+        Err(())
+    }
+}
+
+impl Binder {
+    pub(super) fn bind_alter_table(
+        &mut self,
+        name: ObjectName,
+        if_exists: bool,
+        operations: Vec<crate::parser::AlterTableOperation>,
+    ) -> Result {
+        if if_exists {
+            return Err(ErrorKind::Todo("ALTER TABLE IF EXISTS".into()).with_spanned(&name));
+        }
+        let name = lower_case_name(&name);
+        let (_, table_name) = split_name(&name)?;
+        let table_id = self
+            .get_table_id_by_name(&name)?
+            .ok_or_else(|| ErrorKind::InvalidTable(table_name.into()).with_spanned(&name))?;
+        let table = self.catalog.get_table(&table_id).unwrap();
+        if table.is_view() || table_id.schema_id == RootCatalog::SYSTEM_SCHEMA_ID {
+            return Err(ErrorKind::CanNotAlter.with_spanned(&name));
+        }
+        let [operation] = <[_; 1]>::try_from(operations).map_err(|_| {
+            ErrorKind::Todo("multiple operations in ALTER TABLE".into()).with_spanned(&name)
+        })?;
+
+        use crate::parser::AlterTableOperation as Op;
+        let operation = match operation {
+            Op::AddColumn {
+                if_not_exists: false,
+                column_def,
+                column_position: None,
+                ..
+            } => {
+                let column_name = column_def.name.value.to_lowercase();
+                if table.contains_column(&column_name) {
+                    return Err(ErrorKind::ColumnExists(column_name).with_spanned(&column_def));
+                }
+                let mut column = ColumnCatalog::from(&column_def);
+                if column.is_primary() {
+                    return Err(
+                        ErrorKind::Todo("add primary key column".into()).with_spanned(&column_def)
+                    );
+                }
+                column.set_id(table.next_column_id());
+                let default_value = self.bind_default_value(&column_def)?;
+                column.set_default_value(default_value);
+                AlterTableOperation::AddColumn(column)
+            }
+            Op::DropColumn {
+                column_name,
+                if_exists: false,
+                cascade: false,
+            } => {
+                let column_id = self.bind_alter_column(table_id, &table, &column_name)?;
+                if table.all_columns().len() == 1 {
+                    return Err(ErrorKind::CanNotAlterColumn(
+                        column_name.value.to_lowercase(),
+                        "it is the only column of the table",
+                    )
+                    .with_span(column_name.span));
+                }
+                AlterTableOperation::DropColumn(column_id)
+            }
+            Op::RenameColumn {
+                old_column_name,
+                new_column_name,
+            } => {
+                let column_id = (table
+                    .get_column_id_by_name(&old_column_name.value.to_lowercase()))
+                .ok_or_else(|| {
+                    ErrorKind::InvalidColumn(old_column_name.value.to_lowercase())
+                        .with_span(old_column_name.span)
+                })?;
+                let name = new_column_name.value.to_lowercase();
+                if table.contains_column(&name) {
+                    return Err(ErrorKind::ColumnExists(name).with_span(new_column_name.span));
+                }
+                AlterTableOperation::RenameColumn { column_id, name }
+            }
+            Op::RenameTable {
+                table_name: new_name,
+            } => {
+                let new_name = lower_case_name(&new_name);
+                let (new_schema_name, new_table_name) = split_name(&new_name)?;
+                let schema = self.catalog.get_schema_by_id(table_id.schema_id).unwrap();
+                if new_name.0.len() > 1 && new_schema_name != schema.name() {
+                    return Err(ErrorKind::Todo("move table to another schema".into())
+                        .with_spanned(&new_name));
+                }
+                if schema.get_table_by_name(new_table_name).is_some() {
+                    return Err(
+                        ErrorKind::TableExists(new_table_name.into()).with_spanned(&new_name)
+                    );
+                }
+                AlterTableOperation::RenameTable(new_table_name.into())
+            }
+            Op::AlterColumn {
+                column_name,
+                op:
+                    AlterColumnOperation::SetDataType {
+                        data_type,
+                        using: None,
+                    },
+            } => {
+                let column_id = self.bind_alter_column(table_id, &table, &column_name)?;
+                let column = table.get_column_by_id(column_id).unwrap();
+                let data_type = Type::from(&data_type);
+                // existing values are checked to be castable when the statement is executed
+                if column.data_type().union(&data_type).as_ref() != Some(&data_type)
+                    || data_type == Type::Blob && column.data_type() != Type::Blob
+                {
+                    return Err(ErrorKind::IncompatibleColumnType(
+                        column.name().into(),
+                        column.data_type(),
+                        data_type,
+                    )
+                    .with_span(column_name.span));
+                }
+                AlterTableOperation::AlterColumnType {
+                    column_id,
+                    data_type,
+                }
+            }
+            op => return Err(ErrorKind::Todo(format!("ALTER TABLE {op}")).with_spanned(&name)),
+        };
+        let alter = self.egraph.add(Node::AlterTable(Box::new(AlterTable {
+            table_id,
+            operation,
+        })));
+        Ok(alter)
+    }
+
+    /// Returns the id of a column to be dropped or changed.
+    ///
+    /// Primary key and indexed columns can not be dropped or changed.
+    fn bind_alter_column(
+        &self,
+        table_id: TableRefId,
+        table: &TableCatalog,
+        column_name: &Ident,
+    ) -> Result<ColumnId> {
+        let name = column_name.value.to_lowercase();
+        let column_id = (table.get_column_id_by_name(&name))
+            .ok_or_else(|| ErrorKind::InvalidColumn(name.clone()).with_span(column_name.span))?;
+        if table.primary_keys().contains(&column_id) {
+            return Err(ErrorKind::CanNotAlterColumn(name, "it is a primary key")
+                .with_span(column_name.span));
+        }
+        let indexed = (self
+            .catalog
+            .get_index_on_table(table_id.schema_id, table_id.table_id))
+        .into_iter()
+        .filter_map(|id| self.catalog.get_index_by_id(table_id.schema_id, id))
+        .any(|index| index.column_idxs().contains(&column_id));
+        if indexed {
+            return Err(
+                ErrorKind::CanNotAlterColumn(name, "it is indexed").with_span(column_name.span)
+            );
+        }
+        Ok(column_id)
+    }
+}
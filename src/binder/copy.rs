@@ -1,16 +1,22 @@
 // Copyright 2024 RisingLight Project Authors. Licensed under Apache-2.0.
 
-use std::path::PathBuf;
+use std::fs::File;
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
 
+use ::parquet::file::reader::{FileReader, SerializedFileReader};
 use serde::{Deserialize, Serialize};
 
 use super::*;
+use crate::array::{ArrayBuilderImpl, parquet};
+use crate::types::DataType;
 
 #[derive(Debug, PartialEq, PartialOrd, Ord, Hash, Eq, Clone, Serialize, Deserialize)]
 pub struct ExtSource {
     pub path: PathBuf,
     pub format: FileFormat,
+    /// The names of columns in the file.
+    pub columns: Vec<String>,
 }
 
 /// File format.
@@ -26,6 +32,36 @@ pub enum FileFormat {
         /// Whether or not the file has a header line.
         header: bool,
     },
+    Parquet,
+}
+
+/// A column of an external source.
+///
+/// The source is either a file scanned by a table function, or the working table of a recursive
+/// CTE.
+#[derive(Debug, PartialEq, PartialOrd, Ord, Hash, Eq, Clone, Serialize, Deserialize)]
+pub struct ExtColumn {
+    /// The number of external sources before this one in the query.
+    ///
+    /// This field is used to distinguish the same file in different places.
+    pub source: u32,
+    /// The index of column in the file.
+    pub index: u32,
+    pub name: String,
+    pub ty: DataType,
+}
+
+impl std::fmt::Display for ExtColumn {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{self:?}")
+    }
+}
+
+impl FromStr for Box<ExtColumn> {
+    type Err = ();
+    fn from_str(_s: &str) -> std::result::Result<Self, Self::Err> {
+        Err(())
+    }
 }
 
 impl std::fmt::Display for ExtSource {
@@ -55,13 +91,11 @@ impl Binder {
         target: CopyTarget,
         options: &[CopyOption],
     ) -> Result {
-        let ext_source = self.egraph.add(Node::ExtSource(Box::new(ExtSource {
-            path: match target {
-                CopyTarget::File { filename } => filename.into(),
-                t => todo!("unsupported copy target: {:?}", t),
-            },
-            format: FileFormat::from_options(options),
-        })));
+        let path = match target {
+            CopyTarget::File { filename } => filename.into(),
+            t => return Err(ErrorKind::Todo(format!("copy target: {t}")).into()),
+        };
+        let format = FileFormat::from_options(options)?;
 
         let copy = if to {
             // COPY <source_table> TO <dest_file>
@@ -73,10 +107,18 @@ impl Binder {
                     let (table, _, _) = self.bind_table_id(&table_name)?;
                     let cols = self.bind_table_columns(&table_name, &columns)?;
                     let true_ = self.egraph.add(Node::true_());
-                    self.egraph.add(Node::Scan([table, cols, true_]))
+                    let scan = self.egraph.add(Node::Scan([table, cols, true_]));
+                    (scan, Context::default())
                 }
-                CopySource::Query(query) => self.bind_query(*query)?.0,
+                CopySource::Query(query) => self.bind_query(*query)?,
             };
+            let (query, ctx) = query;
+            let columns = self.column_names(query, &ctx);
+            let ext_source = self.egraph.add(Node::ExtSource(Box::new(ExtSource {
+                path,
+                format,
+                columns,
+            })));
             self.egraph.add(Node::CopyTo([ext_source, query]))
         } else {
             // COPY <dest_table> FROM <source_file>
@@ -100,6 +142,12 @@ impl Binder {
                     return Err(ErrorKind::CopyTo("query".into()).with_spanned(&*query));
                 }
             };
+            let columns = self.column_names(cols, &Context::default());
+            let ext_source = self.egraph.add(Node::ExtSource(Box::new(ExtSource {
+                path,
+                format,
+                columns,
+            })));
             let types = self.type_(cols)?;
             let types = self.egraph.add(Node::Type(types));
             let copy = self.egraph.add(Node::CopyFrom([ext_source, types]));
@@ -110,30 +158,120 @@ impl Binder {
     }
 }
 
+/// The number of CSV records to infer the types of columns.
+const INFER_SAMPLE_ROWS: usize = 1024;
+
+/// The types tried in order to infer a CSV column. The first type that parses all samples wins.
+const INFER_TYPES: &[DataType] = &[
+    DataType::Bool,
+    DataType::Int32,
+    DataType::Int64,
+    DataType::Float64,
+    DataType::Date,
+    DataType::Timestamp,
+];
+
 impl FileFormat {
+    /// Returns the names and types of columns in the file.
+    ///
+    /// For CSV, names come from the header line and types are inferred from the first records.
+    /// For Parquet, both come from the schema in the footer.
+    pub fn infer_schema(&self, path: &Path) -> Result<Vec<(String, DataType)>> {
+        let read_error = |e: &dyn std::fmt::Display| {
+            BindError::from(ErrorKind::ReadFile(
+                path.display().to_string(),
+                e.to_string(),
+            ))
+        };
+        match self {
+            FileFormat::Csv {
+                delimiter,
+                quote,
+                escape,
+                header,
+            } => {
+                let mut reader = csv::ReaderBuilder::new()
+                    .delimiter(*delimiter as u8)
+                    .quote(*quote as u8)
+                    .escape(escape.map(|c| c as u8))
+                    .has_headers(*header)
+                    .from_path(path)
+                    .map_err(|e| read_error(&e))?;
+                let mut names = if *header {
+                    let header = reader.headers().map_err(|e| read_error(&e))?;
+                    header.iter().map(|s| s.to_string()).collect_vec()
+                } else {
+                    vec![]
+                };
+                // the index of candidate type for each column, or none if all values are null
+                let mut candidates = vec![None; names.len()];
+                for record in reader.records().take(INFER_SAMPLE_ROWS) {
+                    let record = record.map_err(|e| read_error(&e))?;
+                    if names.is_empty() {
+                        names = (0..record.len()).map(|i| format!("column{i}")).collect();
+                        candidates = vec![None; names.len()];
+                    }
+                    for (candidate, value) in candidates.iter_mut().zip(record.iter()) {
+                        if value.is_empty() {
+                            continue;
+                        }
+                        let mut i = candidate.unwrap_or(0);
+                        while let Some(ty) = INFER_TYPES.get(i)
+                            && ArrayBuilderImpl::new(ty).push_str(value).is_err()
+                        {
+                            i += 1;
+                        }
+                        *candidate = Some(i);
+                    }
+                }
+                let types = (candidates.into_iter()).map(|i| {
+                    (i.and_then(|i| INFER_TYPES.get(i)).cloned()).unwrap_or(DataType::String)
+                });
+                Ok(names.into_iter().zip(types).collect())
+            }
+            FileFormat::Parquet => {
+                let file = File::open(path).map_err(|e| read_error(&e))?;
+                let reader = SerializedFileReader::new(file).map_err(|e| read_error(&e))?;
+                let schema = reader.metadata().file_metadata().schema_descr();
+                let types = parquet::data_types(schema).map_err(|e| read_error(&e))?;
+                let names = schema.columns().iter().map(|c| c.name().to_string());
+                Ok(names.zip(types).collect())
+            }
+        }
+    }
+
     /// Create from copy options.
-    pub fn from_options(options: &[CopyOption]) -> Self {
+    pub fn from_options(options: &[CopyOption]) -> Result<Self> {
+        let mut parquet = false;
         let mut delimiter = ',';
         let mut quote = '"';
         let mut escape = None;
         let mut header = false;
         for opt in options {
             match opt {
-                CopyOption::Format(fmt) => {
-                    assert_eq!(fmt.value.to_lowercase(), "csv", "only support CSV format")
-                }
+                CopyOption::Format(fmt) => match fmt.value.to_lowercase().as_str() {
+                    "csv" => parquet = false,
+                    "parquet" => parquet = true,
+                    _ => return Err(ErrorKind::Todo(format!("format {fmt}")).into()),
+                },
                 CopyOption::Delimiter(c) => delimiter = *c,
                 CopyOption::Header(b) => header = *b,
                 CopyOption::Quote(c) => quote = *c,
                 CopyOption::Escape(c) => escape = Some(*c),
-                o => panic!("unsupported copy option: {:?}", o),
+                o => return Err(ErrorKind::Todo(format!("copy option {o}")).into()),
             }
         }
-        FileFormat::Csv {
+        if parquet {
+            if let Some(o) = (options.iter()).find(|o| !matches!(o, CopyOption::Format(_))) {
+                return Err(ErrorKind::Todo(format!("copy option {o} for parquet")).into());
+            }
+            return Ok(FileFormat::Parquet);
+        }
+        Ok(FileFormat::Csv {
             delimiter,
             quote,
             escape,
             header,
-        }
+        })
     }
 }
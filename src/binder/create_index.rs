@@ -32,7 +32,15 @@ impl FromStr for VectorDistance {
 
 #[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Serialize, Deserialize)]
 pub enum IndexType {
-    Hnsw,
+    Hnsw {
+        distance: VectorDistance,
+        /// Maximum number of neighbors of a node on each upper layer.
+        m: usize,
+        /// Size of the candidate list when inserting a node.
+        ef_construction: usize,
+        /// Size of the candidate list when searching.
+        ef_search: usize,
+    },
     IvfFlat {
         distance: VectorDistance,
         nlists: usize,
@@ -84,70 +92,57 @@ impl Binder {
         let Some(using) = using else {
             return Err(ErrorKind::InvalidIndex("using clause is required".to_string()).into());
         };
+        let options = parse_with_options(with)?;
+        let invalid_option = |key: &str| {
+            ErrorKind::InvalidIndex(format!("invalid option for {using} index: {key}")).into()
+        };
         match using.to_string().to_lowercase().as_str() {
-            "btree" => Ok(IndexType::Btree),
-            "hnsw" => Ok(IndexType::Hnsw),
-            "ivfflat" => {
-                let mut distfn = None;
-                let mut nlists = None;
-                let mut nprobe = None;
-                for expr in with {
-                    let Expr::BinaryOp { left, op, right } = expr else {
-                        return Err(
-                            ErrorKind::InvalidIndex("invalid with clause".to_string()).into()
-                        );
-                    };
-                    if op != BinaryOperator::Eq {
-                        return Err(
-                            ErrorKind::InvalidIndex("invalid with clause".to_string()).into()
-                        );
+            "btree" => {
+                if let Some((key, _)) = options.first() {
+                    return Err(invalid_option(key));
+                }
+                Ok(IndexType::Btree)
+            }
+            "hnsw" => {
+                let mut distance = VectorDistance::L2;
+                let mut m = 16;
+                let mut ef_construction = 64;
+                let mut ef_search = 40;
+                for (key, value) in options {
+                    match key.as_str() {
+                        "distfn" => distance = parse_distance(&value)?,
+                        "m" => m = parse_positive(&key, &value)?,
+                        "ef_construction" => ef_construction = parse_positive(&key, &value)?,
+                        "ef_search" => ef_search = parse_positive(&key, &value)?,
+                        _ => return Err(invalid_option(&key)),
                     }
-                    let Expr::Identifier(Ident { value: key, .. }) = *left else {
-                        return Err(
-                            ErrorKind::InvalidIndex("invalid with clause".to_string()).into()
-                        );
-                    };
-                    let key = key.to_lowercase();
-                    let Expr::Value(v) = *right else {
-                        return Err(
-                            ErrorKind::InvalidIndex("invalid with clause".to_string()).into()
-                        );
-                    };
-                    let v: DataValue = v.into();
+                }
+                if m < 2 {
+                    return Err(ErrorKind::InvalidIndex("m must be at least 2".into()).into());
+                }
+                Ok(IndexType::Hnsw {
+                    distance,
+                    m,
+                    ef_construction,
+                    ef_search,
+                })
+            }
+            "ivfflat" => {
+                let mut distance = VectorDistance::L2;
+                let mut nlists = 100;
+                let mut nprobe = 10;
+                for (key, value) in options {
                     match key.as_str() {
-                        "distfn" => {
-                            let v = v.as_str();
-                            distfn = Some(v.to_lowercase());
-                        }
-                        "nlists" => {
-                            let Some(v) = v.as_usize().unwrap() else {
-                                return Err(ErrorKind::InvalidIndex(
-                                    "invalid with clause".to_string(),
-                                )
-                                .into());
-                            };
-                            nlists = Some(v);
-                        }
-                        "nprobe" => {
-                            let Some(v) = v.as_usize().unwrap() else {
-                                return Err(ErrorKind::InvalidIndex(
-                                    "invalid with clause".to_string(),
-                                )
-                                .into());
-                            };
-                            nprobe = Some(v);
-                        }
-                        _ => {
-                            return Err(
-                                ErrorKind::InvalidIndex("invalid with clause".to_string()).into()
-                            );
-                        }
+                        "distfn" => distance = parse_distance(&value)?,
+                        "nlists" => nlists = parse_positive(&key, &value)?,
+                        "nprobe" => nprobe = parse_positive(&key, &value)?,
+                        _ => return Err(invalid_option(&key)),
                     }
                 }
                 Ok(IndexType::IvfFlat {
-                    distance: VectorDistance::from_str(distfn.unwrap().as_str()).unwrap(),
-                    nlists: nlists.unwrap(),
-                    nprobe: nprobe.unwrap(),
+                    distance,
+                    nlists,
+                    nprobe,
                 })
             }
             _ => Err(ErrorKind::InvalidIndex("invalid index type".to_string()).into()),
@@ -171,14 +166,12 @@ impl Binder {
         let (_, index_name) = split_name(&index_name)?;
         let table_obj: ObjectName = table_name.clone();
         let table_name = lower_case_name(&table_name);
-        let (schema_name, table_name) = split_name(&table_name)?;
-        let schema = self
-            .catalog
-            .get_schema_by_name(schema_name)
-            .ok_or_else(|| ErrorKind::InvalidSchema(schema_name.into()).with_spanned(&table_obj))?;
-        let Some(table) = schema.get_table_by_name(table_name) else {
+        let Some(table_id) = self.get_table_id_by_name(&table_name)? else {
+            let (_, table_name) = split_name(&table_name)?;
             return Err(ErrorKind::InvalidTable(table_name.into()).with_spanned(&table_obj));
         };
+        let schema = self.catalog.get_schema_by_id(table_id.schema_id).unwrap();
+        let table = schema.get_table_by_id(table_id.table_id).unwrap();
         // Check if every column exists in the table and get the column ids
         let mut column_ids = Vec::new();
         for column in &columns {
@@ -197,13 +190,70 @@ impl Binder {
             column_ids.push(column_catalog.id());
         }
 
+        let index_type = self.parse_index_type(using, with)?;
+        if matches!(
+            index_type,
+            IndexType::Hnsw { .. } | IndexType::IvfFlat { .. }
+        ) {
+            let is_vector = |id: &ColumnId| {
+                let column = table.get_column_by_id(*id).unwrap();
+                matches!(column.data_type(), crate::types::DataType::Vector(_))
+            };
+            if column_ids.len() != 1 || !is_vector(&column_ids[0]) {
+                return Err(ErrorKind::InvalidIndex(
+                    "vector index must be built on a single vector column".to_string(),
+                )
+                .with_spanned(&table_obj));
+            }
+        }
+
         let create = self.egraph.add(Node::CreateIndex(Box::new(CreateIndex {
             schema_id: schema.id(),
             index_name: index_name.into(),
             table_id: table.id(),
             columns: column_ids,
-            index_type: self.parse_index_type(using, with)?,
+            index_type,
         })));
         Ok(create)
     }
 }
+
+/// Parses `WITH (key = value, ...)` into a list of lowercase keys and values.
+fn parse_with_options(with: Vec<Expr>) -> Result<Vec<(String, DataValue)>> {
+    let mut options = vec![];
+    for expr in with {
+        let invalid =
+            || ErrorKind::InvalidIndex("invalid with clause".to_string()).with_spanned(&expr);
+        let Expr::BinaryOp { left, op, right } = &expr else {
+            return Err(invalid());
+        };
+        if *op != BinaryOperator::Eq {
+            return Err(invalid());
+        }
+        let Expr::Identifier(Ident { value: key, .. }) = left.as_ref() else {
+            return Err(invalid());
+        };
+        let Expr::Value(v) = right.as_ref() else {
+            return Err(invalid());
+        };
+        options.push((key.to_lowercase(), v.clone().into()));
+    }
+    Ok(options)
+}
+
+fn parse_distance(value: &DataValue) -> Result<VectorDistance> {
+    let DataValue::String(s) = value else {
+        return Err(ErrorKind::InvalidIndex(format!("invalid distance function: {value}")).into());
+    };
+    VectorDistance::from_str(&s.to_lowercase()).map_err(|e| ErrorKind::InvalidIndex(e).into())
+}
+
+fn parse_positive(key: &str, value: &DataValue) -> Result<usize> {
+    match value.as_usize() {
+        Ok(Some(v)) if v > 0 => Ok(v),
+        _ => Err(ErrorKind::InvalidIndex(format!(
+            "{key} must be a positive integer, but got {value}"
+        ))
+        .into()),
+    }
+}
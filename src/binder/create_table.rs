@@ -54,15 +54,13 @@ impl Binder {
             name,
             columns,
             constraints,
+            temporary,
             ..
         }: crate::parser::CreateTable,
     ) -> Result {
         let name = lower_case_name(&name);
-        let (schema_name, table_name) = split_name(&name)?;
-        let schema = self
-            .catalog
-            .get_schema_by_name(schema_name)
-            .ok_or_else(|| ErrorKind::InvalidSchema(schema_name.into()).with_spanned(&name))?;
+        let (_, table_name) = split_name(&name)?;
+        let schema = self.get_schema_to_create(&name, temporary)?;
         if schema.get_table_by_name(table_name).is_some() {
             return Err(ErrorKind::TableExists(table_name.into()).with_spanned(&name));
         }
@@ -116,11 +114,12 @@ impl Binder {
             .iter()
             .enumerate()
             .map(|(idx, col)| {
-                let mut col = ColumnCatalog::from(col);
-                col.set_id(idx as ColumnId);
-                col
+                let mut catalog = ColumnCatalog::from(col);
+                catalog.set_id(idx as ColumnId);
+                catalog.set_default_value(self.bind_default_value(col)?);
+                Ok::<_, BindError>(catalog)
             })
-            .collect();
+            .try_collect()?;
 
         for &index in &ordered_pk_ids {
             columns[index as usize].set_nullable(false);
@@ -135,6 +134,29 @@ impl Binder {
         Ok(create)
     }
 
+    /// Binds the `DEFAULT` option of a column to a constant of the column type.
+    pub(super) fn bind_default_value(&mut self, column: &ColumnDef) -> Result<Option<DataValue>> {
+        let Some(expr) = column.options.iter().find_map(|opt| match &opt.option {
+            ColumnOption::Default(expr) => Some(expr),
+            _ => None,
+        }) else {
+            return Ok(None);
+        };
+        let id = self.bind_expr(expr.clone())?;
+        let mut egraph = egg::EGraph::<Node, crate::planner::ExprAnalysis>::default();
+        let id = egraph.add_expr(&self.recexpr(id));
+        let Some(value) = egraph[id].data.constant.clone() else {
+            return Err(ErrorKind::InvalidExpression(format!(
+                "default value must be a constant: {expr}"
+            ))
+            .with_spanned(expr));
+        };
+        let ty = crate::types::DataType::from(&column.data_type);
+        let value = (value.cast(&ty))
+            .map_err(|_| ErrorKind::CastError(value.clone(), ty).with_spanned(expr))?;
+        Ok(Some(value).filter(|v| !v.is_null()))
+    }
+
     /// get primary keys' id in declared order。
     /// we use index in columns vector as column id
     fn ordered_pks_from_columns(columns: &[ColumnDef]) -> Vec<ColumnId> {
@@ -178,6 +200,8 @@ impl From<&ColumnDef> for ColumnCatalog {
                 ColumnOption::Null => is_nullable = true,
                 ColumnOption::NotNull => is_nullable = false,
                 ColumnOption::Unique { is_primary: p, .. } => is_primary = p,
+                // bound by `Binder::bind_default_value`
+                ColumnOption::Default(_) => {}
                 _ => todo!("column options"),
             }
         }
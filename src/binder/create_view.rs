@@ -10,13 +10,11 @@ impl Binder {
         name: ObjectName,
         columns: Vec<ViewColumnDef>,
         query: Query,
+        temporary: bool,
     ) -> Result {
         let name = lower_case_name(&name);
-        let (schema_name, table_name) = split_name(&name)?;
-        let schema = self
-            .catalog
-            .get_schema_by_name(schema_name)
-            .ok_or_else(|| ErrorKind::InvalidSchema(schema_name.into()).with_spanned(&name))?;
+        let (_, table_name) = split_name(&name)?;
+        let schema = self.get_schema_to_create(&name, temporary)?;
         if schema.get_table_by_name(table_name).is_some() {
             return Err(ErrorKind::TableExists(table_name.into()).with_spanned(&name));
         }
@@ -19,8 +19,8 @@ impl Binder {
         let mut table_ids = Vec::with_capacity(names.len());
         for name in names {
             let name = lower_case_name(&name);
-            let (schema_name, table_name) = split_name(&name)?;
-            let result = self.catalog.get_table_id_by_name(schema_name, table_name);
+            let (_, table_name) = split_name(&name)?;
+            let result = self.get_table_id_by_name(&name)?;
             if if_exists && result.is_none() {
                 continue;
             }
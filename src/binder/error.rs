@@ -67,10 +67,18 @@ pub enum ErrorKind {
     DuplicatedCteName(String),
     #[error("table {0:?} has {1} columns available but {2} columns specified")]
     ColumnCountMismatch(String, usize, usize),
+    #[error("each {0} query must have the same number of columns")]
+    SetOpColumnCountMismatch(String),
+    #[error("{0} types {1} and {2} cannot be matched")]
+    SetOpTypeMismatch(String, crate::types::DataType, crate::types::DataType),
     #[error("invalid expression {0}")]
     InvalidExpression(String),
     #[error("not nullable column {0:?}")]
     NotNullableColumn(String),
+    #[error("column {0:?} specified in USING clause does not exist in {1} table")]
+    InvalidUsingColumn(String, &'static str),
+    #[error("the combining JOIN type must be INNER or LEFT for a LATERAL reference")]
+    InvalidLateralJoin,
     #[error("ambiguous column {0:?} (use {1})")]
     AmbiguousColumn(String, String),
     #[error("invalid table name {0:?}")]
@@ -91,12 +99,22 @@ pub enum ErrorKind {
     AggInWhere,
     #[error("GROUP BY clause cannot contain aggregates")]
     AggInGroupBy,
+    #[error("arguments to GROUPING must be grouping expressions")]
+    GroupingNotInGroupBy,
     #[error("window function calls cannot be nested")]
     NestedWindow,
+    #[error("window function {0:?} requires an OVER clause")]
+    WindowFunctionWithoutOver(String),
+    #[error("invalid window frame: {0}")]
+    InvalidWindowFrame(String),
+    #[error("UPDATE cannot contain aggregates")]
+    AggInUpdate,
     #[error("WHERE clause cannot contain window functions")]
     WindowInWhere,
     #[error("HAVING clause cannot contain window functions")]
     WindowInHaving,
+    #[error("UPDATE cannot contain window functions")]
+    WindowInUpdate,
     #[error("column {0:?} must appear in the GROUP BY clause or be used in an aggregate function")]
     ColumnNotInAgg(String),
     #[error("ORDER BY items must appear in the select list if DISTINCT is specified")]
@@ -107,16 +125,40 @@ pub enum ErrorKind {
     UnsupportedObjectName(ObjectType),
     #[error("not supported yet: {0}")]
     Todo(String),
+    #[error("invalid table function {0:?}")]
+    InvalidTableFunction(String),
+    #[error("failed to read {0:?}: {1}")]
+    ReadFile(String, String),
     #[error("can not copy to {0}")]
     CopyTo(String),
     #[error("can only insert into table")]
     CanNotInsert,
     #[error("can only delete from table")]
     CanNotDelete,
+    #[error("can only update table")]
+    CanNotUpdate,
+    #[error("can only alter table")]
+    CanNotAlter,
+    #[error("cannot alter column {0:?} because {1}")]
+    CanNotAlterColumn(String, &'static str),
+    #[error("cannot change type of column {0:?} from {1} to {2}")]
+    IncompatibleColumnType(String, crate::types::DataType, crate::types::DataType),
+    #[error("multiple assignments to same column {0:?}")]
+    DuplicatedAssignment(String),
     #[error("VIEW aliases mismatch query result")]
     ViewAliasesMismatch,
     #[error("pragma does not exist: {0}")]
     NoPragma(String),
+    #[error("invalid parameter {0:?}")]
+    InvalidParameter(String),
+    #[error("no value for parameter {0:?}")]
+    NoParameterValue(String),
+    #[error("invalid role {0:?}")]
+    InvalidUser(String),
+    #[error("role {0:?} already exists")]
+    UserExists(String),
+    #[error("role {0:?} must have a password")]
+    NoPassword(String),
 }
 
 impl ErrorKind {
@@ -8,7 +8,7 @@ use sqlparser::tokenizer::Span;
 use super::*;
 use crate::parser::{
     self, BinaryOperator, DataType, DateTimeField, Expr, Function, FunctionArg, FunctionArgExpr,
-    UnaryOperator, Value,
+    UnaryOperator, Value, WindowFrame, WindowFrameBound, WindowFrameUnits,
 };
 use crate::types::{DataValue, Interval};
 
@@ -17,15 +17,12 @@ impl Binder {
     pub fn bind_expr(&mut self, expr: Expr) -> Result {
         let id = match expr {
             Expr::Value(v) => {
-                // This is okay since only sql udf relies on
-                // parameter-like (i.e., `$1`) values at present
-                // TODO: consider formally `bind_parameter` in the future
-                // e.g., lambda function support, etc.
+                // parameters of sql udf take precedence over those of prepared statements
                 if let Value::Placeholder(key) = &v {
-                    self.udf_context
-                        .get_expr(key)
-                        .cloned()
-                        .ok_or_else(|| ErrorKind::InvalidSQL.with_spanned(&v))
+                    match self.udf_context.get_expr(key) {
+                        Some(id) => Ok(*id),
+                        None => self.bind_parameter(&v),
+                    }
                 } else {
                     Ok(self.egraph.add(Node::Constant(v.into())))
                 }
@@ -121,11 +118,76 @@ impl Binder {
         self.find_alias(column_ident, table_ident)
     }
 
+    /// Bind a parameter of prepared statement like `$1`.
+    ///
+    /// The parameter is bound to its value if given, or a null of its type otherwise.
+    fn bind_parameter(&mut self, value: &Value) -> Result {
+        let index = self
+            .param_index(&Expr::Value(value.clone()))
+            .ok_or_else(|| ErrorKind::InvalidParameter(value.to_string()).with_spanned(value))?;
+        if self.param_types.len() <= index {
+            self.param_types.resize(index + 1, None);
+        }
+        let value = if self.params.is_empty() {
+            DataValue::Null
+        } else {
+            (self.params.get(index).cloned())
+                .ok_or_else(|| ErrorKind::NoParameterValue(value.to_string()).with_spanned(value))?
+        };
+        let is_null = value.is_null();
+        let id = self.egraph.add(Node::Constant(value));
+        match &self.param_types[index] {
+            Some(ty) if is_null => {
+                let ty = self.egraph.add(Node::Type(ty.clone()));
+                Ok(self.egraph.add(Node::Cast([ty, id])))
+            }
+            _ => Ok(id),
+        }
+    }
+
+    /// Returns the 0-based index if the expression is a parameter of prepared statement.
+    fn param_index(&self, expr: &Expr) -> Option<usize> {
+        let Expr::Value(Value::Placeholder(key)) = expr else {
+            return None;
+        };
+        if self.udf_context.get_expr(key).is_some() {
+            return None;
+        }
+        let n: usize = key.strip_prefix('$')?.parse().ok()?;
+        n.checked_sub(1)
+    }
+
+    /// Set the type of parameter if the expression is a parameter whose type is unknown.
+    pub(super) fn infer_param_type(&mut self, expr: &Expr, ty: crate::types::DataType) {
+        let Some(index) = self.param_index(expr) else {
+            return;
+        };
+        if ty.is_null() {
+            return;
+        }
+        if self.param_types.len() <= index {
+            self.param_types.resize(index + 1, None);
+        }
+        self.param_types[index].get_or_insert(ty);
+    }
+
     fn bind_binary_op(&mut self, left: Expr, op: BinaryOperator, right: Expr) -> Result {
         use BinaryOperator::*;
 
-        let l = self.bind_expr(left)?;
-        let r = self.bind_expr(right)?;
+        // bind the other side first so that the parameter can take its type
+        let (l, r) = if self.param_index(&left).is_some() {
+            let r = self.bind_expr(right)?;
+            if let Ok(ty) = self.type_(r) {
+                self.infer_param_type(&left, ty);
+            }
+            (self.bind_expr(left)?, r)
+        } else {
+            let l = self.bind_expr(left)?;
+            if let Ok(ty) = self.type_(l) {
+                self.infer_param_type(&right, ty);
+            }
+            (l, self.bind_expr(right)?)
+        };
         let node = match op {
             Plus => Node::Add([l, r]),
             Minus => Node::Sub([l, r]),
@@ -165,7 +227,6 @@ impl Binder {
     }
 
     fn bind_cast(&mut self, expr: Expr, mut ty: DataType) -> Result {
-        let expr = self.bind_expr(expr)?;
         // workaround for 'BLOB'
         if let DataType::Custom(name, _modifiers) = &ty
             && name.0.len() == 1
@@ -173,7 +234,10 @@ impl Binder {
         {
             ty = DataType::Blob(None);
         }
-        let ty = self.egraph.add(Node::Type((&ty).into()));
+        let ty = crate::types::DataType::from(&ty);
+        self.infer_param_type(&expr, ty.clone());
+        let expr = self.bind_expr(expr)?;
+        let ty = self.egraph.add(Node::Type(ty));
         Ok(self.egraph.add(Node::Cast([ty, expr])))
     }
 
@@ -433,7 +497,8 @@ impl Binder {
             return Ok(bind_result);
         }
 
-        let node = match func.name.to_string().to_lowercase().as_str() {
+        let name = func.name.to_string().to_lowercase();
+        let node = match name.as_str() {
             "count" if args.is_empty() => Node::RowCount,
             "count" if distinct => Node::CountDistinct(args[0]),
             "count" => Node::Count(args[0]),
@@ -441,17 +506,46 @@ impl Binder {
             "min" => Node::Min(args[0]),
             "sum" => Node::Sum(args[0]),
             "avg" => {
-                let sum = self.egraph.add(Node::Sum(args[0]));
-                let count = self.egraph.add(Node::Count(args[0]));
-                Node::Div([sum, count])
+                let mut sum = self.egraph.add(Node::Sum(args[0]));
+                let mut count = self.egraph.add(Node::Count(args[0]));
+                if let Some(window) = func.over {
+                    // `avg(a) over w` => `sum(a) over w / count(a) over w`
+                    sum = self.bind_window_function(sum, window.clone(), &func.name)?;
+                    count = self.bind_window_function(count, window, &func.name)?;
+                }
+                return Ok(self.egraph.add(Node::Div([sum, count])));
             }
             "first" => Node::First(args[0]),
             "last" => Node::Last(args[0]),
             "replace" => Node::Replace([args[0], args[1], args[2]]),
             "repeat" => Node::Repeat([args[0], args[1]]),
+            "grouping" => Node::Grouping(self.egraph.add(Node::List(args.into()))),
             "row_number" => Node::RowNumber,
+            "rank" => Node::Rank,
+            "dense_rank" => Node::DenseRank,
+            "ntile" => Node::Ntile(args[0]),
+            "lag" | "lead" => {
+                let offset = match args.get(1) {
+                    Some(offset) => *offset,
+                    None => self.egraph.add(Node::Constant(DataValue::Int32(1))),
+                };
+                let default = match args.get(2) {
+                    Some(default) => *default,
+                    None => self.egraph.add(Node::null()),
+                };
+                let (expr, default) = self.implicit_type_cast(args[0], default)?;
+                match name.as_str() {
+                    "lag" => Node::Lag([expr, offset, default]),
+                    _ => Node::Lead([expr, offset, default]),
+                }
+            }
+            "first_value" => Node::FirstValue(args[0]),
+            "last_value" => Node::LastValue(args[0]),
             name => todo!("Unsupported function: {}", name),
         };
+        if func.over.is_none() && node.is_window_function() && !node.is_aggregate_function() {
+            return Err(ErrorKind::WindowFunctionWithoutOver(name).with_spanned(&func.name));
+        }
         let mut id = self.egraph.add(node);
         if let Some(window) = func.over {
             id = self.bind_window_function(id, window, &func.name)?;
@@ -474,14 +568,110 @@ impl Binder {
         }
         let partitionby = self.bind_exprs(window.partition_by)?;
         let orderby = self.bind_orderby(window.order_by)?;
-        if window.window_frame.is_some() {
-            todo!("support window frame");
+        let frame = self.bind_window_frame(window.window_frame, orderby)?;
+        Ok(self
+            .egraph
+            .add(Node::Over([func, partitionby, orderby, frame])))
+    }
+
+    /// Binds a window frame. Returns a [`Rows`](Node::Rows) or [`Range`](Node::Range) node.
+    ///
+    /// The default frame is `RANGE BETWEEN UNBOUNDED PRECEDING AND CURRENT ROW`, which covers
+    /// the whole partition if there is no ORDER BY.
+    fn bind_window_frame(&mut self, frame: Option<WindowFrame>, orderby: Id) -> Result {
+        let Some(frame) = frame else {
+            let unbounded = self.egraph.add(Node::Unbounded);
+            let start = self.egraph.add(Node::Preceding(unbounded));
+            let end = self.egraph.add(Node::CurrentRow);
+            return Ok(self.egraph.add(Node::Range([start, end])));
+        };
+        let rows = match frame.units {
+            WindowFrameUnits::Rows => true,
+            WindowFrameUnits::Range => false,
+            WindowFrameUnits::Groups => return Err(ErrorKind::Todo("GROUPS frame".into()).into()),
+        };
+        let end_bound = frame.end_bound.unwrap_or(WindowFrameBound::CurrentRow);
+        if let WindowFrameBound::Following(None) = frame.start_bound {
+            return Err(ErrorKind::InvalidWindowFrame(
+                "frame start cannot be UNBOUNDED FOLLOWING".into(),
+            )
+            .into());
+        }
+        if let WindowFrameBound::Preceding(None) = end_bound {
+            return Err(ErrorKind::InvalidWindowFrame(
+                "frame end cannot be UNBOUNDED PRECEDING".into(),
+            )
+            .into());
+        }
+        let start = self.bind_frame_bound(frame.start_bound, rows, orderby)?;
+        let end = self.bind_frame_bound(end_bound, rows, orderby)?;
+        Ok(self.egraph.add(match rows {
+            true => Node::Rows([start, end]),
+            false => Node::Range([start, end]),
+        }))
+    }
+
+    /// Binds a bound of window frame.
+    fn bind_frame_bound(&mut self, bound: WindowFrameBound, rows: bool, orderby: Id) -> Result {
+        let (offset, preceding) = match bound {
+            WindowFrameBound::CurrentRow => return Ok(self.egraph.add(Node::CurrentRow)),
+            WindowFrameBound::Preceding(offset) => (offset, true),
+            WindowFrameBound::Following(offset) => (offset, false),
+        };
+        let offset = match offset {
+            None => self.egraph.add(Node::Unbounded),
+            Some(offset) => self.bind_frame_offset(*offset, rows, orderby)?,
+        };
+        Ok(self.egraph.add(match preceding {
+            true => Node::Preceding(offset),
+            false => Node::Following(offset),
+        }))
+    }
+
+    /// Binds the offset of a frame bound. Returns a non-negative constant.
+    ///
+    /// The offset of `ROWS` frame is a number of rows.
+    /// The offset of `RANGE` frame is casted to the type of the only order key.
+    fn bind_frame_offset(&mut self, expr: Expr, rows: bool, orderby: Id) -> Result {
+        let id = self.bind_expr(expr.clone())?;
+        let invalid = |reason: &str| {
+            ErrorKind::InvalidWindowFrame(format!("{reason}: {expr}")).with_spanned(&expr)
+        };
+        let Node::Constant(value) = self.node(id).clone() else {
+            return Err(invalid("offset must be a non-negative constant"));
+        };
+        if rows {
+            if !matches!(value.as_usize(), Ok(Some(_))) {
+                return Err(invalid("offset must be a non-negative constant"));
+            }
+            return Ok(self.egraph.add(Node::Constant(value)));
         }
-        Ok(self.egraph.add(Node::Over([func, partitionby, orderby])))
+        let &[key] = self.node(orderby).as_list() else {
+            return Err(invalid(
+                "RANGE with offset requires exactly one ORDER BY column",
+            ));
+        };
+        let key = match self.node(key) {
+            Node::Desc(key) => *key,
+            _ => key,
+        };
+        let offset = match self.type_(key)? {
+            ty if ty.is_number() => {
+                let offset = value.cast(&ty).map_err(|_| invalid("invalid offset"))?;
+                let zero = DataValue::Int32(0).cast(&ty).unwrap();
+                if offset.is_null() || offset < zero {
+                    return Err(invalid("offset must be a non-negative constant"));
+                }
+                offset
+            }
+            crate::types::DataType::Date if matches!(value, DataValue::Interval(_)) => value,
+            _ => return Err(invalid("invalid offset for the ORDER BY column")),
+        };
+        Ok(self.egraph.add(Node::Constant(offset)))
     }
 
     /// Add optional type cast to the expressions to make them return the same type.
-    fn implicit_type_cast(&mut self, mut id1: Id, mut id2: Id) -> Result<(Id, Id)> {
+    pub(super) fn implicit_type_cast(&mut self, mut id1: Id, mut id2: Id) -> Result<(Id, Id)> {
         let ty1 = self.type_(id1)?;
         let ty2 = self.type_(id2)?;
         if let Some(compatible_type) = ty1.union(&ty2) {
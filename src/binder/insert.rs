@@ -12,6 +12,15 @@ impl Binder {
             return Err(ErrorKind::CanNotInsert.with_spanned(&insert.table_name));
         }
         let cols = self.bind_table_columns(&insert.table_name, &insert.columns)?;
+        // parameters in VALUES take the types of their columns
+        if let SetExpr::Values(values) = &*source.body {
+            let columns = self.node(cols).as_list().to_vec();
+            for row in &values.rows {
+                for (expr, column) in row.iter().zip(&columns) {
+                    self.infer_param_type(expr, self.type_(*column)?);
+                }
+            }
+        }
         let source = self.bind_query(*source)?.0;
         let id = self.egraph.add(Node::Insert([table, cols, source]));
         Ok(id)
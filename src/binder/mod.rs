@@ -8,13 +8,13 @@ use std::vec::Vec;
 use egg::{Id, Language};
 use itertools::Itertools;
 
-use crate::array;
 use crate::catalog::function::FunctionCatalog;
-use crate::catalog::{RootCatalog, RootCatalogRef, TableRefId};
+use crate::catalog::{RootCatalog, RootCatalogRef, SchemaId, TableRefId};
 use crate::parser::*;
 use crate::planner::{Expr as Node, RecExpr, TypeSchemaAnalysis};
 use crate::types::DataValue;
 
+mod alter_table;
 pub mod copy;
 mod create_function;
 mod create_index;
@@ -27,7 +27,10 @@ mod expr;
 mod insert;
 mod select;
 mod table;
+mod update;
+mod user;
 
+pub use self::alter_table::{AlterTable, AlterTableOperation};
 pub use self::create_function::CreateFunction;
 pub use self::create_index::{CreateIndex, IndexType, VectorDistance};
 pub use self::create_table::CreateTable;
@@ -44,8 +47,23 @@ pub struct Binder {
     contexts: Vec<Context>,
     /// The number of occurrences of each table in the query.
     table_occurrences: HashMap<TableRefId, u32>,
+    /// The number of external sources scanned by table functions in the query.
+    ext_sources: u32,
     /// The context used in sql udf binding
     udf_context: UdfContext,
+    /// The values of parameters (`$1`, `$2`, ...) in a prepared statement.
+    ///
+    /// If empty, parameters are bound to nulls of their types, which is enough to describe the
+    /// statement.
+    params: Vec<DataValue>,
+    /// The types of parameters, either given by the client or inferred from the context.
+    param_types: Vec<Option<crate::types::DataType>>,
+    /// The names and types of output columns if the statement returns rows.
+    output_columns: Vec<(String, crate::types::DataType)>,
+    /// The schema of temporary objects in the session.
+    ///
+    /// Unqualified names refer to the objects in this schema first.
+    temp_schema: Option<SchemaId>,
 }
 
 #[derive(Clone, Debug, Default)]
@@ -170,21 +188,19 @@ impl UdfContext {
     }
 }
 
-pub fn bind_header(mut chunk: array::Chunk, stmt: &Statement) -> array::Chunk {
+/// Returns the header of the output of a statement, which tells the shell how to print it.
+pub fn output_header(stmt: &Statement) -> Option<Vec<String>> {
     let header_values = match stmt {
         Statement::CreateTable { .. } => vec!["$create".to_string()],
         Statement::Drop { .. } => vec!["$drop".to_string()],
         Statement::Insert { .. } => vec!["$insert.row_counts".to_string()],
         Statement::Explain { .. } => vec!["$explain".to_string()],
         Statement::Delete { .. } => vec!["$delete.row_counts".to_string()],
+        Statement::Update { .. } => vec!["$update.row_counts".to_string()],
         _ => Vec::new(),
     };
 
-    if !header_values.is_empty() {
-        chunk.set_header(header_values);
-    }
-
-    chunk
+    (!header_values.is_empty()).then_some(header_values)
 }
 
 /// A set of current accessible table and column aliases.
@@ -204,6 +220,11 @@ struct Context {
     /// Column aliases that can be accessed from the outside query.
     /// `column_alias` -> id
     output_aliases: HashMap<String, Id>,
+    /// Columns merged by `USING` or `NATURAL` joins, which are referred without table name.
+    /// `column_name` -> id
+    using_columns: HashMap<String, Id>,
+    /// Columns expanded by `*` if the FROM clause has `USING` or `NATURAL` joins.
+    from_columns: Option<Vec<Id>>,
 }
 
 impl Binder {
@@ -214,10 +235,50 @@ impl Binder {
             egraph: egg::EGraph::new(TypeSchemaAnalysis { catalog }),
             contexts: vec![Context::default()],
             table_occurrences: HashMap::new(),
+            ext_sources: 0,
             udf_context: UdfContext::new(),
+            params: vec![],
+            param_types: vec![],
+            output_columns: vec![],
+            temp_schema: None,
+        }
+    }
+
+    /// Create a new binder for a prepared statement.
+    ///
+    /// `params` are the values of parameters, and `param_types` are the types specified by the
+    /// client, where `None` means the type should be inferred.
+    pub fn with_params(
+        catalog: Arc<RootCatalog>,
+        params: Vec<DataValue>,
+        param_types: Vec<Option<crate::types::DataType>>,
+    ) -> Self {
+        Binder {
+            params,
+            param_types,
+            ..Self::new(catalog)
         }
     }
 
+    /// Sets the schema of temporary objects in the session.
+    pub fn set_temp_schema(&mut self, schema_id: Option<SchemaId>) {
+        self.temp_schema = schema_id;
+    }
+
+    /// Returns the types of parameters after binding.
+    ///
+    /// Parameters whose types can not be inferred are treated as strings.
+    pub fn param_types(&self) -> Vec<crate::types::DataType> {
+        (self.param_types.iter())
+            .map(|ty| ty.clone().unwrap_or(crate::types::DataType::String))
+            .collect()
+    }
+
+    /// Returns the names and types of output columns after binding a statement that returns rows.
+    pub fn output_columns(&self) -> &[(String, crate::types::DataType)] {
+        &self.output_columns
+    }
+
     /// Bind a statement.
     pub fn bind(&mut self, stmt: Statement) -> Result<RecExpr> {
         let id = self.bind_stmt(stmt)?;
@@ -228,17 +289,36 @@ impl Binder {
 
     fn bind_stmt(&mut self, stmt: Statement) -> Result {
         match stmt {
+            Statement::AlterTable {
+                name,
+                if_exists,
+                operations,
+                ..
+            } => self.bind_alter_table(name, if_exists, operations),
             Statement::CreateIndex(create_index) => self.bind_create_index(create_index),
             Statement::CreateTable(create_table) => self.bind_create_table(create_table),
             Statement::CreateView {
                 name,
                 columns,
                 query,
+                temporary,
                 ..
-            } => self.bind_create_view(name, columns, *query),
+            } => self.bind_create_view(name, columns, *query, temporary),
             Statement::CreateFunction(create_function) => {
                 self.bind_create_function(create_function)
             }
+            Statement::CreateRole {
+                names,
+                login,
+                password,
+                ..
+            } => self.bind_create_user(names, login, password),
+            Statement::Drop {
+                object_type: ObjectType::Role,
+                if_exists,
+                names,
+                ..
+            } => self.bind_drop_user(if_exists, names),
             Statement::Drop {
                 object_type,
                 if_exists,
@@ -248,6 +328,13 @@ impl Binder {
             } => self.bind_drop(object_type, if_exists, names, cascade),
             Statement::Insert(insert) => self.bind_insert(insert),
             Statement::Delete(delete) => self.bind_delete(delete),
+            Statement::Update {
+                table,
+                assignments,
+                from,
+                selection,
+                ..
+            } => self.bind_update(table, assignments, from, selection),
             Statement::Copy {
                 source,
                 to,
@@ -255,7 +342,13 @@ impl Binder {
                 options,
                 ..
             } => self.bind_copy(source, to, target, &options),
-            Statement::Query(query) => self.bind_query(*query).map(|(id, _)| id),
+            Statement::Query(query) => {
+                let (id, ctx) = self.bind_query(*query)?;
+                let names = self.column_names(id, &ctx);
+                let types = self.type_(id)?.as_struct().to_vec();
+                self.output_columns = names.into_iter().zip(types).collect();
+                Ok(id)
+            }
             Statement::Explain {
                 statement, analyze, ..
             } => self.bind_explain(*statement, analyze),
@@ -263,9 +356,22 @@ impl Binder {
             Statement::SetVariable {
                 variables, value, ..
             } => self.bind_set(variables.as_ref(), value),
-            Statement::ShowVariable { .. }
-            | Statement::ShowCreate { .. }
-            | Statement::ShowColumns { .. } => Err(ErrorKind::NotSupportedTSQL.into()),
+            Statement::StartTransaction { .. } => self.bind_transaction("begin"),
+            Statement::Commit { chain: false } => self.bind_transaction("commit"),
+            Statement::Rollback {
+                chain: false,
+                savepoint: None,
+            } => self.bind_transaction("rollback"),
+            Statement::Commit { .. }
+            | Statement::Rollback { .. }
+            | Statement::Savepoint { .. }
+            | Statement::ReleaseSavepoint { .. } => {
+                Err(ErrorKind::Todo("savepoints and chained transactions".into()).into())
+            }
+            Statement::ShowVariable { variable } => self.bind_show(variable),
+            Statement::ShowCreate { .. } | Statement::ShowColumns { .. } => {
+                Err(ErrorKind::NotSupportedTSQL.into())
+            }
             _ => Err(ErrorKind::InvalidSQL.into()),
         }
     }
@@ -318,6 +424,11 @@ impl Binder {
     /// Find an alias.
     fn find_alias(&self, column_ident: &Ident, table_ident: Option<&Ident>) -> Result {
         for context in self.contexts.iter().rev() {
+            if table_ident.is_none()
+                && let Some(id) = context.using_columns.get(&column_ident.value)
+            {
+                return Ok(*id);
+            }
             if let Some(map) = context.column_aliases.get(&column_ident.value) {
                 if let Some(table_ident) = table_ident {
                     if let Some(id) = map.get(&table_ident.value) {
@@ -346,6 +457,27 @@ impl Binder {
             .find_map(|ctx| ctx.ctes.get(cte_name))
     }
 
+    /// Returns the names of output columns of a query.
+    ///
+    /// Columns are named by their aliases or referenced columns, or `?column?` otherwise.
+    fn column_names(&self, query: Id, ctx: &Context) -> Vec<String> {
+        (self.schema(query).into_iter())
+            .map(|id| {
+                if let Some((name, _)) = ctx.output_aliases.iter().find(|(_, v)| **v == id) {
+                    return name.clone();
+                }
+                match self.node(id) {
+                    Node::Column(cid) => match self.catalog.get_column(cid) {
+                        Some(column) => column.name().to_string(),
+                        None => "?column?".into(),
+                    },
+                    Node::ExtColumn(column) => column.name.clone(),
+                    _ => "?column?".into(),
+                }
+            })
+            .collect()
+    }
+
     fn type_(&self, id: Id) -> Result<crate::types::DataType> {
         Ok(self.egraph[id].data.type_.clone()?)
     }
@@ -366,7 +498,6 @@ impl Binder {
         &self.egraph[id].nodes[0]
     }
 
-    #[allow(dead_code)]
     fn recexpr(&self, id: Id) -> RecExpr {
         self.node(id).build_recexpr(|id| self.node(id).clone())
     }
@@ -374,7 +505,7 @@ impl Binder {
     /// Wrap the node with `Ref` if it is not a column unit.
     fn wrap_ref(&mut self, id: Id) -> Id {
         match self.node(id) {
-            Node::Column(_) | Node::Ref(_) => id,
+            Node::Column(_) | Node::ExtColumn(_) | Node::Ref(_) => id,
             _ => self.egraph.add(Node::Ref(id)),
         }
     }
@@ -389,6 +520,7 @@ impl Binder {
 
     fn bind_explain(&mut self, query: Statement, analyze: bool) -> Result {
         let id = self.bind_stmt(query)?;
+        self.output_columns = vec![("QUERY PLAN".into(), crate::types::DataType::String)];
         let id = self.egraph.add(match analyze {
             false => Node::Explain(id),
             true => Node::Analyze(id),
@@ -414,13 +546,85 @@ impl Binder {
         if variables.len() != 1 || values.len() != 1 {
             return Err(ErrorKind::InvalidSQL.into());
         }
-        let name_id = self
-            .egraph
-            .add(Node::Constant(variables[0].to_string().into()));
-        let value_id = self.bind_expr(values.into_iter().next().unwrap())?;
+        let name = variables[0].to_string().to_lowercase();
+        let name_id = self.egraph.add(Node::Constant(name.into()));
+        let value = values.into_iter().next().unwrap();
+        // `SET name TO DEFAULT` and `RESET name`
+        if matches!(&value, Expr::Identifier(ident) if ident.value.eq_ignore_ascii_case("default"))
+        {
+            return Ok(self.egraph.add(Node::Reset(name_id)));
+        }
+        let value_id = self.bind_expr(value)?;
         let id = self.egraph.add(Node::Set([name_id, value_id]));
         Ok(id)
     }
+
+    /// Binds `SHOW name` or `SHOW ALL`.
+    fn bind_show(&mut self, variable: Vec<Ident>) -> Result {
+        let name = (variable.iter().map(|ident| ident.value.as_str()))
+            .collect::<Vec<_>>()
+            .join(" ")
+            .to_lowercase();
+        self.output_columns = if name == "all" {
+            vec![
+                ("name".into(), crate::types::DataType::String),
+                ("setting".into(), crate::types::DataType::String),
+            ]
+        } else {
+            vec![(name.clone(), crate::types::DataType::String)]
+        };
+        let name_id = self.egraph.add(Node::Constant(name.into()));
+        let id = self.egraph.add(Node::Show(name_id));
+        Ok(id)
+    }
+
+    /// Returns the id of a table or view.
+    ///
+    /// Unqualified names refer to the temporary objects of the session first.
+    fn get_table_id_by_name(&self, name: &ObjectName) -> Result<Option<TableRefId>> {
+        if let [table] = name.0.as_slice()
+            && let Some(schema) = self
+                .temp_schema
+                .and_then(|id| self.catalog.get_schema_by_id(id))
+            && let Some(table_id) = schema.get_table_id_by_name(&table.value)
+        {
+            return Ok(Some(TableRefId::new(schema.id(), table_id)));
+        }
+        let (schema_name, table_name) = split_name(name)?;
+        Ok(self.catalog.get_table_id_by_name(schema_name, table_name))
+    }
+
+    /// Returns the schema to create a table or view in.
+    ///
+    /// Temporary objects are created in the temporary schema of the session, and their names
+    /// can not be qualified.
+    fn get_schema_to_create(
+        &self,
+        name: &ObjectName,
+        temporary: bool,
+    ) -> Result<crate::catalog::SchemaCatalog> {
+        let (schema_name, _) = split_name(name)?;
+        if !temporary {
+            return (self.catalog.get_schema_by_name(schema_name))
+                .ok_or_else(|| ErrorKind::InvalidSchema(schema_name.into()).with_spanned(name));
+        }
+        if name.0.len() > 1 {
+            return Err(
+                ErrorKind::Todo("qualified name of temporary object".into()).with_spanned(name)
+            );
+        }
+        (self
+            .temp_schema
+            .and_then(|id| self.catalog.get_schema_by_id(id)))
+        .ok_or_else(|| ErrorKind::Todo("temporary object outside a session".into()).into())
+    }
+
+    /// Binds `BEGIN`, `COMMIT` or `ROLLBACK`.
+    fn bind_transaction(&mut self, command: &str) -> Result {
+        let command_id = self.egraph.add(Node::Constant(command.to_string().into()));
+        let id = self.egraph.add(Node::Transaction(command_id));
+        Ok(id)
+    }
 }
 
 /// Split an object name into `(schema name, table name)`.
@@ -2,8 +2,10 @@
 
 use sqlparser::tokenizer::Span;
 
+use super::copy::ExtColumn;
 use super::*;
-use crate::parser::{Expr, Query, SelectItem, SetExpr};
+use crate::parser::{Expr, Query, SelectItem, SetExpr, SetOperator, SetQuantifier};
+use crate::types::DataType;
 
 impl Binder {
     /// Binds a query in a new sub-context.
@@ -17,16 +19,26 @@ impl Binder {
     /// Binds a query in the current context.
     pub(super) fn bind_query_internal(&mut self, query: Query) -> Result {
         if let Some(with) = query.with {
-            if with.recursive {
-                return Err(ErrorKind::Todo("recursive CTE".into()).with_spanned(&with));
-            }
             for cte in with.cte_tables {
-                self.bind_cte(cte)?;
+                self.bind_cte(cte, with.recursive)?;
             }
         }
         let child = match *query.body {
             SetExpr::Select(select) => self.bind_select(*select, query.order_by)?,
             SetExpr::Values(values) => self.bind_values(values)?,
+            SetExpr::SetOperation {
+                op,
+                set_quantifier,
+                left,
+                right,
+            } => {
+                let plan = self.bind_set_operation(op, set_quantifier, *left, *right)?;
+                let orderby = match query.order_by {
+                    Some(order_by) => self.bind_orderby(order_by.exprs)?,
+                    None => self.egraph.add(Node::List([].into())),
+                };
+                self.egraph.add(Node::Order([orderby, plan]))
+            }
             body => return Err(ErrorKind::Todo("unknown set expr".into()).with_spanned(&body)),
         };
         let limit = match query.limit {
@@ -43,7 +55,19 @@ impl Binder {
     /// Binds a CTE definition: `alias AS query`.
     ///
     /// Returns a node of query and adds the CTE to the context.
-    fn bind_cte(&mut self, Cte { alias, query, .. }: Cte) -> Result {
+    /// In `WITH RECURSIVE`, a CTE defined by `UNION [ALL]` that refers to itself is bound as a
+    /// recursive CTE.
+    fn bind_cte(&mut self, cte: Cte, recursive: bool) -> Result {
+        if recursive
+            && let SetExpr::SetOperation {
+                op: SetOperator::Union,
+                ..
+            } = &*cte.query.body
+            && let Some(id) = self.bind_recursive_cte(cte.alias.clone(), (*cte.query).clone())?
+        {
+            return Ok(id);
+        }
+        let Cte { alias, query, .. } = cte;
         let table_alias = alias.name.value.to_lowercase();
         let (query, ctx) = self.bind_query(*query)?;
         let mut columns = HashMap::new();
@@ -61,13 +85,17 @@ impl Binder {
                 .with_spanned(&alias));
             }
             for (column, id) in alias.columns.iter().zip(self.schema(query)) {
+                let id = self.wrap_ref(id);
                 columns.insert(column.name.value.to_lowercase(), id);
             }
         } else {
             // `with t`
             for (name, mut id) in ctx.output_aliases {
                 // wrap with `Ref` if the node is not a column unit.
-                if !matches!(self.node(id), Node::Column(_) | Node::Ref(_)) {
+                if !matches!(
+                    self.node(id),
+                    Node::Column(_) | Node::ExtColumn(_) | Node::Ref(_)
+                ) {
                     id = self.egraph.add(Node::Ref(id));
                 }
                 columns.insert(name, id);
@@ -77,6 +105,106 @@ impl Binder {
         Ok(query)
     }
 
+    /// Binds a recursive CTE definition: `alias AS (base UNION [ALL] recursive)`.
+    ///
+    /// The CTE is a working table in the recursive term, which holds the rows produced by the
+    /// last iteration. Returns a [`RecursiveUnion`](Node::RecursiveUnion) plan and adds the CTE
+    /// to the context, or `None` if the recursive term does not refer to the CTE.
+    ///
+    /// # Example
+    /// - `bind_recursive_cte(t(n) AS (select 1 union all select n + 1 from t where n < 3))` =>
+    ///   `(recursive_union union_all (list n) (values ..) (proj .. (working_table (list n))))`
+    fn bind_recursive_cte(&mut self, alias: TableAlias, query: Query) -> Result<Option<Id>> {
+        let table_alias = alias.name.value.to_lowercase();
+        if query.order_by.is_some() || query.limit.is_some() || query.offset.is_some() {
+            return Err(ErrorKind::Todo("ORDER BY or LIMIT in a recursive query".into()).into());
+        }
+        let SetExpr::SetOperation {
+            set_quantifier,
+            left,
+            right,
+            ..
+        } = *query.body
+        else {
+            panic!("recursive CTE must be a union");
+        };
+        let op = match set_quantifier {
+            SetQuantifier::All => Node::UnionAll,
+            SetQuantifier::Distinct | SetQuantifier::None => Node::Union,
+            quantifier => return Err(ErrorKind::Todo(format!("UNION {quantifier}")).into()),
+        };
+
+        // bind the non-recursive term
+        let (base, mut names) = self.bind_set_operand(*left)?;
+        let types = self.type_(base)?.as_struct().to_vec();
+        if !alias.columns.is_empty() {
+            if alias.columns.len() != types.len() {
+                return Err(ErrorKind::ColumnCountMismatch(
+                    table_alias,
+                    types.len(),
+                    alias.columns.len(),
+                )
+                .with_spanned(&alias));
+            }
+            names = (alias.columns.iter())
+                .map(|column| column.name.value.to_lowercase())
+                .collect();
+        }
+
+        // the working table has the same columns as the CTE
+        let source = self.ext_sources;
+        self.ext_sources += 1;
+        let mut columns = HashMap::new();
+        let mut ids = vec![];
+        for (i, (name, ty)) in names.into_iter().zip(&types).enumerate() {
+            let id = self.egraph.add(Node::ExtColumn(Box::new(ExtColumn {
+                source,
+                index: i as u32,
+                name: name.clone(),
+                ty: ty.clone(),
+            })));
+            columns.insert(name, id);
+            ids.push(id);
+        }
+        let list = self.egraph.add(Node::List(ids.into()));
+        let working_table = self.egraph.add(Node::WorkingTable(list));
+        self.add_cte(&alias.name, working_table, columns.clone())?;
+
+        // bind the recursive term, which refers to the working table
+        let (recursive, _) = self.bind_set_operand(*right)?;
+        let self_referenced = self
+            .recexpr(recursive)
+            .as_ref()
+            .iter()
+            .any(|node| matches!(node, Node::ExtColumn(column) if column.source == source));
+        if !self_referenced {
+            let context = self.contexts.last_mut().unwrap();
+            context.ctes.remove(&table_alias);
+            return Ok(None);
+        }
+        let recursive_types = self.type_(recursive)?.as_struct().to_vec();
+        if recursive_types.len() != types.len() {
+            return Err(ErrorKind::SetOpColumnCountMismatch("UNION".into()).into());
+        }
+        for (l, r) in types.iter().zip(&recursive_types) {
+            if l.union(r).is_none() {
+                return Err(
+                    ErrorKind::SetOpTypeMismatch("UNION".into(), l.clone(), r.clone()).into(),
+                );
+            }
+        }
+        let recursive = self.cast_columns(recursive, &recursive_types, &types);
+
+        let op = self.egraph.add(op);
+        let id = self
+            .egraph
+            .add(Node::RecursiveUnion([op, list, base, recursive]));
+        // the following references to the CTE read the output of recursive union
+        let context = self.contexts.last_mut().unwrap();
+        context.ctes.insert(table_alias, (id, columns));
+        Ok(Some(id))
+    }
+
     fn bind_select(&mut self, select: Select, order_by: Option<OrderBy>) -> Result {
         let from = self.bind_from(select.from)?;
         let projection = self.bind_projection(select.projection, from)?;
@@ -86,7 +214,9 @@ impl Binder {
                 return Err(ErrorKind::Todo("group by all".into()).with_spanned(&select.group_by));
             }
             GroupByExpr::Expressions(exprs, _) if exprs.is_empty() => None,
-            GroupByExpr::Expressions(exprs, _) => Some(self.bind_groupby(exprs)?),
+            GroupByExpr::Expressions(exprs, modifiers) => {
+                Some(self.bind_groupby(exprs, modifiers)?)
+            }
         };
         let having = self.bind_having(select.having)?;
         let orderby = match order_by {
@@ -104,10 +234,12 @@ impl Binder {
         plan = self.egraph.add(Node::Filter([where_, plan]));
         let mut to_rewrite = [projection, distinct, having, orderby];
         plan = self.plan_agg(&mut to_rewrite, groupby, plan)?;
-        let [mut projection, distinct, mut having, orderby] = to_rewrite;
+        let [projection, distinct, mut having, orderby] = to_rewrite;
         self.plan_apply(&mut having, &mut plan);
         plan = self.egraph.add(Node::Filter([having, plan]));
-        plan = self.plan_window(projection, distinct, orderby, plan)?;
+        let mut to_rewrite = [projection, distinct, orderby];
+        plan = self.plan_window(&mut to_rewrite, plan)?;
+        let [mut projection, distinct, orderby] = to_rewrite;
         plan = self.plan_distinct(distinct, orderby, &mut projection, plan)?;
         plan = self.egraph.add(Node::Order([orderby, plan]));
         plan = self.egraph.add(Node::Proj([projection, plan]));
@@ -139,7 +271,16 @@ impl Binder {
                     select_list.push(id);
                 }
                 SelectItem::Wildcard(_) => {
-                    select_list.append(&mut self.schema(from));
+                    let context = self.contexts.last().unwrap();
+                    if let Some(columns) = &context.from_columns {
+                        let mut columns = columns.clone();
+                        for (name, id) in context.using_columns.clone() {
+                            self.add_output_alias(name, id);
+                        }
+                        select_list.append(&mut columns);
+                    } else {
+                        select_list.append(&mut self.schema(from));
+                    }
                 }
                 _ => todo!("bind select list"),
             }
@@ -179,15 +320,78 @@ impl Binder {
         })
     }
 
-    /// Binds the GROUP BY clause. Returns a list of expressions.
+    /// Binds the GROUP BY clause. Returns a list of keys and a list of grouping sets.
+    /// Each grouping set is a list of indexes of the keys.
     ///
     /// There should be no aggregation in the expressions, otherwise an error will be returned.
-    fn bind_groupby(&mut self, group_by: Vec<Expr>) -> Result {
-        let id = self.bind_exprs(group_by)?;
-        if !self.aggs(id).is_empty() {
-            return Err(ErrorKind::AggInGroupBy.into()); // TODO: ditto
+    ///
+    /// # Example
+    /// - `bind_groupby(a, b)` => `(list a b)`, `(list (list 0 1))`
+    /// - `bind_groupby(a, rollup(b, c))` => `(list a b c)`, `(list (list 0 1 2) (list 0 1) (list
+    ///   0))`
+    fn bind_groupby(
+        &mut self,
+        group_by: Vec<Expr>,
+        modifiers: Vec<GroupByWithModifier>,
+    ) -> Result<(Id, Id)> {
+        if !modifiers.is_empty() {
+            let modifiers = modifiers.iter().join(" ");
+            return Err(ErrorKind::Todo(format!("GROUP BY {modifiers}")).into());
         }
-        Ok(id)
+        // the grouping sets are the cross product of the sets of all elements
+        let mut sets: Vec<Vec<Expr>> = vec![vec![]];
+        for element in group_by {
+            let element_sets = match element {
+                Expr::GroupingSets(sets) => sets,
+                Expr::Rollup(elements) => (0..=elements.len())
+                    .rev()
+                    .map(|n| elements[..n].concat())
+                    .collect(),
+                Expr::Cube(elements) => (0..1usize << elements.len())
+                    .rev()
+                    .map(|mask| {
+                        (elements.iter().enumerate())
+                            .filter(|(i, _)| mask & (1 << (elements.len() - 1 - i)) != 0)
+                            .flat_map(|(_, e)| e.clone())
+                            .collect()
+                    })
+                    .collect(),
+                expr => vec![vec![expr]],
+            };
+            sets = (sets.iter())
+                .cartesian_product(&element_sets)
+                .map(|(s1, s2)| s1.iter().chain(s2).cloned().collect())
+                .collect();
+        }
+
+        let mut keys = vec![];
+        let mut set_ids = vec![];
+        for set in sets {
+            let mut indexes = vec![];
+            for expr in set {
+                let id = self.bind_expr(expr)?;
+                if !self.aggs(id).is_empty() {
+                    return Err(ErrorKind::AggInGroupBy.into()); // TODO: ditto
+                }
+                let index = match keys.iter().position(|key| *key == id) {
+                    Some(index) => index,
+                    None => {
+                        keys.push(id);
+                        keys.len() - 1
+                    }
+                };
+                indexes.push(index);
+            }
+            indexes.sort();
+            indexes.dedup();
+            let indexes = (indexes.into_iter())
+                .map(|i| self.egraph.add(Node::Constant(DataValue::Int32(i as i32))))
+                .collect();
+            set_ids.push(self.egraph.add(Node::List(indexes)));
+        }
+        let keys = self.egraph.add(Node::List(keys.into()));
+        let sets = self.egraph.add(Node::List(set_ids.into()));
+        Ok((keys, sets))
     }
 
     /// Binds the ORDER BY clause. Returns a list of expressions.
@@ -204,6 +408,103 @@ impl Binder {
         Ok(self.egraph.add(Node::List(orderby.into())))
     }
 
+    /// Binds a set operation. Returns a [`SetOp`](Node::SetOp) plan.
+    ///
+    /// Columns of both sides are casted to their union types.
+    /// The output columns are named after the left side.
+    ///
+    /// # Example
+    /// - `bind_set_operation(select a from t union all select b from s)` => `(setop union_all (proj
+    ///   (list $1.0) ..) (proj (list $2.1) ..))`
+    fn bind_set_operation(
+        &mut self,
+        op: SetOperator,
+        quantifier: SetQuantifier,
+        left: SetExpr,
+        right: SetExpr,
+    ) -> Result {
+        let (left, names) = self.bind_set_operand(left)?;
+        let (right, _) = self.bind_set_operand(right)?;
+
+        let op_name = op.to_string();
+        let left_types = self.type_(left)?.as_struct().to_vec();
+        let right_types = self.type_(right)?.as_struct().to_vec();
+        if left_types.len() != right_types.len() {
+            return Err(ErrorKind::SetOpColumnCountMismatch(op_name).into());
+        }
+        let types: Vec<_> = (left_types.iter().zip(&right_types))
+            .map(|(l, r)| {
+                l.union(r).ok_or_else(|| {
+                    ErrorKind::SetOpTypeMismatch(op_name.clone(), l.clone(), r.clone())
+                })
+            })
+            .try_collect()?;
+        let left = self.cast_columns(left, &left_types, &types);
+        let right = self.cast_columns(right, &right_types, &types);
+
+        let op = match (op, quantifier) {
+            (SetOperator::Union, SetQuantifier::All) => Node::UnionAll,
+            (SetOperator::Union, SetQuantifier::Distinct | SetQuantifier::None) => Node::Union,
+            (SetOperator::Intersect, SetQuantifier::All) => Node::IntersectAll,
+            (SetOperator::Intersect, SetQuantifier::Distinct | SetQuantifier::None) => {
+                Node::Intersect
+            }
+            (SetOperator::Except, SetQuantifier::All) => Node::ExceptAll,
+            (SetOperator::Except, SetQuantifier::Distinct | SetQuantifier::None) => Node::Except,
+            (_, quantifier) => {
+                return Err(ErrorKind::Todo(format!("{op_name} {quantifier}")).into());
+            }
+        };
+        let op = self.egraph.add(op);
+        let id = self.egraph.add(Node::SetOp([op, left, right]));
+
+        // the output columns can be referenced by names in ORDER BY and outer queries
+        for (name, column) in names.into_iter().zip(self.schema(id)) {
+            if name != "?column?" {
+                self.add_alias(name.clone(), "".into(), column);
+                self.add_output_alias(name, column);
+            }
+        }
+        Ok(id)
+    }
+
+    /// Binds an operand of a set operation in a new context.
+    /// Returns the plan and the names of its output columns.
+    fn bind_set_operand(&mut self, body: SetExpr) -> Result<(Id, Vec<String>)> {
+        self.contexts.push(Context::default());
+        let ret = match body {
+            SetExpr::Select(select) => self.bind_select(*select, None),
+            SetExpr::Values(values) => self.bind_values(values),
+            SetExpr::Query(query) => self.bind_query_internal(*query),
+            SetExpr::SetOperation {
+                op,
+                set_quantifier,
+                left,
+                right,
+            } => self.bind_set_operation(op, set_quantifier, *left, *right),
+            body => Err(ErrorKind::Todo("unknown set expr".into()).with_spanned(&body)),
+        };
+        let ctx = self.contexts.pop().unwrap();
+        let id = ret?;
+        Ok((id, self.column_names(id, &ctx)))
+    }
+
+    /// Casts the output columns of `plan` from `from` types to `to` types.
+    fn cast_columns(&mut self, plan: Id, from: &[DataType], to: &[DataType]) -> Id {
+        if from == to {
+            return plan;
+        }
+        let mut exprs = self.schema(plan);
+        for ((expr, from), to) in exprs.iter_mut().zip(from).zip(to) {
+            if from != to {
+                let ty = self.egraph.add(Node::Type(to.clone()));
+                *expr = self.egraph.add(Node::Cast([ty, *expr]));
+            }
+        }
+        let exprs = self.egraph.add(Node::List(exprs.into()));
+        self.egraph.add(Node::Proj([exprs, plan]))
+    }
+
     /// Binds the VALUES clause. Returns a [`Values`](Node::Values) plan.
     fn bind_values(&mut self, values: Values) -> Result {
         let values = values.rows;
@@ -230,10 +531,14 @@ impl Binder {
 
     /// Extracts all aggregations from `exprs` and generates an [`Agg`](Node::Agg) plan.
     /// If no aggregation is found and no `groupby` keys, returns the original `plan`.
-    fn plan_agg(&mut self, exprs: &mut [Id], groupby: Option<Id>, plan: Id) -> Result {
+    ///
+    /// A [`GroupingSets`](Node::GroupingSets) plan is generated if there are more than one
+    /// grouping sets or any `grouping` function.
+    fn plan_agg(&mut self, exprs: &mut [Id], groupby: Option<(Id, Id)>, plan: Id) -> Result {
         let expr_list = self.egraph.add(Node::List(exprs.to_vec().into()));
-        let aggs = self.aggs(expr_list).to_vec();
-        if aggs.is_empty() && groupby.is_none() {
+        let (groupings, aggs): (Vec<_>, Vec<_>) = (self.aggs(expr_list).iter().cloned())
+            .partition(|agg| matches!(agg, Node::Grouping(_)));
+        if aggs.is_empty() && groupings.is_empty() && groupby.is_none() {
             return Ok(plan);
         }
         // check nested agg
@@ -247,10 +552,39 @@ impl Binder {
         list.sort();
         list.dedup();
         let aggs = self.egraph.add(Node::List(list.into()));
-        let plan = self.egraph.add(match groupby {
-            Some(groupby) => Node::HashAgg([groupby, aggs, plan]),
-            None => Node::Agg([aggs, plan]),
-        });
+        let plan = match groupby {
+            Some((keys, sets)) if groupings.is_empty() && self.node(sets).as_list().len() == 1 => {
+                let set = self.node(sets).as_list()[0];
+                if self.node(set).as_list().is_empty() {
+                    // GROUP BY ()
+                    self.egraph.add(Node::Agg([aggs, plan]))
+                } else {
+                    self.egraph.add(Node::HashAgg([keys, aggs, plan]))
+                }
+            }
+            Some((keys, sets)) => {
+                // `grouping` functions are evaluated as keys
+                let mut keys = self.node(keys).as_list().to_vec();
+                for grouping in groupings {
+                    let Node::Grouping(args) = &grouping else {
+                        unreachable!()
+                    };
+                    for arg in self.node(*args).as_list() {
+                        if !keys.contains(arg) {
+                            return Err(ErrorKind::GroupingNotInGroupBy.into());
+                        }
+                    }
+                    keys.push(self.egraph.add(grouping));
+                }
+                let keys = self.egraph.add(Node::List(keys.into()));
+                self.egraph
+                    .add(Node::GroupingSets([keys, sets, aggs, plan]))
+            }
+            None if !groupings.is_empty() => {
+                return Err(ErrorKind::GroupingNotInGroupBy.into());
+            }
+            None => self.egraph.add(Node::Agg([aggs, plan])),
+        };
         // check for not aggregated columns
         // rewrite the expressions with a wrapper over agg or group keys
         let schema = self.schema(plan);
@@ -287,6 +621,9 @@ impl Binder {
             let name = self.catalog.get_column(cid).unwrap().name().to_string();
             return Err(ErrorKind::ColumnNotInAgg(name).into());
         }
+        if let Node::ExtColumn(column) = &expr {
+            return Err(ErrorKind::ColumnNotInAgg(column.name.clone()).into());
+        }
         for child in expr.children_mut() {
             *child = self.rewrite_agg_in_expr(*child, schema)?;
         }
@@ -344,14 +681,15 @@ impl Binder {
         Ok(self.egraph.add(Node::HashAgg([distinct, aggs, plan])))
     }
 
-    /// Extracts all over nodes from `projection`, `distinct` and `orderby`.
-    /// Generates an [`Window`](Node::Window) plan if any over node is found.
+    /// Extracts all over nodes from `exprs` (projection, distinct and orderby).
+    /// Generates an [`Window`](Node::Window) plan if any over node is found, and rewrites the
+    /// over nodes in `exprs` with a [`Ref`](Node::Ref) wrapper.
     /// Otherwise returns the original `plan`.
-    fn plan_window(&mut self, projection: Id, distinct: Id, orderby: Id, plan: Id) -> Result {
+    fn plan_window(&mut self, exprs: &mut [Id], plan: Id) -> Result {
         let mut overs = vec![];
-        overs.extend_from_slice(self.overs(projection));
-        overs.extend_from_slice(self.overs(distinct));
-        overs.extend_from_slice(self.overs(orderby));
+        for id in exprs.iter() {
+            overs.extend_from_slice(self.overs(*id));
+        }
 
         if overs.is_empty() {
             return Ok(plan);
@@ -362,10 +700,31 @@ impl Binder {
             .collect();
         list.sort();
         list.dedup();
+        for id in exprs {
+            *id = self.rewrite_over_in_expr(*id, &list);
+        }
         let overs = self.egraph.add(Node::List(list.into()));
         Ok(self.egraph.add(Node::Window([overs, plan])))
     }
 
+    /// Rewrites the expression `id` with over nodes wrapped in a [`Ref`](Node::Ref) node, so that
+    /// the plans above the window refer to its output columns.
+    /// Returns the new expression.
+    fn rewrite_over_in_expr(&mut self, id: Id, overs: &[Id]) -> Id {
+        if overs.contains(&id) {
+            return self.wrap_ref(id);
+        }
+        let mut expr = self.node(id).clone();
+        // stop at subquery
+        if let Node::Max1Row(_) = &expr {
+            return id;
+        }
+        for child in expr.children_mut() {
+            *child = self.rewrite_over_in_expr(*child, overs);
+        }
+        self.egraph.add(expr)
+    }
+
     /// Extract all subqueries from `id` and generate [`Apply`](Node::Apply) plans.
     fn plan_apply(&mut self, id: &mut Id, plan: &mut Id) {
         let mut expr = self.node(*id).clone();
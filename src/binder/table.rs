@@ -1,7 +1,9 @@
 // Copyright 2024 RisingLight Project Authors. Licensed under Apache-2.0.
 
+use std::collections::{HashMap, HashSet};
 use std::vec::Vec;
 
+use super::copy::{ExtColumn, ExtSource, FileFormat};
 use super::*;
 use crate::catalog::{ColumnRefId, RootCatalog};
 
@@ -11,17 +13,27 @@ impl Binder {
     /// If there is no FROM clause, returns `(values (0))`.
     pub(super) fn bind_from(&mut self, tables: Vec<TableWithJoins>) -> Result {
         let mut node = None;
+        let mut columns = vec![];
         for table in tables {
-            let table_node = self.bind_table_with_joins(table)?;
+            let lateral = is_lateral(&table.relation);
+            let (table_node, mut table_columns) = self.bind_table_with_joins(table)?;
             node = Some(if let Some(node) = node {
                 let ty = self.egraph.add(Node::Inner);
-                let expr = self.egraph.add(Node::true_());
-                self.egraph.add(Node::Join([ty, expr, node, table_node]))
+                if lateral {
+                    self.egraph.add(Node::Apply([ty, node, table_node]))
+                } else {
+                    let expr = self.egraph.add(Node::true_());
+                    self.egraph.add(Node::Join([ty, expr, node, table_node]))
+                }
             } else {
                 table_node
             });
+            columns.append(&mut table_columns);
         }
         if let Some(node) = node {
+            if columns != self.schema(node) {
+                self.contexts.last_mut().unwrap().from_columns = Some(columns);
+            }
             Ok(node)
         } else {
             let zero = self.egraph.add(Node::zero());
@@ -30,7 +42,10 @@ impl Binder {
         }
     }
 
-    /// Returns a nested [`Join`](Node::Join) plan of tables.
+    /// Returns a nested [`Join`](Node::Join) plan of tables, and the columns expanded by `*`.
+    ///
+    /// Columns in `USING` or `NATURAL` joins are merged into one, which goes first in the
+    /// expanded columns. A `LATERAL` subquery is joined by an [`Apply`](Node::Apply) plan.
     ///
     /// # Example
     /// ```ignore
@@ -42,14 +57,93 @@ impl Binder {
     ///     (scan $3 (list $3.1 $3.2) null)
     /// )
     /// ```
-    fn bind_table_with_joins(&mut self, tables: TableWithJoins) -> Result {
+    fn bind_table_with_joins(&mut self, tables: TableWithJoins) -> Result<(Id, Vec<Id>)> {
+        let outer_tables = self.table_names();
         let mut node = self.bind_table_factor(tables.relation)?;
+        let mut columns = self.schema(node);
+        // columns merged by previous joins: name -> id
+        let mut merged: HashMap<String, Id> = HashMap::new();
         for join in tables.joins {
+            let lateral = is_lateral(&join.relation);
+            let left_tables = self.table_names();
             let table = self.bind_table_factor(join.relation)?;
-            let (ty, condition) = self.bind_join_op(join.join_operator)?;
-            node = self.egraph.add(Node::Join([ty, condition, node, table]));
+            let right_tables = &self.table_names() - &left_tables;
+            let left_tables = &left_tables - &outer_tables;
+            let mut right_columns = self.schema(table);
+
+            let (ty, constraint) = self.bind_join_op(join.join_operator)?;
+            let using = match constraint {
+                JoinConstraint::On(expr) => Err(self.bind_expr(expr)?),
+                JoinConstraint::None => Err(self.egraph.add(Node::true_())),
+                JoinConstraint::Using(idents) => Ok(idents),
+                JoinConstraint::Natural => {
+                    let right_names = self.table_columns(&right_tables, &right_columns);
+                    let mut left_names = self.table_columns(&left_tables, &columns);
+                    left_names.extend(merged.iter().map(|(name, id)| (name.clone(), *id)));
+                    let idents = (left_names.into_iter())
+                        .map(|(name, _)| name)
+                        .filter(|name| right_names.iter().any(|(n, _)| n == name))
+                        .unique()
+                        .map(Ident::new)
+                        .collect();
+                    Ok(idents)
+                }
+            };
+            let condition = match using {
+                Err(condition) => condition,
+                Ok(idents) => {
+                    let mut condition = self.egraph.add(Node::true_());
+                    let mut using_columns = vec![];
+                    for ident in idents {
+                        let name = ident.value.to_lowercase();
+                        let l = match merged.get(&name) {
+                            Some(id) => *id,
+                            None => self.find_join_column(&ident, &left_tables, "left")?,
+                        };
+                        let r = self.find_join_column(&ident, &right_tables, "right")?;
+                        let eq = self.egraph.add(Node::Eq([l, r]));
+                        self.type_(eq)?;
+                        condition = self.egraph.add(Node::And([condition, eq]));
+                        let id = match self.node(ty) {
+                            Node::Semi | Node::Anti => continue,
+                            Node::RightOuter => r,
+                            Node::FullOuter => {
+                                let (l, r) = self.implicit_type_cast(l, r)?;
+                                let isnull = self.egraph.add(Node::IsNull(l));
+                                self.egraph.add(Node::If([isnull, r, l]))
+                            }
+                            _ => l,
+                        };
+                        columns.retain(|c| !self.same_column(*c, l));
+                        right_columns.retain(|c| !self.same_column(*c, r));
+                        merged.insert(name, id);
+                        using_columns.push(id);
+                    }
+                    using_columns.append(&mut columns);
+                    columns = using_columns;
+                    condition
+                }
+            };
+            if !matches!(self.node(ty), Node::Semi | Node::Anti) {
+                columns.append(&mut right_columns);
+            }
+
+            node = if lateral {
+                if !matches!(self.node(ty), Node::Inner | Node::LeftOuter) {
+                    return Err(ErrorKind::InvalidLateralJoin.into());
+                }
+                let table = self.egraph.add(Node::Filter([condition, table]));
+                self.egraph.add(Node::Apply([ty, node, table]))
+            } else {
+                self.egraph.add(Node::Join([ty, condition, node, table]))
+            };
         }
-        Ok(node)
+        self.contexts
+            .last_mut()
+            .unwrap()
+            .using_columns
+            .extend(merged);
+        Ok((node, columns))
     }
 
     /// Returns a `Scan` plan of table or a plan of subquery.
@@ -59,6 +153,12 @@ impl Binder {
     /// - `bind_table_factor(select 1)` => `(values (1))`
     fn bind_table_factor(&mut self, table: TableFactor) -> Result {
         match table {
+            TableFactor::Table {
+                name,
+                alias,
+                args: Some(args),
+                ..
+            } => self.bind_table_function(&name, args.args, alias),
             TableFactor::Table { name, alias, .. } => self.bind_table_def(&name, alias, false),
             TableFactor::Derived {
                 subquery, alias, ..
@@ -70,6 +170,7 @@ impl Binder {
                     // 'as t(a, b, ..)'
                     let table_name = &alias.name.value;
                     for (column, id) in alias.columns.iter().zip(self.schema(id)) {
+                        let id = self.wrap_ref(id);
                         self.add_alias(column.name.value.to_lowercase(), table_name.clone(), id);
                     }
                 } else {
@@ -82,60 +183,216 @@ impl Binder {
                 }
                 Ok(id)
             }
-            _ => panic!("bind table ref"),
+            table => Err(ErrorKind::Todo(format!("table factor: {table}")).into()),
         }
     }
 
-    fn bind_join_op(&mut self, op: JoinOperator) -> Result<(Id, Id)> {
-        use JoinOperator::*;
-        match op {
-            Inner(constraint) => {
-                let ty = self.egraph.add(Node::Inner);
-                let condition = self.bind_join_constraint(constraint)?;
-                Ok((ty, condition))
-            }
-            LeftOuter(constraint) => {
-                let ty = self.egraph.add(Node::LeftOuter);
-                let condition = self.bind_join_constraint(constraint)?;
-                Ok((ty, condition))
-            }
-            RightOuter(constraint) => {
-                let ty = self.egraph.add(Node::RightOuter);
-                let condition = self.bind_join_constraint(constraint)?;
-                Ok((ty, condition))
-            }
-            FullOuter(constraint) => {
-                let ty = self.egraph.add(Node::FullOuter);
-                let condition = self.bind_join_constraint(constraint)?;
-                Ok((ty, condition))
-            }
-            CrossJoin => {
-                let ty = self.egraph.add(Node::Inner);
-                let condition = self.egraph.add(Node::true_());
-                Ok((ty, condition))
+    /// Returns a `FileScan` plan of a table function reading an external file.
+    ///
+    /// The schema is inferred from the header of CSV or the footer of Parquet.
+    ///
+    /// # Example
+    /// - `bind_table_function(read_csv('t.csv'))` => `(file_scan <source> (list a b) true)`
+    /// - `bind_table_function(read_csv('t.tbl', delim => '|', header => false))`
+    /// - `bind_table_function(read_parquet('t.parquet'))`
+    fn bind_table_function(
+        &mut self,
+        name: &ObjectName,
+        args: Vec<FunctionArg>,
+        alias: Option<TableAlias>,
+    ) -> Result {
+        let func_name = name.to_string().to_lowercase();
+        let invalid = || ErrorKind::InvalidTableFunction(func_name.clone()).with_spanned(name);
+        let mut path = None;
+        let mut options = vec![];
+        for arg in args {
+            match arg {
+                FunctionArg::Unnamed(FunctionArgExpr::Expr(expr)) if path.is_none() => {
+                    path = Some(self.bind_table_function_arg(expr)?)
+                }
+                FunctionArg::Named {
+                    name,
+                    arg: FunctionArgExpr::Expr(expr),
+                    ..
+                }
+                | FunctionArg::ExprNamed {
+                    name: Expr::Identifier(name),
+                    arg: FunctionArgExpr::Expr(expr),
+                    ..
+                } => options.push((name, self.bind_table_function_arg(expr)?)),
+                _ => return Err(invalid()),
             }
-            LeftSemi(constraint) => {
-                let ty = self.egraph.add(Node::Semi);
-                let condition = self.bind_join_constraint(constraint)?;
-                Ok((ty, condition))
+        }
+        let Some(DataValue::String(path)) = path else {
+            return Err(invalid());
+        };
+        let format = match func_name.as_str() {
+            "read_csv" => {
+                let mut delimiter = ',';
+                let mut quote = '"';
+                let mut escape = None;
+                let mut header = true;
+                for (name, value) in options {
+                    let invalid_param =
+                        || ErrorKind::InvalidParameter(name.value.clone()).with_span(name.span);
+                    let char_value = || match &value {
+                        DataValue::String(s) if s.chars().count() == 1 => {
+                            Ok(s.chars().next().unwrap())
+                        }
+                        _ => Err(invalid_param()),
+                    };
+                    match name.value.to_lowercase().as_str() {
+                        "delim" | "delimiter" => delimiter = char_value()?,
+                        "quote" => quote = char_value()?,
+                        "escape" => escape = Some(char_value()?),
+                        "header" => match value {
+                            DataValue::Bool(b) => header = b,
+                            _ => return Err(invalid_param()),
+                        },
+                        _ => return Err(invalid_param()),
+                    }
+                }
+                FileFormat::Csv {
+                    delimiter,
+                    quote,
+                    escape,
+                    header,
+                }
             }
-            LeftAnti(constraint) => {
-                let ty = self.egraph.add(Node::Anti);
-                let condition = self.bind_join_constraint(constraint)?;
-                Ok((ty, condition))
+            "read_parquet" => {
+                if let Some((name, _)) = options.first() {
+                    return Err(
+                        ErrorKind::InvalidParameter(name.value.clone()).with_span(name.span)
+                    );
+                }
+                FileFormat::Parquet
             }
-            op => todo!("unsupported join operator: {op:?}"),
+            _ => return Err(invalid()),
+        };
+        let path = std::path::PathBuf::from(path.as_ref());
+        let schema = format.infer_schema(&path)?;
+
+        let table_alias = match &alias {
+            Some(alias) => alias.name.value.clone(),
+            None => func_name.clone(),
+        };
+        self.add_table_alias(&table_alias)?;
+        if let Some(alias) = &alias
+            && alias.columns.len() > schema.len()
+        {
+            return Err(ErrorKind::ColumnCountMismatch(
+                table_alias,
+                schema.len(),
+                alias.columns.len(),
+            )
+            .with_spanned(alias));
+        }
+
+        let source = self.ext_sources;
+        self.ext_sources += 1;
+        let mut ids = vec![];
+        for (i, (name, ty)) in schema.iter().enumerate() {
+            let column_alias = match alias.as_ref().and_then(|a| a.columns.get(i)) {
+                Some(column) => column.name.value.to_lowercase(),
+                None => name.to_lowercase(),
+            };
+            let id = self.egraph.add(Node::ExtColumn(Box::new(ExtColumn {
+                source,
+                index: i as u32,
+                name: name.clone(),
+                ty: ty.clone(),
+            })));
+            self.add_alias(column_alias, table_alias.clone(), id);
+            ids.push(id);
         }
+
+        let source = self.egraph.add(Node::ExtSource(Box::new(ExtSource {
+            path,
+            format,
+            columns: schema.into_iter().map(|(name, _)| name).collect(),
+        })));
+        let cols = self.egraph.add(Node::List(ids.into()));
+        let true_ = self.egraph.add(Node::true_());
+        Ok(self.egraph.add(Node::FileScan([source, cols, true_])))
     }
 
-    fn bind_join_constraint(&mut self, constraint: JoinConstraint) -> Result {
-        match constraint {
-            JoinConstraint::On(expr) => self.bind_expr(expr),
-            JoinConstraint::None => Ok(self.egraph.add(Node::true_())),
-            _ => todo!("Support more join constraints"),
+    /// Binds an argument of table function, which must be a constant.
+    fn bind_table_function_arg(&mut self, expr: Expr) -> Result<DataValue> {
+        let id = self.bind_expr(expr.clone())?;
+        match self.node(id) {
+            Node::Constant(value) => Ok(value.clone()),
+            _ => Err(ErrorKind::InvalidExpression(expr.to_string()).with_spanned(&expr)),
         }
     }
 
+    /// Returns the join type and constraint of a join operator.
+    fn bind_join_op(&mut self, op: JoinOperator) -> Result<(Id, JoinConstraint)> {
+        use JoinOperator::*;
+        let (ty, constraint) = match op {
+            Inner(constraint) => (Node::Inner, constraint),
+            LeftOuter(constraint) => (Node::LeftOuter, constraint),
+            RightOuter(constraint) => (Node::RightOuter, constraint),
+            FullOuter(constraint) => (Node::FullOuter, constraint),
+            CrossJoin => (Node::Inner, JoinConstraint::None),
+            LeftSemi(constraint) => (Node::Semi, constraint),
+            LeftAnti(constraint) => (Node::Anti, constraint),
+            op => return Err(ErrorKind::Todo(format!("join operator: {op:?}")).into()),
+        };
+        Ok((self.egraph.add(ty), constraint))
+    }
+
+    /// Returns the names of tables in the current context.
+    fn table_names(&self) -> HashSet<String> {
+        let context = self.contexts.last().unwrap();
+        (context.column_aliases.values())
+            .flat_map(|map| map.keys().cloned())
+            .collect()
+    }
+
+    /// Returns the column names and ids of the given tables, in the order of `schema`.
+    fn table_columns(&self, tables: &HashSet<String>, schema: &[Id]) -> Vec<(String, Id)> {
+        let context = self.contexts.last().unwrap();
+        let mut columns = (context.column_aliases.iter())
+            .flat_map(|(name, map)| {
+                (map.iter())
+                    .filter(|(table, _)| tables.contains(*table))
+                    .map(|(_, id)| (name.clone(), *id))
+            })
+            .collect_vec();
+        columns.sort_by_key(|(_, id)| schema.iter().position(|c| self.same_column(*c, *id)));
+        columns
+    }
+
+    /// Finds the column used in a `USING` or `NATURAL` join from one side of the join.
+    fn find_join_column(
+        &self,
+        ident: &Ident,
+        tables: &HashSet<String>,
+        side: &'static str,
+    ) -> Result {
+        let name = ident.value.to_lowercase();
+        let context = self.contexts.last().unwrap();
+        let candidates = (context.column_aliases.get(&name).into_iter())
+            .flat_map(|map| map.iter().filter(|(table, _)| tables.contains(*table)))
+            .collect_vec();
+        match candidates.as_slice() {
+            [] => Err(ErrorKind::InvalidUsingColumn(name, side).with_span(ident.span)),
+            [(_, id)] => Ok(**id),
+            _ => {
+                let use_ = candidates
+                    .iter()
+                    .map(|(table_name, _)| format!("\"{table_name}.{name}\""))
+                    .join(" or ");
+                Err(ErrorKind::AmbiguousColumn(name, use_).with_span(ident.span))
+            }
+        }
+    }
+
+    /// Returns true if `column` in a schema is referred by the alias `id`.
+    fn same_column(&self, column: Id, id: Id) -> bool {
+        column == id || matches!(self.node(id), Node::Ref(c) if *c == column)
+    }
+
     /// Defines the table name so that it can be referred later.
     /// Returns a `Scan` node if the table is a base table, or a subquery if it is a CTE.
     ///
@@ -150,7 +407,7 @@ impl Binder {
         with_rowid: bool,
     ) -> Result {
         let name = lower_case_name(name);
-        let (schema_name, table_name) = split_name(&name)?;
+        let (_, table_name) = split_name(&name)?;
 
         // check duplicated alias
         let table_alias = match &alias {
@@ -170,8 +427,7 @@ impl Binder {
 
         // find table in catalog
         let ref_id = self
-            .catalog
-            .get_table_id_by_name(schema_name, table_name)
+            .get_table_id_by_name(&name)?
             .ok_or_else(|| ErrorKind::InvalidTable(table_name.into()))?;
 
         let table = self.catalog.get_table(&ref_id).unwrap();
@@ -215,11 +471,10 @@ impl Binder {
         columns: &[Ident],
     ) -> Result {
         let name = lower_case_name(table_name);
-        let (schema_name, table_name) = split_name(&name)?;
+        let (_, table_name) = split_name(&name)?;
 
         let table_ref_id = self
-            .catalog
-            .get_table_id_by_name(schema_name, table_name)
+            .get_table_id_by_name(&name)?
             .ok_or_else(|| ErrorKind::InvalidTable(table_name.into()).with_spanned(&name))?;
 
         let table = self.catalog.get_table(&table_ref_id).unwrap();
@@ -254,11 +509,10 @@ impl Binder {
     /// - `bind_table_id(t)` => `$1`
     pub(super) fn bind_table_id(&mut self, table_name: &ObjectName) -> Result<(Id, bool, bool)> {
         let name = lower_case_name(table_name);
-        let (schema_name, table_name) = split_name(&name)?;
+        let (_, table_name) = split_name(&name)?;
 
         let table_ref_id = self
-            .catalog
-            .get_table_id_by_name(schema_name, table_name)
+            .get_table_id_by_name(&name)?
             .ok_or_else(|| ErrorKind::InvalidTable(table_name.into()).with_spanned(&name))?;
         let table = self.catalog.get_table(&table_ref_id).unwrap();
         let id = self.egraph.add(Node::Table(table_ref_id));
@@ -270,6 +524,11 @@ impl Binder {
     }
 }
 
+/// Returns true if the table factor is a `LATERAL` subquery.
+fn is_lateral(table: &TableFactor) -> bool {
+    matches!(table, TableFactor::Derived { lateral: true, .. })
+}
+
 #[cfg(test)]
 mod tests {
     use std::sync::Arc;
@@ -0,0 +1,77 @@
+// Copyright 2024 RisingLight Project Authors. Licensed under Apache-2.0.
+
+use super::*;
+
+impl Binder {
+    /// Binds an UPDATE statement.
+    ///
+    /// The child plan outputs the new value of every column in the table,
+    /// followed by the `_rowid_` of the old row.
+    ///
+    /// # Example
+    /// - `update t set b = a + 1 where a > 1` => ```text (update $1 (list $1.0 $1.1) (proj (list
+    ///   $1.0 (+ $1.0 1) $1.4294967295) (filter (> $1.0 1) (scan $1 (list $1.0 $1.1 $1.4294967295)
+    ///   true)))) ```
+    pub(super) fn bind_update(
+        &mut self,
+        table: TableWithJoins,
+        assignments: Vec<Assignment>,
+        from: Option<TableWithJoins>,
+        selection: Option<Expr>,
+    ) -> Result {
+        if let Some(from) = &from {
+            return Err(ErrorKind::Todo("update ... from".into()).with_spanned(from));
+        }
+        if !table.joins.is_empty() {
+            return Err(ErrorKind::Todo(format!("update {table}")).with_spanned(&table));
+        }
+        let TableFactor::Table { name, alias, .. } = &table.relation else {
+            return Err(ErrorKind::Todo(format!("update {table}")).with_spanned(&table.relation));
+        };
+        let (table_id, is_system, is_view) = self.bind_table_id(name)?;
+        if is_system || is_view {
+            return Err(ErrorKind::CanNotUpdate.with_spanned(name));
+        }
+        let cols = self.bind_table_columns(name, &[])?;
+        let scan = self.bind_table_def(name, alias.clone(), true)?;
+
+        // the last column of the scan is `_rowid_`
+        let mut values = self.schema(scan);
+        let rowid = values.pop().unwrap();
+        let columns = self.node(cols).as_list().to_vec();
+
+        let mut assigned = vec![false; columns.len()];
+        for assignment in assignments {
+            let AssignmentTarget::ColumnName(column_name) = &assignment.target else {
+                return Err(ErrorKind::Todo(format!("update {}", assignment.target))
+                    .with_spanned(&assignment.target));
+            };
+            let ident = column_name.0.last().unwrap();
+            let column_id = self.find_alias(ident, None)?;
+            let idx = (columns.iter().position(|id| *id == column_id)).ok_or_else(|| {
+                ErrorKind::InvalidColumn(ident.value.clone()).with_span(ident.span)
+            })?;
+            if std::mem::replace(&mut assigned[idx], true) {
+                return Err(
+                    ErrorKind::DuplicatedAssignment(ident.value.clone()).with_span(ident.span)
+                );
+            }
+            self.infer_param_type(&assignment.value, self.type_(columns[idx])?);
+            let value = self.bind_expr(assignment.value.clone())?;
+            if !self.aggs(value).is_empty() {
+                return Err(ErrorKind::AggInUpdate.with_spanned(&assignment));
+            }
+            if !self.overs(value).is_empty() {
+                return Err(ErrorKind::WindowInUpdate.with_spanned(&assignment));
+            }
+            values[idx] = value;
+        }
+        values.push(rowid);
+
+        let cond = self.bind_where(selection)?;
+        let filter = self.egraph.add(Node::Filter([cond, scan]));
+        let values = self.egraph.add(Node::List(values.into()));
+        let proj = self.egraph.add(Node::Proj([values, filter]));
+        Ok(self.egraph.add(Node::Update([table_id, cols, proj])))
+    }
+}
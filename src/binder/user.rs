@@ -0,0 +1,58 @@
+// Copyright 2025 RisingLight Project Authors. Licensed under Apache-2.0.
+
+use super::*;
+use crate::catalog::UserCatalog;
+
+impl Binder {
+    pub(super) fn bind_create_user(
+        &mut self,
+        names: Vec<ObjectName>,
+        login: Option<bool>,
+        password: Option<Password>,
+    ) -> Result {
+        let [name] = names.as_slice() else {
+            return Err(ErrorKind::Todo("create multiple roles".into()).into());
+        };
+        if login == Some(false) {
+            return Err(ErrorKind::Todo("NOLOGIN role".into()).with_spanned(name));
+        }
+        let name = lower_case_name(name).to_string();
+        if self.catalog.get_user(&name).is_some() {
+            return Err(ErrorKind::UserExists(name).into());
+        }
+        let password = match password {
+            Some(Password::Password(Expr::Value(Value::SingleQuotedString(s)))) => s,
+            Some(Password::Password(expr)) => {
+                return Err(ErrorKind::InvalidExpression(
+                    "password must be a string literal".into(),
+                )
+                .with_spanned(&expr));
+            }
+            Some(Password::NullPassword) | None => {
+                return Err(ErrorKind::NoPassword(name).into());
+            }
+        };
+        // only the hashes of password are kept in the plan
+        let user = UserCatalog::new(&name, &password);
+        Ok(self.egraph.add(Node::CreateUser(Box::new(user))))
+    }
+
+    pub(super) fn bind_drop_user(&mut self, if_exists: bool, names: Vec<ObjectName>) -> Result {
+        let mut user_names = Vec::with_capacity(names.len());
+        for name in names {
+            let name = lower_case_name(&name);
+            if self.catalog.get_user(&name.to_string()).is_none() {
+                if if_exists {
+                    continue;
+                }
+                return Err(ErrorKind::InvalidUser(name.to_string()).with_spanned(&name));
+            }
+            let id = self
+                .egraph
+                .add(Node::Constant(DataValue::String(name.to_string().into())));
+            user_names.push(id);
+        }
+        let list = self.egraph.add(Node::List(user_names.into()));
+        Ok(self.egraph.add(Node::DropUser(list)))
+    }
+}
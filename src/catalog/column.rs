@@ -4,7 +4,7 @@ use pretty_xmlish::Pretty;
 use serde::{Deserialize, Serialize};
 
 use super::ColumnId;
-use crate::types::DataType;
+use crate::types::{DataType, DataValue};
 
 /// A descriptor of a column.
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
@@ -13,6 +13,9 @@ pub struct ColumnDesc {
     data_type: DataType,
     is_nullable: bool,
     is_primary: bool,
+    /// The value of the column in rows that don't specify it.
+    #[serde(default)]
+    default_value: Option<DataValue>,
 }
 
 impl ColumnDesc {
@@ -22,6 +25,7 @@ impl ColumnDesc {
             data_type: datatype,
             is_nullable,
             is_primary: false,
+            default_value: None,
         }
     }
 
@@ -49,6 +53,14 @@ impl ColumnDesc {
         &self.name
     }
 
+    pub fn set_default_value(&mut self, value: Option<DataValue>) {
+        self.default_value = value;
+    }
+
+    pub fn default_value(&self) -> Option<&DataValue> {
+        self.default_value.as_ref()
+    }
+
     pub fn pretty<'a>(&self) -> Pretty<'a> {
         let mut fields = vec![
             ("name", Pretty::display(&self.name)),
@@ -60,6 +72,9 @@ impl ColumnDesc {
         if self.is_nullable {
             fields.push(("nullable", Pretty::display(&self.is_nullable)));
         }
+        if let Some(value) = &self.default_value {
+            fields.push(("default", Pretty::display(value)));
+        }
         Pretty::childless_record("Column", fields)
     }
 }
@@ -88,6 +103,10 @@ impl ColumnCatalog {
         &self.desc.name
     }
 
+    pub fn set_name(&mut self, name: impl Into<String>) {
+        self.desc.name = name.into();
+    }
+
     pub(crate) fn into_name(self) -> String {
         self.desc.name
     }
@@ -100,6 +119,19 @@ impl ColumnCatalog {
         self.desc.data_type.clone()
     }
 
+    pub fn set_data_type(&mut self, data_type: DataType) {
+        self.desc.data_type = data_type;
+    }
+
+    pub fn set_default_value(&mut self, value: Option<DataValue>) {
+        self.desc.set_default_value(value);
+    }
+
+    /// Returns the default value of the column. `None` means null.
+    pub fn default_value(&self) -> Option<&DataValue> {
+        self.desc.default_value()
+    }
+
     pub fn set_primary(&mut self, is_primary: bool) {
         self.desc.set_primary(is_primary);
     }
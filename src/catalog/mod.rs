@@ -10,6 +10,7 @@ pub use self::index::*;
 pub use self::root::*;
 pub use self::schema::*;
 pub use self::table::*;
+pub use self::user::*;
 use crate::types::*;
 
 mod column;
@@ -18,6 +19,7 @@ mod index;
 mod root;
 mod schema;
 mod table;
+mod user;
 
 pub type SchemaId = u32;
 pub type TableId = u32;
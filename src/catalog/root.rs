@@ -1,11 +1,12 @@
 // Copyright 2024 RisingLight Project Authors. Licensed under Apache-2.0.
 
 use std::collections::HashMap;
+use std::sync::atomic::AtomicU32;
 use std::sync::{Arc, Mutex};
 
 use super::function::FunctionCatalog;
 use super::*;
-use crate::binder::IndexType;
+use crate::binder::{AlterTableOperation, IndexType, VectorDistance};
 use crate::parser;
 use crate::planner::RecExpr;
 
@@ -19,6 +20,10 @@ struct Inner {
     schema_idxs: HashMap<String, SchemaId>,
     schemas: HashMap<SchemaId, SchemaCatalog>,
     next_schema_id: SchemaId,
+    /// The next id of tables, views and indexes in temporary schemas.
+    temp_ids: Arc<AtomicU32>,
+    /// Users who can log in to the server, indexed by name.
+    users: HashMap<String, UserCatalog>,
 }
 
 impl Default for RootCatalog {
@@ -32,6 +37,7 @@ impl RootCatalog {
         let mut inner = Inner::default();
         inner.add_system_schema();
         inner.add_schema(Self::DEFAULT_SCHEMA_NAME.into()).unwrap();
+        inner.temp_ids = Arc::new(AtomicU32::new(Self::TEMP_TABLE_ID_START));
         RootCatalog {
             inner: Mutex::new(inner),
         }
@@ -58,6 +64,26 @@ impl RootCatalog {
         inner.schemas.get(id).cloned()
     }
 
+    /// Creates a schema for the temporary objects of a session.
+    pub fn add_temp_schema(&self) -> SchemaId {
+        let mut inner = self.inner.lock().unwrap();
+        let schema_id = inner.next_schema_id;
+        inner.next_schema_id += 1;
+        let name = format!("{}{schema_id}", Self::TEMP_SCHEMA_PREFIX);
+        let schema = SchemaCatalog::new_temp(schema_id, name.clone(), inner.temp_ids.clone());
+        inner.schema_idxs.insert(name, schema_id);
+        inner.schemas.insert(schema_id, schema);
+        schema_id
+    }
+
+    /// Removes a schema created by [`add_temp_schema`](Self::add_temp_schema).
+    pub fn drop_temp_schema(&self, schema_id: SchemaId) {
+        let mut inner = self.inner.lock().unwrap();
+        if let Some(schema) = inner.schemas.remove(&schema_id) {
+            inner.schema_idxs.remove(&schema.name());
+        }
+    }
+
     pub fn get_table(&self, table_ref_id: &TableRefId) -> Option<Arc<TableCatalog>> {
         let schema = self.get_schema_by_id(table_ref_id.schema_id)?;
         schema.get_table_by_id(table_ref_id.table_id)
@@ -128,6 +154,37 @@ impl RootCatalog {
         schema.get_index_by_id(index_id)
     }
 
+    /// Returns the vector index on the column that supports the distance function.
+    pub fn get_vector_index(
+        &self,
+        column: &ColumnRefId,
+        distance: &VectorDistance,
+    ) -> Option<IndexId> {
+        let inner = self.inner.lock().unwrap();
+        let schema = inner.schemas.get(&column.schema_id)?;
+        (schema.get_indexes_on_table(column.table_id).into_iter())
+            .filter(|id| {
+                let index = schema.get_index_by_id(*id).unwrap();
+                index.column_idxs() == [column.column_id]
+                    && matches!(
+                        index.index_type(),
+                        IndexType::Hnsw { distance: d, .. } | IndexType::IvfFlat { distance: d, .. }
+                            if d == *distance
+                    )
+            })
+            .min()
+    }
+
+    pub fn alter_table(
+        &self,
+        table_ref_id: TableRefId,
+        operation: &AlterTableOperation,
+    ) -> Result<(), CatalogError> {
+        let mut inner = self.inner.lock().unwrap();
+        let schema = inner.schemas.get_mut(&table_ref_id.schema_id).unwrap();
+        schema.alter_table(table_ref_id.table_id, operation)
+    }
+
     pub fn drop_table(&self, table_ref_id: TableRefId) {
         let mut inner = self.inner.lock().unwrap();
         let schema = inner.schemas.get_mut(&table_ref_id.schema_id).unwrap();
@@ -170,9 +227,46 @@ impl RootCatalog {
         schema.create_function(name, arg_types, arg_names, return_type, language, body);
     }
 
+    pub fn add_user(&self, user: UserCatalog) -> Result<(), CatalogError> {
+        let mut inner = self.inner.lock().unwrap();
+        if inner.users.contains_key(user.name()) {
+            return Err(CatalogError::Duplicated("user", user.name().into()));
+        }
+        inner.users.insert(user.name().into(), user);
+        Ok(())
+    }
+
+    pub fn drop_user(&self, name: &str) -> Result<(), CatalogError> {
+        let mut inner = self.inner.lock().unwrap();
+        match inner.users.remove(name) {
+            Some(_) => Ok(()),
+            None => Err(CatalogError::NotFound("user", name.into())),
+        }
+    }
+
+    pub fn get_user(&self, name: &str) -> Option<UserCatalog> {
+        let inner = self.inner.lock().unwrap();
+        inner.users.get(name).cloned()
+    }
+
+    /// Returns all users ordered by name.
+    pub fn all_users(&self) -> Vec<UserCatalog> {
+        let inner = self.inner.lock().unwrap();
+        let mut users = inner.users.values().cloned().collect::<Vec<_>>();
+        users.sort_by(|a, b| a.name().cmp(b.name()));
+        users
+    }
+
     pub const DEFAULT_SCHEMA_NAME: &'static str = "postgres";
     pub const SYSTEM_SCHEMA_NAME: &'static str = "pg_catalog";
     pub const SYSTEM_SCHEMA_ID: TableId = 0;
+    /// The name prefix of temporary schemas, which is followed by the schema id.
+    pub const TEMP_SCHEMA_PREFIX: &'static str = "pg_temp_";
+    /// The first id of tables in temporary schemas.
+    ///
+    /// Temporary schemas are not persisted, so storage drops the tables with ids from here
+    /// when it restarts.
+    pub const TEMP_TABLE_ID_START: TableId = 1 << 31;
 }
 
 impl Inner {
@@ -262,6 +356,9 @@ const CREATE_SYSTEM_TABLE_SQL: &str = "
         n_row int,
         n_distinct int
     );
+    create table pg_user (
+        user_name string not null
+    );
 ";
 
 #[cfg(test)]
@@ -2,10 +2,11 @@
 
 use std::collections::HashMap;
 use std::sync::Arc;
+use std::sync::atomic::{AtomicU32, Ordering};
 
 use super::function::FunctionCatalog;
 use super::*;
-use crate::binder::IndexType;
+use crate::binder::{AlterTableOperation, IndexType};
 use crate::planner::RecExpr;
 
 /// The catalog of a schema.
@@ -18,6 +19,8 @@ pub struct SchemaCatalog {
     indexes_idxs: HashMap<String, IndexId>,
     indexes: HashMap<IndexId, Arc<IndexCatalog>>,
     next_id: u32,
+    /// The id counter shared by temporary schemas, which is `None` for other schemas.
+    temp_ids: Option<Arc<AtomicU32>>,
     /// Currently indexed by function name
     functions: HashMap<String, Arc<FunctionCatalog>>,
 }
@@ -32,10 +35,36 @@ impl SchemaCatalog {
             indexes_idxs: HashMap::new(),
             indexes: HashMap::new(),
             next_id: 0,
+            temp_ids: None,
             functions: HashMap::new(),
         }
     }
 
+    /// Creates a schema for the temporary objects of a session.
+    ///
+    /// Tables in temporary schemas take ids from `ids`, so that they never share an id with
+    /// tables in other schemas.
+    pub(super) fn new_temp(id: SchemaId, name: String, ids: Arc<AtomicU32>) -> SchemaCatalog {
+        SchemaCatalog {
+            temp_ids: Some(ids),
+            ..SchemaCatalog::new(id, name)
+        }
+    }
+
+    /// Returns true if the schema holds the temporary objects of a session.
+    pub fn is_temp(&self) -> bool {
+        self.temp_ids.is_some()
+    }
+
+    fn alloc_id(&mut self) -> u32 {
+        if let Some(ids) = &self.temp_ids {
+            return ids.fetch_add(1, Ordering::Relaxed);
+        }
+        let id = self.next_id;
+        self.next_id += 1;
+        id
+    }
+
     pub(super) fn add_table(
         &mut self,
         name: String,
@@ -45,8 +74,7 @@ impl SchemaCatalog {
         if self.table_idxs.contains_key(&name) {
             return Err(CatalogError::Duplicated("table", name));
         }
-        let table_id = self.next_id;
-        self.next_id += 1;
+        let table_id = self.alloc_id();
         let table_catalog = Arc::new(TableCatalog::new(
             table_id,
             name.clone(),
@@ -68,8 +96,7 @@ impl SchemaCatalog {
         if self.indexes_idxs.contains_key(&name) {
             return Err(CatalogError::Duplicated("index", name));
         }
-        let index_id = self.next_id;
-        self.next_id += 1;
+        let index_id = self.alloc_id();
         let index_catalog = Arc::new(IndexCatalog::new(
             index_id,
             name.clone(),
@@ -91,8 +118,7 @@ impl SchemaCatalog {
         if self.table_idxs.contains_key(&name) {
             return Err(CatalogError::Duplicated("view", name));
         }
-        let table_id = self.next_id;
-        self.next_id += 1;
+        let table_id = self.alloc_id();
         let table_catalog = Arc::new(TableCatalog::new_view(
             table_id,
             name.clone(),
@@ -104,6 +130,29 @@ impl SchemaCatalog {
         Ok(table_id)
     }
 
+    pub(super) fn alter_table(
+        &mut self,
+        id: TableId,
+        operation: &AlterTableOperation,
+    ) -> Result<(), CatalogError> {
+        let table = (self.tables.get(&id))
+            .ok_or_else(|| CatalogError::NotFound("table", id.to_string()))?;
+        if let AlterTableOperation::RenameTable(name) = operation
+            && self.table_idxs.contains_key(name)
+        {
+            return Err(CatalogError::Duplicated("table", name.clone()));
+        }
+        let mut table = TableCatalog::clone(table);
+        let old_name = table.name().to_string();
+        table.alter(operation)?;
+        if old_name != table.name() {
+            self.table_idxs.remove(&old_name);
+            self.table_idxs.insert(table.name().into(), id);
+        }
+        self.tables.insert(id, Arc::new(table));
+        Ok(())
+    }
+
     pub(super) fn delete_table(&mut self, id: TableId) {
         let catalog = self.tables.remove(&id).unwrap();
         self.table_idxs.remove(catalog.name()).unwrap();
@@ -3,9 +3,11 @@
 use std::collections::{BTreeMap, HashMap};
 
 use super::*;
+use crate::binder::AlterTableOperation;
 use crate::planner::RecExpr;
 
 /// The catalog of a table.
+#[derive(Clone)]
 pub struct TableCatalog {
     id: TableId,
     name: String,
@@ -14,6 +16,7 @@ pub struct TableCatalog {
     columns: BTreeMap<ColumnId, ColumnCatalog>,
 
     kind: TableKind,
+    /// The id of the next added column. Ids of dropped columns are never reused.
     next_column_id: ColumnId,
     primary_key: Vec<ColumnId>,
 }
@@ -79,14 +82,58 @@ impl TableCatalog {
                 col_catalog.name().into(),
             ));
         }
-        self.next_column_id += 1;
         let id = col_catalog.id();
+        if id != u32::MAX {
+            self.next_column_id = self.next_column_id.max(id + 1);
+        }
         self.column_idxs
             .insert(col_catalog.name().to_string(), col_catalog.id());
         self.columns.insert(id, col_catalog);
         Ok(id)
     }
 
+    /// Applies an `ALTER TABLE` operation to the table.
+    pub(super) fn alter(&mut self, operation: &AlterTableOperation) -> Result<(), CatalogError> {
+        let not_found = |id: &ColumnId| CatalogError::NotFound("column", id.to_string());
+        match operation {
+            AlterTableOperation::AddColumn(column) => {
+                self.add_column(column.clone())?;
+            }
+            AlterTableOperation::DropColumn(column_id) => {
+                let column =
+                    (self.columns.remove(column_id)).ok_or_else(|| not_found(column_id))?;
+                self.column_idxs.remove(column.name());
+            }
+            AlterTableOperation::RenameColumn { column_id, name } => {
+                if self.column_idxs.contains_key(name) {
+                    return Err(CatalogError::Duplicated("column", name.clone()));
+                }
+                let column =
+                    (self.columns.get_mut(column_id)).ok_or_else(|| not_found(column_id))?;
+                self.column_idxs.remove(column.name());
+                self.column_idxs.insert(name.clone(), *column_id);
+                column.set_name(name.clone());
+            }
+            AlterTableOperation::RenameTable(name) => self.name = name.clone(),
+            AlterTableOperation::AlterColumnType {
+                column_id,
+                data_type,
+            } => {
+                let column =
+                    (self.columns.get_mut(column_id)).ok_or_else(|| not_found(column_id))?;
+                let default_value = (column.default_value()).and_then(|v| v.cast(data_type).ok());
+                column.set_data_type(data_type.clone());
+                column.set_default_value(default_value);
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the id for a new column.
+    pub fn next_column_id(&self) -> ColumnId {
+        self.next_column_id
+    }
+
     pub fn contains_column(&self, name: &str) -> bool {
         self.column_idxs.contains_key(name)
     }
@@ -0,0 +1,91 @@
+// Copyright 2025 RisingLight Project Authors. Licensed under Apache-2.0.
+
+use std::fmt;
+use std::str::FromStr;
+
+use pgwire::api::auth::scram::gen_salted_password;
+use rand::Rng;
+
+use super::*;
+
+/// The catalog of a user who can log in to the server.
+///
+/// The password is not stored. Instead, we keep the hashes required by password authentication.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Serialize, Deserialize)]
+pub struct UserCatalog {
+    name: String,
+    /// `md5(password || name)` in hex, which is the same as Postgres.
+    md5: String,
+    /// The salt of SCRAM-SHA-256.
+    salt: Vec<u8>,
+    /// The password salted by SCRAM-SHA-256.
+    salted_password: Vec<u8>,
+}
+
+impl UserCatalog {
+    /// The number of iterations of SCRAM-SHA-256.
+    pub const SCRAM_ITERATIONS: usize = 4096;
+
+    pub fn new(name: &str, password: &str) -> Self {
+        let salt: [u8; 16] = rand::thread_rng().r#gen();
+        Self {
+            name: name.into(),
+            md5: format!("{:x}", md5::compute(format!("{password}{name}"))),
+            salt: salt.to_vec(),
+            salted_password: gen_salted_password(password, &salt, Self::SCRAM_ITERATIONS),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns `md5(password || name)` in hex.
+    pub fn md5(&self) -> &str {
+        &self.md5
+    }
+
+    /// Returns the salt of SCRAM-SHA-256.
+    pub fn salt(&self) -> &[u8] {
+        &self.salt
+    }
+
+    /// Returns the password salted by SCRAM-SHA-256.
+    pub fn salted_password(&self) -> &[u8] {
+        &self.salted_password
+    }
+
+    /// Returns true if the password is correct.
+    pub fn check_password(&self, password: &str) -> bool {
+        gen_salted_password(password, &self.salt, Self::SCRAM_ITERATIONS) == self.salted_password
+    }
+}
+
+impl fmt::Display for UserCatalog {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name)
+    }
+}
+
+impl FromStr for Box<UserCatalog> {
+    type Err = ();
+
+    fn from_str(_s: &str) -> Result<Self, Self::Err> {
+        Err(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn password() {
+        let user = UserCatalog::new("alice", "secret");
+        assert!(user.check_password("secret"));
+        assert!(!user.check_password("Secret"));
+        // select md5('secretalice')
+        assert_eq!(user.md5(), "4a0a68b43b6cd5cf266fa02f196e2371");
+        assert_ne!(UserCatalog::new("alice", "secret").salt(), user.salt());
+    }
+}
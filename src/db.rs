@@ -1,34 +1,124 @@
 // Copyright 2024 RisingLight Project Authors. Licensed under Apache-2.0.
 
-use std::sync::{Arc, Mutex};
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
 
-use futures::TryStreamExt;
+use futures::stream::BoxStream;
+use futures::{Stream, StreamExt, TryStreamExt, stream};
 use minitrace::Span;
 use minitrace::collector::SpanContext;
 use risinglight_proto::rowset::block_statistics::BlockStatisticsType;
 
-use crate::array::Chunk;
-use crate::binder::bind_header;
+use crate::array::{ArrayImpl, Chunk, DataChunk};
+use crate::binder::output_header;
 use crate::catalog::{RootCatalog, RootCatalogRef, TableRefId};
-use crate::parser::{ParserError, parse};
+use crate::parser::{Ident, ObjectName, ParserError, Statement, parse};
 use crate::planner::{Expr, RecExpr, Statistics};
 use crate::storage::{
     InMemoryStorage, SecondaryStorage, SecondaryStorageOptions, Storage, StorageColumnRef,
     StorageImpl, Table,
 };
+use crate::types::{DataType, DataValue};
+
+mod session;
+
+pub use self::session::Session;
+use self::session::*;
 
 /// The database instance.
 pub struct Database {
     catalog: RootCatalogRef,
     storage: StorageImpl,
-    config: Mutex<Config>,
+    /// The session of statements run by [`Database::run`].
+    session: Session,
+}
+
+/// A statement prepared by [`Database::prepare`].
+#[derive(Debug, Clone)]
+pub struct PreparedStatement {
+    sql: String,
+    /// `None` for an empty query.
+    stmt: Option<Statement>,
+    param_types: Vec<DataType>,
+    columns: Vec<(String, DataType)>,
+}
+
+impl PreparedStatement {
+    fn new(sql: &str, stmt: Option<Statement>, binder: &crate::binder::Binder) -> Self {
+        PreparedStatement {
+            sql: sql.into(),
+            stmt,
+            param_types: binder.param_types(),
+            columns: binder.output_columns().to_vec(),
+        }
+    }
+
+    /// Returns the statement, or `None` if it is empty.
+    pub fn statement(&self) -> Option<&Statement> {
+        self.stmt.as_ref()
+    }
+
+    /// Returns the types of parameters.
+    pub fn param_types(&self) -> &[DataType] {
+        &self.param_types
+    }
+
+    /// Returns the names and types of output columns. Empty if the statement returns no rows.
+    pub fn columns(&self) -> &[(String, DataType)] {
+        &self.columns
+    }
+
+    /// Returns true if the statement returns rows, e.g. `SELECT` and `EXPLAIN`.
+    pub fn returns_rows(&self) -> bool {
+        !self.columns.is_empty()
+    }
+
+    /// Returns true if the statement is empty.
+    pub fn is_empty(&self) -> bool {
+        self.stmt.is_none()
+    }
+}
+
+/// The output of a statement, streamed as the statement is executed.
+///
+/// The statement finishes when the stream is exhausted. Dropping the stream cancels it.
+pub struct OutputStream<'a> {
+    header: Option<Vec<String>>,
+    chunks: BoxStream<'a, Result<DataChunk, Error>>,
+}
+
+impl OutputStream<'_> {
+    /// Returns the header that tells the shell how to print the output.
+    pub fn header(&self) -> Option<&[String]> {
+        self.header.as_deref()
+    }
+
+    /// Waits for the statement to finish and collects all the output.
+    pub async fn into_chunk(self) -> Result<Chunk, Error> {
+        let mut chunk = Chunk::new(self.chunks.try_collect().await?);
+        if let Some(header) = self.header {
+            chunk.set_header(header);
+        }
+        Ok(chunk)
+    }
+
+    /// Waits for the statement to finish, and buffers the output in memory.
+    async fn buffered(self) -> Result<Self, Error> {
+        let chunks: Vec<_> = self.chunks.try_collect().await?;
+        Ok(OutputStream {
+            header: self.header,
+            chunks: stream::iter(chunks.into_iter().map(Ok)).boxed(),
+        })
+    }
 }
 
-/// The configuration of the database.
-#[derive(Debug, Default)]
-struct Config {
-    disable_optimizer: bool,
-    mock_stat: Option<Statistics>,
+impl Stream for OutputStream<'_> {
+    type Item = Result<DataChunk, Error>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.chunks.poll_next_unpin(cx)
+    }
 }
 
 impl Database {
@@ -38,7 +128,7 @@ impl Database {
         Database {
             catalog: storage.catalog().clone(),
             storage: StorageImpl::InMemoryStorage(Arc::new(storage)),
-            config: Default::default(),
+            session: Session::new(),
         }
     }
 
@@ -49,11 +139,18 @@ impl Database {
         Database {
             catalog: storage.catalog().clone(),
             storage: StorageImpl::SecondaryStorage(storage),
-            config: Default::default(),
+            session: Session::new(),
         }
     }
 
+    /// Returns the catalog of the database.
+    pub fn catalog(&self) -> &RootCatalogRef {
+        &self.catalog
+    }
+
+    /// Close the session of [`Database::run`] and shut down the storage.
     pub async fn shutdown(&self) -> Result<(), Error> {
+        self.close_session(&self.session).await?;
         if let StorageImpl::SecondaryStorage(storage) = &self.storage {
             storage.shutdown().await?;
         }
@@ -66,6 +163,7 @@ impl Database {
         Ok(match tokens.as_slice() {
             ["dt"] => "SELECT * FROM pg_catalog.pg_tables".to_string(),
             ["di"] => "SELECT * FROM pg_catalog.pg_indexes".to_string(),
+            ["du"] => "SELECT * FROM pg_catalog.pg_user".to_string(),
             ["d", table] => {
                 format!("SELECT * FROM pg_catalog.pg_attribute WHERE table_name = '{table}'",)
             }
@@ -82,52 +180,334 @@ impl Database {
 
     /// Run SQL queries and return the outputs.
     pub async fn run(&self, sql: &str) -> Result<Vec<Chunk>, Error> {
+        self.run_in(&self.session, sql).await
+    }
+
+    /// Run SQL queries in a session and return the outputs.
+    pub async fn run_in(&self, session: &Session, sql: &str) -> Result<Vec<Chunk>, Error> {
+        let outputs = self.run_statements_in(session, sql).await?;
+        Ok(outputs.into_iter().filter_map(|(_, chunk)| chunk).collect())
+    }
+
+    /// Run SQL queries and return the outputs along with the statements.
+    ///
+    /// The output is `None` if the statement is handled without execution, e.g. `SET`.
+    pub async fn run_statements(
+        &self,
+        sql: &str,
+    ) -> Result<Vec<(PreparedStatement, Option<Chunk>)>, Error> {
+        self.run_statements_in(&self.session, sql).await
+    }
+
+    /// Run SQL queries in a session and return the outputs along with the statements.
+    pub async fn run_statements_in(
+        &self,
+        session: &Session,
+        sql: &str,
+    ) -> Result<Vec<(PreparedStatement, Option<Chunk>)>, Error> {
+        let mut outputs = vec![];
+        for result in self.stream_statements_in(session, sql).await {
+            let (stmt, output) = result?;
+            let chunk = match output {
+                Some(output) => Some(output.into_chunk().await?),
+                None => None,
+            };
+            outputs.push((stmt, chunk));
+        }
+        Ok(outputs)
+    }
+
+    /// Run SQL queries, and stream the output of the last statement.
+    ///
+    /// The outputs of other statements are buffered, as each statement runs after the previous
+    /// one finishes. Statements after a failed one are not run, so the result of each statement
+    /// is returned up to and including the first error.
+    pub async fn stream_statements(
+        &self,
+        sql: &str,
+    ) -> Vec<Result<(PreparedStatement, Option<OutputStream<'_>>), Error>> {
+        self.stream_statements_in(&self.session, sql).await
+    }
+
+    /// Run SQL queries in a session, and stream the output of the last statement.
+    pub async fn stream_statements_in<'a>(
+        &'a self,
+        session: &'a Session,
+        sql: &str,
+    ) -> Vec<Result<(PreparedStatement, Option<OutputStream<'a>>), Error>> {
         let _root = Span::root("run_sql", SpanContext::random());
 
+        let (sql, optimizer, stmts) = match self.parse_sql(session, sql).await {
+            Ok(parsed) => parsed,
+            Err(e) => return vec![Err(e)],
+        };
+        let num_stmts = stmts.len();
+        let mut outputs = vec![];
+        for (i, stmt) in stmts.into_iter().enumerate() {
+            let mut binder = crate::binder::Binder::new(self.catalog.clone());
+            let mut output =
+                (self.run_stmt(session, &optimizer, &sql, stmt.clone(), &mut binder)).await;
+            if i + 1 < num_stmts
+                && let Ok(Some(stream)) = output
+            {
+                output = stream.buffered().await.map(Some);
+            }
+            match output {
+                Ok(output) => outputs.push(Ok((
+                    PreparedStatement::new(&sql, Some(stmt), &binder),
+                    output,
+                ))),
+                Err(e) => {
+                    outputs.push(Err(e));
+                    break;
+                }
+            }
+        }
+        outputs
+    }
+
+    /// Parses SQL queries or a `\` command. Returns the SQL, an optimizer and the statements.
+    async fn parse_sql(
+        &self,
+        session: &Session,
+        sql: &str,
+    ) -> Result<(String, crate::planner::Optimizer, Vec<Statement>), Error> {
         let sql = if let Some(cmd) = sql.trim().strip_prefix('\\') {
             self.command_to_sql(cmd)?
         } else {
             sql.to_string()
         };
+        let optimizer = self.optimizer(session).await?;
+        let stmts = parse(&sql)?;
+        Ok((sql, optimizer, stmts))
+    }
+
+    /// Prepare a SQL statement with parameters like `$1`.
+    ///
+    /// `param_types` are the types of parameters specified by the client, where `None` means the
+    /// type should be inferred from the statement.
+    pub fn prepare(
+        &self,
+        sql: &str,
+        param_types: &[Option<DataType>],
+    ) -> Result<PreparedStatement, Error> {
+        self.prepare_in(&self.session, sql, param_types)
+    }
+
+    /// Prepare a SQL statement in a session, which can refer to the temporary objects of the
+    /// session.
+    pub fn prepare_in(
+        &self,
+        session: &Session,
+        sql: &str,
+        param_types: &[Option<DataType>],
+    ) -> Result<PreparedStatement, Error> {
+        let mut stmts = parse(sql)?;
+        if stmts.len() > 1 {
+            return Err(Error::Internal(
+                "cannot insert multiple commands into a prepared statement".into(),
+            ));
+        }
+        let stmt = stmts.pop();
+        let mut binder =
+            crate::binder::Binder::with_params(self.catalog.clone(), vec![], param_types.to_vec());
+        binder.set_temp_schema(session.temp_schema(&self.catalog, false));
+        if let Some(stmt) = &stmt {
+            binder.bind(stmt.clone()).map_err(|e| e.with_sql(sql))?;
+        }
+        Ok(PreparedStatement::new(sql, stmt, &binder))
+    }
+
+    /// Execute a prepared statement with the values of parameters.
+    ///
+    /// Returns `None` if the statement is empty or has no output.
+    pub async fn execute(
+        &self,
+        stmt: &PreparedStatement,
+        params: Vec<DataValue>,
+    ) -> Result<Option<Chunk>, Error> {
+        self.execute_in(&self.session, stmt, params).await
+    }
+
+    /// Execute a prepared statement in a session.
+    pub async fn execute_in(
+        &self,
+        session: &Session,
+        stmt: &PreparedStatement,
+        params: Vec<DataValue>,
+    ) -> Result<Option<Chunk>, Error> {
+        match self.stream_execute_in(session, stmt, params).await? {
+            Some(output) => Ok(Some(output.into_chunk().await?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Execute a prepared statement in a session, and stream the output.
+    pub async fn stream_execute_in<'a>(
+        &'a self,
+        session: &'a Session,
+        stmt: &PreparedStatement,
+        params: Vec<DataValue>,
+    ) -> Result<Option<OutputStream<'a>>, Error> {
+        let _root = Span::root("execute", SpanContext::random());
+
+        let Some(ast) = stmt.stmt.clone() else {
+            return Ok(None);
+        };
+        let binder = self.binder_for(stmt, params)?;
+        let optimizer = self.optimizer(session).await?;
+        (self.run_stmt(session, &optimizer, &stmt.sql, ast, &mut { binder })).await
+    }
+
+    /// Returns a binder of a prepared statement with the values of parameters.
+    fn binder_for(
+        &self,
+        stmt: &PreparedStatement,
+        params: Vec<DataValue>,
+    ) -> Result<crate::binder::Binder, Error> {
+        if params.len() != stmt.param_types.len() {
+            return Err(Error::Internal(format!(
+                "prepared statement requires {} parameters, but {} given",
+                stmt.param_types.len(),
+                params.len()
+            )));
+        }
+        let types = stmt.param_types.iter().cloned().map(Some).collect();
+        Ok(crate::binder::Binder::with_params(
+            self.catalog.clone(),
+            params,
+            types,
+        ))
+    }
+
+    /// Close a session. Its transaction block is rolled back, and its temporary objects are
+    /// dropped.
+    pub async fn close_session(&self, session: &Session) -> Result<(), Error> {
+        session.txn.rollback().await?;
+        let Some(schema_id) = session.take_temp_schema() else {
+            return Ok(());
+        };
+        if let Some(schema) = self.catalog.get_schema_by_id(schema_id) {
+            for table in schema.all_tables().values() {
+                let object_type = if table.is_view() { "VIEW" } else { "TABLE" };
+                let name = ObjectName(vec![
+                    Ident::with_quote('"', schema.name()),
+                    Ident::with_quote('"', table.name()),
+                ]);
+                let sql = format!("DROP {object_type} IF EXISTS {name}");
+                self.run_statements_in(session, &sql).await?;
+            }
+        }
+        self.catalog.drop_temp_schema(schema_id);
+        Ok(())
+    }
 
-        let optimizer = crate::planner::Optimizer::new(
+    async fn optimizer(&self, session: &Session) -> Result<crate::planner::Optimizer, Error> {
+        Ok(crate::planner::Optimizer::new(
             self.catalog.clone(),
-            self.get_storage_statistics().await?,
+            self.get_storage_statistics(session).await?,
             crate::planner::Config {
                 enable_range_filter_scan: self.storage.support_range_filter_scan(),
                 table_is_sorted_by_primary_key: self.storage.table_is_sorted_by_primary_key(),
             },
-        );
+        ))
+    }
 
-        let stmts = parse(&sql)?;
-        let mut outputs: Vec<Chunk> = vec![];
-        for stmt in stmts {
-            let mut binder = crate::binder::Binder::new(self.catalog.clone());
-            let mut plan = binder.bind(stmt.clone()).map_err(|e| e.with_sql(&sql))?;
-            if self.handle_set(&plan)? {
-                continue;
+    /// Bind, optimize and start executing a statement.
+    ///
+    /// Returns `None` if the statement is handled without execution.
+    /// If the statement fails in a transaction block, the block is aborted.
+    async fn run_stmt<'a>(
+        &'a self,
+        session: &'a Session,
+        optimizer: &crate::planner::Optimizer,
+        sql: &str,
+        stmt: Statement,
+        binder: &mut crate::binder::Binder,
+    ) -> Result<Option<OutputStream<'a>>, Error> {
+        let output = match self
+            .run_stmt_inner(session, optimizer, sql, stmt, binder)
+            .await
+        {
+            Ok(output) => output,
+            Err(e) => {
+                session.txn.fail().await?;
+                return Err(e);
             }
-            if !self.config.lock().unwrap().disable_optimizer {
+        };
+        Ok(output.map(|output| OutputStream {
+            header: output.header,
+            chunks: (output.chunks)
+                .then(move |chunk| async move {
+                    if chunk.is_err() {
+                        session.txn.fail().await?;
+                    }
+                    chunk
+                })
+                .boxed(),
+        }))
+    }
+
+    async fn run_stmt_inner(
+        &self,
+        session: &Session,
+        optimizer: &crate::planner::Optimizer,
+        sql: &str,
+        stmt: Statement,
+        binder: &mut crate::binder::Binder,
+    ) -> Result<Option<OutputStream<'static>>, Error> {
+        let Some(stmt) = self.handle_prepare(session, stmt, binder)? else {
+            return Ok(None);
+        };
+        let creates_temp = match &stmt {
+            Statement::CreateTable(create) => create.temporary,
+            Statement::CreateView { temporary, .. } => *temporary,
+            _ => false,
+        };
+        binder.set_temp_schema(session.temp_schema(&self.catalog, creates_temp));
+        let mut plan = binder.bind(stmt.clone()).map_err(|e| e.with_sql(sql))?;
+        if self.handle_transaction(&plan, session).await? {
+            return Ok(None);
+        }
+        let block = session.txn.block()?;
+        if self.handle_set(&plan, session)? {
+            return Ok(None);
+        }
+        if let Some(chunk) = self.handle_show(&plan, session)? {
+            return Ok(Some(OutputStream {
+                header: None,
+                chunks: stream::once(async { Ok(chunk) }).boxed(),
+            }));
+        }
+        let config = {
+            let config = session.config.lock().unwrap();
+            if !config.disable_optimizer {
                 plan = optimizer.optimize(plan);
             }
-            let executor = match self.storage.clone() {
-                StorageImpl::InMemoryStorage(s) => {
-                    crate::executor::build(optimizer.clone(), s, &plan)
-                }
-                StorageImpl::SecondaryStorage(s) => {
-                    crate::executor::build(optimizer.clone(), s, &plan)
-                }
-            };
-            let output = executor.try_collect().await?;
-            let mut chunk = Chunk::new(output);
-            chunk = bind_header(chunk, &stmt);
-            outputs.push(chunk);
-        }
-        Ok(outputs)
+            config.executor.clone()
+        };
+        let executor = match (self.storage.clone(), block) {
+            (StorageImpl::InMemoryStorage(s), Some(Block::InMemory(block))) => {
+                crate::executor::build(optimizer.clone(), s, config, Some(block), &plan)
+            }
+            (StorageImpl::InMemoryStorage(s), _) => {
+                crate::executor::build(optimizer.clone(), s, config, None, &plan)
+            }
+            (StorageImpl::SecondaryStorage(s), Some(Block::Secondary(block))) => {
+                crate::executor::build(optimizer.clone(), s, config, Some(block), &plan)
+            }
+            (StorageImpl::SecondaryStorage(s), _) => {
+                crate::executor::build(optimizer.clone(), s, config, None, &plan)
+            }
+        };
+        Ok(Some(OutputStream {
+            header: output_header(&stmt),
+            chunks: executor.map_err(Error::from).boxed(),
+        }))
     }
 
-    async fn get_storage_statistics(&self) -> Result<Statistics, Error> {
-        if let Some(mock) = &self.config.lock().unwrap().mock_stat {
+    async fn get_storage_statistics(&self, session: &Session) -> Result<Statistics, Error> {
+        if let Some(mock) = &session.config.lock().unwrap().mock_stat {
             return Ok(mock.clone());
         }
         let mut stat = Statistics::default();
@@ -157,20 +537,84 @@ impl Database {
         Ok(stat)
     }
 
-    /// Handle PRAGMA and SET statements.
-    fn handle_set(&self, plan: &RecExpr) -> Result<bool, Error> {
+    /// Handle PREPARE, EXECUTE and DEALLOCATE statements.
+    ///
+    /// Returns the statement to run, which is the prepared one for EXECUTE, or `None` if the
+    /// statement is handled. For EXECUTE, `binder` is replaced by one with the parameters.
+    fn handle_prepare(
+        &self,
+        session: &Session,
+        stmt: Statement,
+        binder: &mut crate::binder::Binder,
+    ) -> Result<Option<Statement>, Error> {
+        match stmt {
+            Statement::Prepare {
+                name,
+                data_types,
+                statement,
+            } => {
+                let types = data_types.iter().map(|ty| Some(ty.into())).collect();
+                let sql = statement.to_string();
+                let mut binder =
+                    crate::binder::Binder::with_params(self.catalog.clone(), vec![], types);
+                binder.set_temp_schema(session.temp_schema(&self.catalog, false));
+                binder
+                    .bind((*statement).clone())
+                    .map_err(|e| e.with_sql(&sql))?;
+                let prepared = PreparedStatement::new(&sql, Some(*statement), &binder);
+                session.add_prepared(&name.to_string(), prepared)?;
+                Ok(None)
+            }
+            Statement::Execute {
+                name, parameters, ..
+            } => {
+                let prepared = session.get_prepared(&name.to_string())?;
+                let params = (parameters.into_iter().zip(&prepared.param_types))
+                    .map(|(expr, ty)| {
+                        literal(expr)?
+                            .cast(ty)
+                            .map_err(|e| Error::Internal(e.to_string()))
+                    })
+                    .try_collect()?;
+                *binder = self.binder_for(&prepared, params)?;
+                Ok(prepared.stmt)
+            }
+            Statement::Deallocate { name, .. } => {
+                session.deallocate(&name.to_string())?;
+                Ok(None)
+            }
+            stmt => Ok(Some(stmt)),
+        }
+    }
+
+    /// Handle BEGIN, COMMIT and ROLLBACK statements.
+    async fn handle_transaction(&self, plan: &RecExpr, session: &Session) -> Result<bool, Error> {
         let root = &plan.as_ref()[plan.as_ref().len() - 1];
+        let Expr::Transaction(command) = root else {
+            return Ok(false);
+        };
+        match plan[*command].as_const().as_str() {
+            "begin" => session.txn.begin(&self.storage),
+            "commit" => session.txn.commit().await?,
+            "rollback" => session.txn.rollback().await?,
+            command => {
+                return Err(Error::Internal(format!(
+                    "unsupported transaction command: {command}"
+                )));
+            }
+        }
+        Ok(true)
+    }
+
+    /// Handle PRAGMA, SET and RESET statements.
+    fn handle_set(&self, plan: &RecExpr, session: &Session) -> Result<bool, Error> {
+        let root = &plan.as_ref()[plan.as_ref().len() - 1];
+        let mut config = session.config.lock().unwrap();
         match root {
             Expr::Pragma([name, _value]) => match plan[*name].as_const().as_str() {
-                "enable_optimizer" => {
-                    self.config.lock().unwrap().disable_optimizer = false;
-                    Ok(true)
-                }
-                "disable_optimizer" => {
-                    self.config.lock().unwrap().disable_optimizer = true;
-                    Ok(true)
-                }
-                name => Err(Error::Internal(format!("no such pragma: {name}"))),
+                "enable_optimizer" => config.disable_optimizer = false,
+                "disable_optimizer" => config.disable_optimizer = true,
+                name => return Err(Error::Internal(format!("no such pragma: {name}"))),
             },
             Expr::Set([name, value]) => match plan[*name].as_const().as_str() {
                 // Mock the row count of a table for planner test.
@@ -181,18 +625,49 @@ impl Database {
                         .catalog
                         .get_table_id_by_name("postgres", table_name)
                         .ok_or_else(|| Error::Internal("table not found".into()))?;
-                    self.config
-                        .lock()
-                        .unwrap()
-                        .mock_stat
-                        .get_or_insert_with(Default::default)
+                    (config.mock_stat.get_or_insert_with(Default::default))
                         .add_row_count(table_id, count);
-                    Ok(true)
                 }
-                _ => Ok(false),
+                name => {
+                    let value = match &plan[*value] {
+                        Expr::Constant(v) => v.clone(),
+                        _ => DataValue::Null,
+                    };
+                    config.set(name, &value)?;
+                }
             },
-            _ => Ok(false),
+            Expr::Reset(name) => config.reset(plan[*name].as_const().as_str())?,
+            _ => return Ok(false),
         }
+        Ok(true)
+    }
+
+    /// Handle SHOW statements. Returns the values of settings.
+    fn handle_show(&self, plan: &RecExpr, session: &Session) -> Result<Option<DataChunk>, Error> {
+        let root = &plan.as_ref()[plan.as_ref().len() - 1];
+        let Expr::Show(name) = root else {
+            return Ok(None);
+        };
+        let config = session.config.lock().unwrap();
+        let show = |name: &str| -> Result<String, Error> {
+            Ok(match config.get(name)? {
+                DataValue::String(s) => s.to_string(),
+                value => value.to_string(),
+            })
+        };
+        let chunk = match plan[*name].as_const().as_str() {
+            "all" => {
+                let settings: Vec<_> = Config::NAMES.iter().map(|name| show(name)).try_collect()?;
+                DataChunk::from_iter([
+                    ArrayImpl::new_string(Config::NAMES.iter().map(Some).collect()),
+                    ArrayImpl::new_string(settings.iter().map(Some).collect()),
+                ])
+            }
+            name => DataChunk::from_iter([ArrayImpl::new_string(
+                [Some(show(name)?)].into_iter().collect(),
+            )]),
+        };
+        Ok(Some(chunk))
     }
 
     /// Return all available pragma options.
@@ -201,6 +676,32 @@ impl Database {
     }
 }
 
+/// Converts a literal parameter of `EXECUTE` to a value.
+fn literal(expr: crate::parser::Expr) -> Result<DataValue, Error> {
+    use crate::parser::{Expr, UnaryOperator, Value};
+    let value = match expr {
+        Expr::Value(
+            v
+            @ (Value::Number(..) | Value::SingleQuotedString(_) | Value::Boolean(_) | Value::Null),
+        ) => v,
+        Expr::UnaryOp {
+            op: UnaryOperator::Minus,
+            expr,
+        } if matches!(*expr, Expr::Value(Value::Number(..))) => {
+            let Expr::Value(Value::Number(n, long)) = *expr else {
+                unreachable!()
+            };
+            Value::Number(format!("-{n}"), long)
+        }
+        expr => {
+            return Err(Error::Internal(format!(
+                "EXECUTE parameter must be a literal: {expr}"
+            )));
+        }
+    };
+    Ok(value.into())
+}
+
 /// The error type of database operations.
 #[derive(thiserror::Error, Debug)]
 pub enum Error {
@@ -233,6 +734,18 @@ pub enum Error {
     Internal(String),
 }
 
+impl Error {
+    /// Returns true if the statement failed due to a conflict with a concurrent transaction, and
+    /// the transaction can be retried.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            Error::Execute(e) => e.is_conflict(),
+            Error::Storage(e) => e.is_conflict(),
+            _ => false,
+        }
+    }
+}
+
 impl rustyline::Helper for &Database {}
 impl rustyline::validate::Validator for &Database {}
 impl rustyline::highlight::Highlighter for &Database {}
@@ -304,6 +817,215 @@ mod tests {
         assert_complete(&db, "pragma en", "pragma enable_optimizer");
     }
 
+    #[tokio::test]
+    async fn prepared_statement() {
+        let db = Database::new_in_memory();
+        db.run("create table t (a int, b string)").await.unwrap();
+
+        let insert = db.prepare("insert into t values ($1, $2)", &[]).unwrap();
+        assert_eq!(insert.param_types(), [DataType::Int32, DataType::String]);
+        assert!(!insert.returns_rows());
+        for (a, b) in [(1, "one"), (2, "two")] {
+            let params = vec![DataValue::Int32(a), DataValue::String(b.into())];
+            db.execute(&insert, params).await.unwrap();
+        }
+
+        let select = (db.prepare("select a, b as c, a + 1 from t where a > $1", &[])).unwrap();
+        assert_eq!(select.param_types(), [DataType::Int32]);
+        assert_eq!(
+            select.columns(),
+            [
+                ("a".to_string(), DataType::Int32),
+                ("c".to_string(), DataType::String),
+                ("?column?".to_string(), DataType::Int32),
+            ]
+        );
+        let chunk = db
+            .execute(&select, vec![DataValue::Int32(1)])
+            .await
+            .unwrap();
+        let rows = crate::array::datachunk_to_sqllogictest_string(&chunk.unwrap());
+        assert_eq!(rows, [["2", "two", "3"]]);
+
+        // parameter types can be specified, inferred from casts, or default to string
+        let stmt = db
+            .prepare("select $1, $2::int, $3", &[Some(DataType::Int64)])
+            .unwrap();
+        assert_eq!(
+            stmt.param_types(),
+            [DataType::Int64, DataType::Int32, DataType::String]
+        );
+
+        assert!(db.execute(&select, vec![]).await.is_err());
+        assert!(db.prepare("select 1; select 2", &[]).is_err());
+        assert!(db.prepare("", &[]).unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn transaction_sessions() {
+        let db = Database::new_in_memory();
+        db.run("create table t (a int)").await.unwrap();
+
+        let (s1, s2) = (Session::new(), Session::new());
+        let count = async |session| {
+            let outputs = db
+                .run_statements_in(session, "select count(*) from t")
+                .await
+                .unwrap();
+            let chunk = outputs[0].1.as_ref().unwrap();
+            crate::array::datachunk_to_sqllogictest_string(chunk)[0][0].clone()
+        };
+        (db.run_statements_in(&s1, "begin; insert into t values (1), (2)"))
+            .await
+            .unwrap();
+        // uncommitted writes are only visible in their own session
+        assert_eq!(count(&s1).await, "2");
+        assert_eq!(count(&s2).await, "0");
+
+        db.run_statements_in(&s1, "commit").await.unwrap();
+        assert_eq!(count(&s2).await, "2");
+    }
+
+    #[tokio::test]
+    async fn stream_outputs() {
+        let db = Database::new_in_memory();
+        let sql = "
+            create table t (a string);
+            insert into t values ('1');
+            insert into t values ('x');
+            select a::int from t;
+        ";
+        let mut outputs = (db.stream_statements(sql).await.into_iter())
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(
+            outputs[1].1.as_ref().unwrap().header().unwrap(),
+            ["$insert.row_counts"]
+        );
+        // the error is returned from the stream after the first chunk
+        let (_, output) = outputs.pop().unwrap();
+        let rows = output.unwrap().collect::<Vec<_>>().await;
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0].as_ref().unwrap().cardinality(), 1);
+        assert!(rows[1].is_err());
+
+        // statements after a failed one are not run
+        let outputs =
+            (db.stream_statements("insert into t values ('2'); select x; drop table t")).await;
+        assert_eq!(outputs.len(), 2);
+        assert!(outputs[0].is_ok());
+        assert!(outputs[1].is_err());
+
+        // an error in the stream aborts the transaction block
+        let session = Session::new();
+        let (_, output) = db
+            .stream_statements_in(&session, "begin; select a::int from t")
+            .await
+            .pop()
+            .unwrap()
+            .unwrap();
+        assert!(output.unwrap().into_chunk().await.is_err());
+        let err = db
+            .run_statements_in(&session, "select 1")
+            .await
+            .unwrap_err();
+        assert!(
+            err.to_string().contains("current transaction is aborted"),
+            "{err}"
+        );
+    }
+
+    #[tokio::test]
+    async fn transaction_conflict() {
+        let db = Database::new_on_disk(SecondaryStorageOptions::default_for_test()).await;
+        db.run("create table t (a int); insert into t values (1), (2)")
+            .await
+            .unwrap();
+
+        let (s1, s2) = (Session::new(), Session::new());
+        for s in [&s1, &s2] {
+            (db.run_statements_in(s, "begin; delete from t where a = 1"))
+                .await
+                .unwrap();
+        }
+        db.run_statements_in(&s1, "commit").await.unwrap();
+        let err = db.run_statements_in(&s2, "commit").await.unwrap_err();
+        assert!(err.is_retryable(), "{err}");
+
+        // the transaction is rolled back and can be retried
+        let count = db.run("select count(*) from t").await.unwrap();
+        assert_eq!(
+            crate::array::datachunk_to_sqllogictest_string(&count[0]),
+            [["1"]]
+        );
+    }
+
+    #[tokio::test]
+    async fn session_state() {
+        let db = Database::new_in_memory();
+        let show = async |session| {
+            let chunks = db.run_in(session, "show parallelism").await.unwrap();
+            crate::array::datachunk_to_sqllogictest_string(&chunks[0])[0][0].clone()
+        };
+        let (s1, s2) = (Session::new(), Session::new());
+        db.run_in(&s1, "set parallelism = 4").await.unwrap();
+        // settings are only changed in their own session
+        assert_eq!(show(&s1).await, "4");
+        assert_eq!(show(&s2).await, "1");
+        db.run_in(&s1, "reset parallelism").await.unwrap();
+        assert_eq!(show(&s1).await, "1");
+
+        // prepared statements are only visible in their own session
+        (db.run_in(&s1, "prepare add(int) as select $1 + 1"))
+            .await
+            .unwrap();
+        let chunks = db.run_in(&s1, "execute add(41)").await.unwrap();
+        assert_eq!(
+            crate::array::datachunk_to_sqllogictest_string(&chunks[0]),
+            [["42"]]
+        );
+        assert!(db.run_in(&s2, "execute add(41)").await.is_err());
+
+        // temporary tables are only visible in their own session
+        (db.run_in(&s1, "create temporary table t (a int)"))
+            .await
+            .unwrap();
+        db.run_in(&s1, "insert into t values (1)").await.unwrap();
+        assert!(db.run_in(&s2, "select * from t").await.is_err());
+        (db.run_in(&s2, "create temporary table t (a int, b int)"))
+            .await
+            .unwrap();
+        // and shadow other tables of the same name
+        (db.run_in(&s2, "create table u (a int, b int)"))
+            .await
+            .unwrap();
+        (db.run_in(&s1, "create temporary table u (a int)"))
+            .await
+            .unwrap();
+        let has_b = async |session, table| {
+            let sql = format!("select b from {table}");
+            db.run_in(session, &sql).await.is_ok()
+        };
+        assert!(!has_b(&s1, "t").await);
+        assert!(has_b(&s2, "t").await);
+        assert!(!has_b(&s1, "u").await);
+        assert!(has_b(&s2, "u").await);
+
+        // a failed CREATE leaves no temporary table
+        assert!(
+            (db.run_in(&s1, "create temporary table v (a int, a int)"))
+                .await
+                .is_err()
+        );
+        assert!(db.run_in(&s1, "select * from v").await.is_err());
+
+        // temporary tables are dropped when the session is closed
+        db.close_session(&s1).await.unwrap();
+        assert!(has_b(&s1, "u").await);
+        assert!(db.run_in(&s1, "select * from t").await.is_err());
+        assert!(has_b(&s2, "t").await);
+    }
+
     /// Assert that if complete (e.g. press tab) the given `line`, the result will be
     /// `completed_line`.
     ///
@@ -0,0 +1,274 @@
+// Copyright 2024 RisingLight Project Authors. Licensed under Apache-2.0.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use super::{Error, PreparedStatement};
+use crate::catalog::{RootCatalog, SchemaId};
+use crate::executor::TransactionBlock;
+use crate::planner::Statistics;
+use crate::storage::{InMemoryStorage, SecondaryStorage, StorageImpl};
+use crate::types::DataValue;
+
+/// The state of a client session.
+///
+/// Each pgwire connection, Python handle and shell has its own session, which holds:
+///
+/// * the settings changed by `SET` and `PRAGMA`,
+/// * the transaction block started by `BEGIN`,
+/// * the statements prepared by `PREPARE`,
+/// * the schema of temporary tables and views created by `CREATE TEMPORARY`.
+///
+/// Temporary objects are stored in a schema of the session, which is only visible to the session
+/// and shadows other schemas for unqualified names. The schema is dropped when the session is
+/// closed by [`Database::close_session`](super::Database::close_session).
+#[derive(Default)]
+pub struct Session {
+    pub(super) config: Mutex<Config>,
+    pub(super) txn: TransactionState,
+    prepared: Mutex<HashMap<String, PreparedStatement>>,
+    temp_schema: Mutex<Option<SchemaId>>,
+}
+
+impl Session {
+    /// Creates a new session with default settings.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Saves a statement prepared by `PREPARE`.
+    pub(super) fn add_prepared(&self, name: &str, stmt: PreparedStatement) -> Result<(), Error> {
+        let mut prepared = self.prepared.lock().unwrap();
+        if prepared.contains_key(name) {
+            return Err(Error::Internal(format!(
+                "prepared statement \"{name}\" already exists"
+            )));
+        }
+        prepared.insert(name.into(), stmt);
+        Ok(())
+    }
+
+    /// Returns a statement prepared by `PREPARE`.
+    pub(super) fn get_prepared(&self, name: &str) -> Result<PreparedStatement, Error> {
+        (self.prepared.lock().unwrap().get(name).cloned())
+            .ok_or_else(|| Error::Internal(format!("prepared statement \"{name}\" does not exist")))
+    }
+
+    /// Removes a statement prepared by `PREPARE`, or all of them if `name` is `ALL`.
+    pub(super) fn deallocate(&self, name: &str) -> Result<(), Error> {
+        let mut prepared = self.prepared.lock().unwrap();
+        if name.eq_ignore_ascii_case("all") {
+            prepared.clear();
+        } else if prepared.remove(name).is_none() {
+            return Err(Error::Internal(format!(
+                "prepared statement \"{name}\" does not exist"
+            )));
+        }
+        Ok(())
+    }
+
+    /// Returns the schema of temporary objects in the session, and creates it if `create` is
+    /// true.
+    pub(super) fn temp_schema(&self, catalog: &RootCatalog, create: bool) -> Option<SchemaId> {
+        let mut schema = self.temp_schema.lock().unwrap();
+        if schema.is_none() && create {
+            *schema = Some(catalog.add_temp_schema());
+        }
+        *schema
+    }
+
+    /// Takes the schema of temporary objects in the session.
+    pub(super) fn take_temp_schema(&self) -> Option<SchemaId> {
+        self.temp_schema.lock().unwrap().take()
+    }
+}
+
+/// The settings of a session.
+#[derive(Debug, Default)]
+pub(super) struct Config {
+    pub disable_optimizer: bool,
+    pub mock_stat: Option<Statistics>,
+    pub executor: crate::executor::Config,
+}
+
+impl Config {
+    /// The settings that can be changed by `SET`, shown by `SHOW` and reset by `RESET`.
+    pub const NAMES: &[&str] = &[
+        "agg_memory_limit",
+        "cte_max_recursion_depth",
+        "join_memory_limit",
+        "parallelism",
+        "query_memory_limit",
+        "sort_memory_limit",
+        "spill_dir",
+    ];
+
+    /// Returns the value of a setting.
+    pub fn get(&self, name: &str) -> Result<DataValue, Error> {
+        let config = &self.executor;
+        let value = match name {
+            "agg_memory_limit" => config.agg_memory_limit,
+            "cte_max_recursion_depth" => config.cte_max_recursion_depth,
+            "join_memory_limit" => config.join_memory_limit,
+            "parallelism" => config.parallelism,
+            "query_memory_limit" => config.query_memory_limit,
+            "sort_memory_limit" => config.sort_memory_limit,
+            "spill_dir" => {
+                let dir = config.spill_dir.display().to_string();
+                return Ok(DataValue::String(dir.into()));
+            }
+            _ => return Err(unknown_setting(name)),
+        };
+        Ok(DataValue::Int64(value as i64))
+    }
+
+    /// Changes the value of a setting.
+    pub fn set(&mut self, name: &str, value: &DataValue) -> Result<(), Error> {
+        let non_negative =
+            || {
+                value.as_usize().ok().flatten().ok_or_else(|| {
+                    Error::Internal(format!("{name} must be a non-negative integer"))
+                })
+            };
+        let config = &mut self.executor;
+        match name {
+            "agg_memory_limit" => config.agg_memory_limit = non_negative()?,
+            "cte_max_recursion_depth" => config.cte_max_recursion_depth = non_negative()?,
+            "join_memory_limit" => config.join_memory_limit = non_negative()?,
+            "parallelism" => {
+                config.parallelism = non_negative().ok().filter(|n| *n > 0).ok_or_else(|| {
+                    Error::Internal("parallelism must be a positive integer".into())
+                })?
+            }
+            "query_memory_limit" => config.query_memory_limit = non_negative()?,
+            "sort_memory_limit" => config.sort_memory_limit = non_negative()?,
+            "spill_dir" => {
+                let DataValue::String(dir) = value else {
+                    return Err(Error::Internal("spill_dir must be a string".into()));
+                };
+                config.spill_dir = dir.to_string().into();
+            }
+            _ => return Err(unknown_setting(name)),
+        }
+        Ok(())
+    }
+
+    /// Resets a setting to its default value, or all settings if `name` is `all`.
+    pub fn reset(&mut self, name: &str) -> Result<(), Error> {
+        if name == "all" {
+            *self = Config::default();
+            return Ok(());
+        }
+        let value = Config::default().get(name)?;
+        self.set(name, &value)
+    }
+}
+
+fn unknown_setting(name: &str) -> Error {
+    Error::Internal(format!("unrecognized configuration parameter \"{name}\""))
+}
+
+/// The transaction state of a session.
+///
+/// Statements run with the same state share the transaction block started by `BEGIN`, and see
+/// the writes of each other. Only data changes are transactional. DDL statements take effect
+/// immediately even in a transaction block.
+#[derive(Default)]
+pub(super) struct TransactionState {
+    inner: Mutex<BlockState>,
+}
+
+#[derive(Default)]
+enum BlockState {
+    /// Not in a transaction block.
+    #[default]
+    Idle,
+    /// In a transaction block.
+    InBlock(Block),
+    /// A statement failed in the transaction block. The block has been aborted, and statements
+    /// are rejected until `COMMIT` or `ROLLBACK`.
+    Failed,
+}
+
+/// A transaction block on a storage engine.
+#[derive(Clone)]
+pub(super) enum Block {
+    InMemory(Arc<TransactionBlock<InMemoryStorage>>),
+    Secondary(Arc<TransactionBlock<SecondaryStorage>>),
+}
+
+impl Block {
+    async fn commit(&self) -> Result<(), Error> {
+        match self {
+            Block::InMemory(block) => block.commit().await?,
+            Block::Secondary(block) => block.commit().await?,
+        }
+        Ok(())
+    }
+
+    async fn abort(&self) -> Result<(), Error> {
+        match self {
+            Block::InMemory(block) => block.abort().await?,
+            Block::Secondary(block) => block.abort().await?,
+        }
+        Ok(())
+    }
+}
+
+impl TransactionState {
+    /// Starts a transaction block. Does nothing if already in a block.
+    pub fn begin(&self, storage: &StorageImpl) {
+        let mut state = self.inner.lock().unwrap();
+        if let BlockState::Idle = *state {
+            *state = BlockState::InBlock(match storage {
+                StorageImpl::InMemoryStorage(_) => Block::InMemory(Default::default()),
+                StorageImpl::SecondaryStorage(_) => Block::Secondary(Default::default()),
+            });
+        }
+    }
+
+    /// Commits the transaction block. A failed block is rolled back.
+    pub async fn commit(&self) -> Result<(), Error> {
+        let state = std::mem::take(&mut *self.inner.lock().unwrap());
+        if let BlockState::InBlock(block) = state {
+            block.commit().await?;
+        }
+        Ok(())
+    }
+
+    /// Rolls back the transaction block.
+    pub async fn rollback(&self) -> Result<(), Error> {
+        let state = std::mem::take(&mut *self.inner.lock().unwrap());
+        if let BlockState::InBlock(block) = state {
+            block.abort().await?;
+        }
+        Ok(())
+    }
+
+    /// Aborts the transaction block after a statement in it failed.
+    pub async fn fail(&self) -> Result<(), Error> {
+        let state = {
+            let mut state = self.inner.lock().unwrap();
+            match *state {
+                BlockState::Idle => return Ok(()),
+                _ => std::mem::replace(&mut *state, BlockState::Failed),
+            }
+        };
+        if let BlockState::InBlock(block) = state {
+            block.abort().await?;
+        }
+        Ok(())
+    }
+
+    /// Returns the current transaction block.
+    pub fn block(&self) -> Result<Option<Block>, Error> {
+        match &*self.inner.lock().unwrap() {
+            BlockState::Idle => Ok(None),
+            BlockState::InBlock(block) => Ok(Some(block.clone())),
+            BlockState::Failed => Err(Error::Internal(
+                "current transaction is aborted, commands ignored until end of transaction block"
+                    .into(),
+            )),
+        }
+    }
+}
@@ -0,0 +1,73 @@
+// Copyright 2025 RisingLight Project Authors. Licensed under Apache-2.0.
+
+use std::sync::Arc;
+
+use super::*;
+use crate::binder::{AlterTable, AlterTableOperation};
+use crate::catalog::ColumnId;
+use crate::storage::{ScanOptions, Storage, StorageColumnRef, Table, Transaction, TxnIterator};
+use crate::types::DataType;
+
+/// The executor of `alter table` statement.
+pub struct AlterTableExecutor<S: Storage> {
+    pub alter: Box<AlterTable>,
+    pub storage: Arc<S>,
+}
+
+impl<S: Storage> AlterTableExecutor<S> {
+    #[try_stream(boxed, ok = DataChunk, error = ExecutorError)]
+    pub async fn execute(self) {
+        // existing rows would take null in a new column without default value
+        if let AlterTableOperation::AddColumn(column) = &self.alter.operation
+            && !column.is_nullable()
+            && column.default_value().is_none_or(|v| v.is_null())
+            && !self.is_empty().await?
+        {
+            Err(ExecutorError::contains_null(column.name()))?;
+        }
+        // existing values must be casted to the new type when reading
+        if let AlterTableOperation::AlterColumnType {
+            column_id,
+            data_type,
+        } = &self.alter.operation
+        {
+            self.check_cast(*column_id, data_type).await?;
+        }
+        self.storage
+            .alter_table(self.alter.table_id, &self.alter.operation)
+            .await?;
+
+        yield DataChunk::single(1);
+    }
+
+    /// Returns true if the table has no rows.
+    async fn is_empty(&self) -> Result<bool> {
+        let table = self.storage.get_table(self.alter.table_id)?;
+        let txn = table.read().await?;
+        let mut iter = (txn.scan(&[StorageColumnRef::RowHandler], ScanOptions::default())).await?;
+        let mut empty = true;
+        while let Some(chunk) = iter.next_batch(None).await? {
+            if chunk.cardinality() > 0 {
+                empty = false;
+                break;
+            }
+        }
+        drop(iter);
+        txn.abort().await?;
+        Ok(empty)
+    }
+
+    /// Returns an error if any value of the column can not be casted to `data_type`.
+    async fn check_cast(&self, column_id: ColumnId, data_type: &DataType) -> Result<()> {
+        let table = self.storage.get_table(self.alter.table_id)?;
+        let txn = table.read().await?;
+        let mut iter =
+            (txn.scan(&[StorageColumnRef::Idx(column_id)], ScanOptions::default())).await?;
+        while let Some(chunk) = iter.next_batch(None).await? {
+            chunk.array_at(0).cast(data_type)?;
+        }
+        drop(iter);
+        txn.abort().await?;
+        Ok(())
+    }
+}
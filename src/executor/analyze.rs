@@ -57,10 +57,13 @@ pub struct Metrics {
 }
 
 impl Metrics {
-    /// Register metrics for a node.
-    pub fn register(&mut self, id: Id, span: TimeSpan, rows: Counter) {
-        self.spans.insert(id, span);
-        self.rows.insert(id, rows);
+    /// Returns the metrics of a node, registering them if not exist.
+    ///
+    /// All partitions of a node share the same metrics.
+    pub fn get_or_register(&mut self, id: Id) -> (TimeSpan, Counter) {
+        let span = self.spans.entry(id).or_default().clone();
+        let rows = self.rows.entry(id).or_default().clone();
+        (span, rows)
     }
 
     /// Get the running time for a node.
@@ -2,12 +2,15 @@
 
 use std::fs::File;
 use std::io::BufReader;
+use std::path::Path;
 
+use ::parquet::file::reader::{FileReader, SerializedFileReader};
+use ::parquet::schema::types::Type;
 use indicatif::{ProgressBar, ProgressStyle};
 use tokio::sync::mpsc::Sender;
 
 use super::*;
-use crate::array::{ArrayImpl, DataChunkBuilder};
+use crate::array::{ArrayImpl, DataChunkBuilder, parquet};
 use crate::binder::copy::{ExtSource, FileFormat};
 use crate::types::DataType;
 
@@ -28,7 +31,10 @@ impl CopyFromFileExecutor {
         // # Cancellation
         // When this stream is dropped, the `rx` is dropped, the spawned task will fail to send to
         // `tx`, then the task will finish.
-        let handle = tokio::task::spawn_blocking(|| self.read_file_blocking(tx));
+        let handle = tokio::task::spawn_blocking(move || {
+            let projection = (0..self.types.len()).collect_vec();
+            read_file_blocking(&self.source, &projection, &self.types, tx)
+        });
         while let Some(mut chunk) = rx.recv().await {
             // rescale decimals
             for (i, ty) in types.iter().enumerate() {
@@ -42,73 +48,172 @@ impl CopyFromFileExecutor {
         }
         handle.await.unwrap()?;
     }
+}
 
-    /// Read records from file using blocking IO.
-    ///
-    /// The read data chunks will be sent through `tx`.
-    fn read_file_blocking(self, tx: Sender<DataChunk>) -> Result<()> {
-        let file = File::open(self.source.path)?;
-        let file_size = file.metadata()?.len();
-        let mut buf_reader = BufReader::new(file);
-        let mut reader = match self.source.format {
-            FileFormat::Csv {
-                delimiter,
-                quote,
-                escape,
-                header,
-            } => csv::ReaderBuilder::new()
+/// Read records from file using blocking IO.
+///
+/// Only columns at the `projection` indices are read, and converted to `types`.
+/// The read data chunks will be sent through `tx`.
+pub(super) fn read_file_blocking(
+    source: &ExtSource,
+    projection: &[usize],
+    types: &[DataType],
+    tx: Sender<DataChunk>,
+) -> Result<()> {
+    let column_count = source.columns.len();
+    match source.format {
+        FileFormat::Csv {
+            delimiter,
+            quote,
+            escape,
+            header,
+        } => {
+            let mut builder = csv::ReaderBuilder::new();
+            builder
                 .delimiter(delimiter as u8)
                 .quote(quote as u8)
                 .escape(escape.map(|c| c as u8))
-                .has_headers(header)
-                .from_reader(&mut buf_reader),
-        };
+                .has_headers(header);
+            read_csv(&source.path, &builder, column_count, projection, types, tx)
+        }
+        FileFormat::Parquet => read_parquet(&source.path, column_count, projection, types, tx),
+    }
+}
 
-        let bar = if file_size < IMPORT_PROGRESS_BAR_LIMIT {
-            // disable progress bar if file size is < 1MB
-            ProgressBar::hidden()
-        } else {
-            let bar = ProgressBar::new(file_size);
-            bar.set_style(
-                ProgressStyle::default_bar()
-                    .template("[{elapsed_precise}] {bar:40.cyan/blue} {bytes}/{total_bytes}")
-                    .unwrap()
-                    .progress_chars("=>-"),
-            );
-            bar
-        };
+/// Read CSV records from file and send data chunks through `tx`.
+fn read_csv(
+    path: &Path,
+    builder: &csv::ReaderBuilder,
+    column_count: usize,
+    projection: &[usize],
+    types: &[DataType],
+    tx: Sender<DataChunk>,
+) -> Result<()> {
+    let file = File::open(path)?;
+    let file_size = file.metadata()?.len();
+    let mut buf_reader = BufReader::new(file);
+    let mut reader = builder.from_reader(&mut buf_reader);
 
-        let column_count = self.types.len();
+    let bar = progress_bar(file_size);
 
-        // create chunk builder
-        let mut chunk_builder = DataChunkBuilder::new(&self.types, PROCESSING_WINDOW_SIZE);
-        let mut size_count = 0;
+    // create chunk builder
+    let mut chunk_builder = DataChunkBuilder::new(types, PROCESSING_WINDOW_SIZE);
+    let mut size_count = 0;
 
-        for record in reader.records() {
-            // read records and push raw str rows into data chunk builder
-            let record = record?;
+    for record in reader.records() {
+        // read records and push raw str rows into data chunk builder
+        let record = record?;
 
-            if !(record.len() == column_count
-                || record.len() == column_count + 1 && record.get(column_count) == Some(""))
-            {
-                return Err(Error::length_mismatch(column_count, record.len()));
-            }
+        if !(record.len() == column_count
+            || record.len() == column_count + 1 && record.get(column_count) == Some(""))
+        {
+            return Err(Error::length_mismatch(column_count, record.len()));
+        }
 
-            size_count += record.as_slice().len();
+        size_count += record.as_slice().len();
 
-            // push a raw str row and send it if necessary
-            if let Some(chunk) = chunk_builder.push_str_row(record.iter())? {
-                bar.set_position(size_count as u64);
-                tx.blocking_send(chunk).map_err(|_| Error::aborted())?;
+        // push a raw str row and send it if necessary
+        let row = projection.iter().map(|i| &record[*i]);
+        if let Some(chunk) = chunk_builder.push_str_row(row)? {
+            bar.set_position(size_count as u64);
+            tx.blocking_send(chunk).map_err(|_| Error::aborted())?;
+        }
+    }
+    // send left chunk
+    if let Some(chunk) = chunk_builder.take() {
+        tx.blocking_send(chunk).map_err(|_| Error::aborted())?;
+    }
+    bar.finish();
+    Ok(())
+}
+
+/// Read Parquet file row group by row group and send data chunks through `tx`.
+fn read_parquet(
+    path: &Path,
+    column_count: usize,
+    projection: &[usize],
+    types: &[DataType],
+    tx: Sender<DataChunk>,
+) -> Result<()> {
+    let file = File::open(path)?;
+    let file_size = file.metadata()?.len();
+    let reader = SerializedFileReader::new(file)?;
+    let metadata = reader.metadata();
+
+    // the types of columns in file, which are casted to `types` if different
+    let schema = metadata.file_metadata().schema_descr();
+    let file_types = parquet::data_types(schema)?;
+    if file_types.len() != column_count {
+        return Err(Error::length_mismatch(column_count, file_types.len()));
+    }
+    let fields = schema.root_schema().get_fields();
+    let projected_schema = Type::group_type_builder("schema")
+        .with_fields(projection.iter().map(|i| fields[*i].clone()).collect())
+        .build()?;
+    let file_types = projection.iter().map(|i| &file_types[*i]).collect_vec();
+    let bar = progress_bar(file_size);
+
+    // create chunk builder
+    let mut chunk_builder = DataChunkBuilder::new(types, PROCESSING_WINDOW_SIZE);
+    let mut size_count = 0;
+
+    for i in 0..reader.num_row_groups() {
+        if projection.is_empty() {
+            // only count rows
+            for _ in 0..metadata.row_group(i).num_rows() {
+                if let Some(chunk) = chunk_builder.push_row([]) {
+                    tx.blocking_send(chunk).map_err(|_| Error::aborted())?;
+                }
             }
+            size_count += metadata.row_group(i).compressed_size();
+            bar.set_position(size_count as u64);
+            continue;
         }
-        // send left chunk
-        if let Some(chunk) = chunk_builder.take() {
-            tx.blocking_send(chunk).map_err(|_| Error::aborted())?;
+        let row_group = reader.get_row_group(i)?;
+        for row in row_group.get_row_iter(Some(projected_schema.clone()))? {
+            let mut values = Vec::with_capacity(types.len());
+            for ((_, field), (file_type, ty)) in row?
+                .into_columns()
+                .into_iter()
+                .zip(file_types.iter().zip(types))
+            {
+                let value = parquet::value(field, file_type)?;
+                values.push(if value.is_null() || *file_type == ty {
+                    value
+                } else {
+                    value.cast(ty)?
+                });
+            }
+            // push a row and send it if necessary
+            if let Some(chunk) = chunk_builder.push_row(values) {
+                tx.blocking_send(chunk).map_err(|_| Error::aborted())?;
+            }
         }
-        bar.finish();
-        Ok(())
+        size_count += metadata.row_group(i).compressed_size();
+        bar.set_position(size_count as u64);
+    }
+    // send left chunk
+    if let Some(chunk) = chunk_builder.take() {
+        tx.blocking_send(chunk).map_err(|_| Error::aborted())?;
     }
+    bar.finish();
+    Ok(())
+}
+
+/// Returns a progress bar of the file, which is hidden for small files.
+fn progress_bar(file_size: u64) -> ProgressBar {
+    if file_size < IMPORT_PROGRESS_BAR_LIMIT {
+        // disable progress bar if file size is < 1MB
+        return ProgressBar::hidden();
+    }
+    let bar = ProgressBar::new(file_size);
+    bar.set_style(
+        ProgressStyle::default_bar()
+            .template("[{elapsed_precise}] {bar:40.cyan/blue} {bytes}/{total_bytes}")
+            .unwrap()
+            .progress_chars("=>-"),
+    );
+    bar
 }
 
 #[cfg(test)]
@@ -135,6 +240,7 @@ mod tests {
                     escape: None,
                     header: false,
                 },
+                columns: vec!["a".into(), "b".into(), "c".into()],
             },
             types: vec![DataType::Int32, DataType::Float64, DataType::String],
         };
@@ -149,4 +255,37 @@ mod tests {
         .collect();
         assert_eq!(actual, expected);
     }
+
+    #[tokio::test]
+    async fn read_parquet() {
+        let file = tempfile::NamedTempFile::new().expect("failed to create temp file");
+        let source = ExtSource {
+            path: file.path().into(),
+            format: FileFormat::Parquet,
+            columns: vec!["a".into(), "b".into(), "c".into()],
+        };
+        let chunk: DataChunk = [
+            ArrayImpl::new_int32([1, 2].into_iter().collect()),
+            ArrayImpl::new_decimal([Some("1.50".parse().unwrap()), None].into_iter().collect()),
+            ArrayImpl::new_string(["one", "two"].iter().map(Some).collect()),
+        ]
+        .into_iter()
+        .collect();
+        let types = vec![
+            DataType::Int32,
+            DataType::Decimal(Some(10), Some(2)),
+            DataType::String,
+        ];
+
+        let writer = CopyToFileExecutor {
+            source: source.clone(),
+            types: types.clone(),
+        };
+        let child = futures::stream::iter([Ok(chunk.clone())]).boxed();
+        writer.execute(child).next().await.unwrap().unwrap();
+
+        let executor = CopyFromFileExecutor { source, types };
+        let actual = executor.execute().next().await.unwrap().unwrap();
+        assert_eq!(actual, chunk);
+    }
 }
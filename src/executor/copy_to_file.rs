@@ -1,18 +1,27 @@
 // Copyright 2024 RisingLight Project Authors. Licensed under Apache-2.0.
 
 use std::fs::File;
-use std::path::PathBuf;
 
+use ::parquet::basic::Compression;
+use ::parquet::file::properties::WriterProperties;
+use ::parquet::file::writer::SerializedFileWriter;
 use tokio::sync::mpsc;
 
 use super::*;
+use crate::array::parquet;
 use crate::binder::copy::{ExtSource, FileFormat};
+use crate::types::DataType;
 
 /// The executor of saving data to file.
 pub struct CopyToFileExecutor {
     pub source: ExtSource,
+    /// The types of columns to write.
+    pub types: Vec<DataType>,
 }
 
+/// The number of rows in a row group of Parquet file.
+const PARQUET_ROW_GROUP_SIZE: usize = 128 * 1024;
+
 impl CopyToFileExecutor {
     #[try_stream(boxed, ok = DataChunk, error = ExecutorError)]
     pub async fn execute(self, child: BoxedExecutor) {
@@ -21,7 +30,7 @@ impl CopyToFileExecutor {
         // When this stream is dropped, the `sender` is dropped, the `recver` will return
         // `None` in the spawned task, then the task will finish.
         let writer = tokio::task::spawn_blocking(move || {
-            Self::write_file_blocking(self.source.path, self.source.format, recver)
+            Self::write_file_blocking(self.source, self.types, recver)
         });
         #[for_await]
         for batch in child {
@@ -37,31 +46,47 @@ impl CopyToFileExecutor {
     }
 
     fn write_file_blocking(
-        path: PathBuf,
-        format: FileFormat,
-        mut recver: mpsc::Receiver<DataChunk>,
+        source: ExtSource,
+        types: Vec<DataType>,
+        recver: mpsc::Receiver<DataChunk>,
     ) -> Result<usize> {
-        let file = File::create(path)?;
-        let mut writer = match format {
+        let file = File::create(&source.path)?;
+        let columns = source.columns.into_iter().zip(types).collect::<Vec<_>>();
+        match source.format {
             FileFormat::Csv {
                 delimiter,
                 quote,
                 escape,
                 header,
-            } => csv::WriterBuilder::new()
-                .delimiter(delimiter as u8)
-                .quote(quote as u8)
-                .escape(escape.unwrap_or(quote) as u8)
-                .has_headers(header)
-                .from_writer(file),
-        };
+            } => {
+                let mut writer = csv::WriterBuilder::new()
+                    .delimiter(delimiter as u8)
+                    .quote(quote as u8)
+                    .escape(escape.unwrap_or(quote) as u8)
+                    .from_writer(file);
+                if header {
+                    writer.write_record(columns.iter().map(|(name, _)| name))?;
+                }
+                Self::write_csv(writer, recver)
+            }
+            FileFormat::Parquet => Self::write_parquet(file, &columns, recver),
+        }
+    }
 
+    fn write_csv(
+        mut writer: csv::Writer<File>,
+        mut recver: mpsc::Receiver<DataChunk>,
+    ) -> Result<usize> {
         let mut rows = 0;
 
         while let Some(chunk) = recver.blocking_recv() {
             for i in 0..chunk.cardinality() {
                 // TODO(wrj): avoid dynamic memory allocation (String)
-                let row = chunk.arrays().iter().map(|a| a.get_to_string(i));
+                // nulls are written as empty fields, which is how they are read
+                let row = (chunk.arrays().iter()).map(|a| match a.get(i) {
+                    DataValue::Null => String::new(),
+                    _ => a.get_to_string(i),
+                });
                 writer.write_record(row)?;
             }
             writer.flush()?;
@@ -72,6 +97,48 @@ impl CopyToFileExecutor {
 
         Ok(rows)
     }
+
+    /// Writes chunks to Parquet file, buffering them until a row group is full.
+    fn write_parquet(
+        file: File,
+        columns: &[(String, DataType)],
+        mut recver: mpsc::Receiver<DataChunk>,
+    ) -> Result<usize> {
+        let schema = parquet::schema(columns)?;
+        let props = WriterProperties::builder()
+            .set_compression(Compression::SNAPPY)
+            .build();
+        let mut writer = SerializedFileWriter::new(file, schema, Arc::new(props))?;
+
+        let mut rows = 0;
+        let mut chunks = vec![];
+        let mut buffered_rows = 0;
+        loop {
+            let chunk = recver.blocking_recv();
+            if let Some(chunk) = &chunk {
+                buffered_rows += chunk.cardinality();
+                chunks.push(chunk.clone());
+            }
+            // flush a row group if it is full or the input is finished
+            if buffered_rows >= PARQUET_ROW_GROUP_SIZE || chunk.is_none() && buffered_rows > 0 {
+                let mut row_group = writer.next_row_group()?;
+                for (i, (_, ty)) in columns.iter().enumerate() {
+                    let mut column = row_group.next_column()?.expect("column count mismatch");
+                    parquet::write_column(&mut column, ty, chunks.iter().map(|c| c.array_at(i)))?;
+                    column.close()?;
+                }
+                row_group.close()?;
+                rows += buffered_rows;
+                chunks.clear();
+                buffered_rows = 0;
+            }
+            if chunk.is_none() {
+                break;
+            }
+        }
+        writer.close()?;
+        Ok(rows)
+    }
 }
 
 #[cfg(test)]
@@ -92,7 +159,9 @@ mod tests {
                     escape: None,
                     header: false,
                 },
+                columns: vec!["a".into(), "b".into(), "c".into()],
             },
+            types: vec![DataType::Int32, DataType::Float64, DataType::String],
         };
         let child = async_stream::try_stream! {
             yield [
@@ -32,12 +32,30 @@ enum Inner {
     Io(#[from] std::io::Error),
     #[error("csv error: {0}")]
     Csv(#[from] csv::Error),
+    #[error("parquet error: {0}")]
+    Parquet(#[from] parquet::errors::ParquetError),
+    #[error("spill error: {0}")]
+    Spill(#[from] bincode::Error),
     #[error("tuple length mismatch: expected {expected} but got {actual}")]
     LengthMismatch { expected: usize, actual: usize },
     #[error("exceed char/varchar length limit: item length {length} > char/varchar width {width}")]
     ExceedLengthLimit { length: u64, width: u64 },
     #[error("value can not be null")]
     NotNullable,
+    #[error("column \"{0}\" contains null values")]
+    ContainsNull(String),
+    #[error("recursive query exceeds the maximum depth {0}")]
+    RecursionLimit(usize),
+    #[error("invalid argument: {0}")]
+    InvalidArgument(String),
+    #[error(
+        "{executor} exceeds the memory limit of the query: {requested} bytes requested, limit is {limit} bytes"
+    )]
+    MemoryLimit {
+        executor: &'static str,
+        requested: usize,
+        limit: usize,
+    },
     #[error("abort")]
     Aborted,
 }
@@ -72,12 +90,24 @@ impl From<std::io::Error> for Error {
     }
 }
 
+impl From<bincode::Error> for Error {
+    fn from(e: bincode::Error) -> Self {
+        Inner::from(e).into()
+    }
+}
+
 impl From<csv::Error> for Error {
     fn from(e: csv::Error) -> Self {
         Inner::from(e).into()
     }
 }
 
+impl From<parquet::errors::ParquetError> for Error {
+    fn from(e: parquet::errors::ParquetError) -> Self {
+        Inner::from(e).into()
+    }
+}
+
 impl Error {
     pub fn length_mismatch(expected: usize, actual: usize) -> Self {
         Inner::LengthMismatch { expected, actual }.into()
@@ -85,10 +115,33 @@ impl Error {
     pub fn not_nullable() -> Self {
         Inner::NotNullable.into()
     }
+    pub fn contains_null(column: &str) -> Self {
+        Inner::ContainsNull(column.into()).into()
+    }
     pub fn exceed_length_limit(length: u64, width: u64) -> Self {
         Inner::ExceedLengthLimit { length, width }.into()
     }
+    pub fn recursion_limit(depth: usize) -> Self {
+        Inner::RecursionLimit(depth).into()
+    }
+    pub fn invalid_argument(reason: impl Into<String>) -> Self {
+        Inner::InvalidArgument(reason.into()).into()
+    }
+    pub fn memory_limit(executor: &'static str, requested: usize, limit: usize) -> Self {
+        Inner::MemoryLimit {
+            executor,
+            requested,
+            limit,
+        }
+        .into()
+    }
     pub fn aborted() -> Self {
         Inner::Aborted.into()
     }
+
+    /// Returns true if the transaction failed due to a conflict with a concurrent transaction,
+    /// and can be retried.
+    pub fn is_conflict(&self) -> bool {
+        matches!(&*self.inner, Inner::Storage(e) if e.is_conflict())
+    }
 }
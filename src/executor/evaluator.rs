@@ -6,6 +6,7 @@ use std::collections::HashSet;
 use std::fmt;
 
 use egg::{Id, Language};
+use serde::{Deserialize, Serialize};
 
 use crate::array::*;
 use crate::planner::{Expr, RecExpr};
@@ -175,12 +176,11 @@ impl<'a> Evaluator<'a> {
     }
 
     /// Returns the initial aggregation state.
-    fn init_agg_state(&self) -> AggState {
+    pub fn init_agg_state(&self) -> AggState {
         use Expr::*;
         match self.node() {
-            Over([window, _, _]) => self.next(*window).init_agg_state(),
             CountDistinct(_) => AggState::DistinctValue(HashSet::default()),
-            RowCount | RowNumber | Count(_) => AggState::Value(DataValue::Int32(0)),
+            RowCount | Count(_) => AggState::Value(DataValue::Int32(0)),
             Sum(_) | Min(_) | Max(_) | First(_) | Last(_) => AggState::Value(DataValue::Null),
             t => panic!("not aggregation: {t}"),
         }
@@ -213,6 +213,19 @@ impl<'a> Evaluator<'a> {
         }
     }
 
+    /// Merge a list of partial agg states into another list of agg states.
+    pub fn agg_list_merge(
+        &self,
+        states: &mut [AggState],
+        others: impl IntoIterator<Item = AggState>,
+    ) {
+        let list = self.node().as_list();
+        for ((state, id), other) in states.iter_mut().zip(list).zip(others) {
+            let s = std::mem::take(state);
+            *state = self.next(*id).agg_merge(s, other);
+        }
+    }
+
     /// Consume a list of agg states and return their results.
     pub fn agg_list_take_result<T: IntoIterator<Item = AggState>>(
         &self,
@@ -221,14 +234,6 @@ impl<'a> Evaluator<'a> {
         states.into_iter().map(|s| s.into_result())
     }
 
-    /// Get the results of a list of agg states.
-    pub fn agg_list_get_result(
-        &self,
-        states: impl IntoIterator<Item = &'a AggState> + 'a,
-    ) -> impl Iterator<Item = DataValue> + 'a {
-        states.into_iter().map(|s| s.result())
-    }
-
     /// Evaluate the aggregation.
     fn eval_agg(&self, state: AggState, chunk: &DataChunk) -> Result<AggState, ConvertError> {
         use Expr::*;
@@ -257,14 +262,11 @@ impl<'a> Evaluator<'a> {
     }
 
     /// Append a value to agg state.
-    fn agg_append(&self, state: AggState, value: DataValue) -> AggState {
+    pub fn agg_append(&self, state: AggState, value: DataValue) -> AggState {
         use Expr::*;
-        if let Over([window, _, _]) = self.node() {
-            return self.next(*window).agg_append(state, value);
-        }
         match state {
             AggState::Value(state) => AggState::Value(match self.node() {
-                RowCount | RowNumber => state.add(DataValue::Int32(1)),
+                RowCount => state.add(DataValue::Int32(1)),
                 Count(_) => state.add(DataValue::Int32(!value.is_null() as _)),
                 Sum(_) => state.add(value),
                 Min(_) => state.min(value),
@@ -280,6 +282,30 @@ impl<'a> Evaluator<'a> {
         }
     }
 
+    /// Merge a partial agg state into another agg state.
+    ///
+    /// `other` must be aggregated from the rows after those of `state`.
+    pub fn agg_merge(&self, state: AggState, other: AggState) -> AggState {
+        use Expr::*;
+        match (state, other) {
+            (AggState::Value(state), AggState::Value(other)) => {
+                AggState::Value(match self.node() {
+                    RowCount | Count(_) | Sum(_) => state.add(other),
+                    Min(_) => state.min(other),
+                    Max(_) => state.max(other),
+                    First(_) => state.or(other),
+                    Last(_) => other,
+                    t => panic!("not aggregation: {t}"),
+                })
+            }
+            (AggState::DistinctValue(mut values), AggState::DistinctValue(others)) => {
+                values.extend(others);
+                AggState::DistinctValue(values)
+            }
+            (state, other) => panic!("can not merge agg states: {state:?} and {other:?}"),
+        }
+    }
+
     /// Returns a list of bools for order keys.
     ///
     /// The bool is false if the order is ascending, true if the order is descending.
@@ -291,7 +317,7 @@ impl<'a> Evaluator<'a> {
 }
 
 /// The aggregate state.
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub enum AggState {
     Value(DataValue),
     DistinctValue(HashSet<DataValue>),
@@ -311,7 +337,7 @@ impl AggState {
         }
     }
 
-    fn result(&self) -> DataValue {
+    pub fn result(&self) -> DataValue {
         match self {
             AggState::Value(v) => v.clone(),
             AggState::DistinctValue(v) => DataValue::Int32(v.len() as _),
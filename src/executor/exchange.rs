@@ -0,0 +1,294 @@
+// Copyright 2024 RisingLight Project Authors. Licensed under Apache-2.0.
+
+//! Exchange operators of parallel execution.
+//!
+//! A parallel plan runs as multiple partitions, each in its own task.
+//! Exchange operators move rows between partitions:
+//!
+//! - [`HashPartitionExecutor`] redistributes rows by the hash of their keys, so that rows with the
+//!   same keys are processed by the same partition.
+//! - [`GatherExecutor`] merges all partitions into a single stream.
+//! - [`MergeSortedExecutor`] merges sorted partitions into a single sorted stream.
+
+use std::hash::{BuildHasher, Hash, Hasher};
+
+use binary_heap_plus::BinaryHeap;
+use tokio::sync::mpsc::{Receiver, Sender};
+
+use super::*;
+use crate::array::{DataChunk, DataChunkBuilder, RowRef};
+use crate::types::{DataType, Row};
+
+/// The executor of a hash repartition exchange.
+///
+/// Rows from all inputs are redistributed to `num_partitions` outputs by the hash of their keys.
+pub struct HashPartitionExecutor {
+    /// A list of expressions to hash.
+    ///
+    /// e.g. `(list #0 #1)`
+    pub keys: RecExpr,
+    pub num_partitions: usize,
+}
+
+impl HashPartitionExecutor {
+    pub fn execute(self, inputs: Vec<BoxedExecutor>) -> Vec<BoxedExecutor> {
+        let (txs, rxs): (Vec<_>, Vec<_>) = (0..self.num_partitions)
+            .map(|_| tokio::sync::mpsc::channel(16))
+            .unzip();
+        // # Cancellation
+        // The tasks are aborted when all output streams are dropped.
+        let handles = inputs
+            .into_iter()
+            .map(|input| {
+                let keys = self.keys.clone();
+                let txs = txs.clone();
+                AbortOnDropHandle(tokio::spawn(async move {
+                    if let Err(e) = Self::partition(input, &keys, &txs).await {
+                        // every output fails with the error
+                        for tx in &txs {
+                            _ = tx.send(Err(e.clone())).await;
+                        }
+                    }
+                }))
+            })
+            .collect_vec();
+        let handles = Arc::new(handles);
+        rxs.into_iter()
+            .map(|rx| Self::receive(rx, handles.clone()))
+            .collect()
+    }
+
+    /// Splits each chunk of `input` by the hash of keys and sends the parts to outputs.
+    async fn partition(
+        mut input: BoxedExecutor,
+        keys: &RecExpr,
+        txs: &[Sender<Result<DataChunk>>],
+    ) -> Result<()> {
+        while let Some(chunk) = input.next().await.transpose()? {
+            let keys_chunk = Evaluator::new(keys).eval_list(&chunk)?;
+            let mut parts: Vec<Vec<RowRef<'_>>> = txs.iter().map(|_| vec![]).collect();
+            for (row, key) in chunk.rows().zip(keys_chunk.rows()) {
+                parts[partition_of(&key, txs.len())].push(row);
+            }
+            for (tx, rows) in txs.iter().zip(parts) {
+                if rows.is_empty() {
+                    continue;
+                }
+                let part = if chunk.column_count() == 0 {
+                    DataChunk::no_column(rows.len())
+                } else {
+                    DataChunk::from_rows(&rows, &chunk)
+                };
+                // an output may be dropped early, e.g. the probe side of a join with empty build
+                // side
+                _ = tx.send(Ok(part)).await;
+            }
+            if txs.iter().all(|tx| tx.is_closed()) {
+                return Ok(());
+            }
+        }
+        Ok(())
+    }
+
+    #[try_stream(boxed, ok = DataChunk, error = ExecutorError)]
+    async fn receive(mut rx: Receiver<Result<DataChunk>>, handles: Arc<Vec<AbortOnDropHandle>>) {
+        while let Some(chunk) = rx.recv().await {
+            yield chunk?;
+        }
+        drop(handles);
+    }
+}
+
+/// Returns the partition of a row by the hash of its keys.
+fn partition_of(keys: &RowRef<'_>, num_partitions: usize) -> usize {
+    // use fixed seeds different from spilling, so that a partition can still be spilled evenly
+    let mut hasher = ahash::RandomState::with_seeds(0xe8c4, 0x2a, 0x51ed, 0x270f).build_hasher();
+    for value in keys.values() {
+        value.hash(&mut hasher);
+    }
+    hasher.finish() as usize % num_partitions
+}
+
+/// The executor of a gather exchange.
+///
+/// Chunks from all inputs are output in the order they arrive.
+pub struct GatherExecutor;
+
+impl GatherExecutor {
+    pub fn execute(self, inputs: Vec<BoxedExecutor>) -> BoxedExecutor {
+        futures::stream::select_all(inputs).boxed()
+    }
+}
+
+/// The executor of a merge exchange.
+///
+/// Each input must be sorted by `order_keys`. Rows are merged with a k-way heap.
+pub struct MergeSortedExecutor {
+    /// A list of expressions to order by.
+    ///
+    /// e.g. `(list (+ #0 #1) (desc #0))`
+    pub order_keys: RecExpr,
+    pub types: Vec<DataType>,
+}
+
+/// The current chunk of an input, its order keys and the index of the next row.
+type Cursor = (DataChunk, DataChunk, usize);
+
+impl MergeSortedExecutor {
+    #[try_stream(boxed, ok = DataChunk, error = ExecutorError)]
+    pub async fn execute(self, mut inputs: Vec<BoxedExecutor>) {
+        let orders = Evaluator::new(&self.order_keys).orders();
+
+        // a min-heap of the order keys of the head rows of all inputs
+        // ties are broken by the input index
+        let mut heap = BinaryHeap::with_capacity_by(
+            inputs.len(),
+            |(row1, i1): &(Row, usize), (row2, i2): &(Row, usize)| {
+                cmp_rows(row2, row1, &orders).then_with(|| i2.cmp(i1))
+            },
+        );
+        let mut cursors: Vec<Option<Cursor>> = vec![];
+        for (i, input) in inputs.iter_mut().enumerate() {
+            let cursor = self.next_cursor(input).await?;
+            if let Some((_, keys, _)) = &cursor {
+                heap.push((keys.row(0).to_owned(), i));
+            }
+            cursors.push(cursor);
+        }
+
+        let mut builder = DataChunkBuilder::new(&self.types, PROCESSING_WINDOW_SIZE);
+        while let Some((_, i)) = heap.pop() {
+            let (chunk, keys, next) = cursors[i].as_mut().unwrap();
+            let row = chunk.row(*next).to_owned();
+            *next += 1;
+            if *next < chunk.cardinality() {
+                heap.push((keys.row(*next).to_owned(), i));
+            } else {
+                cursors[i] = self.next_cursor(&mut inputs[i]).await?;
+                if let Some((_, keys, _)) = &cursors[i] {
+                    heap.push((keys.row(0).to_owned(), i));
+                }
+            }
+            if let Some(chunk) = builder.push_row(row) {
+                yield chunk;
+            }
+        }
+        if let Some(chunk) = builder.take() {
+            yield chunk;
+        }
+    }
+
+    /// Returns the cursor of the next non-empty chunk of `input`.
+    async fn next_cursor(&self, input: &mut BoxedExecutor) -> Result<Option<Cursor>> {
+        while let Some(chunk) = input.next().await.transpose()? {
+            if chunk.cardinality() == 0 {
+                continue;
+            }
+            let keys = Evaluator::new(&self.order_keys).eval_list(&chunk)?;
+            return Ok(Some((chunk, keys, 0)));
+        }
+        Ok(None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::TryStreamExt;
+
+    use super::*;
+    use crate::array::ArrayImpl;
+    use crate::types::DataValue;
+
+    /// Returns a stream of chunks with rows `(i % 10, i)` for `i` in `range`.
+    fn input(range: std::ops::Range<i32>) -> BoxedExecutor {
+        let chunks = range
+            .collect_vec()
+            .chunks(100)
+            .map(|rows| {
+                [
+                    ArrayImpl::new_int32(rows.iter().map(|i| i % 10).collect()),
+                    ArrayImpl::new_int32(rows.iter().copied().collect()),
+                ]
+                .into_iter()
+                .collect::<DataChunk>()
+            })
+            .collect_vec();
+        futures::stream::iter(chunks.into_iter().map(Ok)).boxed()
+    }
+
+    fn rows(chunks: &[DataChunk]) -> Vec<Row> {
+        chunks
+            .iter()
+            .flat_map(|chunk| chunk.rows().map(|row| row.to_owned()))
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn hash_partition() {
+        let outputs = HashPartitionExecutor {
+            keys: "(list #0)".parse().unwrap(),
+            num_partitions: 4,
+        }
+        .execute(vec![input(0..1000), input(1000..2000)]);
+        assert_eq!(outputs.len(), 4);
+
+        // outputs must be consumed concurrently
+        let outputs = futures::future::try_join_all(
+            outputs
+                .into_iter()
+                .map(|output| output.try_collect::<Vec<_>>()),
+        )
+        .await
+        .unwrap();
+        let mut all = vec![];
+        for chunks in outputs {
+            let rows = rows(&chunks);
+            // rows with the same key are in the same partition
+            let keys = rows.iter().map(|row| row[0].clone()).unique().collect_vec();
+            assert!(keys.len() < 10);
+            all.extend(rows);
+        }
+        all.sort();
+        let expected = (0..2000)
+            .map(|i| vec![DataValue::Int32(i % 10), DataValue::Int32(i)])
+            .sorted()
+            .collect_vec();
+        assert_eq!(all, expected);
+    }
+
+    #[tokio::test]
+    async fn merge_sorted() {
+        // each input is sorted by #1 desc
+        let inputs = (0..3)
+            .map(|p| {
+                let rows = (0..500).filter(|i| i % 3 == p).rev().collect_vec();
+                let chunks = rows
+                    .chunks(64)
+                    .map(|rows| {
+                        [
+                            ArrayImpl::new_int32(rows.iter().map(|i| i % 10).collect()),
+                            ArrayImpl::new_int32(rows.iter().copied().collect()),
+                        ]
+                        .into_iter()
+                        .collect::<DataChunk>()
+                    })
+                    .collect_vec();
+                futures::stream::iter(chunks.into_iter().map(Ok)).boxed()
+            })
+            .collect_vec();
+        let chunks = MergeSortedExecutor {
+            order_keys: "(list (desc #1))".parse().unwrap(),
+            types: vec![DataType::Int32, DataType::Int32],
+        }
+        .execute(inputs)
+        .try_collect::<Vec<_>>()
+        .await
+        .unwrap();
+
+        let expected = (0..500)
+            .rev()
+            .map(|i| vec![DataValue::Int32(i % 10), DataValue::Int32(i)])
+            .collect_vec();
+        assert_eq!(rows(&chunks), expected);
+    }
+}
@@ -0,0 +1,50 @@
+// Copyright 2025 RisingLight Project Authors. Licensed under Apache-2.0.
+
+use super::copy_from_file::read_file_blocking;
+use super::*;
+use crate::array::{ArrayImpl, DataChunk};
+use crate::binder::copy::ExtSource;
+use crate::types::DataType;
+
+/// The executor of scanning an external file.
+pub struct FileScanExecutor {
+    pub source: ExtSource,
+    /// The indices of columns to read from the file.
+    pub projection: Vec<usize>,
+    /// The types of projected columns.
+    pub types: Vec<DataType>,
+    /// The condition on projected columns. `None` for no filter.
+    pub filter: Option<RecExpr>,
+}
+
+impl FileScanExecutor {
+    #[try_stream(boxed, ok = DataChunk, error = ExecutorError)]
+    pub async fn execute(self) {
+        let (tx, mut rx) = tokio::sync::mpsc::channel(1);
+        // # Cancellation
+        // When this stream is dropped, the `rx` is dropped, the spawned task will fail to send to
+        // `tx`, then the task will finish.
+        let source = self.source;
+        let projection = self.projection;
+        let types = self.types;
+        let handle = tokio::task::spawn_blocking(move || {
+            read_file_blocking(&source, &projection, &types, tx)
+        });
+        while let Some(chunk) = rx.recv().await {
+            let chunk = match &self.filter {
+                Some(filter) => match Evaluator::new(filter).eval(&chunk)? {
+                    ArrayImpl::Bool(a) => chunk.filter(a.true_array()),
+                    _ => Err(ExecutorError::invalid_argument(
+                        "filters can only accept bool array",
+                    ))?,
+                },
+                None => chunk,
+            };
+            if chunk.cardinality() > 0 {
+                yield chunk;
+            }
+        }
+        // a panic of the reading task is returned as an error
+        handle.await.map_err(std::io::Error::from)??;
+    }
+}
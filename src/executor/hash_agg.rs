@@ -1,55 +1,414 @@
 // Copyright 2024 RisingLight Project Authors. Licensed under Apache-2.0.
 
+use std::path::PathBuf;
+
 use ahash::{HashMap, HashMapExt};
-use iter_chunks::IterChunks;
 use smallvec::SmallVec;
+use tokio::sync::mpsc::Sender;
 
+use super::spill::*;
 use super::*;
 use crate::array::DataChunkBuilder;
 use crate::types::DataValue;
 
 /// The executor of hash aggregation.
+///
+/// The aggregation is split into two phases:
+/// - [`partial`](Self::partial) aggregates the input into a hash table of partial states. Once the
+///   size of the table exceeds `memory_limit` or the memory limit of the query, all groups are
+///   spilled to partitions in `spill_dir` by the hash of their keys, and the table starts over.
+/// - [`finalize`](Self::finalize) merges the partial states of one or more partial phases and
+///   returns the results. Spilled groups are merged partition by partition.
 pub struct HashAggExecutor {
     pub keys: RecExpr,
     pub aggs: RecExpr,
     pub types: Vec<DataType>,
+    /// The maximum estimated size in bytes of the hash table held in memory.
+    pub memory_limit: usize,
+    /// The parent directory of the temporary directory for spilled partitions.
+    pub spill_dir: PathBuf,
+    pub memory: MemoryTracker,
 }
 
 pub type GroupKeys = SmallVec<[DataValue; 4]>;
 pub type AggValue = SmallVec<[AggState; 4]>;
 
+/// The hash table from group keys to aggregation states.
+type AggTable = HashMap<GroupKeys, AggValue>;
+
+/// A spilled group with its partial aggregation states.
+type AggRecord = (GroupKeys, AggValue);
+
+/// The partial aggregation states of a part of the input.
+pub struct PartialAgg {
+    /// Groups in memory.
+    table: AggTable,
+    /// The estimated size in bytes of `table`.
+    size: usize,
+    /// The memory reserved for `table`.
+    reservation: MemoryReservation,
+    /// Groups spilled to disk.
+    spilled: Option<Partitions<AggRecord>>,
+}
+
 impl HashAggExecutor {
     #[try_stream(boxed, ok = DataChunk, error = ExecutorError)]
     pub async fn execute(self, child: BoxedExecutor) {
-        let mut states = HashMap::<GroupKeys, AggValue>::new();
-
+        let partial = self.partial(child).await?;
         #[for_await]
-        for chunk in child {
-            let chunk = chunk?;
-            let keys_chunk = Evaluator::new(&self.keys).eval_list(&chunk)?;
-            let args_chunk = Evaluator::new(&self.aggs).eval_list(&chunk)?;
+        for chunk in self.finalize(vec![partial]) {
+            yield chunk?;
+        }
+    }
+
+    /// Aggregates the input into partial states.
+    pub async fn partial(&self, mut child: BoxedExecutor) -> Result<PartialAgg> {
+        let mut partial = self.new_partial();
+        while let Some(chunk) = child.next().await {
+            self.append(&mut partial, &chunk?)?;
+            if self.should_spill(&mut partial) {
+                self.spill(&mut partial).await?;
+            }
+        }
+        Ok(partial)
+    }
+
+    /// Returns empty partial states.
+    fn new_partial(&self) -> PartialAgg {
+        PartialAgg {
+            table: AggTable::new(),
+            size: 0,
+            reservation: self.memory.reservation("hash aggregation"),
+            spilled: None,
+        }
+    }
+
+    /// Returns true if the hash table exceeds the memory limits and should be spilled.
+    /// Otherwise the memory of the table is reserved.
+    fn should_spill(&self, partial: &mut PartialAgg) -> bool {
+        partial.size > self.memory_limit || !partial.reservation.try_resize(partial.size)
+    }
+
+    /// Aggregates the rows of `chunk` into the hash table.
+    fn append(&self, partial: &mut PartialAgg, chunk: &DataChunk) -> Result<()> {
+        let keys_chunk = Evaluator::new(&self.keys).eval_list(chunk)?;
+        let args_chunk = Evaluator::new(&self.aggs).eval_list(chunk)?;
+
+        for i in 0..chunk.cardinality() {
+            let keys: GroupKeys = keys_chunk.row(i).values().collect();
+            let states = partial.table.entry(keys).or_insert_with_key(|keys| {
+                partial.size += estimated_group_size(keys);
+                Evaluator::new(&self.aggs).init_agg_states()
+            });
+            Evaluator::new(&self.aggs).agg_list_append(states, args_chunk.row(i).values());
+        }
+        Ok(())
+    }
+
+    /// Spills all groups in the hash table to partitions.
+    async fn spill(&self, partial: &mut PartialAgg) -> Result<()> {
+        let partitions = match partial.spilled.take() {
+            Some(partitions) => partitions,
+            None => {
+                let dir = create_spill_dir(&self.spill_dir, "risinglight-agg-")?;
+                Partitions::new(&dir, 0)?
+            }
+        };
+        let records = std::mem::take(&mut partial.table)
+            .into_iter()
+            .map(|(keys, states)| (hash_at_depth(&keys, 0), (keys, states)))
+            .collect();
+        partial.spilled = Some(partitions.write_all(records).await?);
+        partial.size = 0;
+        partial.reservation.free();
+        Ok(())
+    }
+
+    /// Merges the partial states and returns the chunks of group keys and aggregation results.
+    #[try_stream(boxed, ok = DataChunk, error = ExecutorError)]
+    pub async fn finalize(self, mut partials: Vec<PartialAgg>) {
+        if partials.iter().all(|p| p.spilled.is_none()) {
+            let mut table = AggTable::new();
+            // the memory is reserved until all groups are returned
+            let mut reservations = vec![];
+            for partial in partials {
+                self.merge(&mut table, partial.table);
+                reservations.push(partial.reservation);
+            }
+            for chunk in self.finish(table) {
+                yield chunk;
+            }
+            drop(reservations);
+            return Ok(());
+        }
+
+        // spill the remaining groups so that all groups are partitioned in the same way
+        let mut partitions = vec![];
+        for partial in &mut partials {
+            if !partial.table.is_empty() || partial.spilled.is_some() {
+                self.spill(partial).await?;
+                partitions.push(partial.spilled.take().unwrap().finish()?);
+            }
+        }
+        let (tx, mut rx) = tokio::sync::mpsc::channel(1);
+        // # Cancellation
+        // When this stream is dropped, the `rx` is dropped, the spawned task will fail to send
+        // to `tx`, then the task will finish.
+        let handle = tokio::task::spawn_blocking(move || {
+            for i in (0..PARTITION_FANOUT).rev() {
+                let files = partitions.iter_mut().map(|files| files.remove(i)).collect();
+                if !self.merge_partition(files, 1, &tx)? {
+                    break;
+                }
+            }
+            Ok(()) as Result<()>
+        });
+        while let Some(chunk) = rx.recv().await {
+            yield chunk;
+        }
+        handle.await.unwrap()?;
+    }
+
+    /// Merges the groups of a partition from all partial phases and sends the results through
+    /// `tx`.
+    ///
+    /// Returns `false` if the receiver is dropped.
+    fn merge_partition(
+        &self,
+        files: Vec<PartitionFile<AggRecord>>,
+        depth: usize,
+        tx: &Sender<DataChunk>,
+    ) -> Result<bool> {
+        let size = files.iter().map(|file| file.size()).sum::<usize>();
+        let mut reservation = self.memory.reservation("hash aggregation");
+        let in_memory = size <= self.memory_limit && reservation.try_grow(size);
+        if !in_memory && depth < MAX_PARTITION_DEPTH {
+            // partition the groups again with a different hash
+            let hash = |(keys, _): &AggRecord, depth| hash_at_depth(keys, depth);
+            let mut partitions: Vec<_> = (files.into_iter())
+                .map(|file| file.repartition(depth, hash))
+                .try_collect()?;
+            for i in (0..PARTITION_FANOUT).rev() {
+                let files = partitions.iter_mut().map(|files| files.remove(i)).collect();
+                if !self.merge_partition(files, depth + 1, tx)? {
+                    return Ok(false);
+                }
+            }
+            return Ok(true);
+        }
+        if !in_memory {
+            // the partition can not be split any further
+            reservation.grow(size)?;
+        }
 
-            for i in 0..chunk.cardinality() {
-                let keys = keys_chunk.row(i).values().collect();
-                let states = states
-                    .entry(keys)
-                    .or_insert_with(|| Evaluator::new(&self.aggs).init_agg_states());
-                Evaluator::new(&self.aggs).agg_list_append(states, args_chunk.row(i).values());
+        let mut table = AggTable::new();
+        for file in files {
+            for record in file.read()? {
+                let (keys, states) = record?;
+                self.merge(&mut table, [(keys, states)]);
+            }
+        }
+        for chunk in self.finish(table) {
+            if tx.blocking_send(chunk).is_err() {
+                return Ok(false);
             }
         }
+        Ok(true)
+    }
 
-        let mut batches = IterChunks::chunks(states.into_iter(), PROCESSING_WINDOW_SIZE);
-        while let Some(batch) = batches.next() {
-            let mut builder = DataChunkBuilder::new(&self.types, PROCESSING_WINDOW_SIZE);
-            for (key, states) in batch {
+    /// Merges groups into the hash table.
+    fn merge(&self, table: &mut AggTable, groups: impl IntoIterator<Item = (GroupKeys, AggValue)>) {
+        for (keys, states) in groups {
+            match table.entry(keys) {
+                std::collections::hash_map::Entry::Occupied(mut entry) => {
+                    Evaluator::new(&self.aggs).agg_list_merge(entry.get_mut(), states);
+                }
+                std::collections::hash_map::Entry::Vacant(entry) => {
+                    entry.insert(states);
+                }
+            }
+        }
+    }
+
+    /// Returns the chunks of group keys and aggregation results in the hash table.
+    fn finish(&self, table: AggTable) -> impl Iterator<Item = DataChunk> + '_ {
+        let mut groups = table.into_iter();
+        let mut builder = DataChunkBuilder::new(&self.types, PROCESSING_WINDOW_SIZE);
+        std::iter::from_fn(move || {
+            for (key, states) in groups.by_ref() {
                 let agg_results = Evaluator::new(&self.aggs).agg_list_take_result(states);
                 if let Some(chunk) = builder.push_row(key.into_iter().chain(agg_results)) {
-                    yield chunk;
+                    return Some(chunk);
                 }
             }
-            if let Some(chunk) = builder.take() {
-                yield chunk;
+            builder.take()
+        })
+    }
+}
+
+/// Returns the estimated size in bytes of a new group in the hash table.
+fn estimated_group_size(keys: &GroupKeys) -> usize {
+    let heap_size = keys
+        .iter()
+        .map(|key| match key {
+            DataValue::String(s) => s.len(),
+            DataValue::Blob(b) => b.len(),
+            _ => 0,
+        })
+        .sum::<usize>();
+    std::mem::size_of::<(GroupKeys, AggValue)>() + heap_size
+}
+
+/// The executor of hash aggregation over grouping sets.
+///
+/// Each grouping set is aggregated by a [`HashAggExecutor`] whose keys not in the set are
+/// constant. All sets are aggregated in one pass over the input.
+pub struct GroupingSetsExecutor {
+    /// The aggregation of each grouping set.
+    pub sets: Vec<HashAggExecutor>,
+    /// Whether each grouping set is empty.
+    /// An empty set outputs a row even if the input is empty, like a simple aggregation.
+    pub empty: Vec<bool>,
+}
+
+impl GroupingSetsExecutor {
+    #[try_stream(boxed, ok = DataChunk, error = ExecutorError)]
+    pub async fn execute(self, child: BoxedExecutor) {
+        let mut partials = self.sets.iter().map(|set| set.new_partial()).collect_vec();
+        #[for_await]
+        for chunk in child {
+            let chunk = chunk?;
+            for (set, partial) in self.sets.iter().zip(&mut partials) {
+                set.append(partial, &chunk)?;
+                if set.should_spill(partial) {
+                    set.spill(partial).await?;
+                }
+            }
+        }
+        for ((set, mut partial), empty) in self.sets.into_iter().zip(partials).zip(self.empty) {
+            if empty && partial.table.is_empty() && partial.spilled.is_none() {
+                // the keys are all constants
+                let keys_chunk = Evaluator::new(&set.keys).eval_list(&DataChunk::no_column(1))?;
+                let keys = keys_chunk.row(0).values().collect();
+                (partial.table).insert(keys, Evaluator::new(&set.aggs).init_agg_states());
+            }
+            #[for_await]
+            for chunk in set.finalize(vec![partial]) {
+                yield chunk?;
             }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use futures::TryStreamExt;
+    use test_case::test_case;
+
+    use super::*;
+    use crate::array::ArrayImpl;
+    use crate::types::Row;
+
+    /// Returns chunks of rows `(i % 97, i % 13, i)` for `i` in `range`, 100 rows per chunk.
+    fn input(range: std::ops::Range<i32>) -> BoxedExecutor {
+        let chunks = range
+            .chunks(100)
+            .into_iter()
+            .map(|chunk| {
+                let values = chunk.collect_vec();
+                [
+                    ArrayImpl::new_int32(values.iter().map(|i| i % 97).collect()),
+                    ArrayImpl::new_int32(values.iter().map(|i| i % 13).collect()),
+                    ArrayImpl::new_int32(values.iter().copied().collect()),
+                ]
+                .into_iter()
+                .collect::<DataChunk>()
+            })
+            .collect_vec();
+        futures::stream::iter(chunks.into_iter().map(Ok)).boxed()
+    }
+
+    fn executor(
+        memory_limit: usize,
+        memory: &MemoryTracker,
+        spill_dir: &std::path::Path,
+    ) -> HashAggExecutor {
+        HashAggExecutor {
+            keys: "(list #0)".parse().unwrap(),
+            aggs: "(list rowcount (sum #2) (min #2) (max #2) (first #2) (last #2) (count-distinct #1))"
+                .parse()
+                .unwrap(),
+            types: vec![DataType::Int32; 8],
+            memory_limit,
+            spill_dir: spill_dir.into(),
+            memory: memory.clone(),
+        }
+    }
+
+    /// Returns the expected result of the rows `0..n`.
+    fn expected(n: i32) -> Vec<Row> {
+        (0..97)
+            .map(|k| {
+                let values = (k..n).step_by(97).collect_vec();
+                let distinct = values.iter().map(|i| i % 13).unique().count();
+                [
+                    k,
+                    values.len() as i32,
+                    values.iter().sum(),
+                    values[0],
+                    *values.last().unwrap(),
+                    values[0],
+                    *values.last().unwrap(),
+                    distinct as i32,
+                ]
+                .map(DataValue::Int32)
+                .to_vec()
+            })
+            .collect()
+    }
+
+    async fn collect(stream: BoxedExecutor) -> Vec<Row> {
+        let chunks = stream.try_collect::<Vec<_>>().await.unwrap();
+        let mut rows = chunks
+            .iter()
+            .flat_map(|chunk| chunk.rows().map(|row| row.to_owned()))
+            .collect_vec();
+        rows.sort();
+        rows
+    }
+
+    #[test_case(usize::MAX, usize::MAX; "in memory")]
+    #[test_case(4096, usize::MAX; "spill some groups")]
+    #[test_case(0, usize::MAX; "spill every chunk")]
+    #[test_case(usize::MAX, 4096; "spill by query memory limit")]
+    #[tokio::test]
+    async fn hash_agg(memory_limit: usize, query_memory_limit: usize) {
+        let spill_dir = tempfile::tempdir().unwrap();
+        let memory = MemoryTracker::new(query_memory_limit);
+        let executor = executor(memory_limit, &memory, spill_dir.path());
+        assert_eq!(
+            collect(executor.execute(input(0..3000))).await,
+            expected(3000)
+        );
+        // spilled partitions should be removed
+        assert_eq!(std::fs::read_dir(spill_dir.path()).unwrap().count(), 0);
+        // all memory should be released
+        assert_eq!(memory.used(), 0);
+    }
+
+    #[test_case(usize::MAX; "in memory")]
+    #[test_case(0; "spill every chunk")]
+    #[tokio::test]
+    async fn partial_and_final(memory_limit: usize) {
+        let spill_dir = tempfile::tempdir().unwrap();
+        let memory = MemoryTracker::new(usize::MAX);
+        let executor = executor(memory_limit, &memory, spill_dir.path());
+        let partials = vec![
+            executor.partial(input(0..1000)).await.unwrap(),
+            executor.partial(input(1000..1500)).await.unwrap(),
+            executor.partial(input(1500..3000)).await.unwrap(),
+        ];
+        assert_eq!(collect(executor.finalize(partials)).await, expected(3000));
+        assert_eq!(std::fs::read_dir(spill_dir.path()).unwrap().count(), 0);
+    }
+}
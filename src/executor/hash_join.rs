@@ -1,21 +1,34 @@
 // Copyright 2024 RisingLight Project Authors. Licensed under Apache-2.0.
 
 use std::marker::ConstParamTy;
+use std::path::PathBuf;
 use std::vec::Vec;
 
 use ahash::{HashMap, HashMapExt, HashSet, HashSetExt};
 use smallvec::SmallVec;
+use tokio::sync::mpsc::Sender;
 
+use super::spill::*;
 use super::*;
 use crate::array::{ArrayBuilderImpl, ArrayImpl, DataChunk, DataChunkBuilder, RowRef};
 use crate::types::{DataType, DataValue, Row};
 
 /// The executor for hash join
+///
+/// The left side is the build side. Its rows are buffered in memory until their size exceeds
+/// `memory_limit` or the memory limit of the query. Then both sides are partitioned by the hash of
+/// join keys and spilled to a temporary directory in `spill_dir`, and joined partition by partition
+/// (grace hash join). A partition that still exceeds the limit is partitioned again recursively.
 pub struct HashJoinExecutor<const T: JoinType> {
     pub left_keys: RecExpr,
     pub right_keys: RecExpr,
     pub left_types: Vec<DataType>,
     pub right_types: Vec<DataType>,
+    /// The maximum size in bytes of the build side held in memory.
+    pub memory_limit: usize,
+    /// The parent directory of the temporary directory for spilled partitions.
+    pub spill_dir: PathBuf,
+    pub memory: MemoryTracker,
 }
 
 /// Join types for generating join code during the compilation.
@@ -29,26 +42,62 @@ pub enum JoinType {
 
 pub type JoinKeys = SmallVec<[DataValue; 2]>;
 
+/// Rows of the build side with the same join keys.
+#[derive(Default, Debug)]
+struct LeftKeyInfo {
+    rows: SmallVec<[Row; 1]>,
+    matched: bool,
+}
+
+/// A spilled row with its join keys.
+type JoinRecord = (JoinKeys, Row);
+
 impl<const T: JoinType> HashJoinExecutor<T> {
     #[try_stream(boxed, ok = DataChunk, error = ExecutorError)]
     pub async fn execute(self, left: BoxedExecutor, right: BoxedExecutor) {
         // build
-        #[derive(Default, Debug)]
-        struct LeftKeyInfo {
-            rows: SmallVec<[Row; 1]>,
-            matched: bool,
-        }
-        let mut hash_map: HashMap<JoinKeys, LeftKeyInfo> = HashMap::new();
+        let mut chunks = vec![];
+        let mut reservation = self.memory.reservation("hash join");
+        let mut build: Option<(Partitions<JoinRecord>, BloomFilter)> = None;
         #[for_await]
         for chunk in left {
             let chunk = chunk?;
             let keys_chunk = Evaluator::new(&self.left_keys).eval_list(&chunk)?;
+            if let Some((partitions, mut bloom)) = build.take() {
+                let partitions = Self::spill(partitions, &mut bloom, &keys_chunk, &chunk).await?;
+                build = Some((partitions, bloom));
+                continue;
+            }
+            let size = chunk.estimated_size() + keys_chunk.estimated_size();
+            chunks.push((keys_chunk, chunk));
+            if reservation.size() + size > self.memory_limit || !reservation.try_grow(size) {
+                let dir = create_spill_dir(&self.spill_dir, "risinglight-join-")?;
+                let mut partitions = Partitions::new(&dir, 0)?;
+                let mut bloom = BloomFilter::new(BLOOM_FILTER_BITS);
+                for (keys_chunk, chunk) in std::mem::take(&mut chunks) {
+                    partitions = Self::spill(partitions, &mut bloom, &keys_chunk, &chunk).await?;
+                }
+                build = Some((partitions, bloom));
+                reservation.free();
+            }
+            tokio::task::consume_budget().await;
+        }
+        if let Some((build, bloom)) = build {
+            #[for_await]
+            for chunk in self.execute_spilled(build, bloom, right) {
+                yield chunk?;
+            }
+            return Ok(());
+        }
+
+        let mut hash_map: HashMap<JoinKeys, LeftKeyInfo> = HashMap::new();
+        for (keys_chunk, chunk) in &chunks {
             for (row, keys) in chunk.rows().zip(keys_chunk.rows()) {
                 let keys = keys.values().collect();
                 hash_map.entry(keys).or_default().rows.push(row.to_owned());
             }
-            tokio::task::consume_budget().await;
         }
+        drop(chunks);
 
         let data_types = self.left_types.iter().chain(self.right_types.iter());
         let mut builder = DataChunkBuilder::new(data_types, PROCESSING_WINDOW_SIZE);
@@ -101,6 +150,207 @@ impl<const T: JoinType> HashJoinExecutor<T> {
             yield chunk;
         }
     }
+
+    /// Partitions the probe side and joins the spilled partitions.
+    #[try_stream(boxed, ok = DataChunk, error = ExecutorError)]
+    async fn execute_spilled(
+        self,
+        build: Partitions<JoinRecord>,
+        bloom: BloomFilter,
+        right: BoxedExecutor,
+    ) {
+        let dir = build.dir().clone();
+        let build = build.finish()?;
+        let mut probe = Partitions::new(&dir, 0)?;
+        let mut builder = DataChunkBuilder::new(
+            self.left_types.iter().chain(self.right_types.iter()),
+            PROCESSING_WINDOW_SIZE,
+        );
+        #[for_await]
+        for chunk in right {
+            let chunk = chunk?;
+            let keys_chunk = Evaluator::new(&self.right_keys).eval_list(&chunk)?;
+            let mut records = vec![];
+            for (keys, right_row) in keys_chunk.rows().zip(chunk.rows()) {
+                let keys: JoinKeys = keys.values().collect();
+                let hash = hash_at_depth(&keys, 0);
+                // rows whose keys are not in the bloom filter can not match any build row
+                if bloom.contains(hash) {
+                    records.push((hash, (keys, right_row.to_owned())));
+                } else if T == JoinType::RightOuter || T == JoinType::FullOuter {
+                    // append row: (NULL, right)
+                    let values =
+                        (self.left_types.iter().map(|_| DataValue::Null)).chain(right_row.values());
+                    if let Some(chunk) = builder.push_row(values) {
+                        yield chunk;
+                    }
+                }
+            }
+            probe = probe.write_all(records).await?;
+        }
+        if let Some(chunk) = builder.take() {
+            yield chunk;
+        }
+        let probe = probe.finish()?;
+
+        let (tx, mut rx) = tokio::sync::mpsc::channel(1);
+        // # Cancellation
+        // When this stream is dropped, the `rx` is dropped, the spawned task will fail to send
+        // to `tx`, then the task will finish.
+        let handle = tokio::task::spawn_blocking(move || {
+            for (build, probe) in build.into_iter().zip(probe) {
+                if !self.join_partition(build, probe, 1, &tx)? {
+                    break;
+                }
+            }
+            Ok(()) as Result<()>
+        });
+        while let Some(chunk) = rx.recv().await {
+            yield chunk;
+        }
+        handle.await.unwrap()?;
+    }
+
+    /// Writes the rows of a chunk with their keys to partitions, and adds the keys to `bloom`.
+    async fn spill(
+        partitions: Partitions<JoinRecord>,
+        bloom: &mut BloomFilter,
+        keys_chunk: &DataChunk,
+        chunk: &DataChunk,
+    ) -> Result<Partitions<JoinRecord>> {
+        let mut records = vec![];
+        for (keys, row) in keys_chunk.rows().zip(chunk.rows()) {
+            let keys: JoinKeys = keys.values().collect();
+            let hash = hash_at_depth(&keys, partitions.depth());
+            bloom.insert(hash);
+            records.push((hash, (keys, row.to_owned())));
+        }
+        partitions.write_all(records).await
+    }
+
+    /// Joins a pair of spilled partitions and sends the results through `tx`.
+    ///
+    /// Returns `false` if the receiver is dropped.
+    fn join_partition(
+        &self,
+        build: PartitionFile<JoinRecord>,
+        probe: PartitionFile<JoinRecord>,
+        depth: usize,
+        tx: &Sender<DataChunk>,
+    ) -> Result<bool> {
+        let left_outer = T == JoinType::LeftOuter || T == JoinType::FullOuter;
+        let right_outer = T == JoinType::RightOuter || T == JoinType::FullOuter;
+        if (build.rows() == 0 && !right_outer) || (probe.rows() == 0 && !left_outer) {
+            return Ok(true);
+        }
+        let mut reservation = self.memory.reservation("hash join");
+        let in_memory = build.size() <= self.memory_limit && reservation.try_grow(build.size());
+        if !in_memory && depth < MAX_PARTITION_DEPTH {
+            // partition both sides again with a different hash
+            let hash = |(keys, _): &JoinRecord, depth| hash_at_depth(keys, depth);
+            let build = build.repartition(depth, hash)?;
+            let probe = probe.repartition(depth, hash)?;
+            for (build, probe) in build.into_iter().zip(probe) {
+                if !self.join_partition(build, probe, depth + 1, tx)? {
+                    return Ok(false);
+                }
+            }
+            return Ok(true);
+        }
+        if !in_memory {
+            // the partition can not be split any further
+            reservation.grow(build.size())?;
+        }
+
+        let mut hash_map: HashMap<JoinKeys, LeftKeyInfo> = HashMap::new();
+        for record in build.read()? {
+            let (keys, row) = record?;
+            hash_map.entry(keys).or_default().rows.push(row);
+        }
+
+        let data_types = self.left_types.iter().chain(self.right_types.iter());
+        let mut builder = DataChunkBuilder::new(data_types, PROCESSING_WINDOW_SIZE);
+        let send = |chunk| tx.blocking_send(chunk).is_ok();
+        for record in probe.read()? {
+            let (keys, right_row) = record?;
+            if let Some(left_rows) = hash_map.get_mut(&keys) {
+                left_rows.matched = true;
+                for left_row in &left_rows.rows {
+                    let values = left_row.iter().chain(&right_row).cloned();
+                    if let Some(chunk) = builder.push_row(values)
+                        && !send(chunk)
+                    {
+                        return Ok(false);
+                    }
+                }
+            } else if right_outer {
+                // append row: (NULL, right)
+                let values = (self.left_types.iter().map(|_| DataValue::Null)).chain(right_row);
+                if let Some(chunk) = builder.push_row(values)
+                    && !send(chunk)
+                {
+                    return Ok(false);
+                }
+            }
+        }
+        if left_outer {
+            for row in hash_map.into_values().filter(|rows| !rows.matched) {
+                for row in row.rows {
+                    // append row: (left, NULL)
+                    let values =
+                        (row.into_iter()).chain(self.right_types.iter().map(|_| DataValue::Null));
+                    if let Some(chunk) = builder.push_row(values)
+                        && !send(chunk)
+                    {
+                        return Ok(false);
+                    }
+                }
+            }
+        }
+        if let Some(chunk) = builder.take() {
+            return Ok(send(chunk));
+        }
+        Ok(true)
+    }
+}
+
+/// The number of bits in the bloom filter of the build side.
+const BLOOM_FILTER_BITS: usize = 1 << 23;
+
+/// A bloom filter of hash values.
+struct BloomFilter {
+    bits: Vec<u64>,
+}
+
+impl BloomFilter {
+    /// The number of bits set for each value.
+    const NUM_HASHES: u64 = 3;
+
+    fn new(num_bits: usize) -> Self {
+        BloomFilter {
+            bits: vec![0; num_bits.div_ceil(64)],
+        }
+    }
+
+    /// Returns the bit positions of the hash value, using double hashing.
+    fn positions(&self, hash: u64) -> impl Iterator<Item = usize> + use<> {
+        let num_bits = self.bits.len() as u64 * 64;
+        let (h1, h2) = (hash, hash.rotate_left(32) | 1);
+        (0..Self::NUM_HASHES)
+            .map(move |i| (h1.wrapping_add(i.wrapping_mul(h2)) % num_bits) as usize)
+    }
+
+    fn insert(&mut self, hash: u64) {
+        for pos in self.positions(hash) {
+            self.bits[pos / 64] |= 1 << (pos % 64);
+        }
+    }
+
+    /// Returns `false` if the hash value is definitely not in the set.
+    fn contains(&self, hash: u64) -> bool {
+        self.positions(hash)
+            .all(|pos| self.bits[pos / 64] & (1 << (pos % 64)) != 0)
+    }
 }
 
 /// The executor for hash semi/anti join
@@ -207,3 +457,79 @@ impl HashSemiJoinExecutor2 {
             .collect()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use futures::TryStreamExt;
+    use test_case::test_case;
+
+    use super::*;
+
+    /// Returns chunks of rows `(i % modulo, i)` for `i` in `range`, 100 rows per chunk.
+    fn input(range: std::ops::Range<i32>, modulo: i32) -> BoxedExecutor {
+        let chunks = range
+            .chunks(100)
+            .into_iter()
+            .map(|chunk| {
+                let values = chunk.collect_vec();
+                [
+                    ArrayImpl::new_int32(values.iter().map(|i| i % modulo).collect()),
+                    ArrayImpl::new_int32(values.iter().copied().collect()),
+                ]
+                .into_iter()
+                .collect::<DataChunk>()
+            })
+            .collect_vec();
+        futures::stream::iter(chunks.into_iter().map(Ok)).boxed()
+    }
+
+    async fn join<const T: JoinType>(memory_limit: usize, query_memory_limit: usize) -> Vec<Row> {
+        let spill_dir = tempfile::tempdir().unwrap();
+        let executor = HashJoinExecutor::<T> {
+            left_keys: "(list #0)".parse().unwrap(),
+            right_keys: "(list #0)".parse().unwrap(),
+            left_types: vec![DataType::Int32, DataType::Int32],
+            right_types: vec![DataType::Int32, DataType::Int32],
+            memory_limit,
+            spill_dir: spill_dir.path().into(),
+            memory: MemoryTracker::new(query_memory_limit),
+        };
+        let chunks = executor
+            .execute(input(0..1000, 50), input(0..300, 70))
+            .try_collect::<Vec<_>>()
+            .await
+            .unwrap();
+        // spilled partitions should be removed
+        assert_eq!(std::fs::read_dir(spill_dir.path()).unwrap().count(), 0);
+        let mut rows = chunks
+            .iter()
+            .flat_map(|chunk| chunk.rows().map(|row| row.to_owned()))
+            .collect_vec();
+        rows.sort();
+        rows
+    }
+
+    #[test_case(4096, usize::MAX; "spill some partitions")]
+    #[test_case(0, usize::MAX; "spill every partition")]
+    #[test_case(usize::MAX, 4096; "spill by query memory limit")]
+    #[tokio::test]
+    async fn grace_hash_join(memory_limit: usize, query_memory_limit: usize) {
+        assert_eq!(
+            join::<{ JoinType::Inner }>(memory_limit, query_memory_limit).await,
+            join::<{ JoinType::Inner }>(usize::MAX, usize::MAX).await,
+        );
+        assert_eq!(
+            join::<{ JoinType::FullOuter }>(memory_limit, query_memory_limit).await,
+            join::<{ JoinType::FullOuter }>(usize::MAX, usize::MAX).await,
+        );
+        // left keys 0..50 have 20 rows each
+        // right keys 0..20 have 5 rows each, and keys 20..70 have 4 rows each
+        let rows = join::<{ JoinType::FullOuter }>(memory_limit, query_memory_limit).await;
+        let inner = rows
+            .iter()
+            .filter(|row| !row[0].is_null() && !row[2].is_null());
+        assert_eq!(inner.count(), 20 * 20 * 5 + 30 * 20 * 4);
+        let right_only = rows.iter().filter(|row| row[0].is_null());
+        assert_eq!(right_only.count(), 20 * 4);
+    }
+}
@@ -0,0 +1,91 @@
+// Copyright 2025 RisingLight Project Authors. Licensed under Apache-2.0.
+
+use std::sync::Arc;
+
+use super::*;
+use crate::array::{ArrayImpl, DataChunk, I64Array};
+use crate::binder::VectorDistance;
+use crate::catalog::{CatalogError, ColumnRefId, TableRefId};
+use crate::storage::{RowHandler, Storage, StorageColumnRef, Transaction};
+use crate::types::DataValue;
+
+/// The number of rows fetched from the index for each requested row when there is a filter.
+const OVERFETCH_FACTOR: usize = 4;
+
+/// The executor of vector index scan.
+///
+/// It gets the nearest rows to `key` from the index, fetches them from the table, and applies the
+/// filter. If too many rows are filtered out, it fetches more rows from the index and retries.
+pub struct IndexScanExecutor<S: Storage> {
+    pub table_id: TableRefId,
+    pub columns: Vec<ColumnRefId>,
+    /// The vector column and distance function to find the index.
+    pub column: ColumnRefId,
+    pub distance: VectorDistance,
+    pub key: DataValue,
+    /// The condition on `columns`. `None` for no filter.
+    pub filter: Option<RecExpr>,
+    /// The maximum number of rows to return. `None` for all rows.
+    pub limit: Option<usize>,
+    pub storage: Arc<S>,
+    /// The transaction block the statement runs in, if any.
+    pub block: Option<Arc<TransactionBlock<S>>>,
+}
+
+impl<S: Storage> IndexScanExecutor<S> {
+    #[try_stream(boxed, ok = DataChunk, error = ExecutorError)]
+    pub async fn execute(self) {
+        // the index may have been dropped since the statement was planned
+        let index_id = (self.storage.get_catalog())
+            .get_vector_index(&self.column, &self.distance)
+            .ok_or_else(|| CatalogError::NotFound("vector index", self.column.to_string()))?;
+        let index = (self.storage)
+            .get_index(self.table_id.schema_id, index_id)
+            .await?;
+        let table = self.storage.get_table(self.table_id)?;
+        let col_idx = self
+            .columns
+            .iter()
+            .map(|x| match x.column_id {
+                u32::MAX => StorageColumnRef::RowHandler,
+                id => StorageColumnRef::Idx(id),
+            })
+            .collect_vec();
+        let txn = StatementTxn::read(&table, &self.block).await?;
+
+        let limit = self.limit.unwrap_or(usize::MAX);
+        let mut k = match self.filter {
+            Some(_) => limit.saturating_mul(OVERFETCH_FACTOR),
+            None => limit,
+        };
+        let chunk = loop {
+            let row_handlers = ArrayImpl::new_int64(I64Array::from_iter(
+                index.search(&self.key, k).into_iter().map(Some),
+            ));
+            let exhausted = row_handlers.len() < k;
+            let ids = (0..row_handlers.len())
+                .map(|idx| {
+                    <S::Transaction as Transaction>::RowHandlerType::from_column(&row_handlers, idx)
+                })
+                .collect_vec();
+            let chunk = txn.fetch(&col_idx, &ids).await?;
+            let chunk = match &self.filter {
+                Some(filter) => match Evaluator::new(filter).eval(&chunk)? {
+                    ArrayImpl::Bool(a) => chunk.filter(a.true_array()),
+                    _ => Err(ExecutorError::invalid_argument(
+                        "filters can only accept bool array",
+                    ))?,
+                },
+                None => chunk,
+            };
+            if chunk.cardinality() >= limit || exhausted {
+                break chunk;
+            }
+            k = k.saturating_mul(2);
+        };
+
+        if chunk.cardinality() > 0 {
+            yield chunk;
+        }
+    }
+}
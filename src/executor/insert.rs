@@ -5,7 +5,7 @@ use std::sync::Arc;
 use super::*;
 use crate::array::DataChunk;
 use crate::catalog::{ColumnId, TableRefId};
-use crate::storage::{Storage, Table, Transaction};
+use crate::storage::{Storage, Table};
 use crate::types::ColumnIndex;
 
 /// The executor of `insert` statement.
@@ -13,6 +13,8 @@ pub struct InsertExecutor<S: Storage> {
     pub table_id: TableRefId,
     pub column_ids: Vec<ColumnId>,
     pub storage: Arc<S>,
+    /// The transaction block the statement runs in, if any.
+    pub block: Option<Arc<TransactionBlock<S>>>,
 }
 
 impl<S: Storage> InsertExecutor<S> {
@@ -29,7 +31,9 @@ impl<S: Storage> InsertExecutor<S> {
                 let val = expr.add(
                     match self.column_ids.iter().position(|&id| id == col.id()) {
                         Some(index) => Expr::ColumnIndex(ColumnIndex(index as _)),
-                        None => Expr::null(),
+                        None => {
+                            Expr::Constant(col.default_value().cloned().unwrap_or(DataValue::Null))
+                        }
                     },
                 );
                 let ty = expr.add(Expr::Type(col.data_type()));
@@ -38,7 +42,7 @@ impl<S: Storage> InsertExecutor<S> {
             .collect();
         expr.add(Expr::List(list));
 
-        let mut txn = table.write().await?;
+        let mut txn = StatementTxn::write(&table, &self.block).await?;
         let mut cnt = 0;
         #[for_await]
         for chunk in child {
@@ -46,7 +50,7 @@ impl<S: Storage> InsertExecutor<S> {
             cnt += chunk.cardinality();
             txn.append(chunk).await?;
         }
-        txn.commit().await?;
+        txn.finish().await?;
 
         yield DataChunk::single(cnt as i32);
     }
@@ -69,6 +73,7 @@ mod tests {
             table_id: TableRefId::new(1, 0),
             column_ids: vec![0, 1],
             storage: storage.as_in_memory_storage(),
+            block: None,
         };
         let source = async_stream::try_stream! {
             yield [
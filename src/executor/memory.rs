@@ -0,0 +1,157 @@
+// Copyright 2024 RisingLight Project Authors. Licensed under Apache-2.0.
+
+//! Memory accounting of a query.
+//!
+//! All executors of a query share a [`MemoryTracker`] with a limit. Executors that buffer chunks
+//! reserve memory through a [`MemoryReservation`]. When the limit is reached, executors that can
+//! spill write their buffers to disk, and others fail the query.
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use super::{ExecutorError, Result};
+
+/// Tracks the memory used by all executors of a query.
+#[derive(Debug, Clone)]
+pub struct MemoryTracker {
+    inner: Arc<TrackerInner>,
+}
+
+#[derive(Debug)]
+struct TrackerInner {
+    limit: usize,
+    used: AtomicUsize,
+}
+
+impl MemoryTracker {
+    /// Creates a tracker with a limit in bytes.
+    pub fn new(limit: usize) -> Self {
+        MemoryTracker {
+            inner: Arc::new(TrackerInner {
+                limit,
+                used: AtomicUsize::new(0),
+            }),
+        }
+    }
+
+    /// Returns the limit in bytes.
+    pub fn limit(&self) -> usize {
+        self.inner.limit
+    }
+
+    /// Returns the memory in bytes reserved by all executors.
+    pub fn used(&self) -> usize {
+        self.inner.used.load(Ordering::Relaxed)
+    }
+
+    /// Creates an empty reservation for an executor.
+    pub fn reservation(&self, executor: &'static str) -> MemoryReservation {
+        MemoryReservation {
+            tracker: self.clone(),
+            executor,
+            size: 0,
+        }
+    }
+
+    fn try_grow(&self, bytes: usize) -> bool {
+        (self.inner.used)
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |used| {
+                used.checked_add(bytes)
+                    .filter(|new| *new <= self.inner.limit)
+            })
+            .is_ok()
+    }
+
+    fn shrink(&self, bytes: usize) {
+        self.inner.used.fetch_sub(bytes, Ordering::Relaxed);
+    }
+}
+
+/// Memory reserved by an executor. Released when dropped.
+#[derive(Debug)]
+pub struct MemoryReservation {
+    tracker: MemoryTracker,
+    executor: &'static str,
+    size: usize,
+}
+
+impl MemoryReservation {
+    /// Returns the reserved memory in bytes.
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Reserves `bytes` more memory. Returns `false` if the limit of the query is exceeded, in
+    /// which case nothing is reserved.
+    pub fn try_grow(&mut self, bytes: usize) -> bool {
+        if !self.tracker.try_grow(bytes) {
+            return false;
+        }
+        self.size += bytes;
+        true
+    }
+
+    /// Reserves `bytes` more memory, or fails the query if the limit is exceeded.
+    pub fn grow(&mut self, bytes: usize) -> Result<()> {
+        if !self.try_grow(bytes) {
+            return Err(ExecutorError::memory_limit(
+                self.executor,
+                self.tracker.used() + bytes,
+                self.tracker.limit(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Resizes the reservation to `bytes`. Returns `false` if the limit of the query is
+    /// exceeded, in which case the reservation is unchanged.
+    pub fn try_resize(&mut self, bytes: usize) -> bool {
+        if bytes > self.size {
+            return self.try_grow(bytes - self.size);
+        }
+        self.tracker.shrink(self.size - bytes);
+        self.size = bytes;
+        true
+    }
+
+    /// Releases all reserved memory.
+    pub fn free(&mut self) {
+        self.tracker.shrink(self.size);
+        self.size = 0;
+    }
+}
+
+impl Drop for MemoryReservation {
+    fn drop(&mut self) {
+        self.free();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reservation() {
+        let tracker = MemoryTracker::new(100);
+        let mut r1 = tracker.reservation("r1");
+        let mut r2 = tracker.reservation("r2");
+        assert!(r1.try_grow(60));
+        assert!(!r2.try_grow(50));
+        assert_eq!(r2.size(), 0);
+        assert!(r2.try_grow(40));
+        assert_eq!(tracker.used(), 100);
+
+        let err = r2.grow(1).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "r2 exceeds the memory limit of the query: 101 bytes requested, limit is 100 bytes"
+        );
+
+        assert!(r1.try_resize(10));
+        assert_eq!(tracker.used(), 50);
+        r1.free();
+        drop(r2);
+        assert_eq!(tracker.used(), 0);
+    }
+}
@@ -11,8 +11,16 @@
 //! To write async-stream in Rust, we use the [`try_stream`] macro from [`async_stream`] crate.
 //!
 //! [`try_stream`]: async_stream::try_stream
+//!
+//! # Parallel Execution
+//!
+//! When `parallelism` is greater than 1, table scans, hash aggregations, hash joins and sorts
+//! run as multiple partitions, each in its own task. Partitions are connected by exchange
+//! operators defined in the [`exchange`] module.
 
 use std::collections::HashMap;
+use std::ops::Bound;
+use std::path::PathBuf;
 use std::sync::Arc;
 use std::time::Duration;
 
@@ -23,6 +31,7 @@ use itertools::Itertools;
 use tracing::Instrument;
 
 // use minitrace::prelude::*;
+use self::alter_table::*;
 use self::analyze::*;
 use self::copy_from_file::*;
 use self::copy_to_file::*;
@@ -35,32 +44,43 @@ use self::drop::*;
 pub use self::error::Error as ExecutorError;
 use self::error::*;
 use self::evaluator::*;
+use self::exchange::*;
 use self::explain::*;
+use self::file_scan::*;
 use self::filter::*;
 use self::hash_agg::*;
 use self::hash_join::*;
+use self::index_scan::*;
 use self::insert::*;
 use self::limit::*;
+use self::memory::*;
 use self::merge_join::*;
 use self::nested_loop_join::*;
 use self::order::*;
 // #[allow(unused_imports)]
 // use self::perfect_hash_agg::*;
 use self::projection::*;
+use self::recursive_union::*;
+use self::set_op::*;
 use self::simple_agg::*;
 use self::sort_agg::*;
 use self::system_table_scan::*;
 use self::table_scan::*;
 use self::top_n::TopNExecutor;
+pub use self::transaction::TransactionBlock;
+use self::transaction::*;
+use self::update::*;
+use self::user::*;
 use self::values::*;
 use self::window::*;
 use crate::array::DataChunk;
 use crate::catalog::{RootCatalog, RootCatalogRef, TableRefId};
 use crate::planner::{Expr, ExprAnalysis, Optimizer, RecExpr, TypeSchemaAnalysis};
-use crate::storage::Storage;
-use crate::types::{ColumnIndex, DataType};
+use crate::storage::{KeyRange, Storage};
+use crate::types::{ColumnIndex, DataType, DataValue};
 use crate::utils::timed::{FutureExt as _, Span as TimeSpan};
 
+mod alter_table;
 mod analyze;
 mod copy_from_file;
 mod copy_to_file;
@@ -70,12 +90,16 @@ mod create_view;
 mod delete;
 mod drop;
 mod evaluator;
+mod exchange;
 mod explain;
+mod file_scan;
 mod filter;
 mod hash_agg;
 mod hash_join;
+mod index_scan;
 mod insert;
 mod limit;
+mod memory;
 mod nested_loop_join;
 mod order;
 mod system_table_scan;
@@ -84,10 +108,16 @@ mod create_index;
 mod error;
 mod merge_join;
 mod projection;
+mod recursive_union;
+mod set_op;
 mod simple_agg;
 mod sort_agg;
+mod spill;
 mod table_scan;
 mod top_n;
+mod transaction;
+mod update;
+mod user;
 mod values;
 mod window;
 
@@ -102,25 +132,83 @@ const PROCESSING_WINDOW_SIZE: usize = 1024;
 /// and produces a stream to its parent.
 pub type BoxedExecutor = BoxStream<'static, Result<DataChunk>>;
 
-pub fn build(optimizer: Optimizer, storage: Arc<impl Storage>, plan: &RecExpr) -> BoxedExecutor {
-    Builder::new(optimizer, storage, plan).build()
+/// Runtime options of executors.
+#[derive(Debug, Clone)]
+pub struct Config {
+    /// The maximum memory in bytes an order executor can use before spilling sorted runs to disk.
+    pub sort_memory_limit: usize,
+    /// The maximum memory in bytes the build side of a hash join can use before spilling
+    /// partitions to disk.
+    pub join_memory_limit: usize,
+    /// The maximum memory in bytes a hash aggregation can use before spilling groups to disk.
+    pub agg_memory_limit: usize,
+    /// The directory to store spilled data.
+    pub spill_dir: PathBuf,
+    /// The maximum number of iterations of a recursive CTE.
+    pub cte_max_recursion_depth: usize,
+    /// The maximum memory in bytes all executors of a query can use.
+    ///
+    /// Executors that exceed the limit spill to disk if they can, otherwise the query fails.
+    pub query_memory_limit: usize,
+    /// The number of partitions to run table scans, hash aggregations, hash joins and sorts in
+    /// parallel. 1 disables parallel execution.
+    pub parallelism: usize,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            sort_memory_limit: 256 << 20,
+            join_memory_limit: 256 << 20,
+            agg_memory_limit: 256 << 20,
+            spill_dir: std::env::temp_dir(),
+            cte_max_recursion_depth: 1000,
+            query_memory_limit: 4 << 30,
+            parallelism: 1,
+        }
+    }
+}
+
+pub fn build<S: Storage>(
+    optimizer: Optimizer,
+    storage: Arc<S>,
+    config: Config,
+    block: Option<Arc<TransactionBlock<S>>>,
+    plan: &RecExpr,
+) -> BoxedExecutor {
+    let memory = MemoryTracker::new(config.query_memory_limit);
+    Builder::new(optimizer, storage, config, memory, block, plan).build()
 }
 
 /// The builder of executor.
 struct Builder<S: Storage> {
     storage: Arc<S>,
     optimizer: Optimizer,
+    config: Config,
+    /// The memory tracker shared by all executors of the query.
+    memory: MemoryTracker,
+    /// The transaction block the query runs in, if any.
+    block: Option<Arc<TransactionBlock<S>>>,
     egraph: egg::EGraph<Expr, TypeSchemaAnalysis>,
     root: Id,
     /// For scans on views, we prebuild their executors and store them here.
     /// Multiple scans on the same view will share the same executor.
     views: HashMap<TableRefId, StreamSubscriber>,
+    /// The working tables of recursive unions, indexed by the source of their columns.
+    working_tables: HashMap<u32, WorkingTableRef>,
     metrics: Metrics,
 }
 
 impl<S: Storage> Builder<S> {
     /// Create a new executor builder.
-    fn new(optimizer: Optimizer, storage: Arc<S>, plan: &RecExpr) -> Self {
+    fn new(
+        optimizer: Optimizer,
+        storage: Arc<S>,
+        config: Config,
+        memory: MemoryTracker,
+        block: Option<Arc<TransactionBlock<S>>>,
+        plan: &RecExpr,
+    ) -> Self {
         let mut egraph = egg::EGraph::new(TypeSchemaAnalysis {
             catalog: optimizer.catalog().clone(),
         });
@@ -132,7 +220,14 @@ impl<S: Storage> Builder<S> {
             if let Expr::Table(tid) = node
                 && let Some(query) = optimizer.catalog().get_table(tid).unwrap().query()
             {
-                let builder = Self::new(optimizer.clone(), storage.clone(), query);
+                let builder = Self::new(
+                    optimizer.clone(),
+                    storage.clone(),
+                    config.clone(),
+                    memory.clone(),
+                    block.clone(),
+                    query,
+                );
                 let subscriber = builder.build_subscriber();
                 views.insert(*tid, subscriber);
             }
@@ -141,9 +236,13 @@ impl<S: Storage> Builder<S> {
         Builder {
             storage,
             optimizer,
+            config,
+            memory,
+            block,
             egraph,
             root,
             views,
+            working_tables: HashMap::new(),
             metrics: Metrics::default(),
         }
     }
@@ -187,11 +286,61 @@ impl<S: Storage> Builder<S> {
             }
             match self.node(id) {
                 Expr::Column(c) => panic!("column {c} not found from input"),
+                Expr::ExtColumn(c) => panic!("column {} not found from input", c.name),
                 e => e.clone(),
             }
         })
     }
 
+    /// Returns the keys of a grouping set and whether the set is empty.
+    ///
+    /// Keys not in the set are replaced by null, and `grouping` functions are replaced by
+    /// their results in the set.
+    fn grouping_set_keys(&mut self, keys: Id, set: Id) -> (Id, bool) {
+        let keys = self.node(keys).as_list().to_vec();
+        let set = (self.node(set).as_list().iter())
+            .map(|i| keys[self.node(*i).as_const().as_usize().unwrap().unwrap()])
+            .collect_vec();
+        let set_keys = (keys.iter())
+            .map(|key| match self.node(*key).clone() {
+                Expr::Grouping(args) => {
+                    let args = self.node(args).as_list().to_vec();
+                    // the bit of the last argument is the least significant bit
+                    let mask =
+                        (args.iter()).fold(0, |mask, arg| mask << 1 | !set.contains(arg) as i32);
+                    self.egraph.add(Expr::Constant(DataValue::Int32(mask)))
+                }
+                _ if set.contains(key) => *key,
+                _ => self.egraph.add(Expr::null()),
+            })
+            .collect();
+        (self.egraph.add(Expr::List(set_keys)), set.is_empty())
+    }
+
+    /// Returns the identifier of the working table with `columns`.
+    fn working_table_id(&self, columns: Id) -> u32 {
+        let column0 = self.node(columns).as_list()[0];
+        self.node(column0).as_ext_column().source
+    }
+
+    /// Analyzes the range filter of a scan.
+    fn scan_range_filter(&self, filter: Id) -> Option<KeyRange> {
+        let mut egraph = egg::EGraph::new(ExprAnalysis::default());
+        let root = egraph.add_expr(&self.recexpr(filter));
+        let expr: Option<KeyRange> = egraph[root].data.range.clone().map(|(_, r)| r);
+        if matches!(
+            expr,
+            Some(KeyRange {
+                start: Bound::Unbounded,
+                end: Bound::Unbounded
+            })
+        ) {
+            None
+        } else {
+            expr
+        }
+    }
+
     /// Returns the catalog.
     fn catalog(&self) -> &RootCatalogRef {
         self.optimizer.catalog()
@@ -215,31 +364,18 @@ impl<S: Storage> Builder<S> {
     /// Builds the executor for the given id and returns its subscriber.
     fn build_id_subscriber(&mut self, id: Id) -> StreamSubscriber {
         use Expr::*;
+        if self.config.parallelism > 1
+            && let Some(subscriber) = self.build_id_parallel(id)
+        {
+            return subscriber;
+        }
         let stream = match self.node(id).clone() {
             Scan([table, list, filter]) => {
                 let table_id = self.node(table).as_table();
                 let columns = (self.node(list).as_list().iter())
                     .map(|id| self.node(*id).as_column())
                     .collect_vec();
-                // analyze range filter
-                let filter = {
-                    use std::ops::Bound;
-                    let mut egraph = egg::EGraph::new(ExprAnalysis::default());
-                    let root = egraph.add_expr(&self.recexpr(filter));
-                    let expr: Option<crate::storage::KeyRange> =
-                        egraph[root].data.range.clone().map(|(_, r)| r);
-                    if matches!(
-                        expr,
-                        Some(crate::storage::KeyRange {
-                            start: Bound::Unbounded,
-                            end: Bound::Unbounded
-                        })
-                    ) {
-                        None
-                    } else {
-                        expr
-                    }
-                };
+                let filter = self.scan_range_filter(filter);
 
                 if let Some(subscriber) = self.views.get(&table_id) {
                     // scan a view
@@ -274,11 +410,55 @@ impl<S: Storage> Builder<S> {
                         columns,
                         filter,
                         storage: self.storage.clone(),
+                        block: self.block.clone(),
+                        partition: None,
                     }
                     .execute()
                 }
             }
 
+            IndexScan([table, list, filter, distance, limit]) => {
+                let table_id = self.node(table).as_table();
+                let columns = (self.node(list).as_list().iter())
+                    .map(|id| self.node(*id).as_column())
+                    .collect_vec();
+                let (op, [column, key]) = match self.node(distance) {
+                    VectorL2Distance(args) => ("<->", args),
+                    VectorCosineDistance(args) => ("<=>", args),
+                    VectorNegtiveInnerProduct(args) => ("<#>", args),
+                    e => panic!("not a vector distance: {e}"),
+                };
+                let filter = match self.node(filter) {
+                    Constant(DataValue::Bool(true)) | Constant(DataValue::Null) => None,
+                    _ => Some(self.resolve_column_index(filter, id)),
+                };
+                IndexScanExecutor {
+                    table_id,
+                    columns,
+                    column: self.node(*column).as_column(),
+                    distance: op.parse().unwrap(),
+                    key: self.node(*key).as_const(),
+                    filter,
+                    limit: self.node(limit).as_const().as_usize().unwrap(),
+                    storage: self.storage.clone(),
+                    block: self.block.clone(),
+                }
+                .execute()
+            }
+
+            FileScan([source, list, filter]) => FileScanExecutor {
+                source: self.node(source).as_ext_source(),
+                projection: (self.node(list).as_list().iter())
+                    .map(|id| self.node(*id).as_ext_column().index as usize)
+                    .collect(),
+                types: self.plan_types(id).to_vec(),
+                filter: match self.node(filter) {
+                    Constant(DataValue::Bool(true)) => None,
+                    _ => Some(self.resolve_column_index(filter, id)),
+                },
+            }
+            .execute(),
+
             Values(rows) => ValuesExecutor {
                 column_types: self.plan_types(id).to_vec(),
                 values: {
@@ -306,6 +486,9 @@ impl<S: Storage> Builder<S> {
             Order([order_keys, child]) => OrderExecutor {
                 order_keys: self.resolve_column_index(order_keys, child),
                 types: self.plan_types(id).to_vec(),
+                memory_limit: self.config.sort_memory_limit,
+                spill_dir: self.config.spill_dir.clone(),
+                memory: self.memory.clone(),
             }
             .execute(self.build_id(child)),
 
@@ -323,32 +506,19 @@ impl<S: Storage> Builder<S> {
             }
             .execute(self.build_id(child)),
 
-            Join([op, on, left, right]) => match self.node(op) {
-                Inner | LeftOuter | RightOuter | FullOuter => NestedLoopJoinExecutor {
-                    op: self.node(op).clone(),
-                    condition: self.resolve_column_index2(on, left, right),
-                    left_types: self.plan_types(left).to_vec(),
-                    right_types: self.plan_types(right).to_vec(),
-                }
-                .execute(self.build_id(left), self.build_id(right)),
-                op @ Semi | op @ Anti => NestedLoopSemiJoinExecutor {
-                    anti: matches!(op, Anti),
-                    condition: self.resolve_column_index2(on, left, right),
-                    left_types: self.plan_types(left).to_vec(),
-                }
-                .execute(self.build_id(left), self.build_id(right)),
-                t => panic!("invalid join type: {t:?}"),
-            },
+            Join([op, on, left, right]) => NestedLoopJoinExecutor {
+                op: self.node(op).clone(),
+                condition: self.resolve_column_index2(on, left, right),
+                left_types: self.plan_types(left).to_vec(),
+                right_types: self.plan_types(right).to_vec(),
+                memory: self.memory.clone(),
+            }
+            .execute(self.build_id(left), self.build_id(right)),
 
-            HashJoin(args @ [op, ..]) => match self.node(op) {
-                Inner => self.build_hashjoin::<{ JoinType::Inner }>(args),
-                LeftOuter => self.build_hashjoin::<{ JoinType::LeftOuter }>(args),
-                RightOuter => self.build_hashjoin::<{ JoinType::RightOuter }>(args),
-                FullOuter => self.build_hashjoin::<{ JoinType::FullOuter }>(args),
-                Semi => self.build_hashsemijoin(args, false),
-                Anti => self.build_hashsemijoin(args, true),
-                t => panic!("invalid join type: {t:?}"),
-            },
+            HashJoin(args @ [.., left, right]) => {
+                let (left, right) = (self.build_id(left), self.build_id(right));
+                self.build_hashjoin(args, left, right)
+            }
 
             MergeJoin(args @ [op, ..]) => match self.node(op) {
                 Inner => self.build_mergejoin::<{ JoinType::Inner }>(args),
@@ -358,6 +528,51 @@ impl<S: Storage> Builder<S> {
                 t => panic!("invalid join type: {t:?}"),
             },
 
+            SetOp([op, left, right]) => match self.node(op) {
+                UnionAll => UnionAllExecutor.execute(self.build_id(left), self.build_id(right)),
+                Union | Intersect | IntersectAll | Except | ExceptAll => HashSetOpExecutor {
+                    op: self.node(op).clone(),
+                }
+                .execute(self.build_id(left), self.build_id(right)),
+                t => panic!("invalid set operation: {t:?}"),
+            },
+
+            RecursiveUnion([op, columns, base, recursive]) => {
+                let working_table = WorkingTableRef::default();
+                (self.working_tables).insert(self.working_table_id(columns), working_table.clone());
+                // the recursive term is built again for each iteration
+                let plan = self.recexpr(recursive);
+                let optimizer = self.optimizer.clone();
+                let storage = self.storage.clone();
+                let config = self.config.clone();
+                let memory = self.memory.clone();
+                let block = self.block.clone();
+                let working_tables = self.working_tables.clone();
+                let build_recursive = move || {
+                    let mut builder = Builder::new(
+                        optimizer.clone(),
+                        storage.clone(),
+                        config.clone(),
+                        memory.clone(),
+                        block.clone(),
+                        &plan,
+                    );
+                    builder.working_tables = working_tables.clone();
+                    builder.build()
+                };
+                RecursiveUnionExecutor {
+                    distinct: matches!(self.node(op), Union),
+                    max_iterations: self.config.cte_max_recursion_depth,
+                    working_table,
+                }
+                .execute(self.build_id(base), build_recursive)
+            }
+
+            WorkingTable(columns) => WorkingTableExecutor {
+                table: self.working_tables[&self.working_table_id(columns)].clone(),
+            }
+            .execute(),
+
             Apply(_) => {
                 panic!(
                     "Apply is not supported in executor. It should be rewritten to join by optimizer."
@@ -374,9 +589,32 @@ impl<S: Storage> Builder<S> {
                 keys: self.resolve_column_index(keys, child),
                 aggs: self.resolve_column_index(aggs, child),
                 types: self.plan_types(id).to_vec(),
+                memory_limit: self.config.agg_memory_limit,
+                spill_dir: self.config.spill_dir.clone(),
+                memory: self.memory.clone(),
             }
             .execute(self.build_id(child)),
 
+            GroupingSets([keys, sets, aggs, child]) => {
+                let sets = (self.node(sets).as_list().to_vec().into_iter())
+                    .map(|set| self.grouping_set_keys(keys, set))
+                    .collect_vec();
+                GroupingSetsExecutor {
+                    empty: sets.iter().map(|(_, empty)| *empty).collect(),
+                    sets: (sets.into_iter())
+                        .map(|(set_keys, _)| HashAggExecutor {
+                            keys: self.resolve_column_index(set_keys, child),
+                            aggs: self.resolve_column_index(aggs, child),
+                            types: self.plan_types(id).to_vec(),
+                            memory_limit: self.config.agg_memory_limit,
+                            spill_dir: self.config.spill_dir.clone(),
+                            memory: self.memory.clone(),
+                        })
+                        .collect(),
+                }
+                .execute(self.build_id(child))
+            }
+
             SortAgg([keys, aggs, child]) => SortAggExecutor {
                 keys: self.resolve_column_index(keys, child),
                 aggs: self.resolve_column_index(aggs, child),
@@ -387,6 +625,7 @@ impl<S: Storage> Builder<S> {
             Window([exprs, child]) => WindowExecutor {
                 exprs: self.resolve_column_index(exprs, child),
                 types: self.plan_types(exprs).to_vec(),
+                memory: self.memory.clone(),
             }
             .execute(self.build_id(child)),
 
@@ -424,18 +663,50 @@ impl<S: Storage> Builder<S> {
             }
             .execute(),
 
+            AlterTable(alter) => AlterTableExecutor {
+                alter,
+                storage: self.storage.clone(),
+            }
+            .execute(),
+
+            CreateUser(user) => CreateUserExecutor {
+                user: *user,
+                storage: self.storage.clone(),
+            }
+            .execute(),
+
+            DropUser(names) => DropUserExecutor {
+                names: (self.node(names).as_list().iter())
+                    .map(|id| self.node(*id).as_const().as_str().to_string())
+                    .collect(),
+                storage: self.storage.clone(),
+            }
+            .execute(),
+
             Insert([table, cols, child]) => InsertExecutor {
                 table_id: self.node(table).as_table(),
                 column_ids: (self.node(cols).as_list().iter())
                     .map(|id| self.node(*id).as_column().column_id)
                     .collect(),
                 storage: self.storage.clone(),
+                block: self.block.clone(),
             }
             .execute(self.build_id(child)),
 
             Delete([table, child]) => DeleteExecutor {
                 table_id: self.node(table).as_table(),
                 storage: self.storage.clone(),
+                block: self.block.clone(),
+            }
+            .execute(self.build_id(child)),
+
+            Update([table, cols, child]) => UpdateExecutor {
+                table_id: self.node(table).as_table(),
+                column_ids: (self.node(cols).as_list().iter())
+                    .map(|id| self.node(*id).as_column().column_id)
+                    .collect(),
+                storage: self.storage.clone(),
+                block: self.block.clone(),
             }
             .execute(self.build_id(child)),
 
@@ -447,6 +718,7 @@ impl<S: Storage> Builder<S> {
 
             CopyTo([src, child]) => CopyToFileExecutor {
                 source: self.node(src).as_ext_source(),
+                types: self.plan_types(child).to_vec(),
             }
             .execute(self.build_id(child)),
 
@@ -473,7 +745,169 @@ impl<S: Storage> Builder<S> {
         self.spawn(id, stream)
     }
 
-    fn build_hashjoin<const T: JoinType>(&mut self, args: [Id; 6]) -> BoxedExecutor {
+    /// Builds the executor for the given id in partitions and merges them by an exchange.
+    ///
+    /// Returns `None` if the node does not run in parallel.
+    fn build_id_parallel(&mut self, id: Id) -> Option<StreamSubscriber> {
+        use Expr::*;
+        let stream = match self.node(id).clone() {
+            Order([order_keys, child]) => {
+                // sort each partition and merge the sorted partitions
+                let order_keys = self.resolve_column_index(order_keys, child);
+                let types = self.plan_types(id).to_vec();
+                let mut partitions = vec![];
+                for child in self.build_id_partitioned(child) {
+                    let stream = OrderExecutor {
+                        order_keys: order_keys.clone(),
+                        types: types.clone(),
+                        memory_limit: self.config.sort_memory_limit,
+                        spill_dir: self.config.spill_dir.clone(),
+                        memory: self.memory.clone(),
+                    }
+                    .execute(child);
+                    partitions.push(self.spawn(id, stream).subscribe());
+                }
+                MergeSortedExecutor { order_keys, types }.execute(partitions)
+            }
+            HashAgg(_) | HashJoin(_) => GatherExecutor.execute(self.build_id_partitioned(id)),
+            _ => return None,
+        };
+        Some(self.spawn_exchange(id, stream))
+    }
+
+    /// Builds the executor for the given id as `parallelism` partitions.
+    ///
+    /// Scans are split by the storage. Hash aggregations and hash joins repartition their inputs
+    /// by the hash of keys. Other nodes are built as a single partition.
+    fn build_id_partitioned(&mut self, id: Id) -> Vec<BoxedExecutor> {
+        use Expr::*;
+        let num_partitions = self.config.parallelism;
+        let streams = match self.node(id).clone() {
+            Scan([table, list, filter])
+                if !self.views.contains_key(&self.node(table).as_table())
+                    && self.node(table).as_table().schema_id != RootCatalog::SYSTEM_SCHEMA_ID =>
+            {
+                let table_id = self.node(table).as_table();
+                let columns = (self.node(list).as_list().iter())
+                    .map(|id| self.node(*id).as_column())
+                    .collect_vec();
+                let filter = self.scan_range_filter(filter);
+                // all partitions share the same transaction
+                let txn = Arc::new(tokio::sync::OnceCell::new());
+                (0..num_partitions)
+                    .map(|index| {
+                        TableScanExecutor {
+                            table_id,
+                            columns: columns.clone(),
+                            filter: filter.clone(),
+                            storage: self.storage.clone(),
+                            block: self.block.clone(),
+                            partition: Some(ScanPartition {
+                                index,
+                                count: num_partitions,
+                                txn: txn.clone(),
+                            }),
+                        }
+                        .execute()
+                    })
+                    .collect_vec()
+            }
+
+            Proj([projs, child]) => {
+                let projs = self.resolve_column_index(projs, child);
+                (self.build_id_partitioned(child).into_iter())
+                    .map(|child| {
+                        ProjectionExecutor {
+                            projs: projs.clone(),
+                        }
+                        .execute(child)
+                    })
+                    .collect()
+            }
+
+            Filter([cond, child]) => {
+                let condition = self.resolve_column_index(cond, child);
+                (self.build_id_partitioned(child).into_iter())
+                    .map(|child| {
+                        FilterExecutor {
+                            condition: condition.clone(),
+                        }
+                        .execute(child)
+                    })
+                    .collect()
+            }
+
+            HashAgg([keys, aggs, child]) => {
+                let keys = self.resolve_column_index(keys, child);
+                let aggs = self.resolve_column_index(aggs, child);
+                let inputs = HashPartitionExecutor {
+                    keys: keys.clone(),
+                    num_partitions,
+                }
+                .execute(self.build_id_partitioned(child));
+                (inputs.into_iter())
+                    .map(|input| {
+                        HashAggExecutor {
+                            keys: keys.clone(),
+                            aggs: aggs.clone(),
+                            types: self.plan_types(id).to_vec(),
+                            memory_limit: self.config.agg_memory_limit,
+                            spill_dir: self.config.spill_dir.clone(),
+                            memory: self.memory.clone(),
+                        }
+                        .execute(input)
+                    })
+                    .collect()
+            }
+
+            HashJoin(args @ [_, _, lkeys, rkeys, left, right]) => {
+                let lefts = HashPartitionExecutor {
+                    keys: self.resolve_column_index(lkeys, left),
+                    num_partitions,
+                }
+                .execute(self.build_id_partitioned(left));
+                let rights = HashPartitionExecutor {
+                    keys: self.resolve_column_index(rkeys, right),
+                    num_partitions,
+                }
+                .execute(self.build_id_partitioned(right));
+                (lefts.into_iter().zip(rights))
+                    .map(|(left, right)| self.build_hashjoin(args, left, right))
+                    .collect()
+            }
+
+            _ => return vec![self.build_id(id)],
+        };
+        (streams.into_iter())
+            .map(|stream| self.spawn(id, stream).subscribe())
+            .collect()
+    }
+
+    /// Builds a hash join on the streams of its children.
+    fn build_hashjoin(
+        &self,
+        args: [Id; 6],
+        left: BoxedExecutor,
+        right: BoxedExecutor,
+    ) -> BoxedExecutor {
+        use Expr::*;
+        match self.node(args[0]) {
+            Inner => self.build_hashjoin_of::<{ JoinType::Inner }>(args, left, right),
+            LeftOuter => self.build_hashjoin_of::<{ JoinType::LeftOuter }>(args, left, right),
+            RightOuter => self.build_hashjoin_of::<{ JoinType::RightOuter }>(args, left, right),
+            FullOuter => self.build_hashjoin_of::<{ JoinType::FullOuter }>(args, left, right),
+            Semi => self.build_hashsemijoin(args, false, left, right),
+            Anti => self.build_hashsemijoin(args, true, left, right),
+            t => panic!("invalid join type: {t:?}"),
+        }
+    }
+
+    fn build_hashjoin_of<const T: JoinType>(
+        &self,
+        args: [Id; 6],
+        left_stream: BoxedExecutor,
+        right_stream: BoxedExecutor,
+    ) -> BoxedExecutor {
         let [_, cond, lkeys, rkeys, left, right] = args;
         assert_eq!(self.node(cond), &Expr::true_());
         HashJoinExecutor::<T> {
@@ -481,11 +915,20 @@ impl<S: Storage> Builder<S> {
             right_keys: self.resolve_column_index(rkeys, right),
             left_types: self.plan_types(left).to_vec(),
             right_types: self.plan_types(right).to_vec(),
+            memory_limit: self.config.join_memory_limit,
+            spill_dir: self.config.spill_dir.clone(),
+            memory: self.memory.clone(),
         }
-        .execute(self.build_id(left), self.build_id(right))
+        .execute(left_stream, right_stream)
     }
 
-    fn build_hashsemijoin(&mut self, args: [Id; 6], anti: bool) -> BoxedExecutor {
+    fn build_hashsemijoin(
+        &self,
+        args: [Id; 6],
+        anti: bool,
+        left_stream: BoxedExecutor,
+        right_stream: BoxedExecutor,
+    ) -> BoxedExecutor {
         let [_, cond, lkeys, rkeys, left, right] = args;
         if self.node(cond) == &Expr::true_() {
             HashSemiJoinExecutor {
@@ -493,7 +936,7 @@ impl<S: Storage> Builder<S> {
                 right_keys: self.resolve_column_index(rkeys, right),
                 anti,
             }
-            .execute(self.build_id(left), self.build_id(right))
+            .execute(left_stream, right_stream)
         } else {
             HashSemiJoinExecutor2 {
                 left_keys: self.resolve_column_index(lkeys, left),
@@ -503,7 +946,7 @@ impl<S: Storage> Builder<S> {
                 right_types: self.plan_types(right).to_vec(),
                 anti,
             }
-            .execute(self.build_id(left), self.build_id(right))
+            .execute(left_stream, right_stream)
         }
     }
 
@@ -520,15 +963,31 @@ impl<S: Storage> Builder<S> {
     }
 
     /// Spawn a new task to execute the given stream.
-    fn spawn(&mut self, id: Id, mut stream: BoxedExecutor) -> StreamSubscriber {
+    fn spawn(&mut self, id: Id, stream: BoxedExecutor) -> StreamSubscriber {
         let name = self.node(id).to_string();
-        let span = TimeSpan::default();
-        let output_row_counter = Counter::default();
+        let (span, output_row_counter) = self.metrics.get_or_register(id);
+        Self::spawn_task(id, name, span, output_row_counter, stream)
+    }
 
-        self.metrics
-            .register(id, span.clone(), output_row_counter.clone());
+    /// Spawn a new task to execute the exchange that merges the partitions of a node.
+    ///
+    /// The metrics of the node are collected from its partitions.
+    fn spawn_exchange(&mut self, id: Id, stream: BoxedExecutor) -> StreamSubscriber {
+        let name = format!("Exchange({})", self.node(id));
+        Self::spawn_task(id, name, TimeSpan::default(), Counter::default(), stream)
+    }
 
+    fn spawn_task(
+        id: Id,
+        name: String,
+        span: TimeSpan,
+        output_row_counter: Counter,
+        mut stream: BoxedExecutor,
+    ) -> StreamSubscriber {
         let (tx, rx) = async_broadcast::broadcast(16);
+        // Deactivate the receiver before the task starts, otherwise the chunks sent to it are
+        // lost, as subscribers only receive chunks sent after they subscribe.
+        let rx = rx.deactivate();
         let handle = tokio::task::Builder::default()
             .name(&format!("{id}.{name}"))
             .spawn(
@@ -549,7 +1008,7 @@ impl<S: Storage> Builder<S> {
             .expect("failed to spawn task");
 
         StreamSubscriber {
-            rx: rx.deactivate(),
+            rx,
             handle: Arc::new(AbortOnDropHandle(handle)),
         }
     }
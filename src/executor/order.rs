@@ -1,49 +1,206 @@
 // Copyright 2024 RisingLight Project Authors. Licensed under Apache-2.0.
 
 use std::cmp::Ordering;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use binary_heap_plus::BinaryHeap;
+use tempfile::TempDir;
+use tokio::sync::mpsc::Sender;
 
 use super::*;
 use crate::array::{DataChunk, DataChunkBuilder, RowRef};
-use crate::types::DataType;
+use crate::types::{DataType, Row};
 
 /// The executor of an order operation.
+///
+/// Rows are buffered in memory until their size exceeds `memory_limit` or the memory limit of
+/// the query. Then the buffered rows are sorted and spilled to a run file in `spill_dir`.
+/// At the end, all sorted runs are merged with a k-way heap.
 pub struct OrderExecutor {
     /// A list of expressions to order by.
     ///
     /// e.g. `(list (+ #0 #1) (desc #0))`
     pub order_keys: RecExpr,
     pub types: Vec<DataType>,
+    /// The maximum size in bytes of rows buffered in memory.
+    pub memory_limit: usize,
+    /// The parent directory of the temporary directory for sorted runs.
+    pub spill_dir: PathBuf,
+    pub memory: MemoryTracker,
 }
 
 impl OrderExecutor {
     #[try_stream(boxed, ok = DataChunk, error = ExecutorError)]
     pub async fn execute(self, child: BoxedExecutor) {
+        let orders = Evaluator::new(&self.order_keys).orders();
+        let order_keys_len = orders.len();
+
         // evaluate order keys and append the original rows
         // chunks = keys || child
         let mut chunks = vec![];
+        let mut reservation = self.memory.reservation("order");
+        let mut runs: Option<SortedRuns> = None;
         #[for_await]
         for chunk in child {
             let chunk = chunk?;
             let order_key_chunk = Evaluator::new(&self.order_keys).eval_list(&chunk)?;
-            chunks.push(order_key_chunk.row_concat(chunk));
+            let chunk = order_key_chunk.row_concat(chunk);
+            let size = chunk.estimated_size();
+            chunks.push(chunk);
+            if reservation.size() + size > self.memory_limit || !reservation.try_grow(size) {
+                let runs = match &mut runs {
+                    Some(runs) => runs,
+                    None => runs.insert(SortedRuns::new(&self.spill_dir)?),
+                };
+                runs.spill(std::mem::take(&mut chunks), orders.clone())
+                    .await?;
+                reservation.free();
+            }
         }
 
-        // sort the rows by keys
-        let mut rows = gen_row_array(&chunks);
-        let orders = Evaluator::new(&self.order_keys).orders();
-        rows.sort_unstable_by(|row1, row2| cmp(row1, row2, &orders));
+        if let Some(mut runs) = runs {
+            // spill the remaining rows and merge all runs
+            if !chunks.is_empty() {
+                runs.spill(chunks, orders.clone()).await?;
+            }
+            let (tx, mut rx) = tokio::sync::mpsc::channel(1);
+            // # Cancellation
+            // When this stream is dropped, the `rx` is dropped, the spawned task will fail to send
+            // to `tx`, then the task will finish.
+            let handle = tokio::task::spawn_blocking(move || {
+                runs.merge(&orders, order_keys_len, &self.types, tx)
+            });
+            while let Some(chunk) = rx.recv().await {
+                yield chunk;
+            }
+            handle.await.unwrap()?;
+        } else {
+            // sort the rows by keys
+            let mut rows = gen_row_array(&chunks);
+            rows.sort_unstable_by(|row1, row2| cmp(row1, row2, &orders));
 
-        // build chunk by the new order
-        let order_keys_len = self.order_keys.as_ref().last().unwrap().as_list().len();
-        let mut builder = DataChunkBuilder::new(&self.types, PROCESSING_WINDOW_SIZE);
-        for row in rows {
-            if let Some(chunk) = builder.push_row(row.values().skip(order_keys_len)) {
+            // build chunk by the new order
+            let mut builder = DataChunkBuilder::new(&self.types, PROCESSING_WINDOW_SIZE);
+            for row in rows {
+                if let Some(chunk) = builder.push_row(row.values().skip(order_keys_len)) {
+                    yield chunk;
+                }
+            }
+            if let Some(chunk) = builder.take() {
                 yield chunk;
             }
         }
+    }
+}
+
+/// Sorted runs spilled to a temporary directory.
+///
+/// The directory and all run files are removed on drop.
+struct SortedRuns {
+    dir: TempDir,
+    paths: Vec<PathBuf>,
+}
+
+impl SortedRuns {
+    /// Create a temporary directory under `parent` for sorted runs.
+    fn new(parent: &Path) -> Result<Self> {
+        let dir = tempfile::Builder::new()
+            .prefix("risinglight-sort-")
+            .tempdir_in(parent)?;
+        Ok(SortedRuns { dir, paths: vec![] })
+    }
+
+    /// Sort the rows in `chunks` and write them to a new run file.
+    async fn spill(&mut self, chunks: Vec<DataChunk>, orders: Vec<bool>) -> Result<()> {
+        let path = self.dir.path().join(format!("run-{}", self.paths.len()));
+        let run_path = path.clone();
+        tokio::task::spawn_blocking(move || write_run(&run_path, &chunks, &orders))
+            .await
+            .unwrap()?;
+        self.paths.push(path);
+        Ok(())
+    }
+
+    /// Merge all sorted runs and send the rows without order keys through `tx`.
+    fn merge(
+        self,
+        orders: &[bool],
+        order_keys_len: usize,
+        types: &[DataType],
+        tx: Sender<DataChunk>,
+    ) -> Result<()> {
+        let mut readers: Vec<RunReader> = self.paths.iter().map(RunReader::open).try_collect()?;
+
+        // a min-heap of the head rows of all runs
+        // ties are broken by the run index to make the sort stable across runs
+        let mut heap = BinaryHeap::with_capacity_by(
+            readers.len(),
+            |(row1, i1): &(Row, usize), (row2, i2): &(Row, usize)| {
+                cmp_rows(row2, row1, orders).then_with(|| i2.cmp(i1))
+            },
+        );
+        for (i, reader) in readers.iter_mut().enumerate() {
+            if let Some(row) = reader.next()? {
+                heap.push((row, i));
+            }
+        }
+
+        let mut builder = DataChunkBuilder::new(types, PROCESSING_WINDOW_SIZE);
+        while let Some((row, i)) = heap.pop() {
+            if let Some(next) = readers[i].next()? {
+                heap.push((next, i));
+            }
+            if let Some(chunk) = builder.push_row(row.into_iter().skip(order_keys_len))
+                && tx.blocking_send(chunk).is_err()
+            {
+                return Ok(());
+            }
+        }
         if let Some(chunk) = builder.take() {
-            yield chunk;
+            _ = tx.blocking_send(chunk);
+        }
+        Ok(())
+    }
+}
+
+/// Sort the rows in `chunks` and write them to a run file at `path`.
+///
+/// The file starts with the number of rows, followed by the encoded rows.
+fn write_run(path: &Path, chunks: &[DataChunk], orders: &[bool]) -> Result<()> {
+    let mut rows = gen_row_array(chunks);
+    rows.sort_unstable_by(|row1, row2| cmp(row1, row2, orders));
+
+    let mut writer = BufWriter::new(File::create(path)?);
+    bincode::serialize_into(&mut writer, &(rows.len() as u64))?;
+    for row in rows {
+        bincode::serialize_into(&mut writer, &row.to_owned())?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// A sequential reader of a run file.
+struct RunReader {
+    reader: BufReader<File>,
+    remaining: u64,
+}
+
+impl RunReader {
+    fn open(path: &PathBuf) -> Result<Self> {
+        let mut reader = BufReader::new(File::open(path)?);
+        let remaining = bincode::deserialize_from(&mut reader)?;
+        Ok(RunReader { reader, remaining })
+    }
+
+    /// Read the next row, or `None` if the run is exhausted.
+    fn next(&mut self) -> Result<Option<Row>> {
+        if self.remaining == 0 {
+            return Ok(None);
         }
+        self.remaining -= 1;
+        Ok(Some(bincode::deserialize_from(&mut self.reader)?))
     }
 }
 
@@ -51,7 +208,16 @@ impl OrderExecutor {
 ///
 /// The order is `false` for ascending and `true` for descending.
 fn cmp(row1: &RowRef, row2: &RowRef, orders: &[bool]) -> Ordering {
-    for ((v1, v2), desc) in row1.values().zip(row2.values()).zip(orders) {
+    cmp_values(row1.values().zip(row2.values()), orders)
+}
+
+/// Compare two owned rows by orders.
+pub(super) fn cmp_rows(row1: &Row, row2: &Row, orders: &[bool]) -> Ordering {
+    cmp_values(row1.iter().zip(row2.iter()), orders)
+}
+
+fn cmp_values<V: Ord>(pairs: impl Iterator<Item = (V, V)>, orders: &[bool]) -> Ordering {
+    for ((v1, v2), desc) in pairs.zip(orders) {
         match v1.cmp(&v2) {
             Ordering::Equal => continue,
             o if *desc => return o.reverse(),
@@ -65,3 +231,64 @@ fn cmp(row1: &RowRef, row2: &RowRef, orders: &[bool]) -> Ordering {
 fn gen_row_array(chunks: &[DataChunk]) -> Vec<RowRef<'_>> {
     chunks.iter().flat_map(|chunk| chunk.rows()).collect()
 }
+
+#[cfg(test)]
+mod tests {
+    use futures::TryStreamExt;
+    use test_case::test_case;
+
+    use super::*;
+    use crate::array::ArrayImpl;
+    use crate::types::DataValue;
+
+    #[test_case(usize::MAX, usize::MAX; "in memory")]
+    #[test_case(4096, usize::MAX; "spill some runs")]
+    #[test_case(0, usize::MAX; "spill every chunk")]
+    #[test_case(usize::MAX, 4096; "spill by query memory limit")]
+    #[tokio::test]
+    async fn order(memory_limit: usize, query_memory_limit: usize) {
+        // (a, b) = (i * 7 % 10, i) for i in 0..3000, split into chunks of 100 rows
+        let inputs = (0..30)
+            .map(|c| {
+                let range = c * 100..(c + 1) * 100;
+                [
+                    ArrayImpl::new_int32(range.clone().map(|i| i * 7 % 10).collect()),
+                    ArrayImpl::new_int32(range.collect()),
+                ]
+                .into_iter()
+                .collect::<DataChunk>()
+            })
+            .collect_vec();
+        let child = futures::stream::iter(inputs.into_iter().map(Ok)).boxed();
+
+        let spill_dir = tempfile::tempdir().unwrap();
+        let executor = OrderExecutor {
+            // order by a asc, b desc
+            order_keys: "(list #0 (desc #1))".parse().unwrap(),
+            types: vec![DataType::Int32, DataType::Int32],
+            memory_limit,
+            spill_dir: spill_dir.path().into(),
+            memory: MemoryTracker::new(query_memory_limit),
+        };
+        let actual = executor
+            .execute(child)
+            .try_collect::<Vec<_>>()
+            .await
+            .unwrap();
+        let actual = actual
+            .iter()
+            .flat_map(|chunk| chunk.rows().map(|row| row.to_owned()))
+            .collect_vec();
+
+        let mut expected = (0..3000).map(|i| (i * 7 % 10, i)).collect_vec();
+        expected.sort_by_key(|&(a, b)| (a, std::cmp::Reverse(b)));
+        let expected = expected
+            .into_iter()
+            .map(|(a, b)| vec![DataValue::Int32(a), DataValue::Int32(b)])
+            .collect_vec();
+        assert_eq!(actual, expected);
+
+        // sorted runs should be removed
+        assert_eq!(std::fs::read_dir(spill_dir.path()).unwrap().count(), 0);
+    }
+}
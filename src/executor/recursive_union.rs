@@ -0,0 +1,88 @@
+// Copyright 2024 RisingLight Project Authors. Licensed under Apache-2.0.
+
+use std::sync::Mutex;
+
+use ahash::{HashSet, HashSetExt};
+
+use super::*;
+use crate::array::DataChunk;
+use crate::types::Row;
+
+/// The rows produced by the last iteration of a recursive union.
+pub type WorkingTableRef = Arc<Mutex<Vec<DataChunk>>>;
+
+/// The executor of a recursive CTE.
+///
+/// It outputs the rows from `base`, then repeatedly executes `recursive` over the working
+/// table, which holds the rows produced by the last iteration, until no new rows are produced.
+pub struct RecursiveUnionExecutor {
+    /// Whether to remove duplicate rows. i.e. `UNION` instead of `UNION ALL`.
+    pub distinct: bool,
+    /// The maximum number of iterations.
+    pub max_iterations: usize,
+    pub working_table: WorkingTableRef,
+}
+
+impl RecursiveUnionExecutor {
+    #[try_stream(boxed, ok = DataChunk, error = ExecutorError)]
+    pub async fn execute(
+        self,
+        base: BoxedExecutor,
+        recursive: impl Fn() -> BoxedExecutor + Send + 'static,
+    ) {
+        let mut seen: HashSet<Row> = HashSet::new();
+        let mut working = vec![];
+        #[for_await]
+        for chunk in base {
+            let chunk = self.dedup(chunk?, &mut seen);
+            if chunk.cardinality() > 0 {
+                working.push(chunk.clone());
+                yield chunk;
+            }
+        }
+
+        let mut iterations = 0;
+        while !working.is_empty() {
+            if iterations == self.max_iterations {
+                return Err(ExecutorError::recursion_limit(self.max_iterations));
+            }
+            iterations += 1;
+            *self.working_table.lock().unwrap() = std::mem::take(&mut working);
+            #[for_await]
+            for chunk in recursive() {
+                let chunk = self.dedup(chunk?, &mut seen);
+                if chunk.cardinality() > 0 {
+                    working.push(chunk.clone());
+                    yield chunk;
+                }
+            }
+        }
+    }
+
+    /// Removes the rows that have been seen if `distinct` is true.
+    fn dedup(&self, chunk: DataChunk, seen: &mut HashSet<Row>) -> DataChunk {
+        if !self.distinct {
+            return chunk;
+        }
+        let visibility = chunk
+            .rows()
+            .map(|row| seen.insert(row.to_owned()))
+            .collect_vec();
+        chunk.filter(&visibility)
+    }
+}
+
+/// The executor of the working table of a recursive CTE.
+pub struct WorkingTableExecutor {
+    pub table: WorkingTableRef,
+}
+
+impl WorkingTableExecutor {
+    #[try_stream(boxed, ok = DataChunk, error = ExecutorError)]
+    pub async fn execute(self) {
+        let chunks = self.table.lock().unwrap().clone();
+        for chunk in chunks {
+            yield chunk;
+        }
+    }
+}
@@ -0,0 +1,118 @@
+// Copyright 2024 RisingLight Project Authors. Licensed under Apache-2.0.
+
+use std::collections::hash_map::Entry;
+
+use ahash::{HashMap, HashMapExt, HashSet, HashSetExt};
+
+use super::*;
+use crate::array::DataChunk;
+use crate::types::Row;
+
+/// The executor of `UNION ALL`.
+///
+/// It outputs all rows from the left child, then all rows from the right child.
+pub struct UnionAllExecutor;
+
+impl UnionAllExecutor {
+    #[try_stream(boxed, ok = DataChunk, error = ExecutorError)]
+    pub async fn execute(self, left: BoxedExecutor, right: BoxedExecutor) {
+        #[for_await]
+        for chunk in left {
+            yield chunk?;
+        }
+        #[for_await]
+        for chunk in right {
+            yield chunk?;
+        }
+    }
+}
+
+/// The executor of `UNION`, `INTERSECT [ALL]` and `EXCEPT [ALL]` using hash tables.
+///
+/// Two rows are considered equal if all their values are equal, including NULLs.
+pub struct HashSetOpExecutor {
+    /// The type of set operation. One of `union`, `intersect`, `intersect_all`, `except` and
+    /// `except_all`.
+    pub op: Expr,
+}
+
+impl HashSetOpExecutor {
+    #[try_stream(boxed, ok = DataChunk, error = ExecutorError)]
+    pub async fn execute(self, left: BoxedExecutor, right: BoxedExecutor) {
+        if let Expr::Union = self.op {
+            // output rows that have not been seen
+            let mut seen: HashSet<Row> = HashSet::new();
+            #[for_await]
+            for chunk in left.chain(right) {
+                let chunk = chunk?;
+                let visibility = chunk
+                    .rows()
+                    .map(|row| seen.insert(row.to_owned()))
+                    .collect_vec();
+                let chunk = chunk.filter(&visibility);
+                if chunk.cardinality() > 0 {
+                    yield chunk;
+                }
+                tokio::task::consume_budget().await;
+            }
+            return Ok(());
+        }
+
+        // build: count the rows from right
+        let mut counts: HashMap<Row, usize> = HashMap::new();
+        #[for_await]
+        for chunk in right {
+            let chunk = chunk?;
+            for row in chunk.rows() {
+                *counts.entry(row.to_owned()).or_default() += 1;
+            }
+            tokio::task::consume_budget().await;
+        }
+
+        // probe: output rows from left
+        #[for_await]
+        for chunk in left {
+            let chunk = chunk?;
+            let mut visibility = Vec::with_capacity(chunk.cardinality());
+            for row in chunk.rows() {
+                let row = row.to_owned();
+                let visible = match self.op {
+                    // output once if the row is in right
+                    Expr::Intersect => counts
+                        .get_mut(&row)
+                        .is_some_and(|c| std::mem::replace(c, 0) > 0),
+                    // output min(m, n) times
+                    Expr::IntersectAll => counts.get_mut(&row).is_some_and(|c| {
+                        let visible = *c > 0;
+                        *c = c.saturating_sub(1);
+                        visible
+                    }),
+                    // output once if the row is not in right
+                    // then insert it to the table to skip its duplicates
+                    Expr::Except => match counts.entry(row) {
+                        Entry::Vacant(e) => {
+                            e.insert(0);
+                            true
+                        }
+                        Entry::Occupied(_) => false,
+                    },
+                    // output max(m - n, 0) times
+                    Expr::ExceptAll => match counts.get_mut(&row) {
+                        Some(c) if *c > 0 => {
+                            *c -= 1;
+                            false
+                        }
+                        _ => true,
+                    },
+                    ref op => panic!("invalid set operation: {op}"),
+                };
+                visibility.push(visible);
+            }
+            let chunk = chunk.filter(&visibility);
+            if chunk.cardinality() > 0 {
+                yield chunk;
+            }
+            tokio::task::consume_budget().await;
+        }
+    }
+}
@@ -0,0 +1,167 @@
+// Copyright 2024 RisingLight Project Authors. Licensed under Apache-2.0.
+
+//! Records partitioned by hash and spilled to temporary files.
+//!
+//! Used by hash join and hash aggregation when their in-memory states exceed the memory limit.
+
+use std::fs::File;
+use std::hash::Hash;
+use std::io::{BufReader, BufWriter, Write};
+use std::marker::PhantomData;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use tempfile::TempDir;
+
+use super::Result;
+
+/// The number of partitions to split the input into at each level.
+pub const PARTITION_FANOUT: usize = 16;
+
+/// The maximum level of recursive partitioning.
+///
+/// Partitions beyond this level are processed in memory regardless of their size,
+/// e.g. when a single key has too many rows.
+pub const MAX_PARTITION_DEPTH: usize = 4;
+
+/// Returns the hash of a key at the given partition depth.
+///
+/// Each depth uses a different seed, so that a partition can be split again.
+pub fn hash_at_depth<K: Hash + ?Sized>(key: &K, depth: usize) -> u64 {
+    ahash::RandomState::with_seeds(depth as u64, 0x5eed, 0x9e37_79b9, 0x7f4a_7c15).hash_one(key)
+}
+
+/// Creates a temporary directory under `parent` for spilled partitions.
+///
+/// The directory is removed when the last reference is dropped.
+pub fn create_spill_dir(parent: &Path, prefix: &str) -> Result<Arc<TempDir>> {
+    let dir = tempfile::Builder::new().prefix(prefix).tempdir_in(parent)?;
+    Ok(Arc::new(dir))
+}
+
+/// A set of partition files being written.
+pub struct Partitions<R> {
+    dir: Arc<TempDir>,
+    depth: usize,
+    files: Vec<PartitionFile<R>>,
+    writers: Vec<BufWriter<File>>,
+}
+
+/// A spilled partition. The file is removed on drop.
+pub struct PartitionFile<R> {
+    dir: Arc<TempDir>,
+    path: PathBuf,
+    rows: u64,
+    size: usize,
+    _marker: PhantomData<fn() -> R>,
+}
+
+impl<R: Serialize + DeserializeOwned> Partitions<R> {
+    /// Creates empty partition files under `dir` for records hashed at `depth`.
+    pub fn new(dir: &Arc<TempDir>, depth: usize) -> Result<Self> {
+        let mut files = vec![];
+        let mut writers = vec![];
+        for _ in 0..PARTITION_FANOUT {
+            let file = tempfile::Builder::new()
+                .prefix(&format!("part-{depth}-"))
+                .tempfile_in(dir.path())?;
+            let (file, path) = file.keep().map_err(|e| e.error)?;
+            writers.push(BufWriter::new(file));
+            files.push(PartitionFile {
+                dir: dir.clone(),
+                path,
+                rows: 0,
+                size: 0,
+                _marker: PhantomData,
+            });
+        }
+        Ok(Partitions {
+            dir: dir.clone(),
+            depth,
+            files,
+            writers,
+        })
+    }
+
+    /// Returns the directory of partition files.
+    pub fn dir(&self) -> &Arc<TempDir> {
+        &self.dir
+    }
+
+    /// Returns the depth of hash used to partition records.
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    /// Writes a record to the partition of its hash.
+    pub fn write(&mut self, hash: u64, record: &R) -> Result<()> {
+        let i = hash as usize % PARTITION_FANOUT;
+        bincode::serialize_into(&mut self.writers[i], record)?;
+        self.files[i].rows += 1;
+        Ok(())
+    }
+
+    /// Writes records with their hash in a blocking thread.
+    pub async fn write_all(mut self, records: Vec<(u64, R)>) -> Result<Self>
+    where
+        R: Send + 'static,
+    {
+        tokio::task::spawn_blocking(move || {
+            for (hash, record) in &records {
+                self.write(*hash, record)?;
+            }
+            Ok(self)
+        })
+        .await
+        .unwrap()
+    }
+
+    /// Flushes all partitions and returns the files.
+    pub fn finish(mut self) -> Result<Vec<PartitionFile<R>>> {
+        for (writer, file) in self.writers.iter_mut().zip(&mut self.files) {
+            writer.flush()?;
+            file.size = writer.get_ref().metadata()?.len() as usize;
+        }
+        Ok(self.files)
+    }
+}
+
+impl<R: Serialize + DeserializeOwned> PartitionFile<R> {
+    /// Returns the number of records.
+    pub fn rows(&self) -> u64 {
+        self.rows
+    }
+
+    /// Returns the size of the file in bytes.
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Returns an iterator of records in the file.
+    pub fn read(&self) -> Result<impl Iterator<Item = Result<R>> + use<R>> {
+        let mut reader = BufReader::new(File::open(&self.path)?);
+        Ok((0..self.rows).map(move |_| Ok(bincode::deserialize_from(&mut reader)?)))
+    }
+
+    /// Splits the partition into smaller ones by the hash at the given depth.
+    pub fn repartition(
+        self,
+        depth: usize,
+        hash: impl Fn(&R, usize) -> u64,
+    ) -> Result<Vec<PartitionFile<R>>> {
+        let mut partitions = Partitions::new(&self.dir, depth)?;
+        for record in self.read()? {
+            let record = record?;
+            partitions.write(hash(&record, depth), &record)?;
+        }
+        partitions.finish()
+    }
+}
+
+impl<R> Drop for PartitionFile<R> {
+    fn drop(&mut self) {
+        _ = std::fs::remove_file(&self.path);
+    }
+}
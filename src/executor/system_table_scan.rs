@@ -30,6 +30,7 @@ impl<S: Storage> SystemTableScan<S> {
             "pg_indexes" => pg_indexes(self.catalog),
             "pg_attribute" => pg_attribute(self.catalog),
             "pg_stat" => pg_stat(self.catalog, &*self.storage).await?,
+            "pg_user" => pg_user(self.catalog),
             name => panic!("unknown system table: {:?}", name),
         };
     }
@@ -175,6 +176,15 @@ fn pg_tables(catalog: RootCatalogRef) -> DataChunk {
     .collect()
 }
 
+/// Returns `pg_user` table.
+fn pg_user(catalog: RootCatalogRef) -> DataChunk {
+    let mut user_name = StringArrayBuilder::new();
+    for user in catalog.all_users() {
+        user_name.push(Some(user.name()));
+    }
+    [ArrayBuilderImpl::from(user_name)].into_iter().collect()
+}
+
 /// Returns `pg_attribute` table.
 fn pg_attribute(catalog: RootCatalogRef) -> DataChunk {
     // let mut schema_id = I32ArrayBuilder::new();
@@ -5,9 +5,7 @@ use std::sync::Arc;
 use super::*;
 use crate::array::DataChunk;
 use crate::catalog::{ColumnRefId, TableRefId};
-use crate::storage::{
-    KeyRange, ScanOptions, Storage, StorageColumnRef, Table, Transaction, TxnIterator,
-};
+use crate::storage::{KeyRange, ScanOptions, Storage, StorageColumnRef, TxnIterator};
 
 /// The executor of table scan operation.
 pub struct TableScanExecutor<S: Storage> {
@@ -15,6 +13,20 @@ pub struct TableScanExecutor<S: Storage> {
     pub columns: Vec<ColumnRefId>,
     pub filter: Option<KeyRange>,
     pub storage: Arc<S>,
+    /// The transaction block the statement runs in, if any.
+    pub block: Option<Arc<TransactionBlock<S>>>,
+    /// If set, only one partition of the table is scanned.
+    pub partition: Option<ScanPartition<S>>,
+}
+
+/// A partition of a parallel table scan.
+///
+/// All partitions of a scan read from the same transaction, so that they see the same snapshot.
+pub struct ScanPartition<S: Storage> {
+    pub index: usize,
+    pub count: usize,
+    /// The transaction shared by all partitions. Started by the first partition.
+    pub txn: Arc<tokio::sync::OnceCell<StatementTxn<S>>>,
 }
 
 impl<S: Storage> TableScanExecutor<S> {
@@ -36,14 +48,22 @@ impl<S: Storage> TableScanExecutor<S> {
             col_idx.push(StorageColumnRef::RowHandler);
         }
 
-        let txn = table.read().await?;
+        let mut options = ScanOptions::default().with_filter_opt(self.filter);
+        let owned_txn;
+        let txn = match &self.partition {
+            Some(partition) => {
+                options = options.with_partition(partition.index, partition.count);
+                (partition.txn)
+                    .get_or_try_init(|| StatementTxn::read(&table, &self.block))
+                    .await?
+            }
+            None => {
+                owned_txn = StatementTxn::read(&table, &self.block).await?;
+                &owned_txn
+            }
+        };
 
-        let mut it = txn
-            .scan(
-                &col_idx,
-                ScanOptions::default().with_filter_opt(self.filter),
-            )
-            .await?;
+        let mut it = txn.scan(&col_idx, options).await?;
 
         while let Some(mut x) = it.next_batch(None).await? {
             if self.columns.is_empty() {
@@ -0,0 +1,214 @@
+// Copyright 2024 RisingLight Project Authors. Licensed under Apache-2.0.
+
+//! Transactions of statements.
+//!
+//! Outside a transaction block, each statement starts a storage transaction on every table it
+//! accesses, and commits it when the statement finishes. Inside a block started by `BEGIN`,
+//! statements share the storage transactions of the block, so that they see the writes of each
+//! other. The transactions are committed by `COMMIT` or aborted by `ROLLBACK`.
+
+use std::collections::HashMap;
+use std::collections::hash_map::Entry;
+use std::sync::Arc;
+
+use tokio::sync::{MappedMutexGuard, Mutex, MutexGuard};
+
+use super::*;
+use crate::storage::{ScanOptions, StorageColumnRef, Table, Transaction};
+
+/// The storage transactions of a transaction block.
+///
+/// The transaction of a table is started on its first access in the block, and reads the snapshot
+/// taken at that time until the block ends. Committing fails with a conflict error if another
+/// transaction deleted the same rows after the snapshot was taken.
+///
+/// The transactions of all tables are committed atomically.
+pub struct TransactionBlock<S: Storage> {
+    txns: Mutex<HashMap<TableRefId, S::Transaction>>,
+}
+
+impl<S: Storage> Default for TransactionBlock<S> {
+    fn default() -> Self {
+        TransactionBlock {
+            txns: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+impl<S: Storage> TransactionBlock<S> {
+    /// Returns the transaction of a table. Starts one on the first access.
+    ///
+    /// The transaction is read-only until a statement that writes the table accesses it.
+    async fn get(
+        &self,
+        table: &S::Table,
+        writable: bool,
+    ) -> Result<MappedMutexGuard<'_, S::Transaction>> {
+        let mut txns = self.txns.lock().await;
+        match txns.entry(table.table_id()) {
+            Entry::Vacant(entry) => {
+                // any statement in the block that writes may also delete rows
+                let txn = if writable {
+                    table.update().await?
+                } else {
+                    table.read().await?
+                };
+                entry.insert(txn);
+            }
+            Entry::Occupied(mut entry) if writable => entry.get_mut().set_writable(),
+            Entry::Occupied(_) => {}
+        }
+        Ok(MutexGuard::map(txns, |txns| {
+            txns.get_mut(&table.table_id()).unwrap()
+        }))
+    }
+
+    /// Commits the transactions of all tables atomically.
+    pub async fn commit(&self) -> Result<()> {
+        let txns = std::mem::take(&mut *self.txns.lock().await);
+        S::Transaction::commit_all(txns.into_values().collect()).await?;
+        Ok(())
+    }
+
+    /// Aborts the transactions of all tables.
+    pub async fn abort(&self) -> Result<()> {
+        let txns = std::mem::take(&mut *self.txns.lock().await);
+        for txn in txns.into_values() {
+            txn.abort().await?;
+        }
+        Ok(())
+    }
+}
+
+/// The transaction of a statement on a table.
+pub enum StatementTxn<S: Storage> {
+    /// A transaction started by the statement.
+    Own(S::Transaction),
+    /// The transaction of the table in a transaction block, and whether the statement writes.
+    Block(Arc<TransactionBlock<S>>, S::Table, bool),
+}
+
+impl<S: Storage> StatementTxn<S> {
+    /// Starts a read-only transaction, or uses the one in `block`.
+    pub async fn read(table: &S::Table, block: &Option<Arc<TransactionBlock<S>>>) -> Result<Self> {
+        Ok(match block {
+            Some(block) => Self::Block(block.clone(), table.clone(), false),
+            None => Self::Own(table.read().await?),
+        })
+    }
+
+    /// Starts a transaction that appends rows, or uses the one in `block`.
+    pub async fn write(table: &S::Table, block: &Option<Arc<TransactionBlock<S>>>) -> Result<Self> {
+        Ok(match block {
+            Some(block) => Self::Block(block.clone(), table.clone(), true),
+            None => Self::Own(table.write().await?),
+        })
+    }
+
+    /// Starts a transaction that might delete or update rows, or uses the one in `block`.
+    pub async fn update(
+        table: &S::Table,
+        block: &Option<Arc<TransactionBlock<S>>>,
+    ) -> Result<Self> {
+        Ok(match block {
+            Some(block) => Self::Block(block.clone(), table.clone(), true),
+            None => Self::Own(table.update().await?),
+        })
+    }
+
+    pub async fn scan(
+        &self,
+        col_idx: &[StorageColumnRef],
+        options: ScanOptions,
+    ) -> Result<<S::Transaction as Transaction>::TxnIteratorType> {
+        Ok(match self {
+            Self::Own(txn) => txn.scan(col_idx, options).await?,
+            Self::Block(block, table, writable) => {
+                block
+                    .get(table, *writable)
+                    .await?
+                    .scan(col_idx, options)
+                    .await?
+            }
+        })
+    }
+
+    pub async fn fetch(
+        &self,
+        col_idx: &[StorageColumnRef],
+        ids: &[<S::Transaction as Transaction>::RowHandlerType],
+    ) -> Result<DataChunk> {
+        Ok(match self {
+            Self::Own(txn) => txn.fetch(col_idx, ids).await?,
+            Self::Block(block, table, writable) => {
+                block
+                    .get(table, *writable)
+                    .await?
+                    .fetch(col_idx, ids)
+                    .await?
+            }
+        })
+    }
+
+    pub async fn append(&mut self, columns: DataChunk) -> Result<()> {
+        match self {
+            Self::Own(txn) => txn.append(columns).await?,
+            Self::Block(block, table, writable) => {
+                block.get(table, *writable).await?.append(columns).await?
+            }
+        }
+        Ok(())
+    }
+
+    pub async fn delete(
+        &mut self,
+        ids: &[<S::Transaction as Transaction>::RowHandlerType],
+    ) -> Result<()> {
+        match self {
+            Self::Own(txn) => {
+                for id in ids {
+                    txn.delete(id).await?;
+                }
+            }
+            Self::Block(block, table, writable) => {
+                let mut txn = block.get(table, *writable).await?;
+                for id in ids {
+                    txn.delete(id).await?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub async fn update_rows(
+        &mut self,
+        ids: &[<S::Transaction as Transaction>::RowHandlerType],
+        columns: DataChunk,
+    ) -> Result<()> {
+        match self {
+            Self::Own(txn) => txn.update(ids, columns).await?,
+            Self::Block(block, table, writable) => {
+                block
+                    .get(table, *writable)
+                    .await?
+                    .update(ids, columns)
+                    .await?
+            }
+        }
+        Ok(())
+    }
+
+    /// Finishes the statement.
+    ///
+    /// A transaction started by the statement is committed. Otherwise, the writes are flushed to
+    /// the transaction of the block, so that later statements in the block see them.
+    pub async fn finish(self) -> Result<()> {
+        match self {
+            Self::Own(txn) => txn.commit().await?,
+            Self::Block(block, table, writable) => {
+                block.get(&table, writable).await?.flush().await?
+            }
+        }
+        Ok(())
+    }
+}
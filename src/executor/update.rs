@@ -0,0 +1,67 @@
+// Copyright 2024 RisingLight Project Authors. Licensed under Apache-2.0.
+
+use std::sync::Arc;
+
+use super::*;
+use crate::array::DataChunk;
+use crate::catalog::{ColumnId, TableRefId};
+use crate::storage::{RowHandler, Storage, Table, Transaction};
+use crate::types::ColumnIndex;
+
+/// The executor of `update` statement.
+///
+/// The input data chunk contains the new values of `column_ids`,
+/// followed by the `_row_id_` of the old row.
+pub struct UpdateExecutor<S: Storage> {
+    pub table_id: TableRefId,
+    pub column_ids: Vec<ColumnId>,
+    pub storage: Arc<S>,
+    /// The transaction block the statement runs in, if any.
+    pub block: Option<Arc<TransactionBlock<S>>>,
+}
+
+impl<S: Storage> UpdateExecutor<S> {
+    #[try_stream(boxed, ok = DataChunk, error = ExecutorError)]
+    pub async fn execute(self, child: BoxedExecutor) {
+        let table = self.storage.get_table(self.table_id)?;
+        let columns = table.columns()?;
+
+        // construct an expression to cast new values to the column types
+        let mut expr = RecExpr::default();
+        let mut list = Vec::with_capacity(columns.len());
+        for col in columns.iter() {
+            let Some(index) = self.column_ids.iter().position(|&id| id == col.id()) else {
+                Err(ExecutorError::invalid_argument(format!(
+                    "no new value for column \"{}\"",
+                    col.name()
+                )))?
+            };
+            let val = expr.add(Expr::ColumnIndex(ColumnIndex(index as _)));
+            let ty = expr.add(Expr::Type(col.data_type()));
+            list.push(expr.add(Expr::Cast([ty, val])));
+        }
+        expr.add(Expr::List(list.into()));
+
+        let mut txn = StatementTxn::update(&table, &self.block).await?;
+        let mut cnt = 0;
+        #[for_await]
+        for chunk in child {
+            let chunk = chunk?;
+            if chunk.cardinality() == 0 {
+                continue;
+            }
+            let row_handlers = chunk.array_at(chunk.column_count() - 1);
+            let ids = (0..row_handlers.len())
+                .map(|idx| {
+                    <S::Transaction as Transaction>::RowHandlerType::from_column(row_handlers, idx)
+                })
+                .collect_vec();
+            let new_rows = Evaluator::new(&expr).eval_list(&chunk)?;
+            cnt += chunk.cardinality();
+            txn.update_rows(&ids, new_rows).await?;
+        }
+        txn.finish().await?;
+
+        yield DataChunk::single(cnt as i32);
+    }
+}
@@ -0,0 +1,37 @@
+// Copyright 2025 RisingLight Project Authors. Licensed under Apache-2.0.
+
+use std::sync::Arc;
+
+use super::*;
+use crate::catalog::UserCatalog;
+use crate::storage::Storage;
+
+/// The executor of `create role` statement.
+pub struct CreateUserExecutor<S: Storage> {
+    pub user: UserCatalog,
+    pub storage: Arc<S>,
+}
+
+impl<S: Storage> CreateUserExecutor<S> {
+    #[try_stream(boxed, ok = DataChunk, error = ExecutorError)]
+    pub async fn execute(self) {
+        self.storage.create_user(self.user).await?;
+        yield DataChunk::single(1);
+    }
+}
+
+/// The executor of `drop role` statement.
+pub struct DropUserExecutor<S: Storage> {
+    pub names: Vec<String>,
+    pub storage: Arc<S>,
+}
+
+impl<S: Storage> DropUserExecutor<S> {
+    #[try_stream(boxed, ok = DataChunk, error = ExecutorError)]
+    pub async fn execute(self) {
+        for name in self.names {
+            self.storage.drop_user(&name).await?;
+        }
+        yield DataChunk::single(1);
+    }
+}
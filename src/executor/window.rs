@@ -1,34 +1,430 @@
 // Copyright 2024 RisingLight Project Authors. Licensed under Apache-2.0.
 
+use ahash::{HashMap, HashMapExt};
+
+use super::order::cmp_rows;
 use super::*;
 use crate::array::DataChunkBuilder;
+use crate::types::{ConvertError, DataValue, Row};
 
 /// The executor of window functions.
+///
+/// It buffers all rows from the child, and fails the query if they exceed the memory limit of
+/// the query. For each window function, the rows are divided into
+/// partitions and sorted by order keys, then the function is evaluated over each partition.
+/// The output rows are in the same order as the input rows.
 pub struct WindowExecutor {
     /// A list of over window functions.
     ///
-    /// e.g. `(list (over (lag #0) list list))`
+    /// e.g. `(list (over (lag #0 1 null) list list (range (preceding unbounded) current_row)))`
     pub exprs: RecExpr,
     /// The types of window function columns.
     pub types: Vec<DataType>,
+    pub memory: MemoryTracker,
 }
 
 impl WindowExecutor {
     #[try_stream(boxed, ok = DataChunk, error = ExecutorError)]
     pub async fn execute(self, child: BoxedExecutor) {
-        let mut states = Evaluator::new(&self.exprs).init_agg_states::<Vec<_>>();
-
+        let mut chunks = vec![];
+        let mut reservation = self.memory.reservation("window");
         #[for_await]
         for chunk in child {
             let chunk = chunk?;
+            if chunk.cardinality() > 0 {
+                reservation.grow(chunk.estimated_size())?;
+                chunks.push(chunk);
+            }
+        }
+
+        let overs = self.exprs[Id::from(self.exprs.as_ref().len() - 1)].as_list();
+        let mut columns: Vec<Vec<DataValue>> = overs
+            .iter()
+            .map(|id| self.eval_over(*id, &chunks))
+            .try_collect()?;
+
+        let mut row = 0;
+        for chunk in chunks {
             let mut builder = DataChunkBuilder::new(&self.types, chunk.cardinality() + 1);
-            for i in 0..chunk.cardinality() {
-                Evaluator::new(&self.exprs).agg_list_append(&mut states, chunk.row(i).values());
-                let results = Evaluator::new(&self.exprs).agg_list_get_result(&states);
-                _ = builder.push_row(results);
+            for _ in 0..chunk.cardinality() {
+                let values = columns
+                    .iter_mut()
+                    .map(|c| std::mem::replace(&mut c[row], DataValue::Null));
+                _ = builder.push_row(values);
+                row += 1;
             }
             let window_chunk = builder.take().unwrap();
             yield chunk.row_concat(window_chunk);
         }
     }
+
+    /// Evaluates an over node on all rows.
+    fn eval_over(&self, id: Id, chunks: &[DataChunk]) -> Result<Vec<DataValue>> {
+        let Expr::Over([f, partitionby, orderby, frame]) = self.exprs[id] else {
+            panic!("not over: {}", self.exprs[id]);
+        };
+        let partition_keys = self.eval_rows(partitionby, chunks)?;
+        let order_keys = self.eval_rows(orderby, chunks)?;
+        let orders = Evaluator::new(&self.subexpr(orderby)).orders();
+        let func = WindowFunction::new(self, f, chunks)?;
+        let frame = Frame::new(&self.exprs, frame);
+
+        // divide rows into partitions in the order of their first appearance
+        let mut partitions: Vec<Vec<usize>> = vec![];
+        let mut partition_index: HashMap<Row, usize> = HashMap::new();
+        for (i, key) in partition_keys.into_iter().enumerate() {
+            let p = *partition_index.entry(key).or_insert_with(|| {
+                partitions.push(vec![]);
+                partitions.len() - 1
+            });
+            partitions[p].push(i);
+        }
+
+        let mut results = vec![DataValue::Null; order_keys.len()];
+        for mut rows in partitions {
+            rows.sort_by(|&i, &j| cmp_rows(&order_keys[i], &order_keys[j], &orders));
+            let keys = rows.iter().map(|&i| &order_keys[i]).collect_vec();
+            let partition = Partition::new(&rows, &keys, &orders);
+            let values = func.eval(&partition, &frame)?;
+            for (&i, value) in rows.iter().zip(values) {
+                results[i] = value;
+            }
+        }
+        Ok(results)
+    }
+
+    /// Returns the expression rooted at `id`.
+    fn subexpr(&self, id: Id) -> RecExpr {
+        self.exprs[id].build_recexpr(|id| self.exprs[id].clone())
+    }
+
+    /// Evaluates a list of expressions on all rows.
+    fn eval_rows(&self, list: Id, chunks: &[DataChunk]) -> Result<Vec<Row>> {
+        let list = self.subexpr(list);
+        let mut rows = vec![];
+        for chunk in chunks {
+            let chunk = Evaluator::new(&list).eval_list(chunk)?;
+            rows.extend(chunk.rows().map(|row| row.to_owned()));
+        }
+        Ok(rows)
+    }
+
+    /// Evaluates an expression on all rows.
+    fn eval_column(&self, id: Id, chunks: &[DataChunk]) -> Result<Vec<DataValue>> {
+        let expr = self.subexpr(id);
+        let mut values = vec![];
+        for chunk in chunks {
+            values.extend(Evaluator::new(&expr).eval(chunk)?.iter());
+        }
+        Ok(values)
+    }
+}
+
+/// A partition of rows sorted by order keys.
+struct Partition<'a> {
+    /// The indexes of rows in the input.
+    rows: &'a [usize],
+    /// The order keys of rows.
+    keys: &'a [&'a Row],
+    /// Whether the order key is descending.
+    orders: &'a [bool],
+    /// The start and end positions of the peer group of each row.
+    /// Rows with the same order keys are peers.
+    peers: Vec<(usize, usize)>,
+}
+
+impl<'a> Partition<'a> {
+    fn new(rows: &'a [usize], keys: &'a [&'a Row], orders: &'a [bool]) -> Self {
+        let mut peers = Vec::with_capacity(rows.len());
+        let mut start = 0;
+        for end in 1..=rows.len() {
+            if end == rows.len() || keys[end] != keys[start] {
+                peers.extend(std::iter::repeat_n((start, end), end - start));
+                start = end;
+            }
+        }
+        Partition {
+            rows,
+            keys,
+            orders,
+            peers,
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.rows.len()
+    }
+
+    /// Returns the positions `[start, end)` of the frame of the row at `pos`.
+    fn frame(&self, frame: &Frame, pos: usize) -> (usize, usize) {
+        let start = match &frame.start {
+            Bound::Preceding(None) => 0,
+            Bound::CurrentRow if frame.rows => pos,
+            Bound::CurrentRow => self.peers[pos].0,
+            Bound::Preceding(Some(offset)) if frame.rows => pos.saturating_sub(offset.rows()),
+            Bound::Following(Some(offset)) if frame.rows => pos + offset.rows(),
+            Bound::Preceding(Some(offset)) => self.range_start(pos, offset, true),
+            Bound::Following(Some(offset)) => self.range_start(pos, offset, false),
+            Bound::Following(None) => unreachable!("frame start cannot be unbounded following"),
+        };
+        let end = match &frame.end {
+            Bound::Following(None) => self.len(),
+            Bound::CurrentRow if frame.rows => pos + 1,
+            Bound::CurrentRow => self.peers[pos].1,
+            Bound::Preceding(Some(offset)) if frame.rows => (pos + 1).saturating_sub(offset.rows()),
+            Bound::Following(Some(offset)) if frame.rows => pos + 1 + offset.rows(),
+            Bound::Preceding(Some(offset)) => self.range_end(pos, offset, true),
+            Bound::Following(Some(offset)) => self.range_end(pos, offset, false),
+            Bound::Preceding(None) => unreachable!("frame end cannot be unbounded preceding"),
+        };
+        let end = end.min(self.len());
+        (start.min(end), end)
+    }
+
+    /// Returns the bound value of the order key in `RANGE` frame.
+    /// Returns `None` if the order key of the row is null, where the bound is its peer group.
+    fn range_bound(&self, pos: usize, offset: &DataValue, preceding: bool) -> Option<DataValue> {
+        let key = &self.keys[pos][0];
+        if key.is_null() {
+            return None;
+        }
+        // preceding rows have smaller keys in ascending order
+        Some(match preceding != self.orders[0] {
+            true => key - offset,
+            false => key + offset,
+        })
+    }
+
+    /// Returns the position of the first row whose order key is not before the bound.
+    fn range_start(&self, pos: usize, offset: &DataValue, preceding: bool) -> usize {
+        let Some(bound) = self.range_bound(pos, offset, preceding) else {
+            return self.peers[pos].0;
+        };
+        let desc = self.orders[0];
+        self.keys.partition_point(|key| match desc {
+            false => key[0] < bound,
+            true => key[0] > bound,
+        })
+    }
+
+    /// Returns the position after the last row whose order key is not after the bound.
+    fn range_end(&self, pos: usize, offset: &DataValue, preceding: bool) -> usize {
+        let Some(bound) = self.range_bound(pos, offset, preceding) else {
+            return self.peers[pos].1;
+        };
+        let desc = self.orders[0];
+        self.keys.partition_point(|key| match desc {
+            false => key[0] <= bound,
+            true => key[0] >= bound,
+        })
+    }
+}
+
+/// A window frame.
+struct Frame {
+    /// `ROWS` or `RANGE`.
+    rows: bool,
+    start: Bound,
+    end: Bound,
+}
+
+/// A bound of window frame. The offset is `None` if it is unbounded.
+enum Bound {
+    Preceding(Option<DataValue>),
+    CurrentRow,
+    Following(Option<DataValue>),
+}
+
+impl Frame {
+    fn new(expr: &RecExpr, id: Id) -> Self {
+        let bound = |id: Id| {
+            let offset = |id: Id| match &expr[id] {
+                Expr::Unbounded => None,
+                Expr::Constant(v) => Some(v.clone()),
+                e => panic!("invalid frame offset: {e}"),
+            };
+            match expr[id] {
+                Expr::Preceding(a) => Bound::Preceding(offset(a)),
+                Expr::Following(a) => Bound::Following(offset(a)),
+                Expr::CurrentRow => Bound::CurrentRow,
+                ref e => panic!("invalid frame bound: {e}"),
+            }
+        };
+        match expr[id] {
+            Expr::Rows([start, end]) => Frame {
+                rows: true,
+                start: bound(start),
+                end: bound(end),
+            },
+            Expr::Range([start, end]) => Frame {
+                rows: false,
+                start: bound(start),
+                end: bound(end),
+            },
+            ref e => panic!("invalid frame: {e}"),
+        }
+    }
+}
+
+trait OffsetExt {
+    /// Returns the offset of `ROWS` frame.
+    fn rows(&self) -> usize;
+}
+
+impl OffsetExt for DataValue {
+    fn rows(&self) -> usize {
+        self.as_usize()
+            .expect("offset must be non-negative")
+            .expect("offset must not be null")
+    }
+}
+
+/// A window function with its arguments evaluated on all rows.
+enum WindowFunction {
+    RowNumber,
+    Rank,
+    DenseRank,
+    Ntile(usize),
+    /// `lag` has a negative offset and `lead` has a positive one.
+    Shift {
+        values: Vec<DataValue>,
+        offsets: Vec<DataValue>,
+        defaults: Vec<DataValue>,
+        lead: bool,
+    },
+    FirstValue(Vec<DataValue>),
+    LastValue(Vec<DataValue>),
+    /// An aggregate function over the frame.
+    Agg {
+        agg: RecExpr,
+        values: Vec<DataValue>,
+    },
+}
+
+impl WindowFunction {
+    fn new(executor: &WindowExecutor, id: Id, chunks: &[DataChunk]) -> Result<Self> {
+        let column = |id: Id| executor.eval_column(id, chunks);
+        Ok(match executor.exprs[id] {
+            Expr::RowNumber => Self::RowNumber,
+            Expr::Rank => Self::Rank,
+            Expr::DenseRank => Self::DenseRank,
+            Expr::Ntile(n) => {
+                let n = column(n)?.into_iter().next().unwrap_or(DataValue::Int32(1));
+                match n.as_usize() {
+                    Ok(Some(n)) if n > 0 => Self::Ntile(n),
+                    _ => {
+                        return Err(ExecutorError::invalid_argument(format!(
+                            "ntile requires a positive number of buckets, got {n}"
+                        )));
+                    }
+                }
+            }
+            Expr::Lag([a, offset, default]) | Expr::Lead([a, offset, default]) => Self::Shift {
+                values: column(a)?,
+                offsets: column(offset)?,
+                defaults: column(default)?,
+                lead: matches!(executor.exprs[id], Expr::Lead(_)),
+            },
+            Expr::FirstValue(a) => Self::FirstValue(column(a)?),
+            Expr::LastValue(a) => Self::LastValue(column(a)?),
+            Expr::RowCount => Self::Agg {
+                agg: executor.subexpr(id),
+                values: vec![DataValue::Null; chunks.iter().map(|c| c.cardinality()).sum()],
+            },
+            ref e if e.is_aggregate_function() => Self::Agg {
+                agg: executor.subexpr(id),
+                values: column(e.children()[0])?,
+            },
+            ref e => panic!("not window function: {e}"),
+        })
+    }
+
+    /// Evaluates the function on a partition.
+    fn eval(&self, partition: &Partition<'_>, frame: &Frame) -> Result<Vec<DataValue>> {
+        let n = partition.len();
+        let rows = partition.rows;
+        Ok(match self {
+            Self::RowNumber => (1..=n).map(|i| DataValue::Int32(i as _)).collect(),
+            Self::Rank => (partition.peers.iter())
+                .map(|&(start, _)| DataValue::Int32(start as i32 + 1))
+                .collect(),
+            Self::DenseRank => {
+                let mut rank = 0;
+                (0..n)
+                    .map(|pos| {
+                        if partition.peers[pos].0 == pos {
+                            rank += 1;
+                        }
+                        DataValue::Int32(rank)
+                    })
+                    .collect()
+            }
+            Self::Ntile(buckets) => {
+                // the first `n % buckets` buckets have one more row
+                let size = n / buckets;
+                let large = n % buckets;
+                (0..n)
+                    .map(|pos| {
+                        let bucket = match pos < large * (size + 1) {
+                            true => pos / (size + 1),
+                            false => large + (pos - large * (size + 1)) / size,
+                        };
+                        DataValue::Int32(bucket as i32 + 1)
+                    })
+                    .collect()
+            }
+            Self::Shift {
+                values,
+                offsets,
+                defaults,
+                lead,
+            } => (0..n)
+                .map(|pos| {
+                    let i = rows[pos];
+                    let Some(offset) = offsets[i].as_usize()? else {
+                        return Ok(DataValue::Null);
+                    };
+                    let target = match lead {
+                        true => pos.checked_add(offset).filter(|&p| p < n),
+                        false => pos.checked_sub(offset),
+                    };
+                    Ok(match target {
+                        Some(p) => values[rows[p]].clone(),
+                        None => defaults[i].clone(),
+                    })
+                })
+                .try_collect::<_, _, ConvertError>()?,
+            Self::FirstValue(values) | Self::LastValue(values) => (0..n)
+                .map(|pos| match partition.frame(frame, pos) {
+                    (start, end) if start == end => DataValue::Null,
+                    (start, _) if matches!(self, Self::FirstValue(_)) => {
+                        values[rows[start]].clone()
+                    }
+                    (_, end) => values[rows[end - 1]].clone(),
+                })
+                .collect(),
+            Self::Agg { agg, values } => {
+                let evaluator = Evaluator::new(agg);
+                let mut state = evaluator.init_agg_state();
+                // the frame of the state
+                let (mut state_start, mut state_end) = (0, 0);
+                let mut results = Vec::with_capacity(n);
+                for pos in 0..n {
+                    let (start, end) = partition.frame(frame, pos);
+                    // frames only move forward, so the state can be reused if it starts at the
+                    // same position. otherwise recompute the state.
+                    if start != state_start || end < state_end {
+                        state = evaluator.init_agg_state();
+                        (state_start, state_end) = (start, start);
+                    }
+                    for p in state_end..end {
+                        state = evaluator.agg_append(state, values[rows[p]].clone());
+                    }
+                    state_end = end;
+                    results.push(state.result());
+                }
+                results
+            }
+        })
+    }
 }
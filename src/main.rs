@@ -10,11 +10,12 @@ use std::time::{Duration, Instant};
 use anyhow::{Result, anyhow};
 use async_trait::async_trait;
 use clap::Parser;
+use futures::TryStreamExt;
 use humantime::format_duration;
 use itertools::Itertools;
 use risinglight::Database;
 use risinglight::array::{Chunk, datachunk_to_sqllogictest_string};
-use risinglight::server::run_server;
+use risinglight::server::{AuthMethod, ServerOptions, run_server};
 use risinglight::storage::SecondaryStorageOptions;
 use risinglight::utils::time::RoundingDuration;
 use rustyline::Editor;
@@ -65,6 +66,19 @@ struct Args {
     /// Ignored if `--server` is not specified.
     #[clap(long)]
     port: Option<u16>,
+    /// The method to authenticate clients: `trust`, `password`, `md5` or `scram-sha-256`.
+    /// Users are created by `CREATE ROLE name LOGIN PASSWORD '...'`.
+    /// Ignored if `--server` is not specified.
+    #[clap(long, default_value = "trust")]
+    auth: AuthMethod,
+    /// The PEM file of certificate chain to accept TLS connections.
+    /// Requires `--tls-key`.
+    #[clap(long, requires = "tls_key")]
+    tls_cert: Option<PathBuf>,
+    /// The PEM file of private key to accept TLS connections.
+    /// Requires `--tls-cert`.
+    #[clap(long, requires = "tls_cert")]
+    tls_key: Option<PathBuf>,
 }
 
 // human-readable message
@@ -85,6 +99,12 @@ fn print_chunk(chunk: &Chunk, output_format: &Option<String>) {
                         chunk.get_first_data_chunk().array_at(0).get_to_string(0)
                     )
                 }
+                "$update.row_counts" => {
+                    println!(
+                        "{} rows updated",
+                        chunk.get_first_data_chunk().array_at(0).get_to_string(0)
+                    )
+                }
                 "$create" => println!("created"),
                 "$drop" => println!("dropped"),
                 "$explain" => println!(
@@ -105,6 +125,40 @@ fn print_chunk(chunk: &Chunk, output_format: &Option<String>) {
     }
 }
 
+/// Run SQL queries and print the outputs as they arrive.
+///
+/// The outputs of statements before a failed one are printed before the error is returned.
+async fn run_and_print(
+    db: &Database,
+    sql: &str,
+    output_format: &Option<String>,
+) -> Result<(), risinglight::Error> {
+    for result in db.stream_statements(sql).await {
+        let (_, output) = result?;
+        let Some(mut output) = output else {
+            continue;
+        };
+        let header = output.header().map(|header| header.to_vec());
+        let mut empty = true;
+        while let Some(data_chunk) = output.try_next().await? {
+            let mut chunk = Chunk::new(vec![data_chunk]);
+            if let Some(header) = &header {
+                chunk.set_header(header.clone());
+            }
+            print_chunk(&chunk, output_format);
+            empty = false;
+        }
+        if empty {
+            let mut chunk = Chunk::new(vec![]);
+            if let Some(header) = header {
+                chunk.set_header(header);
+            }
+            print_chunk(&chunk, output_format);
+        }
+    }
+    Ok(())
+}
+
 fn print_execution_time(start_time: Instant) {
     let duration = start_time.elapsed();
     let duration_in_seconds = duration.div_duration_f64(Duration::new(1, 0));
@@ -127,14 +181,9 @@ async fn run_query_in_background(db: Arc<Database>, sql: String, output_format:
             // we simply drop the future `task` to cancel the query.
             println!("Interrupted");
         }
-        ret = db.run(&sql) => {
+        ret = run_and_print(&db, &sql, &output_format) => {
             match ret {
-                Ok(chunks) => {
-                    for chunk in chunks {
-                        print_chunk(&chunk, &output_format);
-                    }
-                    print_execution_time(start_time);
-                }
+                Ok(()) => print_execution_time(start_time),
                 Err(err) => println!("{}", err),
             }
         }
@@ -228,12 +277,7 @@ async fn run_sql(db: Database, path: &str, output_format: Option<String>) -> Res
 
     info!("{}", lines);
 
-    let chunks = db.run(&lines).await?;
-
-    for chunk in chunks {
-        print_chunk(&chunk, &output_format);
-    }
-
+    run_and_print(&db, &lines, &output_format).await?;
     Ok(())
 }
 
@@ -342,7 +386,13 @@ async fn main() -> Result<()> {
             run_sql(db, &file, args.output_format).await?;
         }
     } else if args.server {
-        run_server(args.host, args.port, db).await;
+        let options = ServerOptions {
+            host: args.host,
+            port: args.port,
+            auth: args.auth,
+            tls: args.tls_cert.zip(args.tls_key),
+        };
+        run_server(options, db).await?;
     } else {
         interactive(db, args.output_format).await?;
     }
@@ -5,11 +5,47 @@
 
 pub use sqlparser::ast::*;
 use sqlparser::dialect::PostgreSqlDialect;
+use sqlparser::keywords::Keyword;
 use sqlparser::parser::Parser;
 pub use sqlparser::parser::ParserError;
+use sqlparser::tokenizer::Token;
 
 /// Parse the SQL string into a list of ASTs.
+///
+/// In addition to the statements supported by [`sqlparser`], `RESET name` and `RESET ALL` are
+/// parsed as `SET name TO DEFAULT`.
 pub fn parse(sql: &str) -> Result<Vec<Statement>, ParserError> {
     let dialect = PostgreSqlDialect {};
-    Parser::parse_sql(&dialect, sql)
+    let mut parser = Parser::new(&dialect).try_with_sql(sql)?;
+    let mut stmts = Vec::new();
+    let mut expecting_statement_delimiter = false;
+    loop {
+        // ignore empty statements (between successive statement delimiters)
+        while parser.consume_token(&Token::SemiColon) {
+            expecting_statement_delimiter = false;
+        }
+        match parser.peek_token().token {
+            Token::EOF => break,
+            Token::Word(word) if expecting_statement_delimiter && word.keyword == Keyword::END => {
+                break;
+            }
+            _ => {}
+        }
+        if expecting_statement_delimiter {
+            return parser.expected("end of statement", parser.peek_token());
+        }
+        let stmt = if parser.parse_keyword(Keyword::RESET) {
+            Statement::SetVariable {
+                local: false,
+                hivevar: false,
+                variables: OneOrManyWithParens::One(parser.parse_object_name(false)?),
+                value: vec![Expr::Identifier(Ident::new("DEFAULT"))],
+            }
+        } else {
+            parser.parse_statement()?
+        };
+        stmts.push(stmt);
+        expecting_statement_delimiter = true;
+    }
+    Ok(stmts)
 }
@@ -31,7 +31,10 @@ impl egg::CostFunction<Expr> for CostFn<'_> {
 
         let c = match enode {
             // plan nodes
-            Scan(_) | Values(_) | IndexScan(_) => build(),
+            Scan(_) | Values(_) => build(),
+            IndexScan([_, _, filter, _, _]) | FileScan([_, _, filter]) => {
+                costs(filter) * rows(id) + build()
+            }
             Order([_, c]) => nlogn(rows(c)) + build() + costs(c),
             Filter([exprs, c]) => costs(exprs) * rows(c) + build() + costs(c),
             Proj([exprs, c]) | Window([exprs, c]) => costs(exprs) * rows(c) + costs(c),
@@ -40,6 +43,10 @@ impl egg::CostFunction<Expr> for CostFn<'_> {
                 (hash(rows(id)) + costs(keys) + costs(aggs)) * rows(c) + build() + costs(c)
             }
             SortAgg([keys, aggs, c]) => (costs(keys) + costs(aggs)) * rows(c) + build() + costs(c),
+            GroupingSets([keys, sets, aggs, c]) => {
+                let n = self.egraph[*sets].as_list().len() as f32;
+                (hash(rows(id)) + costs(keys) + costs(aggs)) * rows(c) * n + build() + costs(c)
+            }
             Limit([_, _, c]) => build() + costs(c),
             TopN([_, _, _, c]) => (rows(id) + 1.0).log2() * rows(c) + build() + costs(c),
             Join([_, cond, l, r]) => {
@@ -66,11 +73,20 @@ impl egg::CostFunction<Expr> for CostFn<'_> {
                     + costs(r)
             }
             Apply([_, l, r]) => build() + costs(l) + rows(l) * costs(r),
-            Insert([_, _, c]) | CopyTo([_, c]) => rows(c) * cols(c) + costs(c),
+            RecursiveUnion([_, _, base, recursive]) => build() + costs(base) + costs(recursive),
+            WorkingTable(_) => build(),
+            SetOp([t, l, r]) => {
+                let hash = match self.egraph[*t].nodes[0] {
+                    UnionAll => 0.0,
+                    _ => hash(rows(r)) * (rows(l) + rows(r)),
+                };
+                hash + build() + costs(l) + costs(r)
+            }
+            Insert([_, _, c]) | Update([_, _, c]) | CopyTo([_, c]) => rows(c) * cols(c) + costs(c),
             Empty(_) => 0.0,
             Max1Row(c) => costs(c),
             // expressions
-            Column(_) | Ref(_) => 0.01, // column reference is almost free
+            Column(_) | ExtColumn(_) | Ref(_) => 0.01, // column reference is almost free
             List(_) => enode.fold(0.01, |sum, id| sum + costs(&id)), // list is almost free
             // each operator has a cost of 0.1
             _ => enode.fold(0.1, |sum, id| sum + costs(&id)),
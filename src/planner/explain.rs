@@ -81,6 +81,16 @@ impl<'a> Explain<'a> {
         self.expr[*id] == Expr::true_()
     }
 
+    /// Returns the text of a window frame bound. e.g. `1 preceding`.
+    fn frame_bound(&self, id: &Id) -> String {
+        match &self.expr[*id] {
+            Expr::Preceding(a) => format!("{} preceding", self.expr[*a]),
+            Expr::Following(a) => format!("{} following", self.expr[*a]),
+            Expr::CurrentRow => "current row".into(),
+            e => e.to_string(),
+        }
+    }
+
     /// Transforms the plan to `Pretty`, an intermediate representation for pretty printing. It will
     /// be printed to string later.
     pub fn pretty(&self) -> Pretty<'a> {
@@ -124,6 +134,10 @@ impl<'a> Explain<'a> {
                 }
             }
             ColumnIndex(i) => Pretty::display(i),
+            ExtColumn(c) => match c.source {
+                0 => c.name.clone().into(),
+                source => format!("{}({source})", c.name).into(),
+            },
 
             // TODO: use object
             ExtSource(src) => format!("path={:?}, format={}", src.path, src.format).into(),
@@ -216,21 +230,40 @@ impl<'a> Explain<'a> {
             ),
 
             // aggregations
-            RowCount | RowNumber => enode.to_string().into(),
+            RowCount | RowNumber | Rank | DenseRank => enode.to_string().into(),
             Max(a) | Min(a) | Sum(a) | Avg(a) | Count(a) | First(a) | Last(a)
-            | CountDistinct(a) => {
+            | CountDistinct(a) | Ntile(a) | FirstValue(a) | LastValue(a) | Grouping(a) => {
                 let name = enode.to_string();
                 let v = vec![self.expr(a).pretty()];
                 Pretty::fieldless_record(name, v)
             }
-            Over([f, orderby, partitionby]) => Pretty::simple_record(
+            Lag([a, offset, default]) | Lead([a, offset, default]) => Pretty::childless_record(
+                enode.to_string(),
+                vec![
+                    ("expr", self.expr(a).pretty()),
+                    ("offset", self.expr(offset).pretty()),
+                    ("default", self.expr(default).pretty()),
+                ],
+            ),
+            Over([f, partitionby, orderby, frame]) => Pretty::simple_record(
                 "Over",
                 vec![
-                    ("order_by", self.expr(orderby).pretty()),
                     ("partition_by", self.expr(partitionby).pretty()),
+                    ("order_by", self.expr(orderby).pretty()),
+                    ("frame", self.expr(frame).pretty()),
                 ],
                 vec![self.expr(f).pretty()],
             ),
+            Rows([start, end]) | Range([start, end]) => format!(
+                "{} between {} and {}",
+                enode,
+                self.frame_bound(start),
+                self.frame_bound(end)
+            )
+            .into(),
+            Preceding(_) | Following(_) | Unbounded | CurrentRow => {
+                self.frame_bound(&self.id).into()
+            }
 
             Exists(a) => {
                 let v = vec![self.expr(a).pretty()];
@@ -255,14 +288,22 @@ impl<'a> Explain<'a> {
                     ("filter", self.expr(filter).pretty()),
                 ]),
             ),
-            IndexScan([table, columns, filter, key, vector]) => Pretty::childless_record(
+            IndexScan([table, columns, filter, distance, limit]) => Pretty::childless_record(
                 "IndexScan",
                 with_meta(vec![
                     ("table", self.expr(table).pretty()),
                     ("columns", self.expr(columns).pretty()),
                     ("filter", self.expr(filter).pretty()),
-                    ("key", self.expr(key).pretty()),
-                    ("vector", self.expr(vector).pretty()),
+                    ("distance", self.expr(distance).pretty()),
+                    ("limit", self.expr(limit).pretty()),
+                ]),
+            ),
+            FileScan([source, list, filter]) => Pretty::childless_record(
+                "FileScan",
+                with_meta(vec![
+                    ("source", self.expr(source).pretty()),
+                    ("list", self.expr(list).pretty()),
+                    ("filter", self.expr(filter).pretty()),
                 ]),
             ),
             Values(values) => Pretty::simple_record(
@@ -340,6 +381,26 @@ impl<'a> Explain<'a> {
                 vec![self.child(left).pretty(), self.child(right).pretty()],
             ),
             Inner | LeftOuter | RightOuter | FullOuter | Semi | Anti => Pretty::display(enode),
+            SetOp([op, left, right]) => Pretty::simple_record(
+                "SetOp",
+                with_meta(vec![("op", self.expr(op).pretty())]),
+                vec![self.child(left).pretty(), self.child(right).pretty()],
+            ),
+            RecursiveUnion([op, columns, base, recursive]) => Pretty::simple_record(
+                "RecursiveUnion",
+                with_meta(vec![
+                    ("op", self.expr(op).pretty()),
+                    ("columns", self.expr(columns).pretty()),
+                ]),
+                vec![self.child(base).pretty(), self.child(recursive).pretty()],
+            ),
+            WorkingTable(columns) => Pretty::childless_record(
+                "WorkingTable",
+                with_meta(vec![("columns", self.expr(columns).pretty())]),
+            ),
+            Union | UnionAll | Intersect | IntersectAll | Except | ExceptAll => {
+                Pretty::display(enode)
+            }
             Agg([aggs, child]) => Pretty::simple_record(
                 "Agg",
                 with_meta(vec![("aggs", self.expr(aggs).pretty())]),
@@ -357,6 +418,30 @@ impl<'a> Explain<'a> {
                 ]),
                 vec![self.child(child).pretty()],
             ),
+            GroupingSets([keys, sets, aggs, child]) => {
+                // show the keys in each set
+                let keys_list = self.expr[*keys].as_list();
+                let sets = (self.expr[*sets].as_list().iter())
+                    .map(|set| {
+                        let keys = (self.expr[*set].as_list().iter())
+                            .map(|i| {
+                                let i = self.expr[*i].as_const().as_usize().unwrap().unwrap();
+                                self.expr(&keys_list[i]).pretty()
+                            })
+                            .collect();
+                        Pretty::Array(keys)
+                    })
+                    .collect();
+                Pretty::simple_record(
+                    "GroupingSets",
+                    with_meta(vec![
+                        ("keys", self.expr(keys).pretty()),
+                        ("sets", Pretty::Array(sets)),
+                        ("aggs", self.expr(aggs).pretty()),
+                    ]),
+                    vec![self.child(child).pretty()],
+                )
+            }
             Window([windows, child]) => Pretty::simple_record(
                 "Window",
                 with_meta(vec![("windows", self.expr(windows).pretty())]),
@@ -383,6 +468,18 @@ impl<'a> Explain<'a> {
                 let fields = with_meta(vec![("objects", self.expr(tables).pretty())]);
                 Pretty::childless_record("Drop", fields)
             }
+            AlterTable(a) => {
+                let fields = with_meta(a.pretty_alter());
+                Pretty::childless_record("AlterTable", fields)
+            }
+            CreateUser(user) => {
+                let fields = with_meta(vec![("name", Pretty::display(user))]);
+                Pretty::childless_record("CreateUser", fields)
+            }
+            DropUser(names) => {
+                let fields = with_meta(vec![("names", self.expr(names).pretty())]);
+                Pretty::childless_record("DropUser", fields)
+            }
             Insert([table, cols, child]) => Pretty::simple_record(
                 "Insert",
                 with_meta(vec![
@@ -391,6 +488,14 @@ impl<'a> Explain<'a> {
                 ]),
                 vec![self.child(child).pretty()],
             ),
+            Update([table, cols, child]) => Pretty::simple_record(
+                "Update",
+                with_meta(vec![
+                    ("table", self.expr(table).pretty()),
+                    ("cols", self.expr(cols).pretty()),
+                ]),
+                vec![self.child(child).pretty()],
+            ),
             Delete([table, child]) => Pretty::simple_record(
                 "Delete",
                 with_meta(vec![("table", self.expr(table).pretty())]),
@@ -429,6 +534,18 @@ impl<'a> Explain<'a> {
                     ("value", self.expr(value).pretty()),
                 ]),
             ),
+            Reset(name) => Pretty::childless_record(
+                "Reset",
+                with_meta(vec![("name", self.expr(name).pretty())]),
+            ),
+            Show(name) => Pretty::childless_record(
+                "Show",
+                with_meta(vec![("name", self.expr(name).pretty())]),
+            ),
+            Transaction(command) => Pretty::childless_record(
+                "Transaction",
+                with_meta(vec![("command", self.expr(command).pretty())]),
+            ),
             Empty(_) => Pretty::childless_record("Empty", with_meta(vec![])),
             Max1Row(child) => Pretty::fieldless_record("Max1Row", vec![self.expr(child).pretty()]),
         }
@@ -2,9 +2,9 @@
 
 use egg::{Id, Symbol, define_language};
 
-use crate::binder::copy::ExtSource;
-use crate::binder::{CreateFunction, CreateIndex, CreateTable};
-use crate::catalog::{ColumnRefId, TableRefId};
+use crate::binder::copy::{ExtColumn, ExtSource};
+use crate::binder::{AlterTable, CreateFunction, CreateIndex, CreateTable};
+use crate::catalog::{ColumnRefId, TableRefId, UserCatalog};
 use crate::parser::{BinaryOperator, UnaryOperator};
 use crate::types::{ColumnIndex, DataType, DataValue, DateTimeField};
 
@@ -31,6 +31,7 @@ define_language! {
         Column(ColumnRefId),            // $1.2, $2.1, ...
         Table(TableRefId),              // $1, $2, ...
         ColumnIndex(ColumnIndex),       // #0, #1, ...
+        ExtColumn(Box<ExtColumn>),      // column of an external source
 
         // utilities
         "ref" = Ref(Id),                // (ref expr)
@@ -85,11 +86,24 @@ define_language! {
         "rowcount" = RowCount,
         "first" = First(Id),
         "last" = Last(Id),
+        "grouping" = Grouping(Id),              // (grouping [key..])
+                                                    // bitmask of keys not in the grouping set
         // window functions
-        "over" = Over([Id; 3]),                 // (over window_function [partition_key..] [order_key..])
-        // TODO: support frame clause
-            // "range" = Range([Id; 2]),               // (range start end)
+        "over" = Over([Id; 4]),                 // (over window_function [partition_key..] [order_key..] frame)
+            "rows" = Rows([Id; 2]),                 // (rows start end)
+            "range" = Range([Id; 2]),               // (range start end)
+                "preceding" = Preceding(Id),            // (preceding offset)
+                "following" = Following(Id),            // (following offset)
+                    "unbounded" = Unbounded,
+                "current_row" = CurrentRow,
         "row_number" = RowNumber,
+        "rank" = Rank,
+        "dense_rank" = DenseRank,
+        "ntile" = Ntile(Id),                    // (ntile buckets)
+        "lag" = Lag([Id; 3]),                   // (lag expr offset default)
+        "lead" = Lead([Id; 3]),                 // (lead expr offset default)
+        "first_value" = FirstValue(Id),
+        "last_value" = LastValue(Id),
 
         // subquery related
         "exists" = Exists(Id),                  // (exists plan)
@@ -99,7 +113,9 @@ define_language! {
 
         // plans
         "scan" = Scan([Id; 3]),                 // (scan table [column..] filter)
-        "index_scan" = IndexScan([Id; 5]), // (index_scan table [column..] filter key value)
+        "index_scan" = IndexScan([Id; 5]),      // (index_scan table [column..] filter distance limit)
+                                                // returns rows in ascending order of distance
+        "file_scan" = FileScan([Id; 3]),        // (file_scan source [column..] filter)
         "values" = Values(Box<[Id]>),           // (values [expr..]..)
         "proj" = Proj([Id; 2]),                 // (proj [expr..] child)
         "filter" = Filter([Id; 2]),             // (filter expr child)
@@ -123,15 +139,39 @@ define_language! {
                                                     // output = keys || aggs
         "sortagg" = SortAgg([Id; 3]),           // (sortagg keys=[expr..] aggs=[expr..] child)
                                                     // child must be ordered by keys
+        "groupingsets" = GroupingSets([Id; 4]), // (groupingsets keys=[expr..] sets=[[index..]..] aggs=[expr..] child)
+                                                    // output = keys || aggs
+                                                    // aggregate on each set of keys, keys not
+                                                    // in the set are null
         "window" = Window([Id; 2]),             // (window [over..] child)
                                                     // output = child || exprs
+        "setop" = SetOp([Id; 3]),               // (setop op left right)
+                                                    // output = left, types of both sides are equal
+            "union" = Union,
+            "union_all" = UnionAll,
+            "intersect" = Intersect,
+            "intersect_all" = IntersectAll,
+            "except" = Except,
+            "except_all" = ExceptAll,
+        "recursive_union" = RecursiveUnion([Id; 4]),    // (recursive_union op [column..] base recursive)
+                                                    // op = union | union_all
+                                                    // output = [column..]
+                                                    // iterate `recursive` until no new rows
+        "working_table" = WorkingTable(Id),     // (working_table [column..])
+                                                    // rows produced by the last iteration of
+                                                    // `recursive_union` with the same columns
         CreateTable(Box<CreateTable>),
         CreateIndex(Box<CreateIndex>),
         "create_view" = CreateView([Id; 2]),    // (create_view create_table child)
         CreateFunction(CreateFunction),
         "drop" = Drop(Id),                      // (drop [table..])
+        AlterTable(Box<AlterTable>),
+        CreateUser(Box<UserCatalog>),
+        "drop_user" = DropUser(Id),             // (drop_user [name..])
         "insert" = Insert([Id; 3]),             // (insert table [column..] child)
         "delete" = Delete([Id; 2]),             // (delete table child)
+        "update" = Update([Id; 3]),             // (update table [column..] child)
+                                                    // child = new values of [column..] || rowid
         "copy_from" = CopyFrom([Id; 2]),        // (copy_from dest types)
         "copy_to" = CopyTo([Id; 2]),            // (copy_to dest child)
             ExtSource(Box<ExtSource>),
@@ -139,6 +179,10 @@ define_language! {
         "analyze" = Analyze(Id),                // (analyze child)
         "pragma" = Pragma([Id; 2]),             // (pragma name value)
         "set" = Set([Id; 2]),                   // (set name value)
+        "reset" = Reset(Id),                    // (reset name)
+        "show" = Show(Id),                      // (show name)
+        "transaction" = Transaction(Id),        // (transaction command)
+                                                    // command = begin | commit | rollback
 
         // internal functions
         "empty" = Empty(Id),                    // (empty child)
@@ -206,6 +250,13 @@ impl Expr {
         v.clone()
     }
 
+    pub fn as_ext_column(&self) -> &ExtColumn {
+        let Self::ExtColumn(v) = self else {
+            panic!("not an external column: {self}")
+        };
+        v
+    }
+
     pub fn as_ext_source(&self) -> ExtSource {
         let Self::ExtSource(v) = self else {
             panic!("not an external source: {self}")
@@ -264,7 +315,17 @@ impl Expr {
 
     pub const fn is_window_function(&self) -> bool {
         use Expr::*;
-        matches!(self, RowNumber) || self.is_aggregate_function()
+        matches!(
+            self,
+            RowNumber
+                | Rank
+                | DenseRank
+                | Ntile(_)
+                | Lag(_)
+                | Lead(_)
+                | FirstValue(_)
+                | LastValue(_)
+        ) || self.is_aggregate_function()
     }
 }
 
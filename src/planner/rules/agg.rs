@@ -12,6 +12,8 @@ pub fn analyze_aggs(enode: &Expr, x: impl Fn(&Id) -> AggSet) -> AggSet {
     use Expr::*;
     match enode {
         _ if enode.is_aggregate_function() => vec![enode.clone()],
+        // `grouping` is evaluated by aggregation like aggregate functions
+        Grouping(_) => vec![enode.clone()],
         Over(_) | Ref(_) | Max1Row(_) => vec![],
         In([a, _]) => x(a),
         // merge the set from all children
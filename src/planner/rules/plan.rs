@@ -2,11 +2,13 @@
 
 //! Plan optimization rules.
 
+use std::collections::HashMap;
+
 use itertools::Itertools;
 
 use super::schema::schema_is_eq;
 use super::*;
-use crate::binder::{IndexType, VectorDistance};
+use crate::binder::VectorDistance;
 use crate::planner::ExprExt;
 use crate::types::DataValue;
 
@@ -80,27 +82,39 @@ pub fn predicate_pushdown_rules() -> Vec<Rewrite> { vec![
         "(join ?type (and ?cond1 ?cond2) ?left ?right)" =>
         "(join ?type ?cond2 (filter ?cond1 ?left) ?right)"
         if not_depend_on("?cond1", "?right")
+        if is_join_type("?type", &["inner", "semi", "right_outer"])
     ),
     rw!("pushdown-join-condition-left-1";
         "(join ?type ?cond1 ?left ?right)" =>
         "(join ?type true (filter ?cond1 ?left) ?right)"
         if not_depend_on("?cond1", "?right")
+        if is_join_type("?type", &["inner", "semi", "right_outer"])
     ),
     rw!("pushdown-join-condition-right";
         "(join ?type (and ?cond1 ?cond2) ?left ?right)" =>
         "(join ?type ?cond2 ?left (filter ?cond1 ?right))"
         if not_depend_on("?cond1", "?left")
+        if is_join_type("?type", &["inner", "semi", "left_outer", "anti"])
     ),
     rw!("pushdown-join-condition-right-1";
         "(join ?type ?cond1 ?left ?right)" =>
         "(join ?type true ?left (filter ?cond1 ?right))"
         if not_depend_on("?cond1", "?left")
+        if is_join_type("?type", &["inner", "semi", "left_outer", "anti"])
+    ),
+    rw!("pushdown-filter-file-scan";
+        "(filter ?cond (file_scan ?source ?columns true))" =>
+        "(file_scan ?source ?columns ?cond)"
     ),
     rw!("pushdown-filter-apply-left";
         "(filter ?cond (apply ?type ?left ?right))" =>
         "(apply ?type (filter ?cond ?left) ?right)"
         if not_depend_on("?cond", "?right")
     ),
+    rw!("pushdown-filter-union-all";
+        "(filter ?cond (setop union_all ?left ?right))" =>
+        { apply_union_all("(setop union_all (filter ?cond ?left) (filter ?right_cond ?right))") }
+    ),
 ]}
 
 /// Returns a rule to pushdown plan `a` through `b`.
@@ -251,6 +265,12 @@ pub fn subquery_rules() -> Vec<Rewrite> { vec![
         "(apply inner ?left (proj ?keys ?right))" =>
         { extract_key("(proj ?new_keys (apply inner ?left ?right))") }
     ),
+    // columns of the right side are null for unmatched rows either way
+    rw!("pushdown-left-outer-apply-proj";
+        "(apply left_outer ?left (proj ?keys ?right))" =>
+        { extract_key("(proj ?new_keys (apply left_outer ?left ?right))") }
+        if is_column_list("?keys")
+    ),
     rw!("pushdown-semi-apply-proj";
         "(apply semi ?left (proj ?proj ?right))" =>
         "(apply semi ?left ?right)"
@@ -299,7 +319,7 @@ fn apply_column0(pattern_str: &str) -> impl Applier<Expr, ExprAnalysis> {
             if !egraph[id]
                 .nodes
                 .iter()
-                .any(|e| matches!(e, Expr::Column(_) | Expr::Ref(_)))
+                .any(|e| matches!(e, Expr::Column(_) | Expr::ExtColumn(_) | Expr::Ref(_)))
             {
                 id = egraph.add(Expr::Ref(id));
             }
@@ -386,6 +406,10 @@ pub fn projection_pushdown_rules() -> Vec<Rewrite> { vec![
         "(hashagg ?keys ?aggs ?child)" =>
         { apply_proj("(hashagg [?keys] [?aggs] ?child)") }
     ),
+    rw!("pushdown-proj-groupingsets";
+        "(groupingsets ?keys ?sets ?aggs ?child)" =>
+        { apply_proj("(groupingsets [?keys] ?sets [?aggs] ?child)") }
+    ),
     rw!("pushdown-proj-join";
         "(proj ?exprs (join ?type ?on ?left ?right))" =>
         { apply_proj("(proj [?exprs] (join ?type [?on] ?left ?right))") }
@@ -398,31 +422,66 @@ pub fn projection_pushdown_rules() -> Vec<Rewrite> { vec![
         "(proj ?exprs (scan ?table ?columns ?filter))" =>
         { column_prune("(proj ?exprs (scan ?table ?columns ?filter))") }
     ),
-]}
-
-/// Pushdown projections and prune unused columns.
-#[rustfmt::skip]
-pub fn index_scan_rules() -> Vec<Rewrite> { vec![
-    rw!("vector-index-scan-1";
-        "(order (list (<-> ?column ?vector)) (scan ?table ?columns ?filter))" =>
-        "(index_scan ?table ?columns ?filter ?column ?vector)"
-        if has_vector_index("?column", "<->", "?vector", "?filter")
-    ),
-    rw!("vector-index-scan-2";
-        "(order (list (<#> ?column ?vector)) (scan ?table ?columns ?filter))" =>
-        "(index_scan ?table ?columns ?filter ?column ?vector)"
-        if has_vector_index("?column", "<#>", "?vector", "?filter")
-    ),
-    rw!("vector-index-scan-3";
-        "(order (list (<=> ?column ?vector)) (scan ?table ?columns ?filter))" =>
-        "(index_scan ?table ?columns ?filter ?column ?vector)"
-        if has_vector_index("?column", "<=>", "?vector", "?filter")
+    rw!("pushdown-proj-file-scan";
+        "(proj ?exprs (file_scan ?source ?columns ?filter))" =>
+        { column_prune("(proj ?exprs (file_scan ?source ?columns ?filter))") }
+    ),
+    rw!("pushdown-proj-union-all";
+        "(proj ?exprs (setop union_all ?left ?right))" =>
+        { apply_union_all("(proj ?exprs (setop union_all
+            (proj ?left_exprs ?left)
+            (proj ?right_exprs ?right)
+        ))") }
     ),
 ]}
 
+/// Scan tables through vector indexes to get the nearest rows.
+///
+/// The distance in the order key should match a vector index on the column. The filter above the
+/// scan, if any, is applied to the rows fetched from the index. Only an order under a constant
+/// limit is rewritten, and the limit is pushed into the index scan, so that only the nearest
+/// `limit + offset` rows are fetched. A full order still needs a table scan, since rows whose
+/// vector is null are not in the index.
+pub fn index_scan_rules() -> Vec<Rewrite> {
+    let mut rules = vec![];
+    for op in ["<->", "<#>", "<=>"] {
+        let distance = format!("({op} ?column ?vector)");
+        let scan = "(scan ?table ?columns ?filter)";
+        let filter_scan = format!("(filter ?cond {scan})");
+        let index_scan =
+            |filter, limit| format!("(index_scan ?table ?columns {filter} {distance} {limit})");
+        for (child, filter, name) in [
+            (scan, "?filter", "scan"),
+            (filter_scan.as_str(), "?cond", "filter-scan"),
+        ] {
+            let limit_order = format!("(limit ?limit ?offset (order (list {distance}) {child}))");
+            let topn = format!("(topn ?limit ?offset (list {distance}) {child})");
+            let limit_index_scan = format!(
+                "(limit ?limit ?offset {})",
+                index_scan(filter, "(+ ?limit ?offset)")
+            );
+            rules.extend([
+                rw!(format!("vector-index-scan{op}limit-{name}");
+                    { pattern(&limit_order) } => { pattern(&limit_index_scan) }
+                    if is_not_null_constant("?limit")
+                    if has_vector_index("?column", op, "?vector", "?filter")
+                ),
+                rw!(format!("vector-index-scan{op}topn-{name}");
+                    { pattern(&topn) } => { pattern(&limit_index_scan) }
+                    if is_not_null_constant("?limit")
+                    if has_vector_index("?column", op, "?vector", "?filter")
+                ),
+            ]);
+        }
+    }
+    rules
+}
+
 /// Check if there is a vector index matching the statement. i.e.,
 /// `SELECT * FROM t ORDER BY v <-> constant_vector` will match the index
 /// on the table t with the vector column v and using the `<->` distance function.
+///
+/// The range filter of the scan must be null or true.
 fn has_vector_index(
     column: &str,
     op: &str,
@@ -432,45 +491,33 @@ fn has_vector_index(
     let column = var(column);
     let vector = var(vector);
     let filter = var(filter);
-    let op = op.to_string();
+    let op = op.parse::<VectorDistance>().unwrap();
     move |egraph, _, subst| {
         let filter = &egraph[subst[filter]].data;
         let vector = &egraph[subst[vector]].data;
-        let column = &egraph[subst[column]].data;
-        let Ok(vector_op) = op.parse::<VectorDistance>() else {
-            return false;
-        };
-        // Only support null filter or always true filter for now. Check if the filter is null or
-        // true.
         if !matches!(filter.constant, Some(DataValue::Bool(true)) | None) {
             return false;
         }
         if !matches!(vector.constant, Some(DataValue::Vector(_))) {
             return false;
         }
-        // Check if the order by statement is in the form of vector column <-> constant vector
-        if column.columns.len() != 1 {
-            return false;
-        }
-        let column = column.columns.iter().next().unwrap();
-        let Expr::Column(col) = column else {
+        let Some(col) = egraph[subst[column]].iter().find_map(|e| match e {
+            Expr::Column(col) => Some(*col),
+            _ => None,
+        }) else {
             return false;
         };
-        let catalog = &egraph.analysis.catalog;
-        let indexes = catalog.get_index_on_table(col.schema_id, col.table_id);
-        for index_id in indexes {
-            // Check if any index matches the exact op and the column
-            let index = catalog.get_index_by_id(col.schema_id, index_id).unwrap();
-            if index.column_idxs() != [col.column_id] {
-                continue;
-            }
-            if let IndexType::IvfFlat { distance, .. } = index.index_type()
-                && distance == vector_op
-            {
-                return true;
-            }
-        }
-        false
+        (egraph.analysis.catalog)
+            .get_vector_index(&col, &op)
+            .is_some()
+    }
+}
+
+/// Returns true if the node `var1` is a constant other than null.
+fn is_not_null_constant(var1: &str) -> impl Fn(&mut EGraph, Id, &Subst) -> bool {
+    let var1 = var(var1);
+    move |egraph, _, subst| {
+        (egraph[subst[var1]].data.constant.as_ref()).is_some_and(|v| !v.is_null())
     }
 }
 
@@ -512,12 +559,23 @@ fn produced(egraph: &EGraph, plan: Id) -> impl Iterator<Item = Expr> + '_ {
     (egraph[plan].data.schema.iter()).map(|id| {
         egraph[*id]
             .iter()
-            .find(|e| matches!(e, Expr::Column(_) | Expr::Ref(_)))
+            .find(|e| matches!(e, Expr::Column(_) | Expr::ExtColumn(_) | Expr::Ref(_)))
             .cloned()
             .unwrap_or(Expr::Ref(*id))
     })
 }
 
+/// Returns true if the join type `var1` is one of `types`.
+fn is_join_type(
+    var1: &str,
+    types: &'static [&'static str],
+) -> impl Fn(&mut EGraph, Id, &Subst) -> bool {
+    let var1 = var(var1);
+    move |egraph, _, subst| {
+        (egraph[subst[var1]].nodes.iter()).any(|e| types.contains(&e.to_string().as_str()))
+    }
+}
+
 /// Returns true if the node `var1` is not a list.
 fn is_not_list(var1: &str) -> impl Fn(&mut EGraph, Id, &Subst) -> bool {
     let var1 = var(var1);
@@ -529,10 +587,24 @@ fn is_not_list(var1: &str) -> impl Fn(&mut EGraph, Id, &Subst) -> bool {
     }
 }
 
+/// Returns true if the node `var1` is a list of columns.
+fn is_column_list(var1: &str) -> impl Fn(&mut EGraph, Id, &Subst) -> bool {
+    let var1 = var(var1);
+    move |egraph, _, subst| {
+        egraph[subst[var1]].nodes.iter().any(|e| match e {
+            Expr::List(list) => list.iter().all(|id| {
+                (egraph[*id].nodes.iter())
+                    .any(|e| matches!(e, Expr::Column(_) | Expr::ExtColumn(_) | Expr::Ref(_)))
+            }),
+            _ => false,
+        })
+    }
+}
+
 /// The data type of column analysis.
 ///
 /// It is the set of columns used in the expression or plan.
-/// The elements of the set are either `Column` or `Ref`.
+/// The elements of the set are either `Column`, `ExtColumn` or `Ref`.
 pub type ColumnSet = HashSet<Expr>;
 
 /// Returns all columns involved in the node.
@@ -540,7 +612,7 @@ pub fn analyze_columns(egraph: &EGraph, enode: &Expr) -> ColumnSet {
     use Expr::*;
     let columns = |i: &Id| &egraph[*i].data.columns;
     match enode {
-        Column(_) | Ref(_) => [enode.clone()].into_iter().collect(),
+        Column(_) | ExtColumn(_) | Ref(_) => [enode.clone()].into_iter().collect(),
         // others: merge from all children
         _ => (enode.children().iter())
             .flat_map(|id| columns(id).iter().cloned())
@@ -605,6 +677,80 @@ fn apply_proj(pattern_str: &str) -> impl Applier<Expr, ExprAnalysis> {
     }
 }
 
+/// Returns an applier that pushes a plan through `union_all` into both sides.
+///
+/// The output columns of `union_all` are those of `?left`. So expressions pushed into `?right`
+/// must have the columns of `?left` replaced by those of `?right` at the same positions:
+/// - `?right_cond` is `?cond` rewritten for `?right`.
+/// - `?left_exprs` are the columns of `?left` used by `?exprs`.
+/// - `?right_exprs` are the corresponding columns of `?right`.
+fn apply_union_all(pattern_str: &str) -> impl Applier<Expr, ExprAnalysis> {
+    struct UnionAllPushdown {
+        pattern: Pattern,
+        left: Var,
+        right: Var,
+    }
+    impl Applier<Expr, ExprAnalysis> for UnionAllPushdown {
+        fn apply_one(
+            &self,
+            egraph: &mut EGraph,
+            eclass: Id,
+            subst: &Subst,
+            searcher_ast: Option<&PatternAst<Expr>>,
+            rule_name: Symbol,
+        ) -> Vec<Id> {
+            let left = produced(egraph, subst[self.left]).collect_vec();
+            let right = produced(egraph, subst[self.right]).collect_vec();
+            let left = left.into_iter().map(|e| egraph.add(e)).collect_vec();
+            let right = right.into_iter().map(|e| egraph.add(e)).collect_vec();
+            let mut map = HashMap::new();
+            for (&l, &r) in left.iter().zip(&right) {
+                if map.insert(l, r).is_some_and(|r0| r0 != r) {
+                    // the same column of left maps to different columns of right
+                    return vec![];
+                }
+            }
+
+            let mut subst = subst.clone();
+            if let Some(cond) = subst.get(var("?cond")) {
+                let right_cond = substitute(egraph, *cond, &map);
+                subst.insert(var("?right_cond"), right_cond);
+            }
+            if let Some(exprs) = subst.get(var("?exprs")) {
+                let used = &egraph[*exprs].data.columns;
+                let (left_exprs, right_exprs): (Vec<_>, Vec<_>) = (left.iter().zip(&right))
+                    .filter(|(l, _)| egraph[**l].iter().any(|e| used.contains(e)))
+                    .unzip();
+                let left_exprs = egraph.add(Expr::List(left_exprs.into()));
+                let right_exprs = egraph.add(Expr::List(right_exprs.into()));
+                subst.insert(var("?left_exprs"), left_exprs);
+                subst.insert(var("?right_exprs"), right_exprs);
+            }
+            self.pattern
+                .apply_one(egraph, eclass, &subst, searcher_ast, rule_name)
+        }
+    }
+    UnionAllPushdown {
+        pattern: pattern(pattern_str),
+        left: var("?left"),
+        right: var("?right"),
+    }
+}
+
+/// Replaces the e-classes in `map` within the expression `id`.
+fn substitute(egraph: &mut EGraph, id: Id, map: &HashMap<Id, Id>) -> Id {
+    let id = egraph.find(id);
+    if let Some(new) = map.get(&id) {
+        return *new;
+    }
+    let node = egraph[id].nodes[0].clone();
+    if node.is_leaf() {
+        return id;
+    }
+    let node = node.map_children(|child| substitute(egraph, child, map));
+    egraph.add(node)
+}
+
 /// Returns an applier that:
 /// 1. collect all used columns from `?exprs` and `?filter`.
 /// 2. filter out unused columns from `?columns`.
@@ -764,6 +910,22 @@ mod tests {
         )"
     }
 
+    egg::test_fn! {
+        union_all_predicate_pushdown,
+        rules(),
+        // SELECT * FROM (SELECT a, b FROM t1 UNION ALL SELECT d, c FROM t2) WHERE a > 1
+        "
+        (filter (> $1.1 1)
+        (setop union_all
+            (scan $1 (list $1.1 $1.2) null)
+            (scan $2 (list $2.2 $2.1) null)
+        ))" => "
+        (setop union_all
+            (filter (> $1.1 1) (scan $1 (list $1.1 $1.2) null))
+            (filter (> $2.2 1) (scan $2 (list $2.2 $2.1) null))
+        )"
+    }
+
     egg::test_fn! {
         projection_pushdown,
         projection_pushdown_rules(),
@@ -32,12 +32,23 @@ pub fn analyze_rows(egraph: &EGraph, enode: &Expr) -> Rows {
                 .get_row_count(table_id)
                 .unwrap_or(DEFAULT_ROW_COUNT) as f32
         }
+        IndexScan([tid, _, filter, _, limit]) => {
+            let table_id = egraph[*tid].nodes[0].as_table();
+            let rows = (egraph.analysis.stat)
+                .get_row_count(table_id)
+                .unwrap_or(DEFAULT_ROW_COUNT) as f32;
+            (rows * x(filter)).min(get_limit_num(limit))
+        }
+        FileScan([_, _, filter]) => DEFAULT_ROW_COUNT as f32 * x(filter),
         Proj([_, c]) | Order([_, c]) | Window([_, c]) => x(c),
         Agg(_) => 1.0,
         HashAgg([keys, _, c]) | SortAgg([keys, _, c]) => {
             // TODO: consider distinct values of group keys
             10_f32.powi(list_len(keys) as i32).min(x(c))
         }
+        GroupingSets([_, sets, _, c]) => (egraph[*sets].as_list().iter())
+            .map(|set| 10_f32.powi(list_len(set) as i32).min(x(c)))
+            .sum(),
         Filter([cond, c]) => x(c) * x(cond),
         Limit([limit, _, c]) | TopN([limit, _, _, c]) => x(c).min(get_limit_num(limit)),
         Join([t, on, l, r]) => match egraph[*t].nodes[0] {
@@ -74,6 +85,13 @@ pub fn analyze_rows(egraph: &EGraph, enode: &Expr) -> Rows {
             Semi | Anti => x(l),
             _ => x(l) * x(r),
         },
+        SetOp([t, l, r]) => match egraph[*t].nodes[0] {
+            Intersect | IntersectAll => x(l).min(x(r)),
+            Except | ExceptAll => x(l),
+            _ => x(l) + x(r),
+        },
+        RecursiveUnion([_, _, base, recursive]) => x(base) + x(recursive),
+        WorkingTable(_) => DEFAULT_ROW_COUNT as f32,
         Empty(_) => 0.0,
         Max1Row(_) => 1.0,
 
@@ -32,11 +32,17 @@ pub fn analyze_schema(
         List(ids) => ids.to_vec(),
 
         // plans that change schema
-        Scan([_, columns, _]) => x(columns),
+        Scan([_, columns, _]) | IndexScan([_, columns, _, _, _]) | FileScan([_, columns, _]) => {
+            x(columns)
+        }
         Values(vs) => x(&vs[0]),
+        SetOp([_, l, _]) => x(l),
+        RecursiveUnion([_, columns, _, _]) | WorkingTable(columns) => x(columns),
         Proj([exprs, _]) | Agg([exprs, _]) => x(exprs),
         Window([exprs, child]) => concat(x(child), x(exprs)),
-        HashAgg([keys, aggs, _]) | SortAgg([keys, aggs, _]) => concat(x(keys), x(aggs)),
+        HashAgg([keys, aggs, _]) | SortAgg([keys, aggs, _]) | GroupingSets([keys, _, aggs, _]) => {
+            concat(x(keys), x(aggs))
+        }
 
         // not plan node
         _ => vec![],
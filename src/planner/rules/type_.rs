@@ -38,6 +38,7 @@ pub fn analyze_type(
             .get_column(col)
             .ok_or_else(|| TypeError::Unavailable(enode.to_string()))?
             .data_type()),
+        ExtColumn(col) => Ok(col.ty.clone()),
         Ref(a) => x(a),
         List(list) => Ok(DataType::Struct(list.iter().map(x).try_collect()?)),
 
@@ -141,9 +142,18 @@ pub fn analyze_type(
         Avg(a) => check(enode, x(a)?, |a| a.is_number()),
 
         // agg
-        RowCount | RowNumber | Count(_) | CountDistinct(_) => Ok(DataType::Int32),
+        RowCount | RowNumber | Count(_) | CountDistinct(_) | Grouping(_) => Ok(DataType::Int32),
         First(a) | Last(a) => x(a),
-        Over([f, _, _]) => x(f),
+        Over([f, _, _, _]) => x(f),
+
+        // window functions
+        Rank | DenseRank | Ntile(_) => Ok(DataType::Int32),
+        Lag([a, _, default]) | Lead([a, _, default]) => {
+            merge(enode, [x(a)?, x(default)?], |[a, default]| {
+                a.union(&default)
+            })
+        }
+        FirstValue(a) | LastValue(a) => x(a),
 
         // scalar functions
         Replace([a, from, to]) => merge(enode, [x(a)?, x(from)?, x(to)?], |[a, from, to]| {
@@ -163,7 +173,9 @@ pub fn analyze_type(
         }
 
         // plans that change schema
-        Scan([_, columns, _]) => x(columns),
+        Scan([_, columns, _]) | IndexScan([_, columns, _, _, _]) | FileScan([_, columns, _]) => {
+            x(columns)
+        }
         Values(rows) => {
             if rows.is_empty() {
                 return Ok(DataType::Null);
@@ -178,9 +190,16 @@ pub fn analyze_type(
             }
             Ok(type_)
         }
+        RecursiveUnion([_, columns, _, _]) | WorkingTable(columns) => x(columns),
+        SetOp([_, l, r]) => {
+            let (l, r) = (x(l)?, x(r)?);
+            l.union(&r).ok_or(TypeError::NoCast { from: r, to: l })
+        }
         Proj([exprs, _]) | Agg([exprs, _]) => x(exprs),
         Window([exprs, c]) => concat_struct(x(c)?, x(exprs)?),
-        HashAgg([keys, aggs, _]) | SortAgg([keys, aggs, _]) => concat_struct(x(keys)?, x(aggs)?),
+        HashAgg([keys, aggs, _]) | SortAgg([keys, aggs, _]) | GroupingSets([keys, _, aggs, _]) => {
+            concat_struct(x(keys)?, x(aggs)?)
+        }
         Max1Row(c) => Ok(x(c)?.as_struct()[0].clone()),
 
         // other plan nodes
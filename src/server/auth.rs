@@ -0,0 +1,307 @@
+// Copyright 2025 RisingLight Project Authors. Licensed under Apache-2.0.
+
+//! Password authentication backed by the users in the catalog.
+
+use std::fmt::Debug;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use futures::{Sink, SinkExt};
+use parse_display::{Display, FromStr};
+use pgwire::api::auth::md5pass::{
+    MakeMd5PasswordAuthStartupHandler, Md5PasswordAuthStartupHandler,
+};
+use pgwire::api::auth::noop::NoopStartupHandler;
+use pgwire::api::auth::scram::{
+    MakeSASLScramAuthStartupHandler, SASLScramAuthStartupHandler, random_nonce,
+};
+use pgwire::api::auth::{
+    AuthSource, DefaultServerParameterProvider, LoginInfo, Password, StartupHandler,
+    finish_authentication, save_startup_parameters_to_metadata,
+};
+use pgwire::api::{ClientInfo, MakeHandler, PgWireConnectionState};
+use pgwire::error::{ErrorInfo, PgWireError, PgWireResult};
+use pgwire::messages::response::ErrorResponse;
+use pgwire::messages::startup::Authentication;
+use pgwire::messages::{PgWireBackendMessage, PgWireFrontendMessage};
+use rand::Rng;
+
+use crate::catalog::{RootCatalogRef, UserCatalog};
+
+/// The method to authenticate clients.
+#[derive(Debug, Display, FromStr, Default, Clone, Copy, PartialEq, Eq)]
+#[display(style = "kebab-case")]
+pub enum AuthMethod {
+    /// Allow anyone to connect without a password.
+    #[default]
+    Trust,
+    /// Require a password sent in cleartext.
+    Password,
+    /// Require a password hashed with MD5.
+    Md5,
+    /// Require a password verified with SCRAM-SHA-256.
+    #[display("scram-sha-256")]
+    ScramSha256,
+}
+
+type Md5Handler = Md5PasswordAuthStartupHandler<Md5AuthSource, DefaultServerParameterProvider>;
+type ScramHandler = SASLScramAuthStartupHandler<ScramAuthSource, DefaultServerParameterProvider>;
+
+/// Makes an [`Authenticator`] for each connection.
+pub enum MakeAuthenticator {
+    Trust,
+    Password(Arc<CleartextAuthenticator>),
+    Md5(MakeMd5PasswordAuthStartupHandler<Md5AuthSource, DefaultServerParameterProvider>),
+    ScramSha256 {
+        plain: MakeScramHandler,
+        /// Supports channel binding on TLS connections.
+        tls: Option<MakeScramHandler>,
+    },
+}
+
+type MakeScramHandler =
+    MakeSASLScramAuthStartupHandler<ScramAuthSource, DefaultServerParameterProvider>;
+
+impl MakeAuthenticator {
+    /// Creates authenticators of the given method.
+    ///
+    /// `certs_pem` is the certificate of server if TLS is enabled, which enables channel binding
+    /// of SCRAM-SHA-256.
+    pub fn new(
+        method: AuthMethod,
+        catalog: RootCatalogRef,
+        certs_pem: Option<&[u8]>,
+    ) -> PgWireResult<Self> {
+        let parameters = Arc::new(DefaultServerParameterProvider::default());
+        Ok(match method {
+            AuthMethod::Trust => Self::Trust,
+            AuthMethod::Password => Self::Password(Arc::new(CleartextAuthenticator {
+                catalog,
+                parameters,
+            })),
+            AuthMethod::Md5 => Self::Md5(MakeMd5PasswordAuthStartupHandler::new(
+                Arc::new(Md5AuthSource { catalog }),
+                parameters,
+            )),
+            AuthMethod::ScramSha256 => {
+                let auth_source = Arc::new(ScramAuthSource { catalog });
+                let make = || {
+                    let mut make = MakeSASLScramAuthStartupHandler::new(
+                        auth_source.clone(),
+                        parameters.clone(),
+                    );
+                    make.set_iterations(UserCatalog::SCRAM_ITERATIONS);
+                    make
+                };
+                // pgwire offers SCRAM-SHA-256-PLUS whenever a certificate is configured,
+                // which must not happen on plain connections
+                let tls = match certs_pem {
+                    Some(pem) => {
+                        let mut make = make();
+                        make.configure_certificate(pem)?;
+                        Some(make)
+                    }
+                    None => None,
+                };
+                Self::ScramSha256 { plain: make(), tls }
+            }
+        })
+    }
+}
+
+impl MakeHandler for MakeAuthenticator {
+    type Handler = Arc<Authenticator>;
+
+    fn make(&self) -> Self::Handler {
+        Arc::new(match self {
+            Self::Trust => Authenticator::Trust(NoopStartupHandler),
+            Self::Password(handler) => Authenticator::Password(handler.clone()),
+            Self::Md5(make) => Authenticator::Md5(make.make()),
+            Self::ScramSha256 { plain, tls } => Authenticator::ScramSha256 {
+                plain: plain.make(),
+                tls: tls.as_ref().map(|make| make.make()),
+            },
+        })
+    }
+}
+
+/// Handles the startup of a connection with the configured method.
+pub enum Authenticator {
+    Trust(NoopStartupHandler),
+    Password(Arc<CleartextAuthenticator>),
+    Md5(Arc<Md5Handler>),
+    ScramSha256 {
+        plain: Arc<ScramHandler>,
+        tls: Option<Arc<ScramHandler>>,
+    },
+}
+
+#[async_trait]
+impl StartupHandler for Authenticator {
+    async fn on_startup<C>(
+        &self,
+        client: &mut C,
+        message: PgWireFrontendMessage,
+    ) -> PgWireResult<()>
+    where
+        C: ClientInfo + Sink<PgWireBackendMessage> + Unpin + Send,
+        C::Error: Debug,
+        PgWireError: From<<C as Sink<PgWireBackendMessage>>::Error>,
+    {
+        match self {
+            Self::Trust(handler) => handler.on_startup(client, message).await,
+            Self::Password(handler) => handler.on_startup(client, message).await,
+            Self::Md5(handler) => handler.on_startup(client, message).await,
+            Self::ScramSha256 {
+                tls: Some(handler), ..
+            } if client.is_secure() => handler.on_startup(client, message).await,
+            Self::ScramSha256 { plain, .. } => plain.on_startup(client, message).await,
+        }
+    }
+}
+
+/// Authenticates clients with cleartext passwords.
+///
+/// Unlike the handler provided by pgwire, the password is checked against its hash in the catalog.
+pub struct CleartextAuthenticator {
+    catalog: RootCatalogRef,
+    parameters: Arc<DefaultServerParameterProvider>,
+}
+
+#[async_trait]
+impl StartupHandler for CleartextAuthenticator {
+    async fn on_startup<C>(
+        &self,
+        client: &mut C,
+        message: PgWireFrontendMessage,
+    ) -> PgWireResult<()>
+    where
+        C: ClientInfo + Sink<PgWireBackendMessage> + Unpin + Send,
+        C::Error: Debug,
+        PgWireError: From<<C as Sink<PgWireBackendMessage>>::Error>,
+    {
+        match message {
+            PgWireFrontendMessage::Startup(ref startup) => {
+                save_startup_parameters_to_metadata(client, startup);
+                client.set_state(PgWireConnectionState::AuthenticationInProgress);
+                client
+                    .send(PgWireBackendMessage::Authentication(
+                        Authentication::CleartextPassword,
+                    ))
+                    .await?;
+            }
+            PgWireFrontendMessage::PasswordMessageFamily(pwd) => {
+                let pwd = pwd.into_password()?;
+                let login = LoginInfo::from_client_info(client);
+                let user = get_user(&self.catalog, &login)?;
+                if user.check_password(&pwd.password) {
+                    finish_authentication(client, self.parameters.as_ref()).await;
+                } else {
+                    let error = ErrorInfo::new(
+                        "FATAL".into(),
+                        "28P01".into(),
+                        format!("password authentication failed for user {:?}", user.name()),
+                    );
+                    client
+                        .feed(PgWireBackendMessage::ErrorResponse(ErrorResponse::from(
+                            error,
+                        )))
+                        .await?;
+                    client.close().await?;
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+}
+
+/// Provides MD5 hashed passwords.
+pub struct Md5AuthSource {
+    catalog: RootCatalogRef,
+}
+
+#[async_trait]
+impl AuthSource for Md5AuthSource {
+    async fn get_password(&self, login: &LoginInfo) -> PgWireResult<Password> {
+        let user = get_user(&self.catalog, login)?;
+        let salt: [u8; 4] = rand::thread_rng().r#gen();
+        let hash = md5::compute([user.md5().as_bytes(), &salt].concat());
+        Ok(Password::new(
+            Some(salt.to_vec()),
+            format!("md5{hash:x}").into_bytes(),
+        ))
+    }
+}
+
+/// Provides salted passwords of SCRAM-SHA-256.
+pub struct ScramAuthSource {
+    catalog: RootCatalogRef,
+}
+
+#[async_trait]
+impl AuthSource for ScramAuthSource {
+    async fn get_password(&self, login: &LoginInfo) -> PgWireResult<Password> {
+        let user = get_user(&self.catalog, login)?;
+        Ok(Password::new(
+            Some(user.salt().to_vec()),
+            user.salted_password().to_vec(),
+        ))
+    }
+}
+
+/// Returns the user to log in.
+///
+/// An unknown user gets a random password, so that it fails in the same way as a wrong password.
+fn get_user(catalog: &RootCatalogRef, login: &LoginInfo) -> PgWireResult<UserCatalog> {
+    let name = login.user().ok_or(PgWireError::UserNameRequired)?;
+    Ok(catalog
+        .get_user(name)
+        .unwrap_or_else(|| UserCatalog::new(name, &random_nonce())))
+}
+
+#[cfg(test)]
+mod tests {
+    use pgwire::api::auth::md5pass::hash_md5_password;
+
+    use super::*;
+    use crate::catalog::RootCatalog;
+
+    #[tokio::test]
+    async fn auth_source() {
+        let catalog = Arc::new(RootCatalog::new());
+        catalog
+            .add_user(UserCatalog::new("alice", "secret"))
+            .unwrap();
+        let login = LoginInfo::new(Some("alice"), None, "localhost".into());
+
+        let md5 = Md5AuthSource {
+            catalog: catalog.clone(),
+        };
+        let password = md5.get_password(&login).await.unwrap();
+        let expected = hash_md5_password("alice", "secret", password.salt().unwrap());
+        assert_eq!(password.password(), expected.as_bytes());
+
+        let scram = ScramAuthSource { catalog };
+        let password = scram.get_password(&login).await.unwrap();
+        let salted = pgwire::api::auth::scram::gen_salted_password(
+            "secret",
+            password.salt().unwrap(),
+            UserCatalog::SCRAM_ITERATIONS,
+        );
+        assert_eq!(password.password(), salted);
+
+        let unknown = LoginInfo::new(Some("bob"), None, "localhost".into());
+        assert!(scram.get_password(&unknown).await.is_ok());
+    }
+
+    #[test]
+    fn auth_method() {
+        assert_eq!(
+            "scram-sha-256".parse::<AuthMethod>().unwrap(),
+            AuthMethod::ScramSha256
+        );
+        assert_eq!("md5".parse::<AuthMethod>().unwrap(), AuthMethod::Md5);
+        assert_eq!(AuthMethod::Password.to_string(), "password");
+    }
+}
@@ -1,45 +1,111 @@
 // Copyright 2024 RisingLight Project Authors. Licensed under Apache-2.0.
 
+mod auth;
+mod pg_type;
 mod processor;
 
+use std::path::PathBuf;
 use std::sync::Arc;
 
-use pgwire::api::auth::noop::NoopStartupHandler;
-use pgwire::api::query::PlaceholderExtendedQueryHandler;
-use pgwire::api::{MakeHandler, StatelessMakeHandler};
+use anyhow::{Context, Result};
+use pgwire::api::MakeHandler;
 use pgwire::tokio::process_socket;
 use tokio::net::TcpListener;
-use tracing::info;
+use tokio_rustls::TlsAcceptor;
+use tokio_rustls::rustls::ServerConfig;
+use tokio_rustls::rustls::pki_types::{CertificateDer, PrivateKeyDer};
+use tracing::{info, warn};
 
+pub use self::auth::AuthMethod;
+use self::auth::MakeAuthenticator;
 use crate::Database;
 use crate::server::processor::Processor;
 
-pub async fn run_server(host: Option<String>, port: Option<u16>, db: Database) {
-    let processor = Arc::new(Processor::new(db));
-    let authenticator = Arc::new(NoopStartupHandler);
+/// The options of the Postgres server.
+#[derive(Debug, Default)]
+pub struct ServerOptions {
+    /// The host to bind to. Defaults to localhost.
+    pub host: Option<String>,
+    /// The port to listen on. Defaults to 5432.
+    pub port: Option<u16>,
+    /// The method to authenticate clients.
+    pub auth: AuthMethod,
+    /// The PEM files of certificate chain and private key. TLS is enabled if specified.
+    pub tls: Option<(PathBuf, PathBuf)>,
+}
+
+pub async fn run_server(options: ServerOptions, db: Database) -> Result<()> {
+    if options.auth != AuthMethod::Trust && db.catalog().all_users().is_empty() {
+        warn!(
+            "{} authentication is enabled but no user exists, \
+             create users with `CREATE ROLE` in a trusted session first",
+            options.auth
+        );
+    }
+    let (tls_acceptor, certs_pem) = match &options.tls {
+        Some((cert, key)) => {
+            let certs_pem = std::fs::read(cert)
+                .with_context(|| format!("failed to read certificate {}", cert.display()))?;
+            let key_pem = std::fs::read(key)
+                .with_context(|| format!("failed to read private key {}", key.display()))?;
+            let acceptor = tls_acceptor(&certs_pem, &key_pem)?;
+            (Some(Arc::new(acceptor)), Some(certs_pem))
+        }
+        None => (None, None),
+    };
+    let authenticator =
+        MakeAuthenticator::new(options.auth, db.catalog().clone(), certs_pem.as_deref())?;
+    let db = Arc::new(db);
     let addr = format!(
         "{}:{}",
-        host.unwrap_or_else(|| "127.0.0.1".to_string()),
-        port.unwrap_or(5432)
+        options.host.unwrap_or_else(|| "127.0.0.1".to_string()),
+        options.port.unwrap_or(5432)
+    );
+    let listener = TcpListener::bind(&addr).await?;
+    info!(
+        "Listening on: {} (auth: {}, tls: {})",
+        addr,
+        options.auth,
+        tls_acceptor.is_some()
     );
-    let listener = TcpListener::bind(&addr).await.unwrap();
-    info!("Listening on: {}", addr);
     loop {
-        let incoming_socket = listener.accept().await.unwrap();
-        let authenticator_ref = authenticator.clone();
-        let processor_ref = processor.clone();
-        let placeholder = Arc::new(StatelessMakeHandler::new(Arc::new(
-            PlaceholderExtendedQueryHandler,
-        )));
+        let incoming_socket = listener.accept().await?;
+        let authenticator_ref = authenticator.make();
+        let tls_acceptor_ref = tls_acceptor.clone();
+        // each connection has its own transaction state
+        let processor_ref = Arc::new(Processor::new(db.clone()));
         tokio::spawn(async move {
             process_socket(
                 incoming_socket.0,
-                None,
+                tls_acceptor_ref,
                 authenticator_ref,
                 processor_ref.clone(),
-                placeholder.make(),
+                processor_ref,
             )
             .await
         });
     }
 }
+
+/// Creates a TLS acceptor from PEM-encoded certificate chain and private key.
+fn tls_acceptor(certs_pem: &[u8], key_pem: &[u8]) -> Result<TlsAcceptor> {
+    let certs = pem::parse_many(certs_pem)?
+        .into_iter()
+        .filter(|p| p.tag() == "CERTIFICATE")
+        .map(|p| CertificateDer::from(p.into_contents()))
+        .collect::<Vec<_>>();
+    if certs.is_empty() {
+        anyhow::bail!("no certificate found");
+    }
+    let key = pem::parse_many(key_pem)?
+        .into_iter()
+        .find(|p| p.tag().ends_with("PRIVATE KEY"))
+        .context("no private key found")?;
+    let key = PrivateKeyDer::try_from(key.contents())
+        .map_err(anyhow::Error::msg)?
+        .clone_key();
+    let config = ServerConfig::builder()
+        .with_no_client_auth()
+        .with_single_cert(certs, key)?;
+    Ok(TlsAcceptor::from(Arc::new(config)))
+}
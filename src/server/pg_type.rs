@@ -0,0 +1,165 @@
+// Copyright 2025 RisingLight Project Authors. Licensed under Apache-2.0.
+
+//! Conversion between RisingLight values and Postgres wire format.
+
+use chrono::{NaiveDate, NaiveDateTime};
+use pgwire::api::Type;
+use pgwire::api::portal::Portal;
+use pgwire::api::results::{DataRowEncoder, FieldFormat};
+use pgwire::error::{ErrorInfo, PgWireError, PgWireResult};
+
+use crate::array::ArrayImpl;
+use crate::types::{DataType, DataValue};
+
+/// Returns the Postgres type of a data type.
+pub fn pg_type(ty: &DataType) -> Type {
+    match ty {
+        DataType::Bool => Type::BOOL,
+        DataType::Int16 => Type::INT2,
+        DataType::Int32 => Type::INT4,
+        DataType::Int64 => Type::INT8,
+        DataType::Float64 => Type::FLOAT8,
+        DataType::Decimal(_, _) => Type::NUMERIC,
+        DataType::Date => Type::DATE,
+        DataType::Timestamp => Type::TIMESTAMP,
+        DataType::TimestampTz => Type::TIMESTAMPTZ,
+        DataType::Interval => Type::INTERVAL,
+        DataType::Blob => Type::BYTEA,
+        DataType::Null | DataType::String | DataType::Struct(_) | DataType::Vector(_) => Type::TEXT,
+    }
+}
+
+/// Returns the data type of a Postgres type specified by the client.
+///
+/// Returns `None` if the type is unspecified or unsupported, which means it should be inferred.
+pub fn data_type(ty: &Type) -> Option<DataType> {
+    Some(match ty {
+        &Type::BOOL => DataType::Bool,
+        &Type::INT2 => DataType::Int16,
+        &Type::INT4 => DataType::Int32,
+        &Type::INT8 => DataType::Int64,
+        &Type::FLOAT8 => DataType::Float64,
+        &Type::NUMERIC => DataType::Decimal(None, None),
+        &Type::DATE => DataType::Date,
+        &Type::TIMESTAMP => DataType::Timestamp,
+        &Type::TIMESTAMPTZ => DataType::TimestampTz,
+        &Type::INTERVAL => DataType::Interval,
+        &Type::BYTEA => DataType::Blob,
+        &Type::TEXT | &Type::VARCHAR | &Type::BPCHAR => DataType::String,
+        _ => return None,
+    })
+}
+
+/// Decodes the `idx`-th parameter of a portal as a value of type `ty`.
+pub fn decode_param<S: Clone>(
+    portal: &Portal<S>,
+    idx: usize,
+    ty: &DataType,
+) -> PgWireResult<DataValue> {
+    let Some(Some(bytes)) = portal.parameters.get(idx) else {
+        return Ok(DataValue::Null);
+    };
+    if portal.parameter_format.is_text(idx) {
+        let s = std::str::from_utf8(bytes).map_err(|e| user_error(e.to_string()))?;
+        return cast(DataValue::String(s.into()), ty);
+    }
+    let pg_type = pg_type(ty);
+    let value = match ty {
+        DataType::Bool => portal
+            .parameter::<bool>(idx, &pg_type)?
+            .map(DataValue::Bool),
+        DataType::Int16 => portal
+            .parameter::<i16>(idx, &pg_type)?
+            .map(DataValue::Int16),
+        DataType::Int32 => portal
+            .parameter::<i32>(idx, &pg_type)?
+            .map(DataValue::Int32),
+        DataType::Int64 => portal
+            .parameter::<i64>(idx, &pg_type)?
+            .map(DataValue::Int64),
+        DataType::Float64 => {
+            (portal.parameter::<f64>(idx, &pg_type)?).map(|v| DataValue::Float64(v.into()))
+        }
+        DataType::String => {
+            (portal.parameter::<String>(idx, &pg_type)?).map(|v| DataValue::String(v.into()))
+        }
+        DataType::Blob => {
+            (portal.parameter::<Vec<u8>>(idx, &pg_type)?).map(|v| DataValue::Blob(v.into()))
+        }
+        // decode date and time with chrono and convert them through their text forms
+        DataType::Date => match portal.parameter::<NaiveDate>(idx, &pg_type)? {
+            Some(v) => Some(cast(DataValue::String(v.to_string().into()), ty)?),
+            None => None,
+        },
+        DataType::Timestamp => match portal.parameter::<NaiveDateTime>(idx, &pg_type)? {
+            Some(v) => Some(cast(DataValue::String(v.to_string().into()), ty)?),
+            None => None,
+        },
+        _ => {
+            return Err(not_supported(format!(
+                "binary format is not supported for parameters of type {ty}"
+            )));
+        }
+    };
+    Ok(value.unwrap_or(DataValue::Null))
+}
+
+/// Encodes the `row`-th value of an array as a field of data row.
+pub fn encode_field(
+    encoder: &mut DataRowEncoder,
+    array: &ArrayImpl,
+    row: usize,
+    format: FieldFormat,
+) -> PgWireResult<()> {
+    let value = array.get(row);
+    if value.is_null() {
+        return encoder.encode_field(&None::<&str>);
+    }
+    if format == FieldFormat::Text {
+        return encoder.encode_field(&array.get_to_string(row));
+    }
+    match &value {
+        DataValue::Bool(v) => encoder.encode_field(v),
+        DataValue::Int16(v) => encoder.encode_field(v),
+        DataValue::Int32(v) => encoder.encode_field(v),
+        DataValue::Int64(v) => encoder.encode_field(v),
+        DataValue::Float64(v) => encoder.encode_field(&v.0),
+        DataValue::String(v) => {
+            let s: &str = v;
+            encoder.encode_field(&s)
+        }
+        DataValue::Blob(v) => {
+            let bytes: &[u8] = v;
+            encoder.encode_field(&bytes)
+        }
+        _ => Err(not_supported(format!(
+            "binary format is not supported for results of type {}",
+            value.data_type()
+        ))),
+    }
+}
+
+fn cast(value: DataValue, ty: &DataType) -> PgWireResult<DataValue> {
+    match ty {
+        DataType::String | DataType::Null => Ok(value),
+        _ => value.cast(ty).map_err(|e| user_error(e.to_string())),
+    }
+}
+
+/// Returns an `invalid_text_representation` error.
+fn user_error(message: String) -> PgWireError {
+    PgWireError::UserError(Box::new(ErrorInfo::new(
+        "ERROR".into(),
+        "22P02".into(),
+        message,
+    )))
+}
+
+/// Returns a `feature_not_supported` error.
+fn not_supported(message: String) -> PgWireError {
+    PgWireError::UserError(Box::new(ErrorInfo::new(
+        "ERROR".into(),
+        "0A000".into(),
+        message,
+    )))
+}
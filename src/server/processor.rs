@@ -1,24 +1,198 @@
 // Copyright 2024 RisingLight Project Authors. Licensed under Apache-2.0.
 
+use std::fmt::Debug;
 use std::sync::Arc;
 
 use async_trait::async_trait;
-use futures::stream;
-use pgwire::api::query::SimpleQueryHandler;
-use pgwire::api::results::{DataRowEncoder, FieldFormat, FieldInfo, QueryResponse, Response, Tag};
-use pgwire::api::{ClientInfo, Type};
-use pgwire::error::{PgWireError, PgWireResult};
-use tracing::info;
+use futures::{Sink, StreamExt, TryStreamExt, stream};
+use pgwire::api::portal::{Format, Portal};
+use pgwire::api::query::{ExtendedQueryHandler, SimpleQueryHandler};
+use pgwire::api::results::{
+    DataRowEncoder, DescribePortalResponse, DescribeStatementResponse, FieldInfo, QueryResponse,
+    Response, Tag,
+};
+use pgwire::api::stmt::{QueryParser, StoredStatement};
+use pgwire::api::store::PortalStore;
+use pgwire::api::{ClientInfo, ClientPortalStore, Type};
+use pgwire::error::{ErrorInfo, PgWireError, PgWireResult};
+use pgwire::messages::PgWireBackendMessage;
+use pgwire::messages::data::DataRow;
+use tracing::{info, warn};
 
+use super::pg_type::{data_type, decode_param, encode_field, pg_type};
 use crate::Database;
+use crate::array::{Chunk, DataChunk};
+use crate::db::{OutputStream, PreparedStatement, Session};
+use crate::parser::Statement;
 
+/// Handles queries of a connection.
 pub struct Processor {
-    db: Database,
+    db: Arc<Database>,
+    parser: Arc<StatementParser>,
+    /// The session of the connection.
+    session: Arc<Session>,
 }
 
 impl Processor {
-    pub fn new(db: Database) -> Self {
-        Self { db }
+    pub fn new(db: Arc<Database>) -> Self {
+        let session = Arc::new(Session::new());
+        Self {
+            parser: Arc::new(StatementParser {
+                db: db.clone(),
+                session: session.clone(),
+            }),
+            db,
+            session,
+        }
+    }
+}
+
+impl Drop for Processor {
+    /// Closes the session when the connection is closed.
+    fn drop(&mut self) {
+        let Ok(runtime) = tokio::runtime::Handle::try_current() else {
+            return;
+        };
+        let db = self.db.clone();
+        let session = std::mem::take(&mut self.session);
+        runtime.spawn(async move {
+            if let Err(e) = db.close_session(&session).await {
+                warn!("failed to close session: {e}");
+            }
+        });
+    }
+}
+
+/// Prepares statements for the extended query protocol.
+///
+/// Prepared statements are cached in the portal store of each connection.
+pub struct StatementParser {
+    db: Arc<Database>,
+    session: Arc<Session>,
+}
+
+#[async_trait]
+impl QueryParser for StatementParser {
+    type Statement = PreparedStatement;
+
+    async fn parse_sql(&self, sql: &str, types: &[Type]) -> PgWireResult<PreparedStatement> {
+        info!("prepare:{sql:?}");
+        let types = types.iter().map(data_type).collect::<Vec<_>>();
+        (self.db.prepare_in(&self.session, sql, &types)).map_err(api_error)
+    }
+}
+
+/// Returns the fields of the output columns of a statement.
+fn fields(stmt: &PreparedStatement, format: &Format) -> Vec<FieldInfo> {
+    (stmt.columns().iter().enumerate())
+        .map(|(i, (name, ty))| {
+            FieldInfo::new(name.clone(), None, None, pg_type(ty), format.format_for(i))
+        })
+        .collect()
+}
+
+fn api_error(e: crate::Error) -> PgWireError {
+    if e.is_retryable() {
+        // `serialization_failure`, so that clients know to retry the transaction
+        return PgWireError::UserError(Box::new(ErrorInfo::new(
+            "ERROR".into(),
+            "40001".into(),
+            e.to_string(),
+        )));
+    }
+    PgWireError::ApiError(Box::new(e))
+}
+
+/// Converts an error of a statement to a response.
+fn error_response<'a>(e: PgWireError) -> Response<'a> {
+    let info = match e {
+        PgWireError::UserError(info) => info,
+        e => Box::new(ErrorInfo::new(
+            "ERROR".into(),
+            "XX000".into(),
+            e.to_string(),
+        )),
+    };
+    Response::Error(info)
+}
+
+/// Converts the output of a statement to a response.
+///
+/// Rows are encoded and sent to the client as they are produced.
+async fn response<'a>(
+    stmt: &PreparedStatement,
+    output: Option<OutputStream<'a>>,
+    format: &Format,
+) -> PgWireResult<Response<'a>> {
+    let Some(ast) = stmt.statement() else {
+        return Ok(Response::EmptyQuery);
+    };
+    if !stmt.returns_rows() {
+        let output = match output {
+            Some(output) => Some(output.into_chunk().await.map_err(api_error)?),
+            None => None,
+        };
+        return Ok(Response::Execution(command_tag(ast, output.as_ref())));
+    }
+    let headers = Arc::new(fields(stmt, format));
+    let rows = (stream::iter(output).flatten())
+        .map_err(api_error)
+        .and_then({
+            let headers = headers.clone();
+            move |chunk| {
+                let rows = encode_rows(&chunk, &headers);
+                async move { Ok(stream::iter(rows?.into_iter().map(Ok))) }
+            }
+        })
+        .try_flatten()
+        .boxed();
+    Ok(Response::Query(QueryResponse::new(headers, rows)))
+}
+
+/// Encodes the rows of a chunk.
+fn encode_rows(chunk: &DataChunk, headers: &Arc<Vec<FieldInfo>>) -> PgWireResult<Vec<DataRow>> {
+    let mut rows = Vec::with_capacity(chunk.cardinality());
+    for i in 0..chunk.cardinality() {
+        let mut encoder = DataRowEncoder::new(headers.clone());
+        for (array, field) in chunk.arrays().iter().zip(headers.iter()) {
+            encode_field(&mut encoder, array, i, field.format())?;
+        }
+        rows.push(encoder.finish()?);
+    }
+    Ok(rows)
+}
+
+/// Returns the command tag of a statement that returns no rows.
+fn command_tag(stmt: &Statement, output: Option<&Chunk>) -> Tag {
+    // DML statements output the number of affected rows
+    let rows = || {
+        (output.and_then(|c| c.data_chunks().first()))
+            .filter(|c| c.cardinality() > 0)
+            .and_then(|c| c.array_at(0).get(0).as_usize().ok().flatten())
+            .unwrap_or(0)
+    };
+    match stmt {
+        // the OID is always 0, which is not printed by `Tag::with_oid`
+        Statement::Insert(_) => Tag::new("INSERT 0").with_rows(rows()),
+        Statement::Delete(_) => Tag::new("DELETE").with_rows(rows()),
+        Statement::Update { .. } => Tag::new("UPDATE").with_rows(rows()),
+        Statement::Copy { .. } => Tag::new("COPY").with_rows(rows()),
+        Statement::CreateTable(_) => Tag::new("CREATE TABLE"),
+        Statement::CreateView { .. } => Tag::new("CREATE VIEW"),
+        Statement::CreateIndex(_) => Tag::new("CREATE INDEX"),
+        Statement::CreateFunction(_) => Tag::new("CREATE FUNCTION"),
+        Statement::CreateRole { .. } => Tag::new("CREATE ROLE"),
+        Statement::Drop { object_type, .. } => Tag::new(&format!("DROP {object_type}")),
+        Statement::AlterTable { .. } => Tag::new("ALTER TABLE"),
+        Statement::SetVariable { .. } => Tag::new("SET"),
+        Statement::Prepare { .. } => Tag::new("PREPARE"),
+        Statement::Execute { .. } => Tag::new("EXECUTE"),
+        Statement::Deallocate { .. } => Tag::new("DEALLOCATE"),
+        Statement::Pragma { .. } => Tag::new("PRAGMA"),
+        Statement::StartTransaction { .. } => Tag::new("BEGIN"),
+        Statement::Commit { .. } => Tag::new("COMMIT"),
+        Statement::Rollback { .. } => Tag::new("ROLLBACK"),
+        _ => Tag::new("OK"),
     }
 }
 
@@ -33,44 +207,130 @@ impl SimpleQueryHandler for Processor {
         C: ClientInfo + Unpin + Send + Sync,
     {
         info!("query:{query:?}");
-        let chunks = self
-            .db
-            .run(query)
-            .await
-            .map_err(|e| PgWireError::ApiError(Box::new(e)))?;
+        // statements after a failed one are not run, but earlier ones still get their responses
+        let mut responses = vec![];
+        for result in self.db.stream_statements_in(&self.session, query).await {
+            let response = match result {
+                Ok((stmt, output)) => response(&stmt, output, &Format::UnifiedText).await,
+                Err(e) => Err(api_error(e)),
+            };
+            responses.push(response.unwrap_or_else(error_response));
+        }
+        Ok(responses)
+    }
+}
+
+#[async_trait]
+impl ExtendedQueryHandler for Processor {
+    type Statement = PreparedStatement;
+    type QueryParser = StatementParser;
+
+    fn query_parser(&self) -> Arc<StatementParser> {
+        self.parser.clone()
+    }
+
+    async fn do_describe_statement<C>(
+        &self,
+        _client: &mut C,
+        target: &StoredStatement<PreparedStatement>,
+    ) -> PgWireResult<DescribeStatementResponse>
+    where
+        C: ClientInfo + ClientPortalStore + Sink<PgWireBackendMessage> + Unpin + Send + Sync,
+        C::PortalStore: PortalStore<Statement = PreparedStatement>,
+        C::Error: Debug,
+        PgWireError: From<<C as Sink<PgWireBackendMessage>>::Error>,
+    {
+        let stmt = &target.statement;
+        let params = stmt.param_types().iter().map(pg_type).collect();
+        // the result format is unknown until the statement is bound
+        let fields = fields(stmt, &Format::UnifiedText);
+        Ok(DescribeStatementResponse::new(params, fields))
+    }
+
+    async fn do_describe_portal<C>(
+        &self,
+        _client: &mut C,
+        target: &Portal<PreparedStatement>,
+    ) -> PgWireResult<DescribePortalResponse>
+    where
+        C: ClientInfo + ClientPortalStore + Sink<PgWireBackendMessage> + Unpin + Send + Sync,
+        C::PortalStore: PortalStore<Statement = PreparedStatement>,
+        C::Error: Debug,
+        PgWireError: From<<C as Sink<PgWireBackendMessage>>::Error>,
+    {
+        let fields = fields(&target.statement.statement, &target.result_column_format);
+        Ok(DescribePortalResponse::new(fields))
+    }
 
-        if !query.to_uppercase().starts_with("SELECT") {
-            return Ok(vec![Response::Execution(Tag::new("OK"))]);
+    async fn do_query<'a, 'b: 'a, C>(
+        &'b self,
+        _client: &mut C,
+        portal: &'a Portal<PreparedStatement>,
+        _max_rows: usize,
+    ) -> PgWireResult<Response<'a>>
+    where
+        C: ClientInfo + ClientPortalStore + Sink<PgWireBackendMessage> + Unpin + Send + Sync,
+        C::PortalStore: PortalStore<Statement = PreparedStatement>,
+        C::Error: Debug,
+        PgWireError: From<<C as Sink<PgWireBackendMessage>>::Error>,
+    {
+        let stmt = &portal.statement.statement;
+        if stmt.is_empty() {
+            return Ok(Response::EmptyQuery);
         }
-        let mut results = Vec::new();
-        let mut headers = None;
-        for chunk in chunks {
-            for data_chunk in chunk.data_chunks() {
-                for i in 0..data_chunk.cardinality() {
-                    let headers = headers.get_or_insert_with(|| {
-                        Arc::new(vec![
-                            FieldInfo::new(
-                                "++".into(),
-                                None,
-                                None,
-                                Type::CHAR,
-                                FieldFormat::Text
-                            );
-                            data_chunk.arrays().len()
-                        ])
-                    });
-                    let mut encoder = DataRowEncoder::new(headers.clone());
-                    data_chunk.arrays().iter().for_each(|a| {
-                        let field = a.get_to_string(i);
-                        encoder.encode_field(&field).unwrap();
-                    });
-                    results.push(encoder.finish());
-                }
+        let params = (stmt.param_types().iter().enumerate())
+            .map(|(i, ty)| decode_param(portal, i, ty))
+            .collect::<PgWireResult<Vec<_>>>()?;
+        let output = (self.db.stream_execute_in(&self.session, stmt, params))
+            .await
+            .map_err(api_error)?;
+        response(stmt, output, &portal.result_column_format).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pgwire::messages::response::CommandComplete;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn simple_query_responses() {
+        let db = Database::new_in_memory();
+        let sql = "
+            create table t (a int, b string);
+            insert into t values (1, 'x'), (2, 'y');
+            select a, b as c, a + 1 from t where a > 5;
+            delete from t where a = 1;
+            drop table t;
+        ";
+        let outputs = db.stream_statements(sql).await;
+        let mut tags = vec![];
+        let mut schema = None;
+        for result in outputs {
+            let (stmt, output) = result.unwrap();
+            match response(&stmt, output, &Format::UnifiedText).await.unwrap() {
+                Response::Execution(tag) => tags.push(CommandComplete::from(tag).tag),
+                Response::Query(query) => schema = Some(query.row_schema()),
+                _ => panic!("unexpected response"),
             }
         }
-        Ok(vec![Response::Query(QueryResponse::new(
-            headers.expect("fixme: db should return schema even if no output data"),
-            stream::iter(results.into_iter()),
-        ))])
+        assert_eq!(
+            tags,
+            ["CREATE TABLE", "INSERT 0 2", "DELETE 1", "DROP TABLE"]
+        );
+
+        // the schema is returned even if there is no row
+        let fields = (schema.unwrap().iter())
+            .map(|f| (f.name().to_string(), f.datatype().clone()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            fields,
+            [
+                ("a".into(), Type::INT4),
+                ("c".into(), Type::TEXT),
+                ("?column?".into(), Type::INT4)
+            ]
+        );
     }
 }
@@ -1,9 +1,12 @@
 // Copyright 2024 RisingLight Project Authors. Licensed under Apache-2.0.
 
 use bitvec::prelude::BitVec;
+use itertools::Itertools;
 use smallvec::SmallVec;
 
-use crate::array::{ArrayImpl, DataChunk};
+use crate::array::{ArrayBuilderImpl, ArrayImpl, DataChunk};
+use crate::catalog::ColumnCatalog;
+use crate::types::{ConvertError, DataValue};
 
 pub type PackedVec<T> = SmallVec<[T; 16]>;
 
@@ -92,3 +95,44 @@ impl StorageChunk {
         }
     }
 }
+
+/// Returns an array of `len` rows filled with the default value of `column`.
+///
+/// It is the value of a column in rows written before the column was added.
+pub fn default_array(column: &ColumnCatalog, len: usize) -> ArrayImpl {
+    let value = column.default_value().cloned().unwrap_or(DataValue::Null);
+    let mut builder = ArrayBuilderImpl::with_capacity(len, &column.data_type());
+    builder.push_n(len, &value);
+    builder.finish()
+}
+
+/// Converts an array of `old` column to the type of `column` with the same id.
+pub fn cast_array(
+    array: &ArrayImpl,
+    old: &ColumnCatalog,
+    column: &ColumnCatalog,
+) -> Result<ArrayImpl, ConvertError> {
+    if old.data_type() == column.data_type() {
+        return Ok(array.clone());
+    }
+    array.cast(&column.data_type())
+}
+
+/// Converts a chunk written with the `old` schema to the `new` schema.
+///
+/// Columns are matched by id. Dropped columns are removed, added columns are filled with their
+/// default values, and columns whose type has changed are casted.
+pub fn evolve_chunk(
+    chunk: &DataChunk,
+    old: &[ColumnCatalog],
+    new: &[ColumnCatalog],
+) -> Result<DataChunk, ConvertError> {
+    new.iter()
+        .map(
+            |column| match old.iter().position(|c| c.id() == column.id()) {
+                Some(i) => cast_array(chunk.array_at(i), &old[i], column),
+                None => Ok(default_array(column, chunk.cardinality())),
+            },
+        )
+        .try_collect()
+}
@@ -5,7 +5,8 @@ use std::sync::Arc;
 
 use thiserror::Error;
 
-use crate::catalog::ColumnId;
+use crate::catalog::{CatalogError, ColumnId};
+use crate::types::ConvertError;
 
 #[derive(Error, Debug)]
 pub enum StorageError {
@@ -31,6 +32,10 @@ pub enum StorageError {
     ProstEncode(prost::EncodeError),
     #[error("Prost decode error: {0}")]
     ProstDecode(prost::DecodeError),
+    #[error("conversion error: {0}")]
+    Convert(#[from] ConvertError),
+    #[error("could not serialize access due to concurrent update: {0}")]
+    Conflict(String),
     #[error("{0}")]
     Nested(
         #[from]
@@ -67,6 +72,23 @@ impl From<prost::DecodeError> for TracedStorageError {
     }
 }
 
+impl From<ConvertError> for TracedStorageError {
+    #[inline]
+    fn from(e: ConvertError) -> TracedStorageError {
+        StorageError::Convert(e).into()
+    }
+}
+
+impl From<CatalogError> for TracedStorageError {
+    #[inline]
+    fn from(e: CatalogError) -> TracedStorageError {
+        match e {
+            CatalogError::NotFound(ty, item) => StorageError::NotFound(ty, item).into(),
+            CatalogError::Duplicated(ty, item) => StorageError::Duplicated(ty, item).into(),
+        }
+    }
+}
+
 impl From<Arc<TracedStorageError>> for TracedStorageError {
     #[inline]
     fn from(e: Arc<TracedStorageError>) -> TracedStorageError {
@@ -105,6 +127,20 @@ impl TracedStorageError {
     pub fn checksum(found: u64, expected: u64) -> Self {
         StorageError::Checksum(found, expected).into()
     }
+
+    pub fn conflict(message: impl ToString) -> Self {
+        StorageError::Conflict(message.to_string()).into()
+    }
+
+    /// Returns true if the transaction failed due to a conflict with a concurrent transaction,
+    /// and can be retried.
+    pub fn is_conflict(&self) -> bool {
+        match &self.source {
+            StorageError::Conflict(_) => true,
+            StorageError::Nested(e) => e.is_conflict(),
+            _ => false,
+        }
+    }
 }
 
 pub type StorageResult<T> = std::result::Result<T, TracedStorageError>;
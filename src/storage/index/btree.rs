@@ -0,0 +1,83 @@
+// Copyright 2025 RisingLight Project Authors. Licensed under Apache-2.0.
+
+//! Ordered index on scalar keys.
+
+use std::collections::{BTreeSet, HashMap};
+
+use parking_lot::RwLock;
+
+use super::InMemoryIndex;
+use crate::array::ArrayImpl;
+use crate::types::DataValue;
+
+/// A B-tree index.
+///
+/// A search returns the rows whose key equals the given key, ordered by row handler.
+#[derive(Default)]
+pub struct BTreeIndex {
+    inner: RwLock<BTreeInner>,
+}
+
+#[derive(Default)]
+struct BTreeInner {
+    entries: BTreeSet<(DataValue, i64)>,
+    /// Maps a row handler to its key.
+    keys: HashMap<i64, DataValue>,
+}
+
+impl BTreeIndex {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl InMemoryIndex for BTreeIndex {
+    fn insert(&self, keys: &ArrayImpl, row_handlers: &[i64]) {
+        let mut inner = self.inner.write();
+        for (i, &row) in row_handlers.iter().enumerate() {
+            let key = keys.get(i);
+            if key.is_null() {
+                continue;
+            }
+            inner.entries.insert((key.clone(), row));
+            inner.keys.insert(row, key);
+        }
+    }
+
+    fn delete(&self, row_handlers: &[i64]) {
+        let mut inner = self.inner.write();
+        for row in row_handlers {
+            if let Some(key) = inner.keys.remove(row) {
+                inner.entries.remove(&(key, *row));
+            }
+        }
+    }
+
+    fn search(&self, key: &DataValue, k: usize) -> Vec<i64> {
+        let inner = self.inner.read();
+        (inner.entries)
+            .range((key.clone(), i64::MIN)..=(key.clone(), i64::MAX))
+            .take(k)
+            .map(|(_, row)| *row)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::array::I32Array;
+
+    #[test]
+    fn search() {
+        let index = BTreeIndex::new();
+        let keys = ArrayImpl::new_int32(I32Array::from_iter([Some(1), Some(2), None, Some(1)]));
+        index.insert(&keys, &[10, 11, 12, 13]);
+        assert_eq!(index.search(&DataValue::Int32(1), 10), vec![10, 13]);
+        assert_eq!(index.search(&DataValue::Int32(1), 1), vec![10]);
+        assert!(index.search(&DataValue::Null, 10).is_empty());
+        index.delete(&[10, 14]);
+        assert_eq!(index.search(&DataValue::Int32(1), 10), vec![13]);
+        assert!(index.search(&DataValue::Int32(3), 10).is_empty());
+    }
+}
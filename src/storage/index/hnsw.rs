@@ -0,0 +1,297 @@
+// Copyright 2025 RisingLight Project Authors. Licensed under Apache-2.0.
+
+//! Hierarchical navigable small world graph.
+//!
+//! See <https://arxiv.org/abs/1603.09320> for the algorithm.
+
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, HashSet};
+
+use itertools::Itertools;
+use parking_lot::RwLock;
+
+use super::{InMemoryIndex, vector_distance, vector_keys};
+use crate::array::ArrayImpl;
+use crate::binder::VectorDistance;
+use crate::types::{DataValue, F64, Vector, VectorRef};
+
+/// An HNSW index.
+///
+/// Deleted rows are only marked in the graph, so that they can still be used to navigate.
+pub struct HnswIndex {
+    distance: VectorDistance,
+    /// Maximum number of neighbors on upper layers. Layer 0 allows `2 * m` neighbors.
+    m: usize,
+    ef_construction: usize,
+    ef_search: usize,
+    graph: RwLock<Graph>,
+}
+
+struct Node {
+    row: i64,
+    vector: Vector,
+    /// Neighbors on each layer, from layer 0 to the top layer of this node.
+    neighbors: Vec<Vec<u32>>,
+    deleted: bool,
+}
+
+#[derive(Default)]
+struct Graph {
+    nodes: Vec<Node>,
+    entry_point: Option<u32>,
+    /// Maps a live row handler to its node.
+    rows: HashMap<i64, u32>,
+    /// State of the pseudo-random generator for choosing layers.
+    seed: u64,
+}
+
+impl Graph {
+    /// Returns a pseudo-random number in (0, 1].
+    fn next_random(&mut self) -> f64 {
+        // splitmix64
+        self.seed = self.seed.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = self.seed;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^= z >> 31;
+        ((z >> 11) + 1) as f64 / (1u64 << 53) as f64
+    }
+
+    fn top_layer(&self) -> usize {
+        match self.entry_point {
+            Some(ep) => self.nodes[ep as usize].neighbors.len() - 1,
+            None => 0,
+        }
+    }
+}
+
+impl HnswIndex {
+    pub fn new(
+        distance: VectorDistance,
+        m: usize,
+        ef_construction: usize,
+        ef_search: usize,
+    ) -> Self {
+        Self {
+            distance,
+            m: m.max(2),
+            ef_construction: ef_construction.max(1),
+            ef_search: ef_search.max(1),
+            graph: RwLock::new(Graph::default()),
+        }
+    }
+
+    fn distance(&self, a: &VectorRef, b: &VectorRef) -> F64 {
+        vector_distance(&self.distance, a, b)
+    }
+
+    fn max_neighbors(&self, layer: usize) -> usize {
+        if layer == 0 { self.m * 2 } else { self.m }
+    }
+
+    /// Finds the `ef` nearest nodes to `query` on `layer`, starting from `entry_points`.
+    ///
+    /// Returns `(distance, node)` pairs ordered from the nearest.
+    fn search_layer(
+        &self,
+        graph: &Graph,
+        query: &VectorRef,
+        entry_points: &[u32],
+        ef: usize,
+        layer: usize,
+    ) -> Vec<(F64, u32)> {
+        let mut visited: HashSet<u32> = entry_points.iter().copied().collect();
+        // min-heap of nodes to expand
+        let mut candidates = BinaryHeap::new();
+        // max-heap of the current nearest nodes
+        let mut results = BinaryHeap::new();
+        for &ep in entry_points {
+            let d = self.distance(&graph.nodes[ep as usize].vector, query);
+            candidates.push(Reverse((d, ep)));
+            results.push((d, ep));
+        }
+        while let Some(Reverse((d, node))) = candidates.pop() {
+            if results.len() >= ef && d > results.peek().unwrap().0 {
+                break;
+            }
+            for &neighbor in &graph.nodes[node as usize].neighbors[layer] {
+                if !visited.insert(neighbor) {
+                    continue;
+                }
+                let d = self.distance(&graph.nodes[neighbor as usize].vector, query);
+                if results.len() < ef || d < results.peek().unwrap().0 {
+                    candidates.push(Reverse((d, neighbor)));
+                    results.push((d, neighbor));
+                    if results.len() > ef {
+                        results.pop();
+                    }
+                }
+            }
+        }
+        results.into_sorted_vec()
+    }
+
+    /// Descends from the top layer to `layer + 1` greedily, and returns the nearest node found.
+    fn greedy_descend(&self, graph: &Graph, query: &VectorRef, layer: usize) -> u32 {
+        let mut ep = graph.entry_point.unwrap();
+        for l in (layer + 1..=graph.top_layer()).rev() {
+            ep = self.search_layer(graph, query, &[ep], 1, l)[0].1;
+        }
+        ep
+    }
+
+    fn insert_one(&self, graph: &mut Graph, row: i64, vector: Vector) {
+        let ml = 1.0 / (self.m as f64).ln();
+        let level = (-graph.next_random().ln() * ml) as usize;
+        let id = graph.nodes.len() as u32;
+        graph.nodes.push(Node {
+            row,
+            vector,
+            neighbors: vec![vec![]; level + 1],
+            deleted: false,
+        });
+        graph.rows.insert(row, id);
+
+        let Some(_) = graph.entry_point else {
+            graph.entry_point = Some(id);
+            return;
+        };
+        let top_layer = graph.top_layer();
+        let query = graph.nodes[id as usize].vector.clone();
+        let mut entry_points = vec![self.greedy_descend(graph, &query, level)];
+        for layer in (0..=level.min(top_layer)).rev() {
+            let nearest =
+                self.search_layer(graph, &query, &entry_points, self.ef_construction, layer);
+            let neighbors = nearest.iter().take(self.m).map(|(_, n)| *n).collect_vec();
+            for &neighbor in &neighbors {
+                self.connect(graph, neighbor, id, layer);
+            }
+            graph.nodes[id as usize].neighbors[layer] = neighbors;
+            entry_points = nearest.into_iter().map(|(_, n)| n).collect();
+        }
+        if level > top_layer {
+            graph.entry_point = Some(id);
+        }
+    }
+
+    /// Adds an edge from `node` to `new`, pruning the farthest neighbor if `node` has too many.
+    fn connect(&self, graph: &mut Graph, node: u32, new: u32, layer: usize) {
+        let max = self.max_neighbors(layer);
+        let neighbors = &graph.nodes[node as usize].neighbors[layer];
+        if neighbors.len() < max {
+            graph.nodes[node as usize].neighbors[layer].push(new);
+            return;
+        }
+        let base = &graph.nodes[node as usize].vector;
+        let pruned = (neighbors.iter().copied())
+            .chain(std::iter::once(new))
+            .map(|n| (self.distance(&graph.nodes[n as usize].vector, base), n))
+            .k_smallest(max)
+            .map(|(_, n)| n)
+            .collect();
+        graph.nodes[node as usize].neighbors[layer] = pruned;
+    }
+}
+
+impl InMemoryIndex for HnswIndex {
+    fn insert(&self, keys: &ArrayImpl, row_handlers: &[i64]) {
+        let mut graph = self.graph.write();
+        for (key, &row) in vector_keys(keys).zip(row_handlers) {
+            if let Some(key) = key {
+                self.insert_one(&mut graph, row, key.to_vector());
+            }
+        }
+    }
+
+    fn delete(&self, row_handlers: &[i64]) {
+        let mut graph = self.graph.write();
+        for row in row_handlers {
+            if let Some(id) = graph.rows.remove(row) {
+                graph.nodes[id as usize].deleted = true;
+            }
+        }
+    }
+
+    fn search(&self, key: &DataValue, k: usize) -> Vec<i64> {
+        let DataValue::Vector(key) = key else {
+            return vec![];
+        };
+        let graph = self.graph.read();
+        if graph.entry_point.is_none() || k == 0 {
+            return vec![];
+        }
+        let live = graph.rows.len();
+        if k >= live {
+            // all rows are requested, search exhaustively
+            return (graph.rows.values())
+                .map(|&n| &graph.nodes[n as usize])
+                .map(|node| (self.distance(&node.vector, key), node.row))
+                .sorted()
+                .map(|(_, row)| row)
+                .collect();
+        }
+        let entry_point = self.greedy_descend(&graph, key, 0);
+        let mut ef = self.ef_search.max(k);
+        loop {
+            let result = (self
+                .search_layer(&graph, key, &[entry_point], ef, 0)
+                .into_iter())
+            .map(|(_, n)| &graph.nodes[n as usize])
+            .filter(|node| !node.deleted)
+            .map(|node| node.row)
+            .take(k)
+            .collect_vec();
+            // widen the search if deleted nodes take up the candidates
+            if result.len() >= k || ef >= graph.nodes.len() {
+                return result;
+            }
+            ef *= 2;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::tests::{random_vectors, recall, to_array};
+    use super::*;
+
+    #[test]
+    fn search_recall() {
+        for distance in [
+            VectorDistance::L2,
+            VectorDistance::Cosine,
+            VectorDistance::NegativeDotProduct,
+        ] {
+            let index = HnswIndex::new(distance.clone(), 16, 64, 40);
+            let data = random_vectors(1000, 8, 1);
+            for (i, chunk) in data.chunks(100).enumerate() {
+                let start = i as i64 * 100;
+                index.insert(&to_array(chunk), &(start..start + 100).collect_vec());
+            }
+            let queries = random_vectors(20, 8, 2);
+            let recall = recall(&index, &distance, &data, &queries, 10);
+            assert!(recall >= 0.9, "recall of {distance:?}: {recall}");
+        }
+    }
+
+    #[test]
+    fn delete() {
+        let index = HnswIndex::new(VectorDistance::L2, 4, 16, 8);
+        let data = random_vectors(200, 4, 3);
+        index.insert(&to_array(&data), &(0..200).collect_vec());
+        let query = DataValue::Vector(data[42].clone());
+        assert_eq!(index.search(&query, 1), vec![42]);
+        index.delete(&(0..150).collect_vec());
+        let result = index.search(&query, 10);
+        assert_eq!(result.len(), 10);
+        assert!(result.iter().all(|&row| row >= 150));
+        assert_eq!(index.search(&query, 100).len(), 50);
+    }
+
+    #[test]
+    fn empty() {
+        let index = HnswIndex::new(VectorDistance::L2, 16, 64, 40);
+        let query = DataValue::Vector(Vector::new(vec![1.0, 2.0]));
+        assert!(index.search(&query, 10).is_empty());
+    }
+}
@@ -0,0 +1,209 @@
+// Copyright 2025 RisingLight Project Authors. Licensed under Apache-2.0.
+
+//! Inverted file index with flat (uncompressed) vectors.
+
+use std::collections::HashMap;
+
+use itertools::Itertools;
+use parking_lot::RwLock;
+
+use super::{InMemoryIndex, vector_distance, vector_keys};
+use crate::array::ArrayImpl;
+use crate::binder::VectorDistance;
+use crate::types::{DataValue, F64, Vector, VectorRef};
+
+/// Number of k-means iterations when training the centroids.
+const KMEANS_ITERATIONS: usize = 10;
+
+/// An IVF-Flat index.
+///
+/// Vectors are partitioned into `nlists` lists by their nearest centroid, and a search only
+/// scans the `nprobe` lists whose centroids are nearest to the query.
+///
+/// The centroids are trained by k-means once there are `nlists` vectors, and retrained every
+/// time the number of vectors doubles. Before the first training, all vectors are kept in a
+/// single list and searched exhaustively.
+pub struct IvfFlatIndex {
+    distance: VectorDistance,
+    nlists: usize,
+    nprobe: usize,
+    inner: RwLock<IvfFlatInner>,
+}
+
+#[derive(Default)]
+struct IvfFlatInner {
+    centroids: Vec<Vector>,
+    lists: Vec<Vec<(i64, Vector)>>,
+    /// Maps a row handler to the list it belongs to.
+    locations: HashMap<i64, usize>,
+    /// Number of vectors when the centroids were trained last time.
+    trained_size: usize,
+}
+
+impl IvfFlatIndex {
+    pub fn new(distance: VectorDistance, nlists: usize, nprobe: usize) -> Self {
+        Self {
+            distance,
+            nlists: nlists.max(1),
+            nprobe: nprobe.max(1),
+            inner: RwLock::new(IvfFlatInner {
+                lists: vec![vec![]],
+                ..Default::default()
+            }),
+        }
+    }
+
+    fn distance(&self, a: &VectorRef, b: &VectorRef) -> F64 {
+        vector_distance(&self.distance, a, b)
+    }
+
+    /// Returns the index of the nearest centroid.
+    fn nearest_list(&self, centroids: &[Vector], vector: &VectorRef) -> usize {
+        (0..centroids.len())
+            .min_by_key(|&i| self.distance(&centroids[i], vector))
+            .unwrap_or(0)
+    }
+
+    /// Trains the centroids on all vectors and redistributes them into lists.
+    fn train(&self, inner: &mut IvfFlatInner) {
+        let entries = inner.lists.drain(..).flatten().collect_vec();
+        let nlists = self.nlists.min(entries.len());
+        // initialize centroids with evenly spaced vectors to be deterministic
+        let mut centroids = (0..nlists)
+            .map(|i| entries[i * entries.len() / nlists].1.clone())
+            .collect_vec();
+        let mut assignment = vec![0; entries.len()];
+        for _ in 0..KMEANS_ITERATIONS {
+            let mut changed = false;
+            for ((_, vector), list) in entries.iter().zip(assignment.iter_mut()) {
+                let nearest = self.nearest_list(&centroids, vector);
+                changed |= nearest != *list;
+                *list = nearest;
+            }
+            let dim = entries[0].1.len();
+            let mut sums = vec![vec![0.0; dim]; nlists];
+            let mut counts = vec![0usize; nlists];
+            for ((_, vector), &list) in entries.iter().zip(assignment.iter()) {
+                counts[list] += 1;
+                for (sum, v) in sums[list].iter_mut().zip(vector.iter()) {
+                    *sum += v.0;
+                }
+            }
+            for ((centroid, sum), count) in centroids.iter_mut().zip(sums).zip(counts) {
+                // keep the old centroid if the list is empty
+                if count > 0 {
+                    *centroid = Vector::new(sum.into_iter().map(|s| s / count as f64).collect());
+                }
+            }
+            if !changed {
+                break;
+            }
+        }
+        inner.lists = vec![vec![]; nlists];
+        inner.locations.clear();
+        for (entry, &list) in entries.into_iter().zip(assignment.iter()) {
+            inner.locations.insert(entry.0, list);
+            inner.lists[list].push(entry);
+        }
+        inner.trained_size = inner.locations.len();
+        inner.centroids = centroids;
+    }
+}
+
+impl InMemoryIndex for IvfFlatIndex {
+    fn insert(&self, keys: &ArrayImpl, row_handlers: &[i64]) {
+        let mut inner = self.inner.write();
+        for (key, &row) in vector_keys(keys).zip(row_handlers) {
+            let Some(key) = key else { continue };
+            let list = self.nearest_list(&inner.centroids, key);
+            inner.lists[list].push((row, key.to_vector()));
+            inner.locations.insert(row, list);
+        }
+        let size = inner.locations.len();
+        if size >= self.nlists && size >= inner.trained_size * 2 {
+            self.train(&mut inner);
+        }
+    }
+
+    fn delete(&self, row_handlers: &[i64]) {
+        let mut inner = self.inner.write();
+        for row in row_handlers {
+            let Some(list) = inner.locations.remove(row) else {
+                continue;
+            };
+            let list = &mut inner.lists[list];
+            let pos = list.iter().position(|(r, _)| r == row).unwrap();
+            list.swap_remove(pos);
+        }
+    }
+
+    fn search(&self, key: &DataValue, k: usize) -> Vec<i64> {
+        let DataValue::Vector(key) = key else {
+            return vec![];
+        };
+        let inner = self.inner.read();
+        let lists = if inner.centroids.is_empty() || k >= inner.locations.len() {
+            (0..inner.lists.len()).collect_vec()
+        } else {
+            (0..inner.centroids.len())
+                .sorted_by_key(|&i| self.distance(&inner.centroids[i], key))
+                .take(self.nprobe)
+                .collect_vec()
+        };
+        lists
+            .into_iter()
+            .flat_map(|list| inner.lists[list].iter())
+            .map(|(row, vector)| (self.distance(vector, key), *row))
+            .k_smallest(k)
+            .map(|(_, row)| row)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::tests::{random_vectors, recall, to_array};
+    use super::*;
+
+    #[test]
+    fn exhaustive_before_training() {
+        let index = IvfFlatIndex::new(VectorDistance::L2, 100, 1);
+        let data = random_vectors(50, 4, 1);
+        index.insert(&to_array(&data), &(0..50).collect_vec());
+        let queries = random_vectors(10, 4, 2);
+        assert_eq!(recall(&index, &VectorDistance::L2, &data, &queries, 5), 1.0);
+    }
+
+    #[test]
+    fn recall_after_training() {
+        for distance in [
+            VectorDistance::L2,
+            VectorDistance::Cosine,
+            VectorDistance::NegativeDotProduct,
+        ] {
+            let index = IvfFlatIndex::new(distance.clone(), 16, 8);
+            let data = random_vectors(1000, 8, 3);
+            // insert in batches to exercise incremental retraining
+            for (i, chunk) in data.chunks(100).enumerate() {
+                let start = i as i64 * 100;
+                index.insert(&to_array(chunk), &(start..start + 100).collect_vec());
+            }
+            let queries = random_vectors(20, 8, 4);
+            let recall = recall(&index, &distance, &data, &queries, 10);
+            assert!(recall >= 0.8, "recall of {distance:?}: {recall}");
+        }
+    }
+
+    #[test]
+    fn delete() {
+        let index = IvfFlatIndex::new(VectorDistance::L2, 4, 4);
+        let data = random_vectors(100, 4, 5);
+        index.insert(&to_array(&data), &(0..100).collect_vec());
+        let query = DataValue::Vector(data[7].clone());
+        assert_eq!(index.search(&query, 1), vec![7]);
+        index.delete(&[7, 1000]);
+        let result = index.search(&query, 100);
+        assert_eq!(result.len(), 99);
+        assert!(!result.contains(&7));
+    }
+}
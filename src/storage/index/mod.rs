@@ -1,16 +1,112 @@
 // Copyright 2025 RisingLight Project Authors. Licensed under Apache-2.0.
 
+//! In-memory secondary indexes.
+//!
+//! Indexes are shared by the storage engine and its tables. They are populated when created
+//! and kept up to date by the transactions of the indexed table on commit. The secondary storage
+//! also persists index entries along with each rowset, and loads them when reopened.
+//!
+//! Rows are identified by the `i64` form of the storage's row handler, i.e. the same value
+//! produced when scanning `StorageColumnRef::RowHandler`.
+
+use std::collections::HashMap;
 use std::sync::Arc;
 
-use crate::catalog::{ColumnId, IndexId, SchemaId, TableId};
+use itertools::Either;
+use parking_lot::RwLock;
+
+use crate::array::{Array, ArrayImpl};
+use crate::binder::{IndexType, VectorDistance};
+use crate::catalog::{IndexId, SchemaId, TableId, TableRefId};
+use crate::types::{DataValue, F64, VectorRef};
+
+mod btree;
+mod hnsw;
+mod ivfflat;
+
+pub use btree::BTreeIndex;
+pub use hnsw::HnswIndex;
+pub use ivfflat::IvfFlatIndex;
+
+/// An index maintained in memory.
+pub trait InMemoryIndex: 'static + Send + Sync {
+    /// Inserts `keys` into the index. The i-th key belongs to the row `row_handlers[i]`.
+    /// Null keys are ignored.
+    fn insert(&self, keys: &ArrayImpl, row_handlers: &[i64]);
+
+    /// Removes the rows from the index. Unknown rows are ignored.
+    fn delete(&self, row_handlers: &[i64]);
+
+    /// Returns the row handlers of (approximately) the `k` nearest rows to `key`,
+    /// ordered from the closest to the farthest.
+    ///
+    /// The search is exact if `k` is not less than the number of rows in the index.
+    /// For a B-tree index, only the rows whose key equals `key` are returned.
+    fn search(&self, key: &DataValue, k: usize) -> Vec<i64>;
+}
+
+/// Creates an empty index of the given type.
+///
+/// A multi-column B-tree index is keyed on its first column.
+pub fn new_index(index_type: &IndexType) -> Arc<dyn InMemoryIndex> {
+    match index_type {
+        IndexType::Hnsw {
+            distance,
+            m,
+            ef_construction,
+            ef_search,
+        } => Arc::new(HnswIndex::new(
+            distance.clone(),
+            *m,
+            *ef_construction,
+            *ef_search,
+        )),
+        IndexType::IvfFlat {
+            distance,
+            nlists,
+            nprobe,
+        } => Arc::new(IvfFlatIndex::new(distance.clone(), *nlists, *nprobe)),
+        IndexType::Btree => Arc::new(BTreeIndex::new()),
+    }
+}
 
-pub trait InMemoryIndex: 'static + Send + Sync {}
+/// Computes the distance between two vectors. A smaller value means closer.
+///
+/// The results are consistent with the `<->`, `<=>` and `<#>` operators.
+pub(crate) fn vector_distance(distance: &VectorDistance, a: &VectorRef, b: &VectorRef) -> F64 {
+    match distance {
+        VectorDistance::L2 => a.l2_distance(b),
+        VectorDistance::Cosine => a.cosine_distance(b),
+        VectorDistance::NegativeDotProduct => -a.dot_product(b),
+    }
+}
 
-pub struct InMemoryIndexes {}
+/// Returns the vectors in the key column of a vector index.
+///
+/// The binder only allows vector indexes on vector columns, so other arrays have no keys.
+fn vector_keys(keys: &ArrayImpl) -> impl Iterator<Item = Option<&VectorRef>> {
+    match keys {
+        ArrayImpl::Vector(array) => Either::Left(array.iter()),
+        _ => Either::Right(std::iter::empty()),
+    }
+}
+
+struct IndexEntry {
+    table_id: TableId,
+    index: Arc<dyn InMemoryIndex>,
+}
+
+/// All in-memory indexes of a storage engine, for looking up an index by its id.
+///
+/// Each table also holds its own indexes to maintain them on commit.
+#[derive(Default)]
+pub struct InMemoryIndexes {
+    indexes: RwLock<HashMap<(SchemaId, IndexId), IndexEntry>>,
+}
 
 impl InMemoryIndexes {
     pub fn new() -> Self {
-        Self {}
+        Self::default()
     }
 
     pub fn add_index(
@@ -18,9 +114,9 @@ impl InMemoryIndexes {
         schema_id: SchemaId,
         index_id: IndexId,
         table_id: TableId,
-        column_idxs: &[ColumnId],
+        index: Arc<dyn InMemoryIndex>,
     ) {
-        let _ = (schema_id, index_id, table_id, column_idxs);
+        (self.indexes.write()).insert((schema_id, index_id), IndexEntry { table_id, index });
     }
 
     pub fn get_index(
@@ -28,7 +124,78 @@ impl InMemoryIndexes {
         schema_id: SchemaId,
         index_id: IndexId,
     ) -> Option<Arc<dyn InMemoryIndex>> {
-        let _ = (schema_id, index_id);
-        None
+        (self.indexes.read())
+            .get(&(schema_id, index_id))
+            .map(|entry| entry.index.clone())
+    }
+
+    /// Removes all indexes on a table.
+    pub fn drop_table(&self, table_id: TableRefId) {
+        self.indexes.write().retain(|(schema_id, _), entry| {
+            *schema_id != table_id.schema_id || entry.table_id != table_id.table_id
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::array::VectorArray;
+    use crate::types::Vector;
+
+    /// Generates `n` pseudo-random vectors of dimension `dim`.
+    pub(super) fn random_vectors(n: usize, dim: usize, seed: u64) -> Vec<Vector> {
+        let mut state = seed;
+        let mut next = move || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            (state % 10000) as f64 / 100.0
+        };
+        (0..n)
+            .map(|_| Vector::new((0..dim).map(|_| next()).collect()))
+            .collect()
+    }
+
+    pub(super) fn to_array(vectors: &[Vector]) -> ArrayImpl {
+        let array: VectorArray = vectors.iter().map(Some).collect();
+        ArrayImpl::Vector(Arc::new(array))
+    }
+
+    /// Returns the fraction of the exact `k` nearest neighbors found by the index.
+    pub(super) fn recall(
+        index: &dyn InMemoryIndex,
+        distance: &VectorDistance,
+        data: &[Vector],
+        queries: &[Vector],
+        k: usize,
+    ) -> f64 {
+        let mut found = 0;
+        for query in queries {
+            let mut exact = (0..data.len() as i64).collect::<Vec<_>>();
+            exact.sort_by_key(|&i| vector_distance(distance, &data[i as usize], query));
+            exact.truncate(k);
+            let result = index.search(&DataValue::Vector(query.clone()), k);
+            assert!(result.len() <= k);
+            found += result.iter().filter(|id| exact.contains(id)).count();
+        }
+        found as f64 / (queries.len() * k) as f64
+    }
+
+    #[test]
+    fn registry() {
+        let indexes = InMemoryIndexes::new();
+        let index = new_index(&IndexType::IvfFlat {
+            distance: VectorDistance::L2,
+            nlists: 2,
+            nprobe: 1,
+        });
+        indexes.add_index(0, 2, 1, index);
+        assert!(indexes.get_index(0, 2).is_some());
+        assert!(indexes.get_index(0, 3).is_none());
+        indexes.drop_table(TableRefId::new(0, 3));
+        assert!(indexes.get_index(0, 2).is_some());
+        indexes.drop_table(TableRefId::new(0, 1));
+        assert!(indexes.get_index(0, 2).is_none());
     }
 }
@@ -17,6 +17,8 @@ pub struct InMemoryTxnIterator {
     chunks: Arc<Vec<DataChunk>>,
     deleted_rows: Arc<HashSet<usize>>,
     col_idx: Vec<StorageColumnRef>,
+    /// Only chunks in the `index`-th of `count` partitions are returned.
+    partition: Option<(usize, usize)>,
     cnt: usize,
     row_cnt: usize,
 }
@@ -26,10 +28,12 @@ impl InMemoryTxnIterator {
         chunks: Arc<Vec<DataChunk>>,
         deleted_rows: Arc<HashSet<usize>>,
         col_idx: &[StorageColumnRef],
+        partition: Option<(usize, usize)>,
     ) -> Self {
         Self {
             chunks,
             col_idx: col_idx.to_vec(),
+            partition,
             cnt: 0,
             row_cnt: 0,
             deleted_rows,
@@ -40,6 +44,13 @@ impl InMemoryTxnIterator {
         &mut self,
         _expected_size: Option<usize>,
     ) -> StorageResult<Option<DataChunk>> {
+        // skip chunks of other partitions
+        if let Some((index, count)) = self.partition {
+            while self.cnt < self.chunks.len() && self.cnt % count != index {
+                self.row_cnt += self.chunks[self.cnt].cardinality();
+                self.cnt += 1;
+            }
+        }
         if self.cnt >= self.chunks.len() {
             Ok(None)
         } else {
@@ -9,7 +9,7 @@
 //! The in-memory engine provides snapshot isolation. In the current implementation,
 //! a snapshot (clone of all `DataChunk` references) will be created upon a
 //! transaction starts. Inside transaction, we buffer all writes until commit.
-//! We do not guarantee read-after-write consistency.
+//! Scans of a transaction see its own buffered writes.
 //!
 //! Things not supported for now:
 //! * deletion
@@ -20,11 +20,14 @@
 use std::collections::HashMap;
 use std::sync::{Arc, Mutex};
 
-use super::index::InMemoryIndexes;
+use itertools::Itertools;
+
+use super::index::{InMemoryIndexes, new_index};
 use super::{InMemoryIndex, Storage, StorageError, StorageResult, TracedStorageError};
-use crate::binder::IndexType;
+use crate::binder::{AlterTableOperation, IndexType};
 use crate::catalog::{
     ColumnCatalog, ColumnId, IndexId, RootCatalog, RootCatalogRef, SchemaId, TableId, TableRefId,
+    UserCatalog,
 };
 
 mod table;
@@ -43,7 +46,7 @@ pub use row_handler::InMemoryRowHandler;
 pub struct InMemoryStorage {
     catalog: RootCatalogRef,
     tables: Mutex<HashMap<TableRefId, InMemoryTable>>,
-    indexes: Mutex<InMemoryIndexes>,
+    indexes: InMemoryIndexes,
 }
 
 impl Default for InMemoryStorage {
@@ -57,7 +60,7 @@ impl InMemoryStorage {
         InMemoryStorage {
             catalog: Arc::new(RootCatalog::new()),
             tables: Mutex::new(HashMap::new()),
-            indexes: Mutex::new(InMemoryIndexes::new()),
+            indexes: InMemoryIndexes::new(),
         }
     }
 
@@ -120,10 +123,24 @@ impl Storage for InMemoryStorage {
             .unwrap()
             .remove(&table_id)
             .ok_or_else(|| TracedStorageError::not_found("table", table_id.table_id))?;
+        self.indexes.drop_table(table_id);
         self.catalog.drop_table(table_id);
         Ok(())
     }
 
+    async fn alter_table(
+        &self,
+        table_id: TableRefId,
+        operation: &AlterTableOperation,
+    ) -> StorageResult<()> {
+        let table = self.get_table(table_id)?;
+        let mut inner = table.inner.write().unwrap();
+        self.catalog.alter_table(table_id, operation)?;
+        let columns = self.catalog.get_table(&table_id).unwrap().all_columns();
+        inner.alter(&columns.into_values().collect_vec())?;
+        Ok(())
+    }
+
     fn as_disk(&self) -> Option<&super::SecondaryStorage> {
         None
     }
@@ -146,11 +163,15 @@ impl Storage for InMemoryStorage {
                 index_type,
             )
             .map_err(|_| StorageError::Duplicated("index", index_name.into()))?;
-        self.indexes
-            .lock()
-            .unwrap()
-            .add_index(schema_id, idx_id, table_id, column_idxs);
-        // TODO: populate the index
+        let index = new_index(index_type);
+        let table = self.get_table(TableRefId::new(schema_id, table_id))?;
+        let mut inner = table.inner.write().unwrap();
+        if !inner.columns().iter().any(|c| c.id() == column_idxs[0]) {
+            return Err(StorageError::InvalidColumn(column_idxs[0]).into());
+        }
+        inner.add_index(column_idxs[0], index.clone());
+        drop(inner);
+        self.indexes.add_index(schema_id, idx_id, table_id, index);
         Ok(idx_id)
     }
 
@@ -161,13 +182,24 @@ impl Storage for InMemoryStorage {
     ) -> StorageResult<Arc<dyn InMemoryIndex>> {
         let idx = self
             .indexes
-            .lock()
-            .unwrap()
             .get_index(schema_id, index_id)
             .ok_or_else(|| StorageError::NotFound("index", index_id.to_string()))?;
         Ok(idx)
     }
 
+    async fn create_user(&self, user: UserCatalog) -> StorageResult<()> {
+        let name = user.name().to_string();
+        self.catalog
+            .add_user(user)
+            .map_err(|_| TracedStorageError::duplicated("user", name))
+    }
+
+    async fn drop_user(&self, name: &str) -> StorageResult<()> {
+        self.catalog
+            .drop_user(name)
+            .map_err(|_| TracedStorageError::not_found("user", name))
+    }
+
     fn get_catalog(&self) -> Arc<RootCatalog> {
         self.catalog.clone()
     }
@@ -4,50 +4,147 @@ use std::collections::HashSet;
 use std::sync::{Arc, RwLock};
 use std::vec::Vec;
 
+use itertools::Itertools;
+
 use super::*;
-use crate::array::DataChunk;
+use crate::array::{ArrayBuilderImpl, DataChunk};
 use crate::catalog::TableRefId;
-use crate::storage::Table;
+use crate::storage::{Table, evolve_chunk};
+use crate::types::DataValue;
 
 /// A table in in-memory engine. This struct can be freely cloned, as it
 /// only serves as a reference to a table.
 #[derive(Clone)]
 pub struct InMemoryTable {
     pub(super) table_ref_id: TableRefId,
-    pub(super) columns: Arc<[ColumnCatalog]>,
     pub(super) inner: InMemoryTableInnerRef,
     pub(super) ordered_pk_ids: Vec<ColumnId>,
 }
 
 pub(super) struct InMemoryTableInner {
+    /// All columns of the table. Chunks are always stored with the current schema.
+    columns: Arc<[ColumnCatalog]>,
     chunks: Vec<DataChunk>,
     deleted_rows: HashSet<usize>,
+    /// Total number of rows in `chunks`.
+    row_count: usize,
+    /// Indexes on the table, together with the id of the indexed column.
+    indexes: Vec<(ColumnId, Arc<dyn InMemoryIndex>)>,
 }
 
 pub(super) type InMemoryTableInnerRef = Arc<RwLock<InMemoryTableInner>>;
 
 impl InMemoryTableInner {
-    pub fn new() -> Self {
+    pub fn new(columns: &[ColumnCatalog]) -> Self {
         Self {
+            columns: columns.into(),
             chunks: vec![],
             deleted_rows: HashSet::new(),
+            row_count: 0,
+            indexes: vec![],
         }
     }
 
     pub fn append(&mut self, chunk: DataChunk) -> Result<(), StorageError> {
+        let row_ids = (self.row_count..self.row_count + chunk.cardinality())
+            .map(|id| id as i64)
+            .collect_vec();
+        for (column_id, index) in &self.indexes {
+            index.insert(chunk.array_at(self.column_position(*column_id)), &row_ids);
+        }
+        self.row_count += chunk.cardinality();
         self.chunks.push(chunk);
         Ok(())
     }
 
     pub fn delete(&mut self, row_id: usize) -> Result<(), StorageError> {
-        self.deleted_rows.insert(row_id);
+        if self.deleted_rows.insert(row_id) {
+            for (_, index) in &self.indexes {
+                index.delete(&[row_id as i64]);
+            }
+        }
+        Ok(())
+    }
+
+    /// Populates the index with existing rows, and maintains it on later changes.
+    pub fn add_index(&mut self, column_id: ColumnId, index: Arc<dyn InMemoryIndex>) {
+        let column = self.column_position(column_id);
+        let mut start = 0;
+        for chunk in &self.chunks {
+            let row_ids = (start..start + chunk.cardinality() as i64).collect_vec();
+            index.insert(chunk.array_at(column), &row_ids);
+            start += chunk.cardinality() as i64;
+        }
+        let deleted = self.deleted_rows.iter().map(|id| *id as i64).collect_vec();
+        index.delete(&deleted);
+        self.indexes.push((column_id, index));
+    }
+
+    /// Changes the schema of the table, converting all chunks to the new schema.
+    pub fn alter(&mut self, columns: &[ColumnCatalog]) -> StorageResult<()> {
+        let mut start = 0;
+        let mut chunks = Vec::with_capacity(self.chunks.len());
+        for chunk in &self.chunks {
+            let deleted = (start..start + chunk.cardinality())
+                .map(|id| self.deleted_rows.contains(&id))
+                .collect_vec();
+            start += chunk.cardinality();
+            let chunk = if deleted.contains(&true) {
+                &self.clear_deleted(chunk, &deleted, columns)
+            } else {
+                chunk
+            };
+            chunks.push(evolve_chunk(chunk, &self.columns, columns)?);
+        }
+        self.chunks = chunks;
+        self.columns = columns.into();
         Ok(())
     }
 
+    /// Sets deleted rows to null in the columns whose type is changed, so that they are not casted.
+    fn clear_deleted(
+        &self,
+        chunk: &DataChunk,
+        deleted: &[bool],
+        columns: &[ColumnCatalog],
+    ) -> DataChunk {
+        let arrays = self.columns.iter().zip(chunk.arrays()).map(|(old, array)| {
+            let changed =
+                (columns.iter()).any(|c| c.id() == old.id() && c.data_type() != old.data_type());
+            if !changed {
+                return array.clone();
+            }
+            let mut builder = ArrayBuilderImpl::with_capacity(array.len(), &old.data_type());
+            for (i, deleted) in deleted.iter().enumerate() {
+                match deleted {
+                    true => builder.push(&DataValue::Null),
+                    false => builder.push(&array.get(i)),
+                }
+            }
+            builder.finish()
+        });
+        DataChunk::from_iter(arrays)
+    }
+
+    pub fn columns(&self) -> Arc<[ColumnCatalog]> {
+        self.columns.clone()
+    }
+
+    /// Returns the position of a column in chunks.
+    pub fn column_position(&self, column_id: ColumnId) -> usize {
+        (self.columns.iter())
+            .position(|c| c.id() == column_id)
+            .expect("column not found")
+    }
+
     pub fn get_all_chunks(&self) -> Vec<DataChunk> {
         self.chunks.clone()
     }
 
+    pub fn is_deleted(&self, row_id: usize) -> bool {
+        self.deleted_rows.contains(&row_id)
+    }
+
     pub fn get_all_deleted_rows(&self) -> HashSet<usize> {
         self.deleted_rows.clone()
     }
@@ -57,8 +154,7 @@ impl InMemoryTable {
     pub fn new(table_ref_id: TableRefId, columns: &[ColumnCatalog]) -> Self {
         Self {
             table_ref_id,
-            columns: columns.into(),
-            inner: Arc::new(RwLock::new(InMemoryTableInner::new())),
+            inner: Arc::new(RwLock::new(InMemoryTableInner::new(columns))),
             ordered_pk_ids: Vec::new(),
         }
     }
@@ -68,7 +164,7 @@ impl Table for InMemoryTable {
     type Transaction = InMemoryTransaction;
 
     fn columns(&self) -> StorageResult<Arc<[ColumnCatalog]>> {
-        Ok(self.columns.clone())
+        Ok(self.inner.read().unwrap().columns())
     }
 
     fn table_id(&self) -> TableRefId {
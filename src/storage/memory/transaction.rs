@@ -5,10 +5,15 @@ use std::sync::Arc;
 
 use itertools::Itertools;
 
-use super::table::InMemoryTableInnerRef;
+use super::table::{InMemoryTableInner, InMemoryTableInnerRef};
 use super::{InMemoryRowHandler, InMemoryTable, InMemoryTxnIterator};
 use crate::array::{ArrayBuilderImpl, ArrayImplBuilderPickExt, DataChunk};
-use crate::storage::{ScanOptions, StorageColumnRef, StorageResult, Table, Transaction};
+use crate::catalog::{ColumnCatalog, ColumnId};
+use crate::storage::{
+    ScanOptions, StorageColumnRef, StorageResult, Table, TracedStorageError, Transaction,
+    evolve_chunk,
+};
+use crate::types::{DataType, DataValue};
 
 /// A transaction running on `InMemoryStorage`.
 pub struct InMemoryTransaction {
@@ -20,8 +25,10 @@ pub struct InMemoryTransaction {
     /// Includes all to-be-committed data.
     buffer: Vec<DataChunk>,
 
-    /// All rows to be deleted
-    delete_buffer: Vec<usize>,
+    /// All rows to be deleted.
+    ///
+    /// Rows in `buffer` are numbered after the rows in `snapshot`.
+    delete_buffer: HashSet<usize>,
 
     /// When transaction is started, reference to all data chunks will
     /// be cached in `snapshot` to provide snapshot isolation.
@@ -35,17 +42,20 @@ pub struct InMemoryTransaction {
 
     /// Ordered primary key indexes in `column_infos`
     ordered_pk_idx: Vec<usize>,
+
+    /// All columns of the table when the transaction started.
+    columns: Arc<[ColumnCatalog]>,
 }
 
 impl InMemoryTransaction {
     pub(super) fn start(table: &InMemoryTable) -> StorageResult<Self> {
         let inner = table.inner.read().unwrap();
+        let columns = inner.columns();
         let ordered_pk_idx = table
             .ordered_pk_ids()
             .iter()
             .map(|id| {
-                table
-                    .columns
+                columns
                     .iter()
                     .position(|c| c.id() == *id)
                     .expect("Malformed table object")
@@ -54,13 +64,80 @@ impl InMemoryTransaction {
         Ok(Self {
             finished: false,
             buffer: vec![],
-            delete_buffer: vec![],
+            delete_buffer: HashSet::new(),
             table: table.inner.clone(),
             snapshot: Arc::new(inner.get_all_chunks()),
             deleted_rows: Arc::new(inner.get_all_deleted_rows()),
             ordered_pk_idx,
+            columns,
         })
     }
+
+    /// Returns the position of a column in chunks.
+    fn column_position(&self, column_id: ColumnId) -> usize {
+        (self.columns.iter())
+            .position(|c| c.id() == column_id)
+            .expect("column not found")
+    }
+
+    /// Resolves column ids to positions in chunks.
+    fn resolve_columns(&self, col_idx: &[StorageColumnRef]) -> Vec<StorageColumnRef> {
+        (col_idx.iter())
+            .map(|idx| match idx {
+                StorageColumnRef::Idx(id) => {
+                    StorageColumnRef::Idx(self.column_position(*id) as u32)
+                }
+                StorageColumnRef::RowHandler => StorageColumnRef::RowHandler,
+            })
+            .collect()
+    }
+
+    /// Returns a conflict error if a row deleted by the transaction has been deleted by another
+    /// transaction since the snapshot, so that the first committer wins.
+    fn check_conflict(&self, table: &InMemoryTableInner) -> StorageResult<()> {
+        let snapshot_row_count = self.snapshot_row_count();
+        if let Some(row_id) = self.delete_buffer.iter().find(|id| {
+            **id < snapshot_row_count && !self.deleted_rows.contains(id) && table.is_deleted(**id)
+        }) {
+            return Err(TracedStorageError::conflict(format!(
+                "row {row_id} has been deleted"
+            )));
+        }
+        Ok(())
+    }
+
+    /// Applies the changes of the transaction to the locked `table`.
+    fn apply(&mut self, table: &mut InMemoryTableInner) -> StorageResult<()> {
+        let snapshot_row_count = self.snapshot_row_count();
+        // the schema may have been changed since the transaction started
+        let columns = table.columns();
+        // rows appended and then deleted in this transaction are never committed
+        let mut row_id = snapshot_row_count;
+        for chunk in self.buffer.drain(..) {
+            let visibility = (row_id..row_id + chunk.cardinality())
+                .map(|id| !self.delete_buffer.contains(&id))
+                .collect_vec();
+            row_id += chunk.cardinality();
+            let mut chunk = chunk.filter(&visibility);
+            if !Arc::ptr_eq(&columns, &self.columns) {
+                chunk = evolve_chunk(&chunk, &self.columns, &columns)?;
+            }
+            table.append(chunk)?;
+        }
+        for deletion in self.delete_buffer.drain() {
+            if deletion < snapshot_row_count {
+                table.delete(deletion)?;
+            }
+        }
+
+        self.finished = true;
+        Ok(())
+    }
+
+    /// Returns the number of rows in the snapshot.
+    fn snapshot_row_count(&self) -> usize {
+        self.snapshot.iter().map(|chunk| chunk.cardinality()).sum()
+    }
 }
 
 /// If primary key is found in [`ColumnCatalog`], sort all in-memory data using that key.
@@ -126,39 +203,145 @@ impl Transaction for InMemoryTransaction {
         assert!(opts.filter.is_none(), "MemTxn doesn't support filter scan");
         assert!(!opts.reversed, "reverse iterator is not supported for now");
 
-        let snapshot = if opts.is_sorted {
-            sort_datachunk_by_pk(&self.snapshot, &self.ordered_pk_idx)
-        } else {
+        // see the writes of this transaction
+        let chunks = if self.buffer.is_empty() {
             self.snapshot.clone()
+        } else {
+            Arc::new(self.snapshot.iter().chain(&self.buffer).cloned().collect())
+        };
+        let deleted_rows = if self.delete_buffer.is_empty() {
+            self.deleted_rows.clone()
+        } else {
+            Arc::new(
+                self.deleted_rows
+                    .union(&self.delete_buffer)
+                    .copied()
+                    .collect(),
+            )
+        };
+        let chunks = if opts.is_sorted {
+            sort_datachunk_by_pk(&chunks, &self.ordered_pk_idx)
+        } else {
+            chunks
         };
 
         Ok(InMemoryTxnIterator::new(
-            snapshot,
-            self.deleted_rows.clone(),
-            col_idx,
+            chunks,
+            deleted_rows,
+            &self.resolve_columns(col_idx),
+            opts.partition,
         ))
     }
 
+    async fn fetch(
+        &self,
+        col_idx: &[StorageColumnRef],
+        ids: &[Self::RowHandlerType],
+    ) -> StorageResult<DataChunk> {
+        // see the writes of this transaction
+        let chunks = self.snapshot.iter().chain(&self.buffer).collect_vec();
+        // the first row id of each chunk
+        let mut offsets = Vec::with_capacity(chunks.len());
+        let mut row_count = 0;
+        for chunk in &chunks {
+            offsets.push(row_count);
+            row_count += chunk.cardinality();
+        }
+        let rows = (ids.iter())
+            .map(|id| id.0 as usize)
+            .filter(|id| {
+                *id < row_count
+                    && !self.deleted_rows.contains(id)
+                    && !self.delete_buffer.contains(id)
+            })
+            .collect_vec();
+        if col_idx.is_empty() {
+            return Ok(DataChunk::no_column(rows.len()));
+        }
+        let col_idx = self.resolve_columns(col_idx);
+        let mut builders = col_idx
+            .iter()
+            .map(|idx| match idx {
+                StorageColumnRef::Idx(idx) => {
+                    ArrayBuilderImpl::new(&self.columns[*idx as usize].data_type())
+                }
+                StorageColumnRef::RowHandler => ArrayBuilderImpl::new(&DataType::Int64),
+            })
+            .collect_vec();
+        for row in rows {
+            let chunk_idx = offsets.partition_point(|offset| *offset <= row) - 1;
+            let chunk = chunks[chunk_idx];
+            let pos = row - offsets[chunk_idx];
+            for (idx, builder) in col_idx.iter().zip(builders.iter_mut()) {
+                match idx {
+                    StorageColumnRef::Idx(idx) => {
+                        builder.push(&chunk.array_at(*idx as usize).get(pos))
+                    }
+                    StorageColumnRef::RowHandler => builder.push(&DataValue::Int64(row as i64)),
+                }
+            }
+        }
+        Ok(builders.into_iter().map(|b| b.finish()).collect())
+    }
+
     async fn append(&mut self, columns: DataChunk) -> StorageResult<()> {
         self.buffer.push(columns);
         Ok(())
     }
 
     async fn delete(&mut self, id: &Self::RowHandlerType) -> StorageResult<()> {
-        self.delete_buffer.push(id.0 as usize);
+        self.delete_buffer.insert(id.0 as usize);
         Ok(())
     }
 
-    async fn commit(mut self) -> StorageResult<()> {
-        let mut table = self.table.write().unwrap();
-        for chunk in self.buffer.drain(..) {
-            table.append(chunk)?;
+    async fn update(
+        &mut self,
+        ids: &[Self::RowHandlerType],
+        columns: DataChunk,
+    ) -> StorageResult<()> {
+        self.delete_buffer
+            .extend(ids.iter().map(|id| id.0 as usize));
+        self.buffer.push(columns);
+        Ok(())
+    }
+
+    fn set_writable(&mut self) {
+        // all transactions are writable
+    }
+
+    async fn flush(&mut self) -> StorageResult<()> {
+        // buffered chunks are always visible to scans
+        Ok(())
+    }
+
+    async fn commit(self) -> StorageResult<()> {
+        Self::commit_all(vec![self]).await
+    }
+
+    async fn commit_all(mut txns: Vec<Self>) -> StorageResult<()> {
+        // Lock all tables before applying any change, so that no one sees a part of the changes.
+        // Tables are locked in the order of their addresses to avoid deadlocks.
+        txns.sort_by_key(|txn| Arc::as_ptr(&txn.table));
+        let tables = (txns.iter())
+            .map(|txn| &txn.table)
+            .dedup_by(|a, b| Arc::ptr_eq(a, b))
+            .cloned()
+            .collect_vec();
+        let mut guards = (tables.iter())
+            .map(|table| table.write().unwrap())
+            .collect_vec();
+        let position = |txn: &Self| {
+            (tables.iter())
+                .position(|table| Arc::ptr_eq(table, &txn.table))
+                .unwrap()
+        };
+        for txn in &txns {
+            txn.check_conflict(&guards[position(txn)])?;
         }
-        for deletion in self.delete_buffer.drain(..) {
-            table.delete(deletion)?;
+        for txn in &mut txns {
+            let i = position(txn);
+            txn.apply(&mut guards[i])?;
         }
-
-        self.finished = true;
         Ok(())
     }
 
@@ -167,3 +350,76 @@ impl Transaction for InMemoryTransaction {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::array::ArrayImpl;
+    use crate::catalog::{ColumnDesc, TableRefId};
+    use crate::storage::TxnIterator;
+
+    #[tokio::test]
+    async fn fetch_own_writes() {
+        let columns = [ColumnCatalog::new(
+            0,
+            ColumnDesc::new("a", DataType::Int32, false),
+        )];
+        let table = InMemoryTable::new(TableRefId::new(0, 0), &columns);
+        let append = |values: &[i32]| {
+            DataChunk::from_iter([ArrayImpl::new_int32(
+                values.iter().map(|v| Some(*v)).collect(),
+            )])
+        };
+        let mut txn = table.write().await.unwrap();
+        txn.append(append(&[1, 2])).await.unwrap();
+        txn.commit().await.unwrap();
+
+        // rows 2 and 3 are only in the write buffer
+        let mut txn = table.write().await.unwrap();
+        txn.append(append(&[3, 4])).await.unwrap();
+        txn.delete(&InMemoryRowHandler(0)).await.unwrap();
+        let ids = [0, 1, 3].map(InMemoryRowHandler);
+        let chunk = txn.fetch(&[StorageColumnRef::Idx(0)], &ids).await.unwrap();
+        let values = chunk.array_at(0).iter().collect_vec();
+        assert_eq!(values, [DataValue::Int32(2), DataValue::Int32(4)]);
+        txn.abort().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn concurrent_updates_conflict() {
+        let columns = [ColumnCatalog::new(
+            0,
+            ColumnDesc::new("a", DataType::Int32, false),
+        )];
+        let table = InMemoryTable::new(TableRefId::new(0, 0), &columns);
+        let chunk =
+            |v: i32| DataChunk::from_iter([ArrayImpl::new_int32([Some(v)].into_iter().collect())]);
+        let mut txn = table.write().await.unwrap();
+        txn.append(chunk(1)).await.unwrap();
+        txn.commit().await.unwrap();
+
+        // both transactions update row 0, the later committer fails
+        let mut txn1 = table.update().await.unwrap();
+        let mut txn2 = table.update().await.unwrap();
+        txn1.update(&[InMemoryRowHandler(0)], chunk(2))
+            .await
+            .unwrap();
+        txn2.update(&[InMemoryRowHandler(0)], chunk(3))
+            .await
+            .unwrap();
+        txn1.commit().await.unwrap();
+        assert!(txn2.commit().await.unwrap_err().is_conflict());
+
+        let txn = table.read().await.unwrap();
+        let mut iter = (txn.scan(&[StorageColumnRef::Idx(0)], ScanOptions::default()))
+            .await
+            .unwrap();
+        let mut values = vec![];
+        while let Some(chunk) = iter.next_batch(None).await.unwrap() {
+            values.extend(chunk.array_at(0).iter());
+        }
+        assert_eq!(values, [DataValue::Int32(2)]);
+        drop(iter);
+        txn.abort().await.unwrap();
+    }
+}
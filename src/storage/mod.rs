@@ -24,9 +24,9 @@ pub use chunk::*;
 use enum_dispatch::enum_dispatch;
 
 use crate::array::{ArrayImpl, DataChunk};
-use crate::binder::IndexType;
+use crate::binder::{AlterTableOperation, IndexType};
 use crate::catalog::{
-    ColumnCatalog, ColumnId, IndexId, RootCatalog, SchemaId, TableId, TableRefId,
+    ColumnCatalog, ColumnId, IndexId, RootCatalog, SchemaId, TableId, TableRefId, UserCatalog,
 };
 use crate::types::DataValue;
 
@@ -88,6 +88,16 @@ pub trait Storage: Sync + Send + 'static {
 
     fn drop_table(&self, table_id: TableRefId) -> impl Future<Output = StorageResult<()>> + Send;
 
+    /// Changes the schema of a table.
+    ///
+    /// Existing data is not rewritten, but read with the new schema: dropped columns are skipped,
+    /// added columns take their default values, and columns of changed types are casted.
+    fn alter_table(
+        &self,
+        table_id: TableRefId,
+        operation: &AlterTableOperation,
+    ) -> impl Future<Output = StorageResult<()>> + Send;
+
     fn create_index(
         &self,
         schema_id: SchemaId,
@@ -97,6 +107,12 @@ pub trait Storage: Sync + Send + 'static {
         index_type: &IndexType,
     ) -> impl Future<Output = StorageResult<IndexId>> + Send;
 
+    /// Adds a user who can log in to the server.
+    fn create_user(&self, user: UserCatalog) -> impl Future<Output = StorageResult<()>> + Send;
+
+    /// Drops a user by name.
+    fn drop_user(&self, name: &str) -> impl Future<Output = StorageResult<()>> + Send;
+
     /// Get the catalog of the storage engine.
     ///
     /// TODO: users should not be able to modify the catalog.
@@ -143,9 +159,8 @@ pub enum StorageColumnRef {
     /// A runtime column which contains necessary information to locate a row
     /// **only valid in the current transaction**.
     RowHandler,
-    /// User column index. Note that this index is NOT the `ColumnId` in catalog. It is the storage
-    /// column id, which is the same as the position of a column in the column catalog passed to a
-    /// RowSet.
+    /// User column, referred by its `ColumnId` in catalog. The id of a column never changes, while
+    /// its position in the table does if a column before it is dropped.
     Idx(u32),
 }
 
@@ -171,6 +186,15 @@ pub trait Transaction: Sync + Send + 'static {
         options: ScanOptions,
     ) -> impl Future<Output = StorageResult<Self::TxnIteratorType>> + Send;
 
+    /// Fetch rows by their row handlers, in the order of `ids`.
+    ///
+    /// Rows that are not visible in the transaction are skipped.
+    fn fetch(
+        &self,
+        col_idx: &[StorageColumnRef],
+        ids: &[Self::RowHandlerType],
+    ) -> impl Future<Output = StorageResult<DataChunk>> + Send;
+
     /// Append data to the table. Generally, `columns` should be in the same order as
     /// [`ColumnCatalog`] when constructing the [`Table`].
     fn append(&mut self, columns: DataChunk) -> impl Future<Output = StorageResult<()>> + Send;
@@ -181,9 +205,30 @@ pub trait Transaction: Sync + Send + 'static {
         id: &Self::RowHandlerType,
     ) -> impl Future<Output = StorageResult<()>> + Send;
 
+    /// Update records. The old versions referred by `ids` are deleted, and the new versions in
+    /// `columns` are appended to the table. Both take effect when the transaction commits.
+    fn update(
+        &mut self,
+        ids: &[Self::RowHandlerType],
+        columns: DataChunk,
+    ) -> impl Future<Output = StorageResult<()>> + Send;
+
+    /// Allows a transaction started by [`Table::read`] to write. It keeps reading the same
+    /// snapshot.
+    fn set_writable(&mut self);
+
+    /// Make the data appended so far visible to later scans of this transaction.
+    fn flush(&mut self) -> impl Future<Output = StorageResult<()>> + Send;
+
     /// Commit a transaction.
     fn commit(self) -> impl Future<Output = StorageResult<()>> + Send;
 
+    /// Commit transactions on multiple tables atomically: either all of them take effect, or none
+    /// of them does.
+    fn commit_all(txns: Vec<Self>) -> impl Future<Output = StorageResult<()>> + Send
+    where
+        Self: Sized;
+
     /// Abort a transaction.
     fn abort(self) -> impl Future<Output = StorageResult<()>> + Send;
 }
@@ -194,6 +239,8 @@ pub struct ScanOptions {
     is_sorted: bool,
     reversed: bool,
     filter: Option<KeyRange>,
+    /// Scan only the `index`-th of `count` partitions of the table.
+    partition: Option<(usize, usize)>,
 }
 
 impl ScanOptions {
@@ -207,6 +254,15 @@ impl ScanOptions {
         self.is_sorted = sorted;
         self
     }
+
+    /// Scan only the `index`-th of `count` partitions.
+    ///
+    /// Partitions are disjoint and together cover the whole table.
+    pub fn with_partition(mut self, index: usize, count: usize) -> Self {
+        assert!(index < count, "partition index out of range");
+        self.partition = Some((index, count));
+        self
+    }
 }
 
 /// A range of keys.
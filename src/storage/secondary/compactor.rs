@@ -1,5 +1,6 @@
 // Copyright 2024 RisingLight Project Authors. Licensed under Apache-2.0.
 
+use std::collections::HashSet;
 use std::sync::Arc;
 use std::time::Duration;
 
@@ -8,7 +9,8 @@ use risinglight_proto::rowset::block_statistics::BlockStatisticsType;
 use tokio::sync::oneshot::Receiver;
 use tracing::{info, warn};
 
-use super::{SecondaryStorage, SecondaryTable, Snapshot};
+use super::{SecondaryStorage, SecondaryTable, Snapshot, TransactionManager, build_index_data};
+use crate::array::{Array, ArrayImpl};
 use crate::catalog::find_sort_key_id;
 use crate::storage::secondary::column::ColumnSeekPosition;
 use crate::storage::secondary::concat_iterator::ConcatIterator;
@@ -56,12 +58,17 @@ impl Compactor {
             return Ok(());
         }
 
+        // Hold the read lock of indexes, so that the index data of the new rowset is built for
+        // all indexes.
+        let indexes = table.indexes.clone();
+        let indexes = indexes.read().await;
+
         // sort RowSets by id so that the output RowSet will have old rows in the front and new rows
         // at the end.
         selected_rowsets.sort_by_key(|x| x.rowset_id());
 
-        let column_refs: Arc<[StorageColumnRef]> = (0..table.columns.len())
-            .map(|idx| StorageColumnRef::Idx(idx as u32))
+        let column_refs: Arc<[StorageColumnRef]> = (table.columns.iter())
+            .map(|column| StorageColumnRef::Idx(column.id()))
             .collect_vec()
             .into();
         let mut iters = vec![];
@@ -77,7 +84,13 @@ impl Compactor {
 
             iters.push(
                 rowset
-                    .iter(column_refs.clone(), dvs, ColumnSeekPosition::start(), None)
+                    .iter_with_schema(
+                        &table.columns,
+                        column_refs.clone(),
+                        dvs,
+                        ColumnSeekPosition::start(),
+                        None,
+                    )
                     .await?,
             );
         }
@@ -139,6 +152,7 @@ impl Compactor {
         let rowset = builder.finish();
 
         let mut changes: Vec<EpochOp> = vec![];
+        let mut index_data = vec![];
 
         // If the row sets are not empty, add a new rowset.
         let rowset_id: Option<u32> = if rowset.is_empty() {
@@ -154,20 +168,28 @@ impl Compactor {
             writer.create_dir().await?;
             writer.flush(rowset).await?;
 
-            let rowset = DiskRowset::open(
-                directory,
-                table.columns.clone(),
-                self.storage.block_cache.clone(),
-                rowset_id,
-                self.storage.options.io_backend.clone(),
-            )
-            .await?;
+            let rowset = Arc::new(
+                DiskRowset::open(
+                    directory,
+                    table.columns.clone(),
+                    self.storage.block_cache.clone(),
+                    rowset_id,
+                    self.storage.options.io_backend.clone(),
+                )
+                .await?,
+            );
+
+            // Rebuild index data, as row handlers are changed by compaction.
+            if !indexes.is_empty() {
+                index_data = build_index_data(&table, &rowset, &indexes).await?;
+            }
 
             // Add RowSets
             let add_rowset_op = EpochOp::AddRowSet((
                 AddRowSetEntry {
                     rowset_id: rowset.rowset_id(),
                     table_id: table.table_ref_id,
+                    columns: table.columns.to_vec(),
                 },
                 rowset,
             ));
@@ -184,8 +206,56 @@ impl Compactor {
             })
         }));
 
+        // Give up if rows of the selected RowSets were deleted after the snapshot was taken, as
+        // the deletions would be lost. The new RowSet is vacuumed on the next start.
+        let _commit_lock = self.storage.txn_mgr.lock_for_commit(table.table_id()).await;
+        for rowset in &selected_rowsets {
+            let deleted = TransactionManager::deleted_since(
+                &self.storage.version,
+                snapshot,
+                table.table_id(),
+                rowset.rowset_id(),
+            );
+            if deleted.is_none_or(|deleted| !deleted.is_empty()) {
+                info!(
+                    "compaction given up: RowSet #{} changed",
+                    rowset.rowset_id()
+                );
+                return Ok(());
+            }
+        }
+
         self.storage.version.commit_changes(changes).await?;
 
+        // Replace the rows of old RowSets with the new one in indexes.
+        if !indexes.is_empty() {
+            if let Some(rowset_id) = rowset_id {
+                for (index, data) in indexes.iter().zip(index_data) {
+                    data.insert_into(&*index.index, rowset_id, &HashSet::new());
+                }
+            }
+            let mut old_rows = vec![];
+            for rowset in &selected_rowsets {
+                let mut iter = rowset
+                    .iter(
+                        [StorageColumnRef::RowHandler].into(),
+                        vec![],
+                        ColumnSeekPosition::start(),
+                        None,
+                    )
+                    .await?;
+                while let Some(chunk) = iter.next_batch(None).await? {
+                    let ArrayImpl::Int64(handlers) = chunk.array_at(0) else {
+                        panic!("invalid row handler column");
+                    };
+                    old_rows.extend(handlers.raw_iter().copied());
+                }
+            }
+            for index in indexes.iter() {
+                index.index.delete(&old_rows);
+            }
+        }
+
         match rowset_id {
             Some(rowset_id) => {
                 info!(
@@ -211,12 +281,7 @@ impl Compactor {
                 let tables = self.storage.tables.read().clone();
                 let pin_version = self.storage.version.pin();
                 for (_, table) in tables {
-                    if let Some(_guard) = self
-                        .storage
-                        .txn_mgr
-                        .try_lock_for_compaction(table.table_id())
-                        && let Err(err) = self.compact_table(&pin_version.snapshot, table).await
-                    {
+                    if let Err(err) = self.compact_table(&pin_version.snapshot, table).await {
                         warn!("failed to compact: {:?}", err);
                     }
                 }
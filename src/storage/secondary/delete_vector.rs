@@ -75,6 +75,11 @@ impl DeleteVector {
         self.rowset_id
     }
 
+    /// Returns the sorted ids of deleted rows.
+    pub fn deletes(&self) -> &[u32] {
+        &self.deletes
+    }
+
     /// Apply the current DV info to a visibility bitmap
     pub fn apply_to(&self, data: &mut BitVec, offset_row_id: u32) {
         let pos = self.deletes.partition_point(|x| *x < offset_row_id);
@@ -10,6 +10,7 @@
 use std::io::SeekFrom;
 use std::path::Path;
 
+use itertools::Itertools;
 use serde::{Deserialize, Serialize};
 use serde_json::Deserializer;
 use tokio::fs::OpenOptions;
@@ -17,8 +18,12 @@ use tokio::io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt, BufReader};
 use tracing::warn;
 
 use super::version_manager::EpochOp;
-use super::{SecondaryStorage, SecondaryTable, StorageResult, TracedStorageError};
-use crate::catalog::{ColumnCatalog, ColumnId, SchemaId, TableRefId};
+use super::{
+    SecondaryStorage, SecondaryTable, StorageError, StorageResult, TableIndex, TracedStorageError,
+};
+use crate::binder::{AlterTableOperation, IndexType};
+use crate::catalog::{ColumnCatalog, ColumnId, IndexId, SchemaId, TableRefId, UserCatalog};
+use crate::storage::index::new_index;
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct CreateTableEntry {
@@ -33,10 +38,40 @@ pub struct DropTableEntry {
     pub table_id: TableRefId,
 }
 
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CreateIndexEntry {
+    pub table_id: TableRefId,
+    pub index_name: String,
+    pub column_idxs: Vec<ColumnId>,
+    pub index_type: IndexType,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct AlterTableEntry {
+    pub table_id: TableRefId,
+    pub operation: AlterTableOperation,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CreateUserEntry {
+    pub user: UserCatalog,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct DropUserEntry {
+    pub name: String,
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct AddRowSetEntry {
     pub table_id: TableRefId,
     pub rowset_id: u32,
+    /// Columns of the table when the rowset was written.
+    ///
+    /// Empty for rowsets written before schema changes were supported, whose columns are the
+    /// current ones of the table.
+    #[serde(default)]
+    pub columns: Vec<ColumnCatalog>,
 }
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct DeleteRowsetEntry {
@@ -62,6 +97,10 @@ pub struct DeleteDVEntry {
 pub enum ManifestOperation {
     CreateTable(CreateTableEntry),
     DropTable(DropTableEntry),
+    CreateIndex(CreateIndexEntry),
+    AlterTable(AlterTableEntry),
+    CreateUser(CreateUserEntry),
+    DropUser(DropUserEntry),
     AddRowSet(AddRowSetEntry),
     DeleteRowSet(DeleteRowsetEntry),
     AddDV(AddDVEntry),
@@ -271,7 +310,128 @@ impl SecondaryStorage {
             .remove(&table_id)
             .ok_or_else(|| TracedStorageError::not_found("table", table_id.table_id))?;
         self.catalog.drop_table(table_id);
+        self.indexes.drop_table(table_id);
+
+        Ok(())
+    }
+
+    /// Adds the index to the catalog, and returns the table with the new empty index.
+    pub(super) fn apply_create_index(
+        &self,
+        entry: &CreateIndexEntry,
+    ) -> StorageResult<(SecondaryTable, TableIndex)> {
+        let CreateIndexEntry {
+            table_id,
+            index_name,
+            column_idxs,
+            index_type,
+        } = entry.clone();
+
+        let table = self.get_table_inner(table_id)?;
+        let column = column_idxs[0];
+        if !table.column_map.contains_key(&column) {
+            return Err(StorageError::InvalidColumn(column).into());
+        }
+        let index_id = self
+            .catalog
+            .add_index(
+                table_id.schema_id,
+                index_name.clone(),
+                table_id.table_id,
+                &column_idxs,
+                &index_type,
+            )
+            .map_err(|_| TracedStorageError::duplicated("index", index_name))?;
+        let index = TableIndex {
+            index_id,
+            column,
+            sorted: matches!(index_type, IndexType::Btree),
+            index: new_index(&index_type),
+        };
+        Ok((table, index))
+    }
+
+    pub(super) async fn create_index_inner(
+        &self,
+        entry: CreateIndexEntry,
+    ) -> StorageResult<IndexId> {
+        // contrary to create table, we first modify the catalog to get the index id
+        let (table, index) = self.apply_create_index(&entry)?;
+
+        // then build index data and persist to manifest
+        table.add_index(index.clone(), entry.clone()).await?;
+
+        self.indexes.add_index(
+            entry.table_id.schema_id,
+            index.index_id,
+            entry.table_id.table_id,
+            index.index,
+        );
+        Ok(index.index_id)
+    }
+
+    pub(super) fn apply_alter_table(&self, entry: &AlterTableEntry) -> StorageResult<()> {
+        let AlterTableEntry {
+            table_id,
+            operation,
+        } = entry;
+
+        let table = self.get_table_inner(*table_id)?;
+        self.catalog.alter_table(*table_id, operation)?;
+
+        // rowsets are not rewritten, but read with the new columns of the table
+        let columns = (self.catalog.get_table(table_id).unwrap().all_columns())
+            .into_values()
+            .collect_vec();
+        self.tables
+            .write()
+            .insert(*table_id, table.with_columns(&columns));
+        Ok(())
+    }
+
+    pub(super) async fn alter_table_inner(&self, entry: AlterTableEntry) -> StorageResult<()> {
+        // like drop table, we first modify the catalog, so that invalid changes are not persisted
+        self.apply_alter_table(&entry)?;
 
+        // and then persist to manifest
+        self.version
+            .commit_changes(vec![EpochOp::AlterTable(entry)])
+            .await?;
+        Ok(())
+    }
+
+    pub(super) fn apply_create_user(&self, entry: &CreateUserEntry) -> StorageResult<()> {
+        let name = entry.user.name().to_string();
+        self.catalog
+            .add_user(entry.user.clone())
+            .map_err(|_| TracedStorageError::duplicated("user", name))
+    }
+
+    pub(super) async fn create_user_inner(&self, entry: CreateUserEntry) -> StorageResult<()> {
+        if self.catalog.get_user(entry.user.name()).is_some() {
+            return Err(TracedStorageError::duplicated("user", entry.user.name()));
+        }
+
+        // persist to manifest first
+        self.version
+            .commit_changes(vec![EpochOp::CreateUser(entry.clone())])
+            .await?;
+
+        // then apply to catalog
+        self.apply_create_user(&entry)
+    }
+
+    pub(super) fn apply_drop_user(&self, entry: &DropUserEntry) -> StorageResult<()> {
+        self.catalog
+            .drop_user(&entry.name)
+            .map_err(|_| TracedStorageError::not_found("user", &entry.name))
+    }
+
+    pub(super) async fn drop_user_inner(&self, entry: DropUserEntry) -> StorageResult<()> {
+        self.apply_drop_user(&entry)?;
+        self.version
+            .commit_changes(vec![EpochOp::DropUser(entry)])
+            .await?;
         Ok(())
     }
 
@@ -315,3 +475,121 @@ impl SecondaryStorage {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::array::{ArrayImpl, DataChunk};
+    use crate::catalog::{ColumnDesc, RootCatalog};
+    use crate::storage::secondary::StorageOptions;
+    use crate::storage::{ScanOptions, Storage, StorageColumnRef, Table, Transaction, TxnIterator};
+    use crate::types::{DataType, DataValue};
+
+    #[tokio::test]
+    async fn persist_users() {
+        let tempdir = tempfile::tempdir().unwrap();
+        let options = StorageOptions {
+            path: tempdir.path().join("db"),
+            ..StorageOptions::default_for_cli()
+        };
+
+        let storage = SecondaryStorage::open(options.clone()).await.unwrap();
+        storage
+            .create_user(UserCatalog::new("alice", "secret"))
+            .await
+            .unwrap();
+        storage
+            .create_user(UserCatalog::new("bob", "secret"))
+            .await
+            .unwrap();
+        storage.drop_user("bob").await.unwrap();
+        drop(storage);
+
+        // open twice to check the compacted manifest
+        for _ in 0..2 {
+            let storage = SecondaryStorage::open(options.clone()).await.unwrap();
+            let users = storage.catalog().all_users();
+            assert_eq!(users.len(), 1);
+            assert!(users[0].check_password("secret"));
+        }
+    }
+
+    #[tokio::test]
+    async fn persist_alter_table() {
+        let tempdir = tempfile::tempdir().unwrap();
+        let options = StorageOptions {
+            path: tempdir.path().join("db"),
+            ..StorageOptions::default_for_cli()
+        };
+
+        let storage = SecondaryStorage::open(options.clone()).await.unwrap();
+        let schema_id = (storage.catalog())
+            .get_schema_id_by_name(RootCatalog::DEFAULT_SCHEMA_NAME)
+            .unwrap();
+        let columns = [
+            ColumnCatalog::new(0, ColumnDesc::new("a", DataType::Int32, false)),
+            ColumnCatalog::new(1, ColumnDesc::new("b", DataType::Int32, true)),
+        ];
+        storage
+            .create_table(schema_id, "t", &columns, &[])
+            .await
+            .unwrap();
+        let table_id = TableRefId::new(schema_id, 0);
+        let table = storage.get_table(table_id).unwrap();
+        let mut txn = table.write().await.unwrap();
+        txn.append(DataChunk::from_iter([
+            ArrayImpl::new_int32([Some(1), Some(2)].into_iter().collect()),
+            ArrayImpl::new_int32([Some(10), Some(20)].into_iter().collect()),
+        ]))
+        .await
+        .unwrap();
+        txn.commit().await.unwrap();
+
+        let mut c = ColumnCatalog::new(2, ColumnDesc::new("c", DataType::Int32, true));
+        c.set_default_value(Some(DataValue::Int32(7)));
+        for operation in [
+            AlterTableOperation::AddColumn(c),
+            AlterTableOperation::DropColumn(1),
+            AlterTableOperation::AlterColumnType {
+                column_id: 0,
+                data_type: DataType::Int64,
+            },
+        ] {
+            storage.alter_table(table_id, &operation).await.unwrap();
+        }
+        let table = storage.get_table(table_id).unwrap();
+        let mut txn = table.write().await.unwrap();
+        txn.append(DataChunk::from_iter([
+            ArrayImpl::new_int64([Some(3)].into_iter().collect()),
+            ArrayImpl::new_int32([Some(8)].into_iter().collect()),
+        ]))
+        .await
+        .unwrap();
+        txn.commit().await.unwrap();
+        drop(table);
+        drop(storage);
+
+        // open twice to check the compacted manifest
+        for _ in 0..2 {
+            let storage = SecondaryStorage::open(options.clone()).await.unwrap();
+            let table = storage.get_table(table_id).unwrap();
+            let names = table.columns.iter().map(|c| c.name().to_string());
+            assert_eq!(names.collect_vec(), ["a", "c"]);
+
+            let txn = table.read().await.unwrap();
+            let column_refs = [StorageColumnRef::Idx(0), StorageColumnRef::Idx(2)];
+            let mut iter = txn
+                .scan(&column_refs, ScanOptions::default())
+                .await
+                .unwrap();
+            let mut rows = vec![];
+            while let Some(chunk) = iter.next_batch(None).await.unwrap() {
+                rows.extend(chunk.rows().map(|row| row.values().collect_vec()));
+            }
+            txn.abort().await.unwrap();
+            rows.sort();
+            let row = |a, c| vec![DataValue::Int64(a), DataValue::Int32(c)];
+            assert_eq!(rows, [row(1, 7), row(2, 7), row(3, 8)]);
+        }
+    }
+}
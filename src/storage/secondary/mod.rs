@@ -23,6 +23,7 @@ use parking_lot::RwLock;
 pub use row_handler::*;
 use rowset::*;
 pub use table::*;
+use table_index::*;
 use tokio::sync::Mutex;
 use tokio::sync::oneshot::Sender;
 use tokio::task::JoinHandle;
@@ -34,9 +35,10 @@ use version_manager::*;
 
 use super::index::InMemoryIndexes;
 use super::{InMemoryIndex, Storage, StorageError, StorageResult, TracedStorageError};
-use crate::binder::IndexType;
+use crate::binder::{AlterTableOperation, IndexType};
 use crate::catalog::{
     ColumnCatalog, ColumnId, IndexId, RootCatalog, RootCatalogRef, SchemaId, TableId, TableRefId,
+    UserCatalog,
 };
 
 // public modules and structures
@@ -61,6 +63,7 @@ mod merge_iterator;
 mod rowset;
 mod statistics;
 mod storage;
+mod table_index;
 mod transaction_manager;
 mod version_manager;
 
@@ -104,7 +107,7 @@ pub struct SecondaryStorage {
     txn_mgr: Arc<TransactionManager>,
 
     /// Indexes of the current storage engine
-    indexes: Mutex<InMemoryIndexes>,
+    indexes: InMemoryIndexes,
 }
 
 impl SecondaryStorage {
@@ -191,6 +194,18 @@ impl Storage for SecondaryStorage {
         self.drop_table_inner(table_id).await
     }
 
+    async fn alter_table(
+        &self,
+        table_id: TableRefId,
+        operation: &AlterTableOperation,
+    ) -> StorageResult<()> {
+        let entry = AlterTableEntry {
+            table_id,
+            operation: operation.clone(),
+        };
+        self.alter_table_inner(entry).await
+    }
+
     fn as_disk(&self) -> Option<&SecondaryStorage> {
         Some(self)
     }
@@ -203,22 +218,13 @@ impl Storage for SecondaryStorage {
         column_idxs: &[ColumnId],
         index_type: &IndexType,
     ) -> StorageResult<IndexId> {
-        let idx_id = self
-            .catalog
-            .add_index(
-                schema_id,
-                index_name.to_string(),
-                table_id,
-                column_idxs,
-                index_type,
-            )
-            .map_err(|_| StorageError::Duplicated("index", index_name.into()))?;
-        self.indexes
-            .lock()
-            .await
-            .add_index(schema_id, idx_id, table_id, column_idxs);
-        // TODO: populate the index
-        Ok(idx_id)
+        let entry = CreateIndexEntry {
+            table_id: TableRefId::new(schema_id, table_id),
+            index_name: index_name.to_string(),
+            column_idxs: column_idxs.to_vec(),
+            index_type: index_type.clone(),
+        };
+        self.create_index_inner(entry).await
     }
 
     async fn get_index(
@@ -228,13 +234,20 @@ impl Storage for SecondaryStorage {
     ) -> StorageResult<Arc<dyn InMemoryIndex>> {
         let idx = self
             .indexes
-            .lock()
-            .await
             .get_index(schema_id, index_id)
             .ok_or_else(|| StorageError::NotFound("index", index_id.to_string()))?;
         Ok(idx)
     }
 
+    async fn create_user(&self, user: UserCatalog) -> StorageResult<()> {
+        self.create_user_inner(CreateUserEntry { user }).await
+    }
+
+    async fn drop_user(&self, name: &str) -> StorageResult<()> {
+        let entry = DropUserEntry { name: name.into() };
+        self.drop_user_inner(entry).await
+    }
+
     fn get_catalog(&self) -> Arc<RootCatalog> {
         self.catalog.clone()
     }
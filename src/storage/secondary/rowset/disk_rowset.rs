@@ -107,6 +107,11 @@ impl DiskRowset {
         &self.column_infos[storage_column_id]
     }
 
+    /// Returns the columns that the rowset was written with.
+    pub fn column_infos(&self) -> &Arc<[ColumnCatalog]> {
+        &self.column_infos
+    }
+
     pub fn rowset_id(&self) -> u32 {
         self.rowset_id
     }
@@ -118,7 +123,23 @@ impl DiskRowset {
         seek_pos: ColumnSeekPosition,
         filter: Option<KeyRange>,
     ) -> StorageResult<RowSetIterator> {
-        RowSetIterator::new(self.clone(), column_refs, dvs, seek_pos, filter).await
+        let schema = self.column_infos.clone();
+        (self.iter_with_schema(&schema, column_refs, dvs, seek_pos, filter)).await
+    }
+
+    /// Iterates the rowset as if it was written with `schema`.
+    ///
+    /// Columns are referred by id. Columns missing in the rowset take their default values, and
+    /// columns whose type has changed are casted.
+    pub async fn iter_with_schema(
+        self: &Arc<Self>,
+        schema: &[ColumnCatalog],
+        column_refs: Arc<[StorageColumnRef]>,
+        dvs: Vec<Arc<DeleteVector>>,
+        seek_pos: ColumnSeekPosition,
+        filter: Option<KeyRange>,
+    ) -> StorageResult<RowSetIterator> {
+        RowSetIterator::new(self.clone(), schema, column_refs, dvs, seek_pos, filter).await
     }
 
     pub fn on_disk_size(&self) -> u64 {
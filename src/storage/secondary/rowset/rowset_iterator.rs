@@ -9,17 +9,34 @@ use smallvec::smallvec;
 use super::super::{ColumnIteratorImpl, ColumnSeekPosition, SecondaryIteratorImpl};
 use super::DiskRowset;
 use crate::array::ArrayImpl;
+use crate::catalog::ColumnCatalog;
 use crate::storage::secondary::DeleteVector;
-use crate::storage::{KeyRange, PackedVec, StorageChunk, StorageColumnRef, StorageResult};
+use crate::storage::{
+    KeyRange, PackedVec, StorageChunk, StorageColumnRef, StorageResult, cast_array, default_array,
+};
 
 /// When `expected_size` is not specified, we should limit the maximum size of the chunk.
 const ROWSET_MAX_OUTPUT: usize = 2048;
 
+/// Where the data of a referenced column comes from.
+enum ColumnSource {
+    /// Read by a column iterator. The array is casted if the type of the column has changed since
+    /// the rowset was written.
+    Iterator {
+        idx: usize,
+        cast: Option<(ColumnCatalog, ColumnCatalog)>,
+    },
+    /// The column was added after the rowset was written, so it takes the default value.
+    Default(ColumnCatalog),
+}
+
 /// Iterates on a `RowSet`
 pub struct RowSetIterator {
     column_refs: Arc<[StorageColumnRef]>,
     dvs: Vec<Arc<DeleteVector>>,
     column_iterators: Vec<ColumnIteratorImpl>,
+    /// The source of each column in `column_refs`.
+    sources: Vec<ColumnSource>,
     /// An optional filter for the first column.
     filter: Option<KeyRange>,
     /// Indicate whether the iterator has reached the end.
@@ -27,8 +44,11 @@ pub struct RowSetIterator {
 }
 
 impl RowSetIterator {
+    /// Creates an iterator that reads the rowset with the table `schema`, which may be different
+    /// from the columns the rowset was written with.
     pub async fn new(
         rowset: Arc<DiskRowset>,
+        schema: &[ColumnCatalog],
         column_refs: Arc<[StorageColumnRef]>,
         dvs: Vec<Arc<DeleteVector>>,
         seek_pos: ColumnSeekPosition,
@@ -52,39 +72,67 @@ impl RowSetIterator {
             panic!("more than 1 row handler column")
         }
 
+        let row_count = || {
+            rowset
+                .column(0)
+                .index()
+                .indexes()
+                .iter()
+                .fold(0, |acc, index| acc + index.row_count)
+        };
         let mut column_iterators: Vec<ColumnIteratorImpl> = vec![];
+        let mut sources = vec![];
 
         for column_ref in &*column_refs {
             // TODO: parallel seek
             match column_ref {
                 StorageColumnRef::RowHandler => {
-                    let column = rowset.column(0);
-                    let row_count = column
-                        .index()
-                        .indexes()
-                        .iter()
-                        .fold(0, |acc, index| acc + index.row_count);
+                    sources.push(ColumnSource::Iterator {
+                        idx: column_iterators.len(),
+                        cast: None,
+                    });
                     column_iterators.push(ColumnIteratorImpl::new_row_handler(
                         rowset.rowset_id(),
-                        row_count,
+                        row_count(),
                         start_row_id,
                     )?)
                 }
-                StorageColumnRef::Idx(idx) => column_iterators.push(
-                    ColumnIteratorImpl::new(
-                        rowset.column(*idx as usize),
-                        rowset.column_info(*idx as usize),
-                        start_row_id,
-                    )
-                    .await?,
-                ),
+                StorageColumnRef::Idx(id) => {
+                    let column = schema.iter().find(|c| c.id() == *id);
+                    let Some(pos) = rowset.column_infos().iter().position(|c| c.id() == *id) else {
+                        let column = column.expect("column not found in rowset and schema");
+                        sources.push(ColumnSource::Default(column.clone()));
+                        continue;
+                    };
+                    let old = rowset.column_info(pos);
+                    let cast = column
+                        .filter(|c| c.data_type() != old.data_type())
+                        .map(|c| (old.clone(), c.clone()));
+                    sources.push(ColumnSource::Iterator {
+                        idx: column_iterators.len(),
+                        cast,
+                    });
+                    column_iterators.push(
+                        ColumnIteratorImpl::new(rowset.column(pos), old, start_row_id).await?,
+                    );
+                }
             };
         }
 
+        // Columns that are all added after the rowset was written still need the number of rows.
+        if column_iterators.is_empty() {
+            column_iterators.push(ColumnIteratorImpl::new_row_handler(
+                rowset.rowset_id(),
+                row_count(),
+                start_row_id,
+            )?);
+        }
+
         Ok(Self {
             column_refs,
             dvs,
             column_iterators,
+            sources,
             filter,
             end: false,
         })
@@ -178,8 +226,8 @@ impl RowSetIterator {
             // All rows in this batch have been deleted, call `skip`
             // on every columns
             if visi.not_any() {
-                for (id, _) in self.column_refs.iter().enumerate() {
-                    self.column_iterators[id].skip(visi.len());
+                for it in &mut self.column_iterators {
+                    it.skip(visi.len());
                 }
                 return Ok(None);
             }
@@ -188,7 +236,7 @@ impl RowSetIterator {
             visibility_map = Some(visi);
         }
 
-        let mut arrays: PackedVec<ArrayImpl> = smallvec![];
+        let mut batches = Vec::with_capacity(self.column_iterators.len());
         // to make sure all columns have the same chunk range
         let mut common_chunk_range = None;
 
@@ -197,11 +245,8 @@ impl RowSetIterator {
         // indicate the visibility of its rows
         // TODO: Implement the skip interface for column_iterator and call it here.
         // For those already fetched columns, they also need to delete corrensponding blocks.
-        for (id, _) in self.column_refs.iter().enumerate() {
-            let Some((row_id, array)) = self.column_iterators[id]
-                .next_batch(Some(fetch_size))
-                .await?
-            else {
+        for (id, it) in self.column_iterators.iter_mut().enumerate() {
+            let Some((row_id, array)) = it.next_batch(Some(fetch_size)).await? else {
                 self.end = true;
                 return Ok(None);
             };
@@ -211,47 +256,57 @@ impl RowSetIterator {
             if let Some(common_range) = &common_chunk_range {
                 if common_range != &current_range {
                     panic!(
-                        "unmatched row range from column iterator: {:?} of [{:?}], {:?} != {:?}",
-                        self.column_refs[id], self.column_refs, common_range, current_range
+                        "unmatched row range from column iterator {id} of [{:?}], {:?} != {:?}",
+                        self.column_refs, common_range, current_range
                     );
                 }
             } else {
                 common_chunk_range = Some(current_range);
             }
+            batches.push(array);
+        }
+        let len = batches[0].len();
 
-            // For now, we only support range-filter scan by first column.
-            if let Some(range) = &self.filter
-                && id == 0
-            {
-                let len = array.len();
-                let start_row_id = match &range.start {
-                    Bound::Included(key) => (0..array.len()).position(|idx| &array.get(idx) >= key),
-                    Bound::Excluded(key) => (0..array.len()).position(|idx| &array.get(idx) > key),
-                    Bound::Unbounded => Some(0),
-                }
-                .unwrap_or(len);
-                let end_row_id = match &range.end {
-                    Bound::Included(key) => (0..array.len()).position(|idx| &array.get(idx) > key),
-                    Bound::Excluded(key) => (0..array.len()).position(|idx| &array.get(idx) >= key),
-                    Bound::Unbounded => None,
-                }
-                .unwrap_or(len);
-                if (start_row_id..end_row_id) != (0..len) {
-                    let bitmap = (0..len)
-                        .map(|i| (start_row_id..end_row_id).contains(&i))
-                        .collect();
-                    if let Some(ref mut vis) = visibility_map {
-                        *vis &= bitmap;
-                    } else {
-                        visibility_map = Some(bitmap);
-                    }
-                }
-                if end_row_id == 0 {
-                    self.end = true;
+        let mut arrays: PackedVec<ArrayImpl> = smallvec![];
+        for source in &self.sources {
+            arrays.push(match source {
+                ColumnSource::Iterator { idx, cast: None } => batches[*idx].clone(),
+                ColumnSource::Iterator {
+                    idx,
+                    cast: Some((old, column)),
+                } => cast_array(&batches[*idx], old, column)?,
+                ColumnSource::Default(column) => default_array(column, len),
+            });
+        }
+
+        // For now, we only support range-filter scan by first column.
+        if let Some(range) = &self.filter {
+            let array = &arrays[0];
+            let start_row_id = match &range.start {
+                Bound::Included(key) => (0..len).position(|idx| &array.get(idx) >= key),
+                Bound::Excluded(key) => (0..len).position(|idx| &array.get(idx) > key),
+                Bound::Unbounded => Some(0),
+            }
+            .unwrap_or(len);
+            let end_row_id = match &range.end {
+                Bound::Included(key) => (0..len).position(|idx| &array.get(idx) > key),
+                Bound::Excluded(key) => (0..len).position(|idx| &array.get(idx) >= key),
+                Bound::Unbounded => None,
+            }
+            .unwrap_or(len);
+            if (start_row_id..end_row_id) != (0..len) {
+                let bitmap = (0..len)
+                    .map(|i| (start_row_id..end_row_id).contains(&i))
+                    .collect();
+                if let Some(ref mut vis) = visibility_map {
+                    *vis &= bitmap;
+                } else {
+                    visibility_map = Some(bitmap);
                 }
             }
-
-            arrays.push(array);
+            if end_row_id == 0 {
+                self.end = true;
+            }
         }
 
         Ok(StorageChunk::construct(visibility_map, arrays))
@@ -4,13 +4,16 @@ use std::collections::HashMap;
 use std::sync::Arc;
 use std::sync::atomic::{AtomicU32, AtomicU64};
 
+use itertools::Itertools;
 use moka::future::Cache;
 use parking_lot::RwLock;
 use tokio::fs;
 use tokio::sync::Mutex;
 use tracing::info;
 
-use super::{DiskRowset, Manifest, SecondaryStorage, StorageOptions, StorageResult};
+use super::{
+    DiskRowset, Manifest, SecondaryStorage, StorageOptions, StorageResult, load_index_data,
+};
 use crate::catalog::RootCatalog;
 use crate::storage::index::InMemoryIndexes;
 use crate::storage::secondary::manifest::*;
@@ -59,7 +62,7 @@ impl SecondaryStorage {
             compactor_handler: Mutex::new((None, None)),
             vacuum_handler: Mutex::new((None, None)),
             txn_mgr: Arc::new(TransactionManager::default()),
-            indexes: Mutex::new(InMemoryIndexes::new()),
+            indexes: InMemoryIndexes::new(),
         };
 
         info!("applying {} manifest entries", manifest_ops.len());
@@ -69,6 +72,25 @@ impl SecondaryStorage {
 
         let mut table_changeset = vec![];
         for op in manifest_ops {
+            // temporary tables are dropped on restart, together with their rowsets
+            let is_temp = match &op {
+                ManifestOperation::CreateTable(entry) => {
+                    engine.catalog.get_schema_by_id(entry.schema_id).is_none()
+                }
+                ManifestOperation::DropTable(DropTableEntry { table_id })
+                | ManifestOperation::CreateIndex(CreateIndexEntry { table_id, .. })
+                | ManifestOperation::AlterTable(AlterTableEntry { table_id, .. })
+                | ManifestOperation::AddRowSet(AddRowSetEntry { table_id, .. })
+                | ManifestOperation::DeleteRowSet(DeleteRowsetEntry { table_id, .. })
+                | ManifestOperation::AddDV(AddDVEntry { table_id, .. })
+                | ManifestOperation::DeleteDV(DeleteDVEntry { table_id, .. }) => {
+                    table_id.table_id >= RootCatalog::TEMP_TABLE_ID_START
+                }
+                _ => false,
+            };
+            if is_temp {
+                continue;
+            }
             match op {
                 ManifestOperation::CreateTable(entry) => {
                     engine.apply_create_table(&entry)?;
@@ -81,6 +103,33 @@ impl SecondaryStorage {
                     // to manifest may solve it, and there may be other solutions.
                     table_changeset.push(EpochOp::DropTable(entry));
                 }
+                ManifestOperation::CreateIndex(entry) => {
+                    let (table, index) = engine.apply_create_index(&entry)?;
+                    engine.indexes.add_index(
+                        entry.table_id.schema_id,
+                        index.index_id,
+                        entry.table_id.table_id,
+                        index.index.clone(),
+                    );
+                    table.indexes.write().await.push(index);
+                    table_changeset.push(EpochOp::CreateIndex(entry));
+                }
+                ManifestOperation::AlterTable(entry) => {
+                    engine.apply_alter_table(&entry)?;
+                    table_changeset.push(EpochOp::AlterTable(entry));
+                }
+                ManifestOperation::CreateUser(entry) => {
+                    engine.apply_create_user(&entry)?;
+                    table_changeset.push(EpochOp::CreateUser(entry));
+                }
+                ManifestOperation::DropUser(entry) => {
+                    engine.apply_drop_user(&entry)?;
+                    // unlike tables, users have no id to restore, so the compacted manifest
+                    // only needs to keep the users that still exist
+                    table_changeset.retain(
+                        |op| !matches!(op, EpochOp::CreateUser(e) if e.user.name() == entry.name),
+                    );
+                }
                 ManifestOperation::AddRowSet(entry) => {
                     engine
                         .next_id
@@ -141,17 +190,20 @@ impl SecondaryStorage {
 
         let tables = engine.tables.read().clone();
 
-        for (_, entry) in rowsets_to_open {
+        for (_, mut entry) in rowsets_to_open {
             let table = tables.get(&entry.table_id).unwrap();
+            if entry.columns.is_empty() {
+                entry.columns = table.columns.to_vec();
+            }
             let disk_rowset = DiskRowset::open(
                 table.get_rowset_path(entry.rowset_id),
-                table.columns.clone(),
+                entry.columns.clone().into(),
                 engine.block_cache.clone(),
                 entry.rowset_id,
                 options.io_backend.clone(),
             )
             .await?;
-            changeset.push(EpochOp::AddRowSet((entry, disk_rowset)));
+            changeset.push(EpochOp::AddRowSet((entry, Arc::new(disk_rowset))));
         }
 
         for (_, entry) in dvs_to_open {
@@ -176,6 +228,35 @@ impl SecondaryStorage {
                 .await?;
         }
 
+        engine.load_indexes().await?;
+
         Ok(engine)
     }
+
+    /// Loads the data of all indexes into memory.
+    async fn load_indexes(&self) -> StorageResult<()> {
+        let version = self.version.pin();
+        let tables = self.tables.read().clone();
+        for table in tables.values() {
+            let indexes = table.indexes.read().await;
+            if indexes.is_empty() {
+                continue;
+            }
+            let Some(rowsets) = version.snapshot.get_rowsets_of(table.table_id()) else {
+                continue;
+            };
+            for &rowset_id in rowsets {
+                let rowset = self.version.get_rowset(table.table_id(), rowset_id);
+                let dvs = (version.snapshot.get_dvs_of(table.table_id(), rowset_id))
+                    .map(|dvs| {
+                        dvs.iter()
+                            .map(|dv_id| self.version.get_dv(table.table_id(), *dv_id))
+                            .collect_vec()
+                    })
+                    .unwrap_or_default();
+                load_index_data(table, &rowset, &dvs, &indexes).await?;
+            }
+        }
+        Ok(())
+    }
 }
@@ -4,8 +4,8 @@ use std::path::PathBuf;
 use std::sync::Arc;
 use std::sync::atomic::{AtomicU32, AtomicU64};
 
+use itertools::Itertools;
 use moka::future::Cache;
-use tokio::sync::OwnedMutexGuard;
 
 use super::*;
 use crate::catalog::TableRefId;
@@ -46,6 +46,12 @@ pub struct SecondaryTable {
 
     /// Next RowSet Id and DV Id of the current storage engine
     next_id: Arc<(AtomicU32, AtomicU64)>,
+
+    /// Indexes on the table.
+    ///
+    /// Transactions and compactions hold the read lock while committing, so that an index being
+    /// created neither misses nor duplicates any committed row.
+    pub indexes: Arc<tokio::sync::RwLock<Vec<TableIndex>>>,
 }
 
 impl SecondaryTable {
@@ -74,9 +80,26 @@ impl SecondaryTable {
             block_cache,
             txn_mgr,
             ordered_pk_ids,
+            indexes: Default::default(),
+        }
+    }
+
+    /// Returns the table with its columns replaced, sharing everything else.
+    pub fn with_columns(&self, columns: &[ColumnCatalog]) -> Self {
+        Self {
+            columns: columns.into(),
+            column_map: (columns.iter().enumerate())
+                .map(|(idx, col)| (col.id(), idx))
+                .collect(),
+            ..self.clone()
         }
     }
 
+    /// Returns the column with the given id.
+    pub fn column(&self, id: ColumnId) -> &ColumnCatalog {
+        &self.columns[self.column_map[&id]]
+    }
+
     pub fn generate_rowset_id(&self) -> u32 {
         self.next_id
             .0
@@ -105,8 +128,38 @@ impl SecondaryTable {
         self.table_ref_id.table_id
     }
 
-    pub async fn lock_for_deletion(&self) -> OwnedMutexGuard<()> {
-        self.txn_mgr.lock_for_deletion(self.table_id()).await
+    /// Builds the index data of all rowsets, persists the index to manifest, and maintains the
+    /// index on later commits.
+    pub async fn add_index(&self, index: TableIndex, entry: CreateIndexEntry) -> StorageResult<()> {
+        let mut indexes = self.indexes.write().await;
+        let version = self.version.pin();
+        let mut index_data = vec![];
+        if let Some(rowsets) = version.snapshot.get_rowsets_of(self.table_id()) {
+            for &rowset_id in rowsets {
+                let rowset = self.version.get_rowset(self.table_id(), rowset_id);
+                let mut data =
+                    build_index_data(self, &rowset, std::slice::from_ref(&index)).await?;
+                index_data.push((rowset_id, data.pop().unwrap()));
+            }
+        }
+        self.version
+            .commit_changes(vec![EpochOp::CreateIndex(entry)])
+            .await?;
+
+        // No rowset or DV is committed while holding the lock, so the snapshot is up to date.
+        for (rowset_id, data) in index_data {
+            let dvs = (version.snapshot.get_dvs_of(self.table_id(), rowset_id))
+                .map(|dvs| {
+                    dvs.iter()
+                        .map(|dv_id| self.version.get_dv(self.table_id(), *dv_id))
+                        .collect_vec()
+                })
+                .unwrap_or_default();
+            let deleted = dvs.iter().flat_map(|dv| dv.deletes()).copied().collect();
+            data.insert_into(&*index.index, rowset_id, &deleted);
+        }
+        indexes.push(index);
+        Ok(())
     }
 }
 
@@ -122,15 +175,15 @@ impl Table for SecondaryTable {
     }
 
     async fn write(&self) -> StorageResult<SecondaryTransaction> {
-        SecondaryTransaction::start(self, false, false).await
+        SecondaryTransaction::start(self, false).await
     }
 
     async fn read(&self) -> StorageResult<SecondaryTransaction> {
-        SecondaryTransaction::start(self, true, false).await
+        SecondaryTransaction::start(self, true).await
     }
 
     async fn update(&self) -> StorageResult<SecondaryTransaction> {
-        SecondaryTransaction::start(self, false, true).await
+        SecondaryTransaction::start(self, false).await
     }
 
     fn ordered_pk_ids(&self) -> Vec<ColumnId> {
@@ -0,0 +1,357 @@
+// Copyright 2025 RisingLight Project Authors. Licensed under Apache-2.0.
+
+//! Persistence of secondary indexes.
+//!
+//! Indexes are maintained in memory, and their entries are persisted per rowset: the directory
+//! of each rowset contains an `index_{index_id}` sub-rowset with two columns, the index key and
+//! the row id of the entry in the rowset. Entries of B-tree indexes are sorted by key.
+//!
+//! Index data covers all rows of a rowset, including deleted ones. Deletes are applied from the
+//! delete vectors of the rowset when an index is loaded.
+
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use itertools::Itertools;
+use moka::future::Cache;
+use tokio::fs;
+
+use super::{
+    ColumnBuilderOptions, ColumnSeekPosition, DeleteVector, DiskRowset, IOBackend, RowsetBuilder,
+    RowsetWriter, SecondaryRowHandler, SecondaryTable, path_of_data_column,
+};
+use crate::array::{Array, ArrayBuilderImpl, ArrayImpl, DataChunk, I32Array};
+use crate::catalog::{ColumnCatalog, ColumnDesc, ColumnId, IndexId};
+use crate::storage::{InMemoryIndex, StorageColumnRef, StorageResult};
+use crate::types::DataType;
+
+/// An index on a table.
+#[derive(Clone)]
+pub struct TableIndex {
+    pub index_id: IndexId,
+    /// Id of the indexed column.
+    pub column: ColumnId,
+    /// Whether the persisted entries are sorted by key.
+    pub sorted: bool,
+    pub index: Arc<dyn InMemoryIndex>,
+}
+
+/// Entries of an index in a rowset.
+pub struct IndexData {
+    keys: ArrayImpl,
+    row_ids: Vec<u32>,
+}
+
+impl IndexData {
+    /// Inserts the entries into the in-memory index, skipping the `deleted` rows.
+    pub fn insert_into(&self, index: &dyn InMemoryIndex, rowset_id: u32, deleted: &HashSet<u32>) {
+        let visibility = (self.row_ids.iter())
+            .map(|row_id| !deleted.contains(row_id))
+            .collect_vec();
+        let row_handlers = (self.row_ids.iter())
+            .filter(|row_id| !deleted.contains(row_id))
+            .map(|&row_id| SecondaryRowHandler(rowset_id, row_id).as_i64())
+            .collect_vec();
+        index.insert(&self.keys.filter(&visibility), &row_handlers);
+    }
+}
+
+pub fn path_of_index_data(rowset_directory: impl AsRef<Path>, index_id: IndexId) -> PathBuf {
+    rowset_directory.as_ref().join(format!("index_{index_id}"))
+}
+
+/// Columns of the index data rowset.
+fn index_data_columns(key: &ColumnCatalog) -> Arc<[ColumnCatalog]> {
+    [
+        ColumnCatalog::new(0, key.desc().clone()),
+        ColumnCatalog::new(1, ColumnDesc::new("row_id", DataType::Int32, false)),
+    ]
+    .into()
+}
+
+/// Reads the entries of `indexes` from all rows of a rowset.
+async fn read_index_data(
+    table: &SecondaryTable,
+    rowset: &Arc<DiskRowset>,
+    indexes: &[TableIndex],
+) -> StorageResult<Vec<IndexData>> {
+    let column_refs = (indexes.iter())
+        .map(|index| StorageColumnRef::Idx(index.column))
+        .chain(std::iter::once(StorageColumnRef::RowHandler))
+        .collect_vec();
+    let mut builders = (indexes.iter())
+        .map(|index| ArrayBuilderImpl::new(&table.column(index.column).data_type()))
+        .collect_vec();
+    let mut row_ids = vec![];
+    let mut iter = rowset
+        .iter_with_schema(
+            &table.columns,
+            column_refs.into(),
+            vec![],
+            ColumnSeekPosition::start(),
+            None,
+        )
+        .await?;
+    while let Some(chunk) = iter.next_batch(None).await? {
+        let chunk = chunk.to_data_chunk();
+        for (i, builder) in builders.iter_mut().enumerate() {
+            builder.append(chunk.array_at(i));
+        }
+        let ArrayImpl::Int64(handlers) = chunk.array_at(indexes.len()) else {
+            panic!("invalid row handler column");
+        };
+        row_ids.extend(
+            (handlers.raw_iter()).map(|&handler| SecondaryRowHandler::from(handler).row_id()),
+        );
+    }
+    Ok(builders
+        .into_iter()
+        .map(|builder| IndexData {
+            keys: builder.finish(),
+            row_ids: row_ids.clone(),
+        })
+        .collect())
+}
+
+/// Persists the entries of an index in a rowset, replacing the existing data.
+async fn write_index_data(
+    table: &SecondaryTable,
+    rowset_id: u32,
+    index: &TableIndex,
+    data: &IndexData,
+) -> StorageResult<()> {
+    if data.row_ids.is_empty() {
+        return Ok(());
+    }
+    let directory = path_of_index_data(table.get_rowset_path(rowset_id), index.index_id);
+    let io_backend = table.storage_options.io_backend.clone();
+    if !io_backend.is_in_memory() && fs::metadata(&directory).await.is_ok() {
+        // left by a failed index creation
+        fs::remove_dir_all(&directory).await?;
+    }
+
+    let key_column = table.column(index.column);
+    let order = if index.sorted {
+        (0..data.row_ids.len())
+            .sorted_by_cached_key(|&i| data.keys.get(i))
+            .collect_vec()
+    } else {
+        (0..data.row_ids.len()).collect_vec()
+    };
+    let mut keys = ArrayBuilderImpl::with_capacity(order.len(), &key_column.data_type());
+    for &i in &order {
+        keys.push(&data.keys.get(i));
+    }
+    let row_ids = ArrayImpl::new_int32(
+        (order.iter())
+            .map(|&i| Some(data.row_ids[i] as i32))
+            .collect::<I32Array>(),
+    );
+
+    let mut builder = RowsetBuilder::new(
+        index_data_columns(key_column),
+        ColumnBuilderOptions::from_storage_options(&table.storage_options),
+    );
+    builder.append(DataChunk::from_iter([keys.finish(), row_ids]));
+    let writer = RowsetWriter::new(&directory, io_backend);
+    writer.create_dir().await?;
+    writer.flush(builder.finish()).await?;
+    Ok(())
+}
+
+/// Reads the persisted entries of an index in a rowset. Returns `None` if there is no data.
+async fn open_index_data(
+    table: &SecondaryTable,
+    rowset_id: u32,
+    index: &TableIndex,
+) -> StorageResult<Option<IndexData>> {
+    let directory = path_of_index_data(table.get_rowset_path(rowset_id), index.index_id);
+    let columns = index_data_columns(table.column(index.column));
+    let io_backend = table.storage_options.io_backend.clone();
+    let exists = match &io_backend {
+        IOBackend::InMemory(map) => map
+            .lock()
+            .contains_key(&path_of_data_column(&directory, &columns[0])),
+        _ => fs::metadata(&directory).await.is_ok(),
+    };
+    if !exists {
+        return Ok(None);
+    }
+
+    // Index data is only read once, and its block cache keys would collide with those of the
+    // rowset, so it does not go through the block cache of the storage.
+    let rowset = Arc::new(
+        DiskRowset::open(
+            directory,
+            columns.clone(),
+            Cache::new(table.storage_options.cache_size as u64),
+            rowset_id,
+            io_backend,
+        )
+        .await?,
+    );
+    let mut keys = ArrayBuilderImpl::new(columns[0].desc().data_type());
+    let mut row_ids = vec![];
+    let mut iter = rowset
+        .iter(
+            [StorageColumnRef::Idx(0), StorageColumnRef::Idx(1)].into(),
+            vec![],
+            ColumnSeekPosition::start(),
+            None,
+        )
+        .await?;
+    while let Some(chunk) = iter.next_batch(None).await? {
+        let chunk = chunk.to_data_chunk();
+        keys.append(chunk.array_at(0));
+        let ArrayImpl::Int32(ids) = chunk.array_at(1) else {
+            panic!("invalid row id column");
+        };
+        row_ids.extend(ids.raw_iter().map(|&id| id as u32));
+    }
+    Ok(Some(IndexData {
+        keys: keys.finish(),
+        row_ids,
+    }))
+}
+
+/// Builds and persists the entries of `indexes` for a new rowset.
+pub async fn build_index_data(
+    table: &SecondaryTable,
+    rowset: &Arc<DiskRowset>,
+    indexes: &[TableIndex],
+) -> StorageResult<Vec<IndexData>> {
+    let data = read_index_data(table, rowset, indexes).await?;
+    for (index, data) in indexes.iter().zip(&data) {
+        write_index_data(table, rowset.rowset_id(), index, data).await?;
+    }
+    Ok(data)
+}
+
+/// Loads the entries of `indexes` in a rowset into memory, excluding the rows deleted by `dvs`.
+///
+/// The index data is rebuilt from the rowset if it is missing.
+pub async fn load_index_data(
+    table: &SecondaryTable,
+    rowset: &Arc<DiskRowset>,
+    dvs: &[Arc<DeleteVector>],
+    indexes: &[TableIndex],
+) -> StorageResult<()> {
+    let deleted: HashSet<u32> = dvs.iter().flat_map(|dv| dv.deletes()).copied().collect();
+    for index in indexes {
+        let data = match open_index_data(table, rowset.rowset_id(), index).await? {
+            Some(data) => data,
+            None => {
+                let mut data = build_index_data(table, rowset, std::slice::from_ref(index)).await?;
+                data.pop().unwrap()
+            }
+        };
+        data.insert_into(&*index.index, rowset.rowset_id(), &deleted);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::binder::IndexType;
+    use crate::catalog::{RootCatalog, TableRefId};
+    use crate::storage::secondary::{SecondaryStorage, StorageOptions};
+    use crate::storage::{RowHandler, Storage, Table, Transaction};
+    use crate::types::DataValue;
+
+    async fn insert(table: &SecondaryTable, values: &[i32]) {
+        let mut txn = table.write().await.unwrap();
+        let array = ArrayImpl::new_int32(values.iter().map(|v| Some(*v)).collect());
+        txn.append(DataChunk::from_iter([array])).await.unwrap();
+        txn.commit().await.unwrap();
+    }
+
+    /// Returns the values of the rows with key `v` found by the index.
+    async fn search(storage: &SecondaryStorage, table_id: TableRefId, v: i32) -> Vec<DataValue> {
+        let index = storage.get_index(table_id.schema_id, 1).await.unwrap();
+        let row_handlers = index.search(&DataValue::Int32(v), 10);
+        let row_handlers = ArrayImpl::new_int64(row_handlers.into_iter().map(Some).collect());
+        let ids = (0..row_handlers.len())
+            .map(|i| SecondaryRowHandler::from_column(&row_handlers, i))
+            .collect_vec();
+        let table = storage.get_table(table_id).unwrap();
+        let txn = table.read().await.unwrap();
+        let chunk = txn.fetch(&[StorageColumnRef::Idx(0)], &ids).await.unwrap();
+        txn.abort().await.unwrap();
+        chunk.array_at(0).iter().collect()
+    }
+
+    #[tokio::test]
+    async fn persist_and_compact() {
+        let tempdir = tempfile::tempdir().unwrap();
+        let options = StorageOptions {
+            path: tempdir.path().join("db"),
+            ..StorageOptions::default_for_cli()
+        };
+
+        let storage = Arc::new(SecondaryStorage::open(options.clone()).await.unwrap());
+        let schema_id = (storage.catalog())
+            .get_schema_id_by_name(RootCatalog::DEFAULT_SCHEMA_NAME)
+            .unwrap();
+        let columns = [ColumnCatalog::new(
+            0,
+            ColumnDesc::new("a", DataType::Int32, false),
+        )];
+        storage
+            .create_table(schema_id, "t", &columns, &[])
+            .await
+            .unwrap();
+        let table_id = TableRefId::new(schema_id, 0);
+        let table = storage.get_table(table_id).unwrap();
+        insert(&table, &[1, 2, 3]).await;
+        insert(&table, &[2, 4]).await;
+
+        let index_id = storage
+            .create_index(schema_id, "i", 0, &[0], &IndexType::Btree)
+            .await
+            .unwrap();
+        assert_eq!(index_id, 1);
+        let two = DataValue::Int32(2);
+        assert_eq!(
+            search(&storage, table_id, 2).await,
+            [two.clone(), two.clone()]
+        );
+
+        // delete a row and insert a new one
+        let index = storage.get_index(schema_id, 1).await.unwrap();
+        let row = index.search(&two, 1)[0];
+        let mut txn = table.update().await.unwrap();
+        txn.delete(&row.into()).await.unwrap();
+        txn.commit().await.unwrap();
+        insert(&table, &[2]).await;
+        assert_eq!(
+            search(&storage, table_id, 2).await,
+            [two.clone(), two.clone()]
+        );
+
+        // run a compaction
+        storage.spawn_compactor().await;
+        storage.shutdown().await.unwrap();
+        assert_eq!(
+            search(&storage, table_id, 2).await,
+            [two.clone(), two.clone()]
+        );
+        drop(table);
+        drop(storage);
+
+        let storage = SecondaryStorage::open(options).await.unwrap();
+        let rowsets = storage
+            .version
+            .pin()
+            .snapshot
+            .get_rowsets_of(0)
+            .unwrap()
+            .len();
+        assert_eq!(rowsets, 1, "rowsets should be compacted");
+        assert!(storage.catalog().get_index_by_id(schema_id, 1).is_some());
+        assert_eq!(search(&storage, table_id, 2).await, [two.clone(), two]);
+        assert_eq!(search(&storage, table_id, 4).await, [DataValue::Int32(4)]);
+        assert!(search(&storage, table_id, 5).await.is_empty());
+    }
+}
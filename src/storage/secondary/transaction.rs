@@ -1,26 +1,41 @@
 // Copyright 2024 RisingLight Project Authors. Licensed under Apache-2.0.
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::ops::Bound;
 use std::sync::Arc;
 
 use itertools::Itertools;
 use risinglight_proto::rowset::DeleteRecord;
 use risinglight_proto::rowset::block_statistics::BlockStatisticsType;
-use tokio::sync::OwnedMutexGuard;
+use tokio::sync::OwnedRwLockReadGuard;
 use tracing::{info, warn};
 
 use super::version_manager::{Snapshot, Version, VersionManager};
 use super::{
-    AddDVEntry, AddRowSetEntry, ColumnBuilderOptions, ConcatIterator, DeleteVector, DiskRowset,
-    EpochOp, MergeIterator, RowSetIterator, SecondaryMemRowsetImpl, SecondaryRowHandler,
-    SecondaryTable, SecondaryTableTxnIterator,
+    AddDVEntry, AddRowSetEntry, ColumnBuilderOptions, ColumnSeekPosition, ConcatIterator,
+    DeleteVector, DiskRowset, EpochOp, IndexData, MergeIterator, RowSetIterator,
+    SecondaryMemRowsetImpl, SecondaryRowHandler, SecondaryTable, SecondaryTableTxnIterator,
+    TableIndex, TransactionManager, build_index_data,
 };
-use crate::array::DataChunk;
+use crate::array::{ArrayBuilderImpl, DataChunk};
 use crate::catalog::find_sort_key_id;
 use crate::storage::secondary::statistics::create_statistics_global_aggregator;
 use crate::storage::{ScanOptions, StorageColumnRef, StorageResult, Transaction};
-use crate::types::DataValue;
+use crate::types::{DataType, DataValue};
+
+/// The changes of a transaction prepared for committing.
+struct PendingCommit {
+    /// The rowsets written by the transaction.
+    rowsets: Vec<Arc<DiskRowset>>,
+    /// The deleted rows, grouped by rowset id.
+    deletes: HashMap<u32, Vec<DeleteRecord>>,
+    /// The row handlers of the deleted rows.
+    deleted_rows: Vec<i64>,
+    /// The read lock of the indexes on the table, held until the indexes are updated.
+    indexes: OwnedRwLockReadGuard<Vec<TableIndex>>,
+    /// The index data of each new rowset.
+    index_data: Vec<(u32, Vec<IndexData>)>,
+}
 
 /// A transaction running on `SecondaryStorage`.
 pub struct SecondaryTransaction {
@@ -45,9 +60,7 @@ pub struct SecondaryTransaction {
     snapshot: Arc<Snapshot>,
 
     /// The rowsets produced in the txn.
-    to_be_committed_rowsets: Vec<DiskRowset>,
-
-    delete_lock: Option<OwnedMutexGuard<()>>,
+    to_be_committed_rowsets: Vec<Arc<DiskRowset>>,
 
     read_only: bool,
 
@@ -61,13 +74,10 @@ pub struct SecondaryTransaction {
 }
 
 impl SecondaryTransaction {
-    /// Start a transaction on Secondary. If `update` is set to true, we will hold the delete lock
-    /// of a table.
-    pub(super) async fn start(
-        table: &SecondaryTable,
-        read_only: bool,
-        update: bool,
-    ) -> StorageResult<Self> {
+    /// Start a transaction on Secondary.
+    ///
+    /// Transactions that delete rows run concurrently, and conflicts are checked on commit.
+    pub(super) async fn start(table: &SecondaryTable, read_only: bool) -> StorageResult<Self> {
         // pin a snapshot at version manager
         let pin_version = table.version.pin();
         Ok(Self {
@@ -77,11 +87,6 @@ impl SecondaryTransaction {
             table: table.clone(),
             version: table.version.clone(),
             snapshot: pin_version.snapshot.clone(),
-            delete_lock: if update {
-                Some(table.lock_for_deletion().await)
-            } else {
-                None
-            },
             to_be_committed_rowsets: vec![],
             read_only,
             total_size: 0,
@@ -112,18 +117,21 @@ impl SecondaryTransaction {
         )
         .await?;
 
-        self.to_be_committed_rowsets.push(on_disk);
+        self.to_be_committed_rowsets.push(Arc::new(on_disk));
 
         Ok(())
     }
 
-    async fn commit_inner(mut self) -> StorageResult<()> {
+    /// Flushes the writes of the transaction, and builds the index data of its new rowsets.
+    async fn prepare_commit(&mut self) -> StorageResult<PendingCommit> {
         self.flush_rowset().await?;
 
+        let deleted_rows = self.delete_buffer.iter().map(|x| x.as_i64()).collect_vec();
+
         // flush deletes to disk
-        let mut delete_split_map = HashMap::new();
+        let mut deletes = HashMap::new();
         for delete in self.delete_buffer.drain(..) {
-            delete_split_map
+            deletes
                 .entry(delete.rowset_id())
                 .or_insert_with(Vec::new)
                 .push(DeleteRecord {
@@ -133,8 +141,47 @@ impl SecondaryTransaction {
 
         let rowsets = std::mem::take(&mut self.to_be_committed_rowsets);
 
+        // Build index data of the new rowsets before they are visible. Hold the read lock until
+        // the index is updated, so that an index being created neither misses nor duplicates
+        // any committed row.
+        let indexes = self.table.indexes.clone().read_owned().await;
+        let mut index_data = vec![];
+        if !indexes.is_empty() {
+            for rowset in &rowsets {
+                let data = build_index_data(&self.table, rowset, &indexes).await?;
+                index_data.push((rowset.rowset_id(), data));
+            }
+        }
+        Ok(PendingCommit {
+            rowsets,
+            deletes,
+            deleted_rows,
+            indexes,
+            index_data,
+        })
+    }
+
+    /// Checks that the rows deleted by the transaction are not changed by others.
+    ///
+    /// The commit lock of the table should be held. Rows deleted from the rowsets of this
+    /// transaction never conflict.
+    fn check_conflicts(&self, commit: &PendingCommit) -> StorageResult<()> {
+        let deletes = (commit.deletes.iter())
+            .filter(|(rowset_id, _)| !commit.rowsets.iter().any(|r| r.rowset_id() == **rowset_id))
+            .map(|(rowset_id, deletes)| (*rowset_id, deletes.iter().map(|d| d.row_id).collect()))
+            .collect();
+        TransactionManager::check_conflicts(
+            &self.version,
+            &self.snapshot,
+            self.table.table_id(),
+            &deletes,
+        )
+    }
+
+    /// Persists the DVs of the transaction, and returns the changes to the version.
+    async fn write_changes(&self, commit: &mut PendingCommit) -> StorageResult<Vec<EpochOp>> {
         let mut dvs = vec![];
-        for (rowset_id, deletes) in delete_split_map {
+        for (rowset_id, deletes) in std::mem::take(&mut commit.deletes) {
             let dv_id = self.table.generate_dv_id();
             use bytes::Bytes;
 
@@ -162,7 +209,7 @@ impl SecondaryTransaction {
 
         let mut changeset = vec![];
 
-        match rowsets[..] {
+        match commit.rowsets[..] {
             [] => {
                 info!(
                     "DV {} flushed",
@@ -174,7 +221,8 @@ impl SecondaryTransaction {
             _ => {
                 info!(
                     "RowSet {} flushed, DV {} flushed",
-                    rowsets
+                    commit
+                        .rowsets
                         .iter()
                         .map(|x| format!("#{}", x.rowset_id()))
                         .join(","),
@@ -186,13 +234,14 @@ impl SecondaryTransaction {
         }
 
         // Add RowSets
-        changeset.extend(rowsets.into_iter().map(|x| {
+        changeset.extend(commit.rowsets.iter().map(|x| {
             EpochOp::AddRowSet((
                 AddRowSetEntry {
                     rowset_id: x.rowset_id(),
                     table_id: self.table.table_ref_id,
+                    columns: x.column_infos().to_vec(),
                 },
-                x,
+                x.clone(),
             ))
         }));
 
@@ -207,15 +256,149 @@ impl SecondaryTransaction {
                 x,
             ))
         }));
+        Ok(changeset)
+    }
 
-        // Commit changeset
-        self.version.commit_changes(changeset).await?;
+    /// Commits transactions on multiple tables in one change of the version, so that either all
+    /// or none of them take effect.
+    async fn commit_all_inner(mut txns: Vec<Self>) -> StorageResult<()> {
+        let Some(version) = txns.first().map(|txn| txn.version.clone()) else {
+            return Ok(());
+        };
+        // lock tables in the order of their ids to avoid deadlocks
+        txns.sort_by_key(|txn| txn.table.table_id());
 
-        self.finished = true;
+        let mut commits = Vec::with_capacity(txns.len());
+        for txn in &mut txns {
+            commits.push(txn.prepare_commit().await?);
+        }
+
+        // Hold the commit locks until the changes are committed, so that no conflicting change is
+        // committed after the check.
+        let mut commit_locks = Vec::with_capacity(txns.len());
+        for txn in &txns {
+            let table = &txn.table;
+            commit_locks.push(table.txn_mgr.lock_for_commit(table.table_id()).await);
+        }
+        for (txn, commit) in txns.iter().zip(&commits) {
+            txn.check_conflicts(commit)?;
+        }
+
+        let mut changeset = vec![];
+        for (txn, commit) in txns.iter().zip(&mut commits) {
+            changeset.extend(txn.write_changes(commit).await?);
+        }
 
+        // Commit changeset, and then apply the changes to indexes.
+        version.commit_changes(changeset).await?;
+        for (txn, commit) in txns.iter_mut().zip(commits) {
+            for (rowset_id, data) in commit.index_data {
+                for (index, data) in commit.indexes.iter().zip(data) {
+                    data.insert_into(&*index.index, rowset_id, &HashSet::new());
+                }
+            }
+            if !commit.deleted_rows.is_empty() {
+                for index in commit.indexes.iter() {
+                    index.index.delete(&commit.deleted_rows);
+                }
+            }
+            txn.finished = true;
+        }
         Ok(())
     }
 
+    /// Get DV id and read DVs of a rowset in the snapshot.
+    ///
+    /// Rows deleted in this transaction are included as an extra DV.
+    fn get_dvs_of(&self, rowset_id: u32) -> Vec<Arc<DeleteVector>> {
+        let mut dvs = self
+            .snapshot
+            .get_dvs_of(self.table.table_id(), rowset_id)
+            .map(|dvs| {
+                dvs.iter()
+                    .map(|dv_id| self.version.get_dv(self.table.table_id(), *dv_id))
+                    .collect_vec()
+            })
+            .unwrap_or_default();
+        let deletes = (self.delete_buffer.iter())
+            .filter(|id| id.rowset_id() == rowset_id)
+            .map(|id| DeleteRecord {
+                row_id: id.row_id(),
+            })
+            .collect_vec();
+        if !deletes.is_empty() {
+            // the DV is never persisted, so its id doesn't matter
+            dvs.push(Arc::new(DeleteVector::new(u64::MAX, rowset_id, deletes)));
+        }
+        dvs
+    }
+
+    async fn fetch_inner(
+        &self,
+        col_idx: &[StorageColumnRef],
+        ids: &[SecondaryRowHandler],
+    ) -> StorageResult<DataChunk> {
+        // read the row handler column to check whether the row is deleted
+        let mut column_refs = col_idx.to_vec();
+        let handler_idx = match col_idx
+            .iter()
+            .position(|x| *x == StorageColumnRef::RowHandler)
+        {
+            Some(idx) => idx,
+            None => {
+                column_refs.push(StorageColumnRef::RowHandler);
+                col_idx.len()
+            }
+        };
+        let column_refs: Arc<[StorageColumnRef]> = column_refs.into();
+        let mut builders = col_idx
+            .iter()
+            .map(|x| match x {
+                StorageColumnRef::Idx(id) => {
+                    ArrayBuilderImpl::new(&self.table.column(*id).data_type())
+                }
+                StorageColumnRef::RowHandler => ArrayBuilderImpl::new(&DataType::Int64),
+            })
+            .collect_vec();
+        let mut count = 0;
+        let rowsets = self.snapshot.get_rowsets_of(self.table.table_id());
+        for id in ids {
+            if !rowsets.is_some_and(|rowsets| rowsets.contains(&id.rowset_id())) {
+                continue;
+            }
+            let rowset = self
+                .version
+                .get_rowset(self.table.table_id(), id.rowset_id());
+            let mut iter = rowset
+                .iter_with_schema(
+                    &self.table.columns,
+                    column_refs.clone(),
+                    self.get_dvs_of(id.rowset_id()),
+                    ColumnSeekPosition::RowId(id.row_id()),
+                    None,
+                )
+                .await?;
+            // if the row is deleted, the iterator returns the next visible row
+            let Some(chunk) = iter.next_batch(Some(1)).await? else {
+                continue;
+            };
+            let chunk = chunk.to_data_chunk();
+            if chunk.cardinality() == 0
+                || chunk.array_at(handler_idx).get(0) != DataValue::Int64(id.as_i64())
+            {
+                continue;
+            }
+            for (i, builder) in builders.iter_mut().enumerate() {
+                builder.push(&chunk.array_at(i).get(0));
+            }
+            count += 1;
+        }
+        if col_idx.is_empty() {
+            return Ok(DataChunk::no_column(count));
+        }
+        Ok(builders.into_iter().map(|b| b.finish()).collect())
+    }
+
     async fn scan_inner(
         &self,
         col_idx: &[StorageColumnRef],
@@ -225,35 +408,40 @@ impl SecondaryTransaction {
 
         let mut iters: Vec<RowSetIterator> = vec![];
 
-        if let Some(rowsets) = self.snapshot.get_rowsets_of(self.table.table_id()) {
-            for rowset_id in rowsets {
-                let rowset = self.version.get_rowset(self.table.table_id(), *rowset_id);
-
-                // Get DV id and read DVs
-                let dvs = self
-                    .snapshot
-                    .get_dvs_of(self.table.table_id(), *rowset_id)
-                    .map(|dvs| {
-                        dvs.iter()
-                            .map(|dv_id| self.version.get_dv(self.table.table_id(), *dv_id))
-                            .collect_vec()
-                    })
-                    .unwrap_or_default();
+        // rowsets in the snapshot, followed by rowsets written in this transaction
+        let rowsets = (self.snapshot.get_rowsets_of(self.table.table_id()))
+            .into_iter()
+            .flatten()
+            .map(|rowset_id| self.version.get_rowset(self.table.table_id(), *rowset_id))
+            .chain(self.to_be_committed_rowsets.iter().cloned());
+        for (i, rowset) in rowsets.enumerate() {
+            // each partition scans a disjoint subset of rowsets
+            if let Some((index, count)) = opts.partition
+                && i % count != index
+            {
+                continue;
+            }
+            let dvs = self.get_dvs_of(rowset.rowset_id());
 
-                let begin_keys = match &opts.filter {
-                    Some(range) => match &range.start {
-                        Bound::Included(k) | Bound::Excluded(k) => Some(k),
-                        _ => None,
-                    },
+            let begin_keys = match &opts.filter {
+                Some(range) => match &range.start {
+                    Bound::Included(k) | Bound::Excluded(k) => Some(k),
                     _ => None,
-                };
-                let start_rowid = rowset.start_rowid(begin_keys).await;
-                iters.push(
-                    rowset
-                        .iter(col_idx.into(), dvs, start_rowid, opts.filter.clone())
-                        .await?,
-                )
-            }
+                },
+                _ => None,
+            };
+            let start_rowid = rowset.start_rowid(begin_keys).await;
+            iters.push(
+                rowset
+                    .iter_with_schema(
+                        &self.table.columns,
+                        col_idx.into(),
+                        dvs,
+                        start_rowid,
+                        opts.filter.clone(),
+                    )
+                    .await?,
+            )
         }
 
         let final_iter = if iters.len() == 1 {
@@ -263,11 +451,12 @@ impl SecondaryTransaction {
             if !sort_keys.is_empty() {
                 let real_col_idx = sort_keys
                     .iter()
-                    .map(|id| {
+                    .map(|&pos| {
+                        let id = self.table.columns[pos].id();
                         col_idx
                             .iter()
                             .position(|x| match x {
-                                StorageColumnRef::Idx(y) => *y as usize == *id,
+                                StorageColumnRef::Idx(y) => *y == id,
                                 _ => false,
                             })
                             .expect("sorting key not in column list")
@@ -304,12 +493,19 @@ impl SecondaryTransaction {
         if let Some(rowsets) = self.snapshot.get_rowsets_of(self.table.table_id()) {
             for rowset_id in rowsets {
                 let rowset = self.version.get_rowset(self.table.table_id(), *rowset_id);
-                for ((_, col_idx), agg) in ty.iter().zip(agg.iter_mut()) {
-                    let user_col_idx = match col_idx {
-                        StorageColumnRef::Idx(idx) => idx,
+                for ((ty, col_idx), agg) in ty.iter().zip(agg.iter_mut()) {
+                    let column_id = match col_idx {
+                        StorageColumnRef::Idx(id) => *id,
                         _ => panic!("unsupported column ref for block aggregation"),
                     };
-                    let column = rowset.column(*user_col_idx as usize);
+                    let pos = (rowset.column_infos().iter()).position(|c| c.id() == column_id);
+                    let column = match (pos, ty) {
+                        (Some(pos), _) => rowset.column(pos),
+                        // the column is added after the rowset was written, but all columns have
+                        // the same number of rows
+                        (None, BlockStatisticsType::RowCount) => rowset.column(0),
+                        (None, _) => continue,
+                    };
                     agg.apply_batch(column.index());
                 }
             }
@@ -363,21 +559,46 @@ impl Transaction for SecondaryTransaction {
         self.scan_inner(col_idx, options).await
     }
 
+    async fn fetch(
+        &self,
+        col_idx: &[StorageColumnRef],
+        ids: &[SecondaryRowHandler],
+    ) -> StorageResult<DataChunk> {
+        self.fetch_inner(col_idx, ids).await
+    }
+
     async fn append(&mut self, columns: DataChunk) -> StorageResult<()> {
         self.append_inner(columns).await
     }
 
     async fn delete(&mut self, id: &Self::RowHandlerType) -> StorageResult<()> {
-        assert!(
-            self.delete_lock.is_some(),
-            "delete lock is not held for this txn"
-        );
         self.delete_buffer.push(*id);
         Ok(())
     }
 
+    async fn update(
+        &mut self,
+        ids: &[Self::RowHandlerType],
+        columns: DataChunk,
+    ) -> StorageResult<()> {
+        self.delete_buffer.extend_from_slice(ids);
+        self.append_inner(columns).await
+    }
+
+    fn set_writable(&mut self) {
+        self.read_only = false;
+    }
+
+    async fn flush(&mut self) -> StorageResult<()> {
+        self.flush_rowset().await
+    }
+
     async fn commit(self) -> StorageResult<()> {
-        self.commit_inner().await
+        Self::commit_all_inner(vec![self]).await
+    }
+
+    async fn commit_all(txns: Vec<Self>) -> StorageResult<()> {
+        Self::commit_all_inner(txns).await
     }
 
     async fn abort(mut self) -> StorageResult<()> {
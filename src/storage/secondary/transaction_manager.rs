@@ -1,23 +1,30 @@
 // Copyright 2024 RisingLight Project Authors. Licensed under Apache-2.0.
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 
 use parking_lot::Mutex as PLMutex;
 use tokio::sync::{Mutex, OwnedMutexGuard};
 
+use super::version_manager::{Snapshot, VersionManager};
+use crate::storage::{StorageResult, TracedStorageError};
+
 /// Secondary's Transaction Manager.
 ///
-/// The storage engine of RisingLight is designed to provide snapshot isolation (SI) or even SSI.
-/// Therefore, we need a txn manager to coordinate this. We plan to implement this in 3 phases:
+/// The storage engine of RisingLight provides snapshot isolation (SI). Transactions and
+/// compactions run concurrently on their own snapshots, and conflicts are detected lazily when
+/// their changes are committed:
+///
+/// * A transaction fails with a retryable conflict error if a row it deletes was deleted by another
+///   transaction, or compacted into another rowset, after its snapshot was taken. The first
+///   committer wins.
+/// * A compaction gives up if any of its rowsets has new deletions after its snapshot was taken.
 ///
-/// * Only allow one deletion and one compaction on one table. Therefore, the system is by nature
-///   SI.
-/// * Implement concurrent deletion and compaction, and allow lazy detection of conflicts.
-/// * Implement true SI write conflict detection.
+/// Checking conflicts and committing changes to a table hold the commit lock of the table, so
+/// that no change is committed in between.
 #[derive(Default)]
 pub struct TransactionManager {
-    /// A single big lock for each table
+    /// The commit lock of each table.
     lock_map: PLMutex<HashMap<u32, Arc<Mutex<()>>>>,
 }
 
@@ -30,24 +37,181 @@ impl TransactionManager {
             .clone()
     }
 
-    /// Get a lock for compaction, return immediately
-    pub fn try_lock_for_compaction(&self, table: u32) -> Option<OwnedMutexGuard<()>> {
+    /// Get the lock for checking conflicts and committing changes to a table.
+    pub async fn lock_for_commit(&self, table: u32) -> OwnedMutexGuard<()> {
         let mutex = self.get_lock_for_table(table);
-        mutex.try_lock_owned().ok()
+        mutex.lock_owned().await
     }
 
-    async fn lock(&self, table: u32) -> OwnedMutexGuard<()> {
-        let mutex = self.get_lock_for_table(table);
-        mutex.lock_owned().await
+    /// Returns the ids of rows in a rowset deleted after `snapshot` was taken, or `None` if the
+    /// rowset no longer exists.
+    ///
+    /// The commit lock of the table should be held, so that the result is up to date.
+    pub fn deleted_since(
+        version: &VersionManager,
+        snapshot: &Snapshot,
+        table_id: u32,
+        rowset_id: u32,
+    ) -> Option<HashSet<u32>> {
+        let latest = version.pin();
+        let rowsets = latest.snapshot.get_rowsets_of(table_id)?;
+        if !rowsets.contains(&rowset_id) {
+            return None;
+        }
+        let old_dvs = snapshot.get_dvs_of(table_id, rowset_id);
+        let mut deleted = HashSet::new();
+        for dv_id in latest
+            .snapshot
+            .get_dvs_of(table_id, rowset_id)
+            .into_iter()
+            .flatten()
+        {
+            if old_dvs.is_some_and(|dvs| dvs.contains(dv_id)) {
+                continue;
+            }
+            deleted.extend(version.get_dv(table_id, *dv_id).deletes());
+        }
+        Some(deleted)
     }
 
-    /// Get a lock for compaction
-    pub async fn lock_for_compaction(&self, table: u32) -> OwnedMutexGuard<()> {
-        self.lock(table).await
+    /// Checks that the rows a transaction deletes are not changed after `snapshot` was taken.
+    ///
+    /// `deletes` maps a rowset id to the ids of deleted rows.
+    pub fn check_conflicts(
+        version: &VersionManager,
+        snapshot: &Snapshot,
+        table_id: u32,
+        deletes: &HashMap<u32, Vec<u32>>,
+    ) -> StorageResult<()> {
+        for (rowset_id, rows) in deletes {
+            let Some(deleted) = Self::deleted_since(version, snapshot, table_id, *rowset_id) else {
+                return Err(TracedStorageError::conflict(format!(
+                    "rowset {rowset_id} of table {table_id} has been compacted"
+                )));
+            };
+            if let Some(row_id) = rows.iter().find(|row| deleted.contains(row)) {
+                return Err(TracedStorageError::conflict(format!(
+                    "row {row_id} of rowset {rowset_id} in table {table_id} has been deleted"
+                )));
+            }
+        }
+        Ok(())
     }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::array::{ArrayImpl, DataChunk};
+    use crate::catalog::{ColumnCatalog, ColumnDesc, RootCatalog, TableRefId};
+    use crate::storage::secondary::{
+        SecondaryRowHandler, SecondaryStorage, SecondaryTransaction, StorageOptions,
+    };
+    use crate::storage::{Storage, Table, Transaction};
+    use crate::types::DataType;
+
+    #[tokio::test]
+    async fn first_committer_wins() {
+        let storage = SecondaryStorage::open(StorageOptions::default_for_test())
+            .await
+            .unwrap();
+        let schema_id = (storage.catalog())
+            .get_schema_id_by_name(RootCatalog::DEFAULT_SCHEMA_NAME)
+            .unwrap();
+        let columns = [ColumnCatalog::new(
+            0,
+            ColumnDesc::new("a", DataType::Int32, false),
+        )];
+        storage
+            .create_table(schema_id, "t", &columns, &[])
+            .await
+            .unwrap();
+        let table = storage.get_table(TableRefId::new(schema_id, 0)).unwrap();
+        let mut txn = table.write().await.unwrap();
+        let array = ArrayImpl::new_int32((1..=3).map(Some).collect());
+        txn.append(DataChunk::from_iter([array])).await.unwrap();
+        txn.commit().await.unwrap();
+        let version = storage.version.pin();
+        let rowset_id = *version
+            .snapshot
+            .get_rowsets_of(0)
+            .unwrap()
+            .iter()
+            .next()
+            .unwrap();
+        let row = |row_id| SecondaryRowHandler(rowset_id, row_id);
+
+        // deleting different rows concurrently
+        let mut txn1 = table.update().await.unwrap();
+        let mut txn2 = table.update().await.unwrap();
+        txn1.delete(&row(0)).await.unwrap();
+        txn2.delete(&row(1)).await.unwrap();
+        txn1.commit().await.unwrap();
+        txn2.commit().await.unwrap();
+
+        // deleting the same row concurrently
+        let mut txn1 = table.update().await.unwrap();
+        let mut txn2 = table.update().await.unwrap();
+        txn1.delete(&row(2)).await.unwrap();
+        txn2.delete(&row(2)).await.unwrap();
+        txn1.commit().await.unwrap();
+        let err = txn2.commit().await.unwrap_err();
+        assert!(err.is_conflict(), "{err}");
+    }
+
+    #[tokio::test]
+    async fn commit_all_is_atomic() {
+        let storage = SecondaryStorage::open(StorageOptions::default_for_test())
+            .await
+            .unwrap();
+        let schema_id = (storage.catalog())
+            .get_schema_id_by_name(RootCatalog::DEFAULT_SCHEMA_NAME)
+            .unwrap();
+        let columns = [ColumnCatalog::new(
+            0,
+            ColumnDesc::new("a", DataType::Int32, false),
+        )];
+        for name in ["t1", "t2"] {
+            storage
+                .create_table(schema_id, name, &columns, &[])
+                .await
+                .unwrap();
+        }
+        let t1 = storage.get_table(TableRefId::new(schema_id, 0)).unwrap();
+        let t2 = storage.get_table(TableRefId::new(schema_id, 1)).unwrap();
+        let mut txn = t2.write().await.unwrap();
+        let array = ArrayImpl::new_int32((1..=3).map(Some).collect());
+        txn.append(DataChunk::from_iter([array])).await.unwrap();
+        txn.commit().await.unwrap();
+        let version = storage.version.pin();
+        let rowset_id = *version
+            .snapshot
+            .get_rowsets_of(1)
+            .unwrap()
+            .iter()
+            .next()
+            .unwrap();
+        let row = SecondaryRowHandler(rowset_id, 0);
+
+        // insert into t1 and delete from t2, while the row is deleted by another transaction
+        let mut txn1 = t1.write().await.unwrap();
+        let array = ArrayImpl::new_int32((1..=3).map(Some).collect());
+        txn1.append(DataChunk::from_iter([array])).await.unwrap();
+        let mut txn2 = t2.update().await.unwrap();
+        txn2.delete(&row).await.unwrap();
+        let mut other = t2.update().await.unwrap();
+        other.delete(&row).await.unwrap();
+        other.commit().await.unwrap();
 
-    /// Get a lock for deletion
-    pub async fn lock_for_deletion(&self, table: u32) -> OwnedMutexGuard<()> {
-        self.lock(table).await
+        let err = SecondaryTransaction::commit_all(vec![txn1, txn2])
+            .await
+            .unwrap_err();
+        assert!(err.is_conflict(), "{err}");
+        let version = storage.version.pin();
+        assert!(
+            version
+                .snapshot
+                .get_rowsets_of(0)
+                .is_none_or(|r| r.is_empty())
+        );
     }
 }
@@ -18,7 +18,11 @@ use super::{DeleteVector, DiskRowset, MANIFEST_FILE_NAME, StorageOptions, Storag
 pub enum EpochOp {
     CreateTable(CreateTableEntry),
     DropTable(DropTableEntry),
-    AddRowSet((AddRowSetEntry, DiskRowset)),
+    CreateIndex(CreateIndexEntry),
+    AlterTable(AlterTableEntry),
+    CreateUser(CreateUserEntry),
+    DropUser(DropUserEntry),
+    AddRowSet((AddRowSetEntry, Arc<DiskRowset>)),
     DeleteRowSet(DeleteRowsetEntry),
     AddDV((AddDVEntry, DeleteVector)),
     DeleteDV(DeleteDVEntry),
@@ -29,6 +33,10 @@ impl std::fmt::Debug for EpochOp {
         match self {
             Self::CreateTable(e) => f.debug_tuple("EpochOp::CreateTable").field(e).finish(),
             Self::DropTable(e) => f.debug_tuple("EpochOp::DropTable").field(e).finish(),
+            Self::CreateIndex(e) => f.debug_tuple("EpochOp::CreateIndex").field(e).finish(),
+            Self::AlterTable(e) => f.debug_tuple("EpochOp::AlterTable").field(e).finish(),
+            Self::CreateUser(e) => f.debug_tuple("EpochOp::CreateUser").field(e).finish(),
+            Self::DropUser(e) => f.debug_tuple("EpochOp::DropUser").field(e).finish(),
             Self::AddRowSet((e, _)) => f.debug_tuple("EpochOp::AddRowSet").field(e).finish(),
             Self::DeleteRowSet(e) => f.debug_tuple("EpochOp::DeleteRowSet").field(e).finish(),
             Self::AddDV((e, _)) => f.debug_tuple("EpochOp::AddDV").field(e).finish(),
@@ -258,13 +266,23 @@ impl VersionManager {
                         entries.push(ManifestOperation::CreateTable(entry))
                     }
                     EpochOp::DropTable(entry) => entries.push(ManifestOperation::DropTable(entry)),
+                    EpochOp::CreateIndex(entry) => {
+                        entries.push(ManifestOperation::CreateIndex(entry))
+                    }
+                    EpochOp::AlterTable(entry) => {
+                        entries.push(ManifestOperation::AlterTable(entry))
+                    }
+                    EpochOp::CreateUser(entry) => {
+                        entries.push(ManifestOperation::CreateUser(entry))
+                    }
+                    EpochOp::DropUser(entry) => entries.push(ManifestOperation::DropUser(entry)),
 
                     // For other operations, maintain the snapshot in version manager
                     EpochOp::AddRowSet((entry, rowset)) => {
                         // record the rowset into the pool
                         inner
                             .rowsets
-                            .insert((entry.table_id.table_id, entry.rowset_id), Arc::new(rowset));
+                            .insert((entry.table_id.table_id, entry.rowset_id), rowset);
                         // update the snapshot
                         snapshot.add_rowset(entry.table_id.table_id, entry.rowset_id);
                         entries.push(ManifestOperation::AddRowSet(entry));
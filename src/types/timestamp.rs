@@ -5,7 +5,7 @@ use std::str::FromStr;
 use std::sync::OnceLock;
 
 use chrono::{DateTime, Datelike, FixedOffset, NaiveDateTime, Timelike};
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 
 /// unix timestamp counts from 1970-01-01 00:00:00,
 ///
@@ -34,7 +34,9 @@ const TIMESTAMP_TZ_FORMATS: [&str; 5] = [
     "%Y-%m-%d %H:%M:%S BC %z", // 1991-01-08 04:05:06 BC +08:00
 ];
 
-#[derive(PartialOrd, Ord, PartialEq, Eq, Debug, Copy, Clone, Default, Hash, Serialize)]
+#[derive(
+    PartialOrd, Ord, PartialEq, Eq, Debug, Copy, Clone, Default, Hash, Serialize, Deserialize,
+)]
 pub struct Timestamp(i64);
 
 #[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
@@ -53,6 +55,16 @@ impl Timestamp {
     pub fn get_inner(&self) -> i64 {
         self.0
     }
+
+    /// Creates a timestamp from the number of microseconds since the Unix epoch.
+    pub const fn from_unix_micros(micros: i64) -> Self {
+        Self(micros + THIRTY_YEARS_MICROSECONDS)
+    }
+
+    /// Returns the number of microseconds since the Unix epoch.
+    pub const fn unix_micros(&self) -> i64 {
+        self.0 - THIRTY_YEARS_MICROSECONDS
+    }
 }
 
 impl Display for Timestamp {
@@ -78,7 +90,9 @@ impl FromStr for Timestamp {
     }
 }
 
-#[derive(PartialOrd, Ord, PartialEq, Eq, Debug, Copy, Clone, Default, Hash, Serialize)]
+#[derive(
+    PartialOrd, Ord, PartialEq, Eq, Debug, Copy, Clone, Default, Hash, Serialize, Deserialize,
+)]
 pub struct TimestampTz(i64);
 
 impl TimestampTz {
@@ -89,6 +103,16 @@ impl TimestampTz {
     pub fn get_inner(&self) -> i64 {
         self.0
     }
+
+    /// Creates a timestamp from the number of microseconds since the Unix epoch.
+    pub const fn from_unix_micros(micros: i64) -> Self {
+        Self(micros + THIRTY_YEARS_MICROSECONDS)
+    }
+
+    /// Returns the number of microseconds since the Unix epoch.
+    pub const fn unix_micros(&self) -> i64 {
+        self.0 - THIRTY_YEARS_MICROSECONDS
+    }
 }
 
 impl Display for TimestampTz {
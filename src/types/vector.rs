@@ -2,12 +2,12 @@ use std::borrow::Borrow;
 use std::ops::Deref;
 use std::str::FromStr;
 
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 
 use super::{F64, VectorRef};
 
 /// A vector is a specialized array type for floating point numbers.
-#[derive(PartialOrd, Ord, PartialEq, Eq, Debug, Clone, Default, Hash, Serialize)]
+#[derive(PartialOrd, Ord, PartialEq, Eq, Debug, Clone, Default, Hash, Serialize, Deserialize)]
 pub struct Vector(Box<[F64]>);
 
 impl Vector {
@@ -0,0 +1,13 @@
+-- push filter and projection into file scan
+explain select column1 from read_csv('tests/sql/copy/nation.tbl', delim => '|', header => false) where column2 = 1
+
+/*
+Projection { exprs: [ column1 ], cost: 1115, rows: 500 }
+└── FileScan
+    ├── source: path="tests/sql/copy/nation.tbl", format=Csv { delimiter: '|', quote: '"', escape: None, header: false }
+    ├── list: [ column1, column2 ]
+    ├── filter: = { lhs: column2, rhs: 1 }
+    ├── cost: 1105
+    └── rows: 500
+*/
+
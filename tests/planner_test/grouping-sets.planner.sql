@@ -0,0 +1,30 @@
+-- rollup is aggregated in one pass over the input
+explain select a, b, sum(c), grouping(a, b) from t1 group by rollup(a, b)
+
+/*
+Projection
+├── exprs:
+│   ┌── a
+│   ├── b
+│   ├── ref
+│   │   └── sum
+│   │       └── c
+│   └── ref
+│       └── grouping
+│           └── [ a, b ]
+├── cost: 4493.7705
+├── rows: 111
+└── GroupingSets
+    ├── keys:
+    │   ┌── a
+    │   ├── b
+    │   └── grouping
+    │       └── [ a, b ]
+    ├── sets: [ [ a, b ], [ a ], [] ]
+    ├── aggs:sum
+    │   └── c
+    ├── cost: 4488.2207
+    ├── rows: 111
+    └── Scan { table: t1, list: [ a, b, c ], filter: true, cost: 3000, rows: 1000 }
+*/
+
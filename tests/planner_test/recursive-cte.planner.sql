@@ -0,0 +1,19 @@
+-- recursive CTE over a join
+explain with recursive t(n) as (
+    select a from t1 where b = 1
+    union
+    select x from t join t2 on n = y
+)
+select n from t
+
+/*
+RecursiveUnion { op: union, columns: [ n ], cost: 2017159.4, rows: 500500 }
+├── Projection { exprs: [ a ], cost: 3220, rows: 500 }
+│   └── Filter { cond: = { lhs: b, rhs: 1 }, cost: 3210, rows: 500 }
+│       └── Scan { table: t1, list: [ a, b ], filter: true, cost: 2000, rows: 1000 }
+└── Projection { exprs: [ x ], cost: 1513439.4, rows: 500000 }
+    └── HashJoin { type: inner, cond: true, lkey: [ n ], rkey: [ y ], cost: 1503439.4, rows: 500000 }
+        ├── WorkingTable { columns: [ n ], cost: 1000, rows: 1000 }
+        └── Scan { table: t2, list: [ x, y ], filter: true, cost: 2000, rows: 1000 }
+*/
+
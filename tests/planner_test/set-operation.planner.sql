@@ -0,0 +1,28 @@
+-- push filter and projection below union all
+explain select b from (select a, b from t1 union all select y, x from t2) where a > 1
+
+/*
+SetOp { op: union_all, cost: 7440, rows: 1000 }
+├── Projection { exprs: [ b ], cost: 3220, rows: 500 }
+│   └── Filter { cond: > { lhs: a, rhs: 1 }, cost: 3210, rows: 500 }
+│       └── Scan { table: t1, list: [ a, b ], filter: true, cost: 2000, rows: 1000 }
+└── Projection { exprs: [ x ], cost: 3220, rows: 500 }
+    └── Filter { cond: > { lhs: y, rhs: 1 }, cost: 3210, rows: 500 }
+        └── Scan { table: t2, list: [ x, y ], filter: true, cost: 2000, rows: 1000 }
+*/
+
+-- set operations
+explain select a from t1 union select x from t2 intersect select y from t2 except all select 1
+
+/*
+SetOp { op: except_all, cost: 8419.81, rows: 2000 }
+├── SetOp { op: union, cost: 6398.689, rows: 2000 }
+│   ├── Scan { table: t1, list: [ a ], filter: true, cost: 1000, rows: 1000 }
+│   └── SetOp { op: intersect, cost: 3199.3445, rows: 1000 }
+│       ├── Scan { table: t2, list: [ x ], filter: true, cost: 1000, rows: 1000 }
+│       └── Scan { table: t2, list: [ y(1) ], filter: true, cost: 1000, rows: 1000 }
+└── Projection { exprs: [ 1 ], cost: 1.11, rows: 1 }
+    └── Values { rows: 1, cost: 1, rows: 1 }
+        └── [ 0 ]
+*/
+
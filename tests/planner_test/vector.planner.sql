@@ -1,15 +1,121 @@
+-- prepare
+CREATE TABLE t (a vector(3) not null, b text not null, c int not null);
+INSERT INTO t VALUES
+  ('[0, 0, 1]', 'a', 1), ('[0, 0, 2]', 'b', 2), ('[0, 0, 3]', 'c', 3), ('[0, 1, 0]', 'd', 4),
+  ('[0, 2, 0]', 'e', 5), ('[1, 0, 0]', 'f', 6), ('[2, 0, 0]', 'g', 7), ('[1, 1, 1]', 'h', 8);
+CREATE INDEX t_ivfflat ON t USING ivfflat (a) WITH (distfn = '<->', nlists = 3, nprobe = 2);
+CREATE INDEX t_hnsw ON t USING hnsw (a) WITH (distfn = '<=>', m = 4);
+
+/*
+
+*/
+
 -- match the index
+explain select * from t order by a <-> '[0, 0, 1]'::VECTOR(3) limit 3;
+
+/*
+Limit { limit: 3, offset: 0, cost: 18.3, rows: 3 }
+└── IndexScan
+    ├── table: t
+    ├── columns: [ a, b, c ]
+    ├── filter: true
+    ├── distance: VectorL2Distance { lhs: a, rhs: [0,0,1] }
+    ├── limit: 3
+    ├── cost: 9.3
+    └── rows: 3
+*/
+
+-- no index scan without limit, since rows with null vectors are not in the index
 explain select * from t order by a <-> '[0, 0, 1]'::VECTOR(3);
 
 /*
-IndexScan { table: t, columns: [ a, b ], filter: true, key: a, vector: [0,0,1], cost: 0, rows: 1 }
+Order { by: [ VectorL2Distance { lhs: a, rhs: [0,0,1] } ], cost: 73.3594, rows: 8 }
+└── Scan { table: t, list: [ a, b, c ], filter: true, cost: 24, rows: 8 }
 */
 
--- match the index
-explain select * from t order by a <=> '[0, 0, 1]'::VECTOR(3);
+-- no index scan with an offset but without limit
+explain select * from t order by a <-> '[0, 0, 1]'::VECTOR(3) offset 1;
 
 /*
-Order { by: [ VectorCosineDistance { lhs: a, rhs: [0,0,1] } ], cost: 18, rows: 3 }
-└── Scan { table: t, list: [ a, b ], filter: true, cost: 6, rows: 3 }
+TopN { limit: null, offset: 1, order_by: [ VectorL2Distance { lhs: a, rhs: [0,0,1] } ], cost: 73.3594, rows: 8 }
+└── Scan { table: t, list: [ a, b, c ], filter: true, cost: 24, rows: 8 }
+*/
+
+-- no index for the distance function
+explain select * from t order by a <#> '[0, 0, 1]'::VECTOR(3);
+
+/*
+Order { by: [ VectorDotProduct { lhs: a, rhs: [0,0,1] } ], cost: 73.3594, rows: 8 }
+└── Scan { table: t, list: [ a, b, c ], filter: true, cost: 24, rows: 8 }
+*/
+
+-- push the limit into the hnsw index
+explain select b from t order by a <=> '[0, 0, 1]'::VECTOR(3) limit 3;
+
+/*
+Limit { limit: 3, offset: 0, cost: 9.360001, rows: 3 }
+└── Projection { exprs: [ b ], cost: 6.36, rows: 3 }
+    └── IndexScan
+        ├── table: t
+        ├── columns: [ a, b ]
+        ├── filter: true
+        ├── distance: VectorCosineDistance { lhs: a, rhs: [0,0,1] }
+        ├── limit: 3
+        ├── cost: 6.3
+        └── rows: 3
+*/
+
+-- filtered ANN
+explain select b from t where c > 3 order by a <-> '[0, 0, 1]'::VECTOR(3) limit 2 offset 1;
+
+/*
+Limit { limit: 2, offset: 1, cost: 11.690001, rows: 2 }
+└── Projection { exprs: [ b ], cost: 9.690001, rows: 3 }
+    └── IndexScan
+        ├── table: t
+        ├── columns: [ a, b, c ]
+        ├── filter: > { lhs: c, rhs: 3 }
+        ├── distance: VectorL2Distance { lhs: a, rhs: [0,0,1] }
+        ├── limit: 3
+        ├── cost: 9.63
+        └── rows: 3
+*/
+
+-- table scan returns all rows without limit
+select b from t order by a <-> '[0, 0, 1]'::VECTOR(3);
+
+/*
+a
+b
+d
+f
+h
+c
+e
+g
+*/
+
+-- hnsw index scan with limit
+select b from t order by a <=> '[0, 0, 1]'::VECTOR(3) limit 3;
+
+/*
+a
+b
+c
+*/
+
+-- filtered ivfflat index scan with limit and offset
+select b, c from t where c > 3 order by a <-> '[0, 0, 1]'::VECTOR(3) limit 2 offset 1;
+
+/*
+f	6
+h	8
+*/
+
+-- all rows are filtered out
+select b from t where c > 100 order by a <-> '[0, 0, 1]'::VECTOR(3) limit 2;
+
+/*
+
 */
 
@@ -0,0 +1,38 @@
+-- window functions with frames
+explain select a, sum(b) over (partition by a order by c rows between 2 preceding and current row),
+    rank() over (order by b desc)
+from t1
+
+/*
+Projection
+├── exprs:
+│   ┌── a
+│   ├── ref
+│   │   └── Over { partition_by: [ a ], order_by: [ c ], frame: rows between 2 preceding and current row }
+│   │       └── sum
+│   │           └── b
+│   └── ref
+│       └── Over
+│           ├── partition_by: []
+│           ├── order_by:desc
+│           │   └── b
+│           ├── frame: range between unbounded preceding and current row
+│           └── rank
+├── cost: 4430
+├── rows: 1000
+└── Window
+    ├── windows:
+    │   ┌── Over { partition_by: [ a ], order_by: [ c ], frame: rows between 2 preceding and current row }
+    │   │   └── sum
+    │   │       └── b
+    │   └── Over
+    │       ├── partition_by: []
+    │       ├── order_by:desc
+    │       │   └── b
+    │       ├── frame: range between unbounded preceding and current row
+    │       └── rank
+    ├── cost: 4390
+    ├── rows: 1000
+    └── Scan { table: t1, list: [ a, b, c ], filter: true, cost: 3000, rows: 1000 }
+*/
+